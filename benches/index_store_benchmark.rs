@@ -0,0 +1,124 @@
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use googol::{
+    index_store::{IndexStore, StorageFormat},
+    page::PageBuilder,
+};
+use rand::{
+    distr::{Alphanumeric, Distribution},
+    rng,
+    seq::IndexedRandom,
+};
+use std::{env, fs, hint, path::Path};
+use url::Url;
+
+fn generate_random_word(len: usize) -> String {
+    let mut rng = rng();
+    Alphanumeric
+        .sample_iter(&mut rng)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn generate_index(filepath: &Path, total_pages: usize) -> IndexStore {
+    let mut rng = rng();
+    let mut index_store = IndexStore::new(filepath);
+
+    let vocabulary: Vec<String> = (0..5_000).map(|_| generate_random_word(6)).collect();
+    let urls: Vec<Url> = (0..total_pages)
+        .map(|i| format!("https://example.com/page/{}", i).parse().unwrap())
+        .collect();
+
+    for url in &urls {
+        let page = PageBuilder::default()
+            .url(url.clone())
+            .title(generate_random_word(12))
+            .build()
+            .unwrap();
+
+        let words: Vec<&String> = vocabulary.choose_multiple(&mut rng, 10).collect();
+        let outlinks: Vec<Url> = urls.choose_multiple(&mut rng, 5).cloned().collect();
+
+        index_store.store(&page, &[], &words, &outlinks);
+    }
+
+    index_store
+}
+
+fn benchmark_save_load(c: &mut Criterion) {
+    let total_pages = 100_000;
+
+    let mut group = c.benchmark_group(format!("index_store {} pages", total_pages));
+    group.sample_size(10);
+
+    for (format, extension) in [
+        (StorageFormat::Json, "json"),
+        (StorageFormat::Binary, "bin"),
+    ] {
+        let filepath = env::temp_dir().join(format!("googol-index-benchmark.{}", extension));
+
+        let mut index_store = generate_index(&filepath, total_pages);
+
+        let size = index_store.save_as(format).unwrap();
+        println!("{:?} index size: {} bytes", format, size);
+
+        group.bench_function(format!("save {:?}", format), |b| {
+            b.iter(|| hint::black_box(index_store.save_as(format).unwrap()));
+        });
+
+        group.bench_function(format!("load {:?}", format), |b| {
+            b.iter(|| hint::black_box(IndexStore::load(&filepath).unwrap()));
+        });
+
+        fs::remove_file(&filepath).unwrap();
+    }
+
+    group.finish();
+}
+
+/// Compares sorting every page matching a hot term against only keeping the top ones.
+fn benchmark_rank_top(c: &mut Criterion) {
+    let total_pages = 100_000;
+    let limit = 50;
+
+    let mut rng = rng();
+    let mut index_store = IndexStore::new(env::temp_dir().join("googol-rank-benchmark.json"));
+
+    let urls: Vec<Url> = (0..total_pages)
+        .map(|i| format!("https://example.com/page/{}", i).parse().unwrap())
+        .collect();
+
+    // Every page matches, with backlinks spread at random
+    for url in &urls {
+        let page = PageBuilder::default().url(url.clone()).build().unwrap();
+        let outlinks: Vec<Url> = urls.choose_multiple(&mut rng, 5).cloned().collect();
+
+        index_store.store(&page, &[], &["hot"], &outlinks);
+    }
+
+    let pages = index_store.search(&["hot"]);
+
+    let mut group = c.benchmark_group(format!("rank {} matching pages", total_pages));
+    group.sample_size(10);
+
+    group.bench_function("full sort", |b| {
+        b.iter_batched(
+            || pages.clone(),
+            |pages| hint::black_box(index_store.rank_by_relevance(pages)),
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.bench_function(format!("top {}", limit), |b| {
+        b.iter_batched(
+            || pages.clone(),
+            |pages| hint::black_box(index_store.rank_top_by_relevance(pages, limit)),
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_save_load, benchmark_rank_top);
+criterion_main!(benches);
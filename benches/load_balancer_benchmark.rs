@@ -0,0 +1,81 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use googol::{
+    barrel::Barrel,
+    gateway::load_balancer::{LBResult, LoadBalancer},
+    proto::{
+        SearchRequest, barrel_service_client::BarrelServiceClient,
+        barrel_service_server::BarrelServiceServer,
+    },
+};
+use std::{hint, net::SocketAddr};
+use tokio::runtime::Runtime;
+use tonic::transport::{Server, server::TcpIncoming};
+
+const TOTAL_SEARCHES: usize = 100;
+
+fn search_request() -> SearchRequest {
+    SearchRequest {
+        words: vec!["rust".to_string()],
+        ..Default::default()
+    }
+}
+
+/// Serves an empty barrel on a random local port.
+fn spawn_barrel(runtime: &Runtime) -> SocketAddr {
+    runtime.block_on(async {
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let address = incoming.local_addr().unwrap();
+
+        tokio::spawn(
+            Server::builder()
+                .add_service(BarrelServiceServer::new(Barrel::default()))
+                .serve_with_incoming(incoming),
+        );
+
+        address
+    })
+}
+
+fn benchmark_sequential_searches(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let address = spawn_barrel(&runtime);
+
+    let mut group = c.benchmark_group(format!("{} sequential searches", TOTAL_SEARCHES));
+
+    group.bench_function("new channel per search", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                for _ in 0..TOTAL_SEARCHES {
+                    let mut client = BarrelServiceClient::connect(format!("http://{}", address))
+                        .await
+                        .unwrap();
+                    let response = client.search(search_request()).await.unwrap();
+                    hint::black_box(response);
+                }
+            })
+        });
+    });
+
+    let mut load_balancer = LoadBalancer::new(&[address].into_iter().collect());
+
+    group.bench_function("load balancer reusing channel", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                for _ in 0..TOTAL_SEARCHES {
+                    let response = load_balancer
+                        .send_until(|mut client| {
+                            Box::pin(async move { client.search(search_request()).await })
+                        })
+                        .await;
+                    assert!(matches!(response, LBResult::Ok(..)));
+                    hint::black_box(response);
+                }
+            })
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_sequential_searches);
+criterion_main!(benches);
@@ -0,0 +1,57 @@
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use googol::{
+    index_store::IndexStore,
+    testing::{VOCABULARY_SIZE, synthetic_word},
+};
+use std::hint;
+
+/// Searches of frequent, mixed and rare words, by the ranks of the words.
+const SEARCHES: [(&str, &[usize]); 4] = [
+    ("frequent", &[0]),
+    // Each on most pages, so the intersection is large
+    ("frequent pair", &[0, 1]),
+    ("frequent and rare", &[0, 500]),
+    ("rare", &[VOCABULARY_SIZE / 2]),
+];
+
+fn benchmark_search(c: &mut Criterion) {
+    let total_pages = 50_000;
+    let limit = 10;
+
+    // Seeded, so results are comparable between runs
+    let index_store = IndexStore::synthetic(total_pages, 100, 42);
+
+    let mut group = c.benchmark_group(format!("search {} pages", total_pages));
+    group.sample_size(20);
+
+    for (name, ranks) in SEARCHES {
+        let words: Vec<String> = ranks.iter().map(|&rank| synthetic_word(rank)).collect();
+        println!(
+            "{}: {} matching pages",
+            name,
+            index_store.search(&words).len()
+        );
+
+        group.bench_function(format!("search {}", name), |b| {
+            b.iter(|| hint::black_box(index_store.search(&words)));
+        });
+
+        group.bench_function(format!("search_by_relevance {} top {}", name, limit), |b| {
+            b.iter(|| hint::black_box(index_store.search_by_relevance(&words, limit)));
+        });
+
+        // Ranking every match, as the top-k selection of `search_by_relevance` avoids
+        group.bench_function(format!("rank_by_relevance {}", name), |b| {
+            b.iter_batched(
+                || index_store.search(&words),
+                |pages| hint::black_box(index_store.rank_by_relevance(pages)),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_search);
+criterion_main!(benches);
@@ -1,5 +1,8 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("protos/googol.proto")?;
+    // Messages are serializable, e.g. for the client's JSON output
+    tonic_build::configure()
+        .type_attribute(".googol", "#[derive(serde::Serialize)]")
+        .compile_protos(&["protos/googol.proto"], &["protos"])?;
     tonic_build::compile_protos("protos/helloworld.proto")?;
     Ok(())
 }
@@ -5,7 +5,7 @@ use url::Host;
 async fn main() -> Result<(), reqwest::Error> {
     pretty_env_logger::init();
 
-    let mut fishfish = FishFish::new();
+    let fishfish = FishFish::new();
 
     //debugv!(&fishfish);
 
@@ -20,17 +20,17 @@ impl fmt::Display for Foo {
 fn main() {
     pretty_env_logger::init();
 
-    for foo in &[Foo { bar: Some(5) }, Foo { bar: None }] {
-        dbg!(foo);
-        debugv!(foo);
-        debugv!(foo, debug); // Using Debug explicitly
-        debugv!(foo, display); // Using Display explicitly
-        infov!(foo);
-        infov!(foo, debug); // Using Debug explicitly
-        infov!(foo, display); // Using Display explicitly
-        errorv!(foo);
-        errorv!(foo, debug); // Using Debug explicitly
-        errorv!(foo, display); // Using Display explicitly
+    for value in &[Foo { bar: Some(5) }, Foo { bar: None }] {
+        dbg!(value);
+        debugv!(value);
+        debugv!(value, debug); // Using Debug explicitly
+        debugv!(value, display); // Using Display explicitly
+        infov!(value);
+        infov!(value, debug); // Using Debug explicitly
+        infov!(value, display); // Using Display explicitly
+        errorv!(value);
+        errorv!(value, debug); // Using Debug explicitly
+        errorv!(value, display); // Using Display explicitly
         println!("\n-------------------------------------------\n");
     }
 }
@@ -0,0 +1,34 @@
+#!/usr/bin/env bash
+# Generates a throwaway CA, a server certificate for localhost and a client certificate, used
+# by the TLS tests and as a starting point for trying TLS locally. Never use them in production.
+set -euo pipefail
+
+cd "$(dirname "$0")"
+
+DAYS=36500
+
+openssl req -x509 -newkey ec -pkeyopt ec_paramgen_curve:prime256v1 -nodes \
+    -keyout ca.key -out ca.pem -days "$DAYS" -subj "/CN=googol test CA" \
+    -addext "basicConstraints=critical,CA:TRUE" \
+    -addext "keyUsage=critical,keyCertSign,cRLSign"
+
+# sign NAME SUBJECT EXTENSIONS
+sign() {
+    openssl req -newkey ec -pkeyopt ec_paramgen_curve:prime256v1 -nodes \
+        -keyout "$1.key" -out "$1.csr" -subj "$2"
+    openssl x509 -req -in "$1.csr" -CA ca.pem -CAkey ca.key -CAcreateserial \
+        -out "$1.pem" -days "$DAYS" -extfile <(printf '%s' "$3")
+    rm "$1.csr"
+}
+
+sign server "/CN=localhost" \
+    "basicConstraints=CA:FALSE
+extendedKeyUsage=serverAuth
+subjectAltName=DNS:localhost,IP:127.0.0.1"
+
+sign client "/CN=googol gateway" \
+    "basicConstraints=CA:FALSE
+extendedKeyUsage=clientAuth"
+
+# Nothing else is ever signed with the CA
+rm ca.key ca.srl
@@ -32,11 +32,11 @@ impl KeywordCounter {
         for (keyword, &count) in &self.counts {
             if heap.len() < n {
                 heap.push(Reverse((count, keyword.clone())));
-            } else if let Some(&Reverse((min_count, _))) = heap.peek() {
-                if count > min_count {
-                    heap.pop();
-                    heap.push(Reverse((count, keyword.clone())));
-                }
+            } else if let Some(&Reverse((min_count, _))) = heap.peek()
+                && count > min_count
+            {
+                heap.pop();
+                heap.push(Reverse((count, keyword.clone())));
             }
         }
 
@@ -46,7 +46,7 @@ impl KeywordCounter {
             .map(|Reverse((count, keyword))| (count, keyword))
             .collect();
 
-        result.sort_by(|a, b| b.0.cmp(&a.0));
+        result.sort_by_key(|(count, _)| Reverse(*count));
 
         // Convert to (keyword, count) tuple for output
         result
@@ -1,11 +1,15 @@
-use std::{fmt, net::SocketAddr, str::FromStr};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::{
+    fmt,
+    net::{AddrParseError, SocketAddr},
+    str::FromStr,
+};
 
 /// A wrapper around `SocketAddr` providing custom display and default behavior.
 ///
-/// `Address` encapsulates a `SocketAddr` and provides implementations for `Default`
-/// and `Display` traits, along with a constructor method.
-#[derive(Debug, PartialEq)]
-#[allow(dead_code)]
+/// `Address` encapsulates a `SocketAddr` and provides implementations for `Default`,
+/// `Display` and `FromStr` traits, along with a constructor method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Address(SocketAddr);
 
 impl Default for Address {
@@ -30,19 +34,62 @@ impl Default for Address {
 impl fmt::Display for Address {
     /// Formats the `Address` for user-friendly display.
     ///
-    /// This implementation outputs the inner `SocketAddr` using its `Debug` formatting.
+    /// This implementation outputs the inner `SocketAddr` in its `ip:port` form.
     ///
     /// # Examples
     ///
     /// ```
     /// use googol::address::Address;
-    /// use std::{fmt, net::SocketAddr, str::FromStr};
     ///
     /// let addr = Address::default();
-    /// println!("{}", addr); // Outputs: "127.0.0.1:8080"
+    /// assert_eq!(addr.to_string(), "127.0.0.1:8080");
     /// ```
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self.0)
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddrParseError;
+
+    /// Parses an `Address` from its `ip:port` form, like a `SocketAddr`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use googol::address::Address;
+    ///
+    /// let addr: Address = "127.0.0.1:50051".parse().unwrap();
+    /// assert_eq!(addr.as_socket_addr().port(), 50051);
+    ///
+    /// assert!("not an address".parse::<Address>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SocketAddr::from_str(s).map(Self)
+    }
+}
+
+impl Serialize for Address {
+    /// Serializes the `Address` as an `ip:port` string.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    /// Deserializes an `Address` from an `ip:port` string.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let address = String::deserialize(deserializer)?;
+
+        address
+            .parse()
+            .map_err(|e| de::Error::custom(format!("Invalid address `{}`: {}", address, e)))
     }
 }
 
@@ -61,9 +108,58 @@ impl Address {
     ///
     /// let socket_addr: SocketAddr = "192.168.1.1:1234".parse().unwrap();
     /// let addr = Address::new(socket_addr);
-    /// println!("{}", addr); // Outputs: "SocketAddr { ip: V4(192.168.1.1), port: 1234 }"
+    /// println!("{}", addr); // Outputs: "192.168.1.1:1234"
     /// ```
     pub fn new(address: SocketAddr) -> Self {
         Self(address)
     }
+
+    /// Returns the wrapped `SocketAddr`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::net::SocketAddr;
+    /// use googol::address::Address;
+    ///
+    /// let socket_addr: SocketAddr = "192.168.1.1:1234".parse().unwrap();
+    /// assert_eq!(Address::new(socket_addr).as_socket_addr(), socket_addr);
+    /// ```
+    pub fn as_socket_addr(&self) -> SocketAddr {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Address::default().to_string(), "127.0.0.1:8080");
+
+        let address: Address = "[::1]:50051".parse().unwrap();
+        assert_eq!(address.to_string(), "[::1]:50051");
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Config {
+        address: Address,
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let toml = "address = \"127.0.0.1:50051\"\n";
+
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.address, "127.0.0.1:50051".parse().unwrap());
+
+        assert_eq!(toml::to_string(&config).unwrap(), toml);
+    }
+
+    #[test]
+    fn test_deserialize_invalid() {
+        assert!(toml::from_str::<Config>("address = \"localhost\"").is_err());
+        assert!(toml::from_str::<Config>("address = 8080").is_err());
+    }
 }
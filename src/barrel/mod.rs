@@ -19,10 +19,22 @@
 //!     let config = BarrelConfig {
 //!         address: "127.0.0.1:44992".parse().unwrap(),
 //!         filepath: ".barrel-data.json".to_string(),
+//!         stop_words: Default::default(),
+//!         stemming: false,
+//!         case_sensitive: false,
+//!         title_boost: 10.0,
+//!         collapse_duplicates: false,
+//!         tls: None,
+//!         compression: true,
+//!         max_message_size: 64 * 1024 * 1024,
+//!         save_interval_secs: 5,
+//!         save_every_pages: 100,
+//!         log_file: None,
+//!         log_level: "info".to_string(),
 //!     };
 //!     let barrel = Barrel::new(&config).await;
 //!     // Server::builder()
-//!     //     .add_service(BarrelServiceServer::new(barrel))
+//!     //     .add_service(Arc::new(barrel).into_service(config.compression, config.max_message_size))
 //!     //     .serve(config.address)
 //!     //     .await?;
 //!     Ok(())
@@ -32,20 +44,43 @@
 use crate::{
     GoogolStatus,
     address::Address,
+    debugv,
     index_store::IndexStore,
     page::Page,
     proto::{
         BacklinksRequest, BacklinksResponse, BarrelStatusRequest, BarrelStatusResponse,
-        HealthRequest, HealthResponse, IndexRequest, IndexResponse, OutlinksRequest,
-        OutlinksResponse, SearchRequest, SearchResponse, barrel_service_server::BarrelService,
+        BroadcastIndexRequest, BroadcastIndexResponse, HealthRequest, HealthResponse, IndexRequest,
+        IndexResponse, IndexedPage, ListIndexedPagesRequest, ListIndexedPagesResponse,
+        OutlinksRequest, OutlinksResponse, SearchMode, SearchRequest, SearchResponse,
+        SuggestRequest, SuggestResponse, Suggestion,
+        barrel_service_server::{BarrelService, BarrelServiceServer},
     },
     settings::barrel::BarrelConfig,
 };
+use chrono::DateTime;
+use futures::stream::{self, BoxStream};
 use log::{debug, error};
-use tokio::sync::Mutex as AsyncMutex;
-use tonic::{Request, Response, Status};
+use std::{
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+use tokio::{sync::Mutex as AsyncMutex, task::JoinHandle, time::sleep};
+use tonic::{Request, Response, Status, codec::CompressionEncoding};
 use url::Url;
 
+/// Number of pages in each response of a streamed search.
+pub const SEARCH_BATCH_SIZE: usize = 10;
+/// Number of pages returned by a search that doesn't set a limit.
+pub const DEFAULT_SEARCH_LIMIT: u32 = 50;
+/// Number of pages listed by a `list_indexed_pages` request that doesn't set a limit.
+pub const DEFAULT_LIST_LIMIT: u32 = 100;
+/// Number of words suggested by a `suggest` request that doesn't set a limit.
+pub const DEFAULT_SUGGEST_LIMIT: u32 = 10;
+
 /// Represents a Barrel server instance.
 ///
 /// This struct manages the internal index store and handles incoming requests
@@ -56,6 +91,10 @@ pub struct Barrel {
     pub address: Address,
     /// The internal index store. Protected by a mutex for concurrent access.
     index: AsyncMutex<IndexStore>,
+    /// Number of pages indexed since the index was last saved.
+    unsaved: AtomicUsize,
+    /// The index is saved once this many pages are indexed without being saved.
+    save_every_pages: usize,
 }
 
 impl Barrel {
@@ -81,17 +120,184 @@ impl Barrel {
     /// let config = BarrelConfig {
     ///     address: "127.0.0.1:8080".parse().unwrap(),
     ///     filepath: "path/to/index/file".to_string(),
+    ///     stop_words: ["the", "a"].iter().map(|w| w.to_string()).collect(),
+    ///     stemming: true,
+    ///     case_sensitive: false,
+    ///     title_boost: 10.0,
+    ///     collapse_duplicates: false,
+    ///     tls: None,
+    ///     compression: true,
+    ///     max_message_size: 64 * 1024 * 1024,
+    ///     save_interval_secs: 5,
+    ///     save_every_pages: 100,
+    ///     log_file: None,
+    ///     log_level: "info".to_string(),
     /// };
     /// let barrel = Barrel::new(&config);
     /// ```
     pub async fn new(config: &BarrelConfig) -> Self {
         Self {
             address: Address::new(config.address),
-            index: AsyncMutex::new(IndexStore::load(&config.filepath).unwrap()),
+            index: AsyncMutex::new(
+                IndexStore::load(&config.filepath)
+                    .unwrap()
+                    .with_stop_words(config.stop_words.clone())
+                    .with_stemming(config.stemming)
+                    .with_case_sensitive(config.case_sensitive)
+                    .with_title_boost(config.title_boost)
+                    .with_collapse_duplicates(config.collapse_duplicates),
+            ),
+            unsaved: AtomicUsize::new(0),
+            save_every_pages: config.save_every_pages,
+        }
+    }
+
+    /// Wraps the barrel in its gRPC service. The barrel is shared, e.g. to still
+    /// [`Barrel::save`] it once the server stops.
+    ///
+    /// Compressed requests are always accepted, while responses are only compressed if
+    /// `compression` is set and the client accepts them. Requests and responses larger than
+    /// `max_message_size` bytes are refused.
+    pub fn into_service(
+        self: Arc<Self>,
+        compression: bool,
+        max_message_size: usize,
+    ) -> BarrelServiceServer<Self> {
+        let service = BarrelServiceServer::from_arc(self)
+            .accept_compressed(CompressionEncoding::Gzip)
+            .max_decoding_message_size(max_message_size)
+            .max_encoding_message_size(max_message_size);
+
+        match compression {
+            true => service.send_compressed(CompressionEncoding::Gzip),
+            false => service,
+        }
+    }
+
+    /// Saves the index to its file right away, whether it changed or not.
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes written, or an `io::Error` if saving fails.
+    pub async fn save(&self) -> Result<usize, io::Error> {
+        let mut index = self.index.lock().await;
+
+        self.save_index(&mut index)
+    }
+
+    /// Saves the index if pages were indexed since it was last saved. Errors are logged, and
+    /// the changes are saved on the next attempt.
+    pub async fn flush(&self) {
+        let mut index = self.index.lock().await;
+
+        if self.unsaved.load(Ordering::Relaxed) > 0
+            && let Err(e) = self.save_index(&mut index)
+        {
+            error!("Failed to save index: {}", e);
+        }
+    }
+
+    /// Spawns a background task that saves the index each `interval`, if it changed.
+    ///
+    /// # Arguments
+    /// * `interval` - Time between saves.
+    ///
+    /// # Returns
+    /// The `JoinHandle` of the spawned task.
+    pub fn spawn_saver(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                sleep(interval).await;
+                self.flush().await;
+            }
+        })
+    }
+
+    /// Saves the locked index, forgetting the pages indexed since the last save if it succeeds.
+    fn save_index(&self, index: &mut IndexStore) -> Result<usize, io::Error> {
+        let size_bytes = index.save()?;
+        self.unsaved.store(0, Ordering::Relaxed);
+
+        Ok(size_bytes)
+    }
+
+    /// Searches the index and selects the page of results requested.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The search terms, mode and pagination.
+    ///
+    /// # Returns
+    ///
+    /// A `SearchResponse` with the ranked pages and the total number of matches.
+    async fn search_response(&self, request: &SearchRequest) -> SearchResponse {
+        let index = self.index.lock().await;
+
+        let words = &request.words;
+
+        let limit = match request.limit {
+            0 => DEFAULT_SEARCH_LIMIT,
+            limit => limit,
+        };
+
+        let (offset, limit) = (request.offset as usize, limit as usize);
+
+        // Only the pages up to the requested ones are ranked
+        let (pages, total) = match (request.mode(), request.case_sensitive) {
+            (SearchMode::All, false) => index.search_paginated(words, offset, limit),
+            (SearchMode::Any, false) => {
+                index.paginate(index.search_any(words), words, offset, limit)
+            }
+            (SearchMode::All, true) => {
+                index.paginate(index.search_case_sensitive(words), words, offset, limit)
+            }
+            (SearchMode::Any, true) => {
+                index.paginate(index.search_any_case_sensitive(words), words, offset, limit)
+            }
+            (SearchMode::Phrase, _) => {
+                index.paginate(index.search_phrase(words), words, offset, limit)
+            }
+        };
+
+        // The vocabulary is only scanned for corrections when nothing matched
+        let did_you_mean = match total {
+            0 => correct_words(&index, words),
+            _ => vec![],
+        };
+
+        let pages = pages.into_iter().map(|page| page.into()).collect();
+
+        SearchResponse {
+            status: GoogolStatus::Success as i32,
+            pages,
+            total: total as u64,
+            did_you_mean,
         }
     }
 }
 
+/// Corrects the misspelled words of a search, see [`IndexStore::did_you_mean`].
+///
+/// # Returns
+///
+/// The words with the misspelled ones corrected, or none if no word could be corrected.
+fn correct_words(index: &IndexStore, words: &[String]) -> Vec<String> {
+    let mut corrected = false;
+
+    let words = words
+        .iter()
+        .map(|word| match index.did_you_mean(word) {
+            Some(correction) => {
+                corrected = true;
+                correction
+            }
+            None => word.clone(),
+        })
+        .collect();
+
+    if corrected { words } else { vec![] }
+}
+
 /// Implements the `BarrelService` gRPC service trait for the `Barrel` struct.
 ///
 /// This trait is generated by `tonic` based on the `BarrelService` definition
@@ -100,12 +306,16 @@ impl Barrel {
 /// gRPC requests.
 ///
 /// Each method handles a specific RPC call:
+/// - `broadcast_index`: Merges a serialized index into the local one.
 /// - `consult_backlinks`: Retrieves backlinks for a given URL.
 /// - `consult_outlinks`: Retrieves outlinks for a given URL.
 /// - `health`: Checks the health status of the server.
 /// - `index`: Indexes a new page with associated words and outlinks.
+/// - `list_indexed_pages`: Lists the indexed pages, optionally only the stale ones.
 /// - `search`: Searches the index for pages matching given words.
+/// - `search_stream`: Same as `search`, sending the pages in batches.
 /// - `status`: Provides the current status of the Barrel server.
+/// - `suggest`: Suggests the most common indexed words starting with a prefix.
 ///
 /// # Requirements
 ///
@@ -127,12 +337,24 @@ impl Barrel {
 ///     let settings = BarrelConfig {
 ///         address: "127.0.0.1:44992".parse().unwrap(),
 ///         filepath: ".barrel-data.json".to_string(),
+///         stop_words: Default::default(),
+///         stemming: false,
+///         case_sensitive: false,
+///         title_boost: 10.0,
+///         collapse_duplicates: false,
+///         tls: None,
+///         compression: true,
+///         max_message_size: 64 * 1024 * 1024,
+///         save_interval_secs: 5,
+///         save_every_pages: 100,
+///         log_file: None,
+///         log_level: "info".to_string(),
 ///     };
 ///
 ///     let barrel = Barrel::new(&settings).await;
 ///
 ///     // Server::builder()
-///     //     .add_service(BarrelServiceServer::new(barrel))
+///     //     .add_service(Arc::new(barrel).into_service(settings.compression, settings.max_message_size))
 ///     //     .serve(settings.address)
 ///     //     .await?;
 ///
@@ -146,6 +368,56 @@ impl Barrel {
 /// uses internally to allow asynchronous method definitions in traits.
 #[tonic::async_trait]
 impl BarrelService for Barrel {
+    /// Handles a `broadcast_index` gRPC request.
+    ///
+    /// Merges a JSON serialized `IndexStore` into this barrel's index and saves it.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The gRPC request containing the serialized index.
+    ///
+    /// # Returns
+    ///
+    /// A `Response<BroadcastIndexResponse>` indicating whether the index was merged.
+    async fn broadcast_index(
+        &self,
+        request: Request<BroadcastIndexRequest>,
+    ) -> Result<Response<BroadcastIndexResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let request = request.into_inner();
+
+        let other: IndexStore =
+            serde_json::from_str(&request.index_json_serialized).map_err(|e| {
+                error!("Invalid index provided: {}", e);
+                Status::invalid_argument(format!("Invalid index: {}", e))
+            })?;
+
+        let mut index = self.index.lock().await;
+
+        index.merge(other);
+
+        let success = match self.save_index(&mut index) {
+            Ok(_) => true,
+            Err(e) => {
+                error!("Failed to save index: {}", e);
+                false
+            }
+        };
+
+        let status = if success {
+            GoogolStatus::Success as i32
+        } else {
+            GoogolStatus::Error as i32
+        };
+
+        Ok(Response::new(BroadcastIndexResponse {
+            success,
+            status,
+            offline: 0,
+        }))
+    }
+
     /// Handles a `consult_backlinks` gRPC request.
     ///
     /// Retrieves backlinks for the URL specified in the request.
@@ -241,6 +513,7 @@ impl BarrelService for Barrel {
 
         Ok(Response::new(HealthResponse {
             status: format!("OK: Online. Listening at {}...", self.address),
+            ..Default::default()
         }))
     }
 
@@ -267,6 +540,7 @@ impl BarrelService for Barrel {
 
         let page = Page::from(index.page.unwrap());
 
+        let title_words = index.title_words;
         let words = index.words;
 
         let outlinks: Vec<Url> = index
@@ -283,19 +557,90 @@ impl BarrelService for Barrel {
 
         let mut index = self.index.lock().await;
 
-        index.store(&page, &words, &outlinks);
-        index.save().unwrap();
+        index.store(&page, &title_words, &words, &outlinks);
 
-        Ok(Response::new(IndexResponse { size_bytes: 0 }))
+        // Saved in batches, the rest being saved periodically and on shutdown
+        let unsaved = self.unsaved.fetch_add(1, Ordering::Relaxed) + 1;
+        if unsaved >= self.save_every_pages
+            && let Err(e) = self.save_index(&mut index)
+        {
+            error!("Failed to save index: {}", e);
+        }
+
+        Ok(Response::new(IndexResponse {
+            size_bytes: index.size_bytes() as u64,
+        }))
+    }
+
+    /// Handles a `list_indexed_pages` gRPC request.
+    ///
+    /// Lists a page of the indexed pages, sorted by url. If the request sets `indexed_before`,
+    /// only the pages last indexed before then are listed, least recently indexed first, so
+    /// they can be recrawled.
+    ///
+    /// Only the requested pages are cloned while the index is locked, so indexing isn't held
+    /// up by large listings.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The gRPC request containing the optional cutoff time and pagination.
+    ///
+    /// # Returns
+    ///
+    /// A `Response<ListIndexedPagesResponse>` with the listed pages.
+    async fn list_indexed_pages(
+        &self,
+        request: Request<ListIndexedPagesRequest>,
+    ) -> Result<Response<ListIndexedPagesResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let request = request.into_inner();
+
+        let before = match request.indexed_before {
+            Some(timestamp) => Some(DateTime::from_timestamp(timestamp, 0).ok_or_else(|| {
+                Status::invalid_argument(format!("Invalid timestamp: {}", timestamp))
+            })?),
+            None => None,
+        };
+
+        let offset = request.offset as usize;
+        let limit = match request.limit {
+            0 => DEFAULT_LIST_LIMIT,
+            limit => limit,
+        } as usize;
+
+        let pages = {
+            let index = self.index.lock().await;
+
+            match before {
+                Some(before) => index.pages_indexed_before(before, offset, limit),
+                None => index.list_pages(offset, limit),
+            }
+        };
+
+        let pages = pages
+            .into_iter()
+            .map(|page| IndexedPage {
+                url: page.url.to_string(),
+                title: page.title.unwrap_or_default(),
+                timestamp: page.timestamp.timestamp(),
+            })
+            .collect();
+
+        Ok(Response::new(ListIndexedPagesResponse { pages }))
     }
 
     /// Handles a `search` gRPC request.
     ///
-    /// Searches the index for pages matching the provided words.
+    /// Searches the index for pages matching all or any of the provided words, or the words
+    /// as a phrase, depending on the requested `SearchMode`.
+    ///
+    /// Only the page of results selected by `offset` and `limit` is returned, along with the
+    /// total number of matches. Searches without a limit return `DEFAULT_SEARCH_LIMIT` pages.
     ///
     /// # Arguments
     ///
-    /// * `request` - The gRPC request containing search terms.
+    /// * `request` - The gRPC request containing search terms and pagination.
     ///
     /// # Returns
     ///
@@ -308,26 +653,57 @@ impl BarrelService for Barrel {
 
         let request = request.into_inner();
 
-        let index = self.index.lock().await;
+        Ok(Response::new(self.search_response(&request).await))
+    }
 
-        let words = request.words;
+    type SearchStreamStream = BoxStream<'static, Result<SearchResponse, Status>>;
 
-        let pages = index
-            .search_by_relevance(&words)
-            .iter()
-            .cloned()
-            .map(|page| page.into())
-            .collect();
+    /// Handles a `search_stream` gRPC request.
+    ///
+    /// Searches like `search`, but sends the selected pages in batches of `SEARCH_BATCH_SIZE`,
+    /// each along with the status and total number of matches. A search without results
+    /// still sends a single empty batch.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The gRPC request containing search terms and pagination.
+    ///
+    /// # Returns
+    ///
+    /// A `Response` with the stream of `SearchResponse` batches.
+    async fn search_stream(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> Result<Response<Self::SearchStreamStream>, Status> {
+        debug!("{:#?}", request);
 
-        Ok(Response::new(SearchResponse {
-            status: GoogolStatus::Success as i32,
-            pages,
-        }))
+        let request = request.into_inner();
+
+        let response = self.search_response(&request).await;
+
+        let batches: Vec<SearchResponse> = if response.pages.is_empty() {
+            vec![response]
+        } else {
+            response
+                .pages
+                .chunks(SEARCH_BATCH_SIZE)
+                .map(|batch| SearchResponse {
+                    status: response.status,
+                    pages: batch.to_vec(),
+                    total: response.total,
+                    ..Default::default()
+                })
+                .collect()
+        };
+
+        Ok(Response::new(Box::pin(stream::iter(
+            batches.into_iter().map(Ok),
+        ))))
     }
 
     /// Handles a `status` gRPC request.
     ///
-    /// Provides the current status of the Barrel server.
+    /// Provides the current status of the Barrel server, including statistics of its index.
     ///
     /// # Arguments
     ///
@@ -342,8 +718,496 @@ impl BarrelService for Barrel {
     ) -> Result<Response<BarrelStatusResponse>, Status> {
         debug!("{:#?}", request);
 
-        let status = String::default();
+        let stats = self.index.lock().await.stats();
+        debugv!(stats);
+
+        Ok(Response::new(BarrelStatusResponse {
+            status: format!("OK: Online. Listening at {}...", self.address),
+            stats: Some(stats.into()),
+        }))
+    }
+
+    /// Suggests the most common indexed words starting with the requested prefix.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The gRPC request containing the prefix and the maximum number of words.
+    ///
+    /// # Returns
+    ///
+    /// A `Response<SuggestResponse>` with the words, most common first.
+    async fn suggest(
+        &self,
+        request: Request<SuggestRequest>,
+    ) -> Result<Response<SuggestResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let request = request.into_inner();
+
+        let limit = match request.limit {
+            0 => DEFAULT_SUGGEST_LIMIT,
+            limit => limit,
+        } as usize;
+
+        let suggestions = self
+            .index
+            .lock()
+            .await
+            .suggest(&request.prefix, limit)
+            .into_iter()
+            .map(|(word, frequency)| Suggestion {
+                word,
+                document_frequency: frequency as u64,
+            })
+            .collect();
+
+        let status = GoogolStatus::Success as i32;
+
+        Ok(Response::new(SuggestResponse {
+            status,
+            suggestions,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page::PageBuilder;
+    use futures::StreamExt;
+
+    /// Creates a barrel whose index holds `count` pages containing "rust".
+    async fn create_barrel(count: usize) -> Barrel {
+        let barrel = Barrel::default();
+
+        {
+            let mut index = barrel.index.lock().await;
+
+            for i in 0..count {
+                let page = PageBuilder::default()
+                    .url(format!("https://example.com/{}", i).parse().unwrap())
+                    .build()
+                    .unwrap();
+
+                index.store(&page, &[], &["rust"], &[]);
+            }
+        }
+
+        barrel
+    }
+
+    async fn collect_search_stream(barrel: &Barrel, request: SearchRequest) -> Vec<SearchResponse> {
+        barrel
+            .search_stream(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner()
+            .map(Result::unwrap)
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_save() {
+        let filepath =
+            std::env::temp_dir().join(format!("googol-barrel-{}.json", std::process::id()));
+        let config = BarrelConfig {
+            filepath: filepath.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+
+        let barrel = Barrel::new(&config).await;
+        {
+            let page = PageBuilder::default()
+                .url("https://example.com".parse().unwrap())
+                .build()
+                .unwrap();
+            barrel.index.lock().await.store(&page, &[], &["rust"], &[]);
+        }
+
+        assert!(barrel.save().await.unwrap() > 0);
+
+        let index = IndexStore::load(&filepath).unwrap();
+        assert_eq!(index.search(&["rust"]).len(), 1);
+
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    /// Tests that indexed pages are saved once `save_every_pages` of them are unsaved, and that
+    /// `flush` saves whatever is left.
+    #[tokio::test]
+    async fn test_debounced_save() {
+        let filepath = std::env::temp_dir().join(format!(
+            "googol-barrel-debounce-{}.json",
+            std::process::id()
+        ));
+        let config = BarrelConfig {
+            filepath: filepath.to_string_lossy().to_string(),
+            save_every_pages: 2,
+            ..Default::default()
+        };
+        let barrel = Barrel::new(&config).await;
+
+        let index = |i: usize| {
+            barrel.index(Request::new(IndexRequest {
+                index: Some(crate::proto::Index {
+                    page: Some(crate::proto::Page {
+                        url: format!("https://example.com/{}", i),
+                        ..Default::default()
+                    }),
+                    words: vec!["rust".to_string()],
+                    outlinks: vec![],
+                    title_words: vec![],
+                }),
+                depth: 0,
+            }))
+        };
+        let saved_pages = || IndexStore::load(&filepath).unwrap().search(&["rust"]).len();
+
+        index(0).await.unwrap();
+        assert_eq!(saved_pages(), 0);
+
+        index(1).await.unwrap();
+        assert_eq!(saved_pages(), 2);
+
+        index(2).await.unwrap();
+        assert_eq!(saved_pages(), 2);
+
+        barrel.flush().await;
+        assert_eq!(saved_pages(), 3);
+
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    /// Tests that failing to save the index doesn't fail the request.
+    #[tokio::test]
+    async fn test_index_save_error() {
+        let config = BarrelConfig {
+            filepath: "/nonexistent/googol-barrel.json".to_string(),
+            save_every_pages: 1,
+            ..Default::default()
+        };
+        let barrel = Barrel::new(&config).await;
+
+        let request = IndexRequest {
+            index: Some(crate::proto::Index {
+                page: Some(crate::proto::Page {
+                    url: "https://example.com".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            depth: 0,
+        };
+
+        assert!(barrel.index(Request::new(request)).await.is_ok());
+        assert!(barrel.save().await.is_err());
+    }
+
+    /// Tests that searches and link consults are answered from the barrel's `IndexStore`.
+    #[tokio::test]
+    async fn test_search_and_consult() {
+        let barrel = Barrel::default();
+        let rust: Url = "https://rust-lang.org/".parse().unwrap();
+        let crates: Url = "https://crates.io/".parse().unwrap();
+        {
+            let page = PageBuilder::default().url(rust.clone()).build().unwrap();
+            barrel.index.lock().await.store(
+                &page,
+                &[],
+                &["rust", "language"],
+                std::slice::from_ref(&crates),
+            );
+        }
+
+        let response = barrel
+            .search(Request::new(SearchRequest {
+                words: vec!["Rust".to_string()],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let urls: Vec<_> = response
+            .pages
+            .iter()
+            .map(|page| page.url.as_str())
+            .collect();
+        assert_eq!(urls, [rust.as_str()]);
+        assert_eq!(response.total, 1);
+
+        let response = barrel
+            .consult_outlinks(Request::new(OutlinksRequest {
+                url: rust.to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.outlinks, [crates.to_string()]);
+
+        let response = barrel
+            .consult_backlinks(Request::new(BacklinksRequest {
+                url: crates.to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.backlinks, [rust.to_string()]);
+    }
+
+    /// Tests that case-sensitive searches are routed to the case-sensitive index.
+    #[tokio::test]
+    async fn test_search_case_sensitive() {
+        let config = BarrelConfig {
+            filepath: "/nonexistent/googol-barrel.json".to_string(),
+            case_sensitive: true,
+            ..Default::default()
+        };
+        let barrel = Barrel::new(&config).await;
+        {
+            let page = PageBuilder::default()
+                .url("https://rust-lang.org/".parse().unwrap())
+                .build()
+                .unwrap();
+            barrel.index.lock().await.store(&page, &[], &["Rust"], &[]);
+        }
+
+        let barrel = &barrel;
+        let total = |words: &[&str], case_sensitive| {
+            let request = SearchRequest {
+                words: words.iter().map(|word| word.to_string()).collect(),
+                case_sensitive,
+                ..Default::default()
+            };
+
+            async move { barrel.search_response(&request).await.total }
+        };
+
+        assert_eq!(total(&["rust"], false).await, 1);
+        assert_eq!(total(&["rust"], true).await, 0);
+        assert_eq!(total(&["Rust"], true).await, 1);
+    }
+
+    /// Tests that phrase searches only match the words in order.
+    #[tokio::test]
+    async fn test_search_phrase() {
+        let barrel = Barrel::default();
+        {
+            let page = PageBuilder::default()
+                .url("https://rust-lang.org/".parse().unwrap())
+                .build()
+                .unwrap();
+            barrel
+                .index
+                .lock()
+                .await
+                .store(&page, &[], &["rust", "programming", "language"], &[]);
+        }
+
+        let barrel = &barrel;
+        let total = |words: &[&str]| {
+            let request = SearchRequest {
+                words: words.iter().map(|word| word.to_string()).collect(),
+                mode: SearchMode::Phrase as i32,
+                ..Default::default()
+            };
+
+            async move { barrel.search_response(&request).await.total }
+        };
+
+        assert_eq!(total(&["rust", "programming"]).await, 1);
+        assert_eq!(total(&["programming", "rust"]).await, 0);
+        assert_eq!(total(&["rust", "language"]).await, 0);
+    }
+
+    /// Tests that every page is listed by url, and stale pages oldest first.
+    #[tokio::test]
+    async fn test_list_indexed_pages() {
+        let barrel = Barrel::default();
+        let now = chrono::Utc::now();
+        for (url, days) in [
+            ("https://b.com/", 10),
+            ("https://a.com/", 0),
+            ("https://c.com/", 20),
+        ] {
+            let page = PageBuilder::default()
+                .url(url.parse().unwrap())
+                .title("Title")
+                .timestamp(now - chrono::Duration::days(days))
+                .build()
+                .unwrap();
+            barrel.index.lock().await.store(&page, &[], &["rust"], &[]);
+        }
+
+        let barrel = &barrel;
+        let list = |request| async move {
+            barrel
+                .list_indexed_pages(Request::new(request))
+                .await
+                .unwrap()
+                .into_inner()
+                .pages
+        };
+        let urls = |pages: Vec<IndexedPage>| -> Vec<String> {
+            pages.into_iter().map(|page| page.url).collect()
+        };
+
+        let pages = list(ListIndexedPagesRequest::default()).await;
+        assert_eq!(pages[0].title, "Title");
+        assert_eq!(
+            pages[2].timestamp,
+            (now - chrono::Duration::days(20)).timestamp()
+        );
+        assert_eq!(
+            urls(pages),
+            ["https://a.com/", "https://b.com/", "https://c.com/"]
+        );
+
+        let pages = list(ListIndexedPagesRequest {
+            offset: 1,
+            limit: 1,
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(urls(pages), ["https://b.com/"]);
+
+        let pages = list(ListIndexedPagesRequest {
+            indexed_before: Some((now - chrono::Duration::days(1)).timestamp()),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(urls(pages), ["https://c.com/", "https://b.com/"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_did_you_mean() {
+        let barrel = create_barrel(3).await;
+        let barrel = &barrel;
+        let search = |words: &[&str]| {
+            let request = SearchRequest {
+                words: words.iter().map(|word| word.to_string()).collect(),
+                ..Default::default()
+            };
+
+            async move { barrel.search_response(&request).await }
+        };
+
+        let response = search(&["Rsut", "zzz"]).await;
+        assert_eq!(response.total, 0);
+        assert_eq!(response.did_you_mean, ["rust", "zzz"]);
+
+        // Streamed searches carry the correction too
+        let batches = collect_search_stream(
+            barrel,
+            SearchRequest {
+                words: vec![String::from("rsut")],
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(batches[0].did_you_mean, ["rust"]);
+
+        assert!(search(&["rust"]).await.did_you_mean.is_empty());
+        assert!(search(&["zzz"]).await.did_you_mean.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_suggest() {
+        let barrel = create_barrel(3).await;
+        let page = PageBuilder::default()
+            .url("https://example.com/".parse().unwrap())
+            .build()
+            .unwrap();
+        barrel.index.lock().await.store(&page, &[], &["Rusty"], &[]);
+
+        let response = barrel
+            .suggest(Request::new(SuggestRequest {
+                prefix: String::from("RU"),
+                limit: 0,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let suggestions: Vec<_> = response
+            .suggestions
+            .into_iter()
+            .map(|suggestion| (suggestion.word, suggestion.document_frequency))
+            .collect();
+        assert_eq!(
+            suggestions,
+            [(String::from("rust"), 3), (String::from("rusty"), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_stream_batches() {
+        let barrel = create_barrel(SEARCH_BATCH_SIZE * 2 + 1).await;
+
+        let request = SearchRequest {
+            words: vec!["rust".to_string()],
+            ..Default::default()
+        };
+        let batches = collect_search_stream(&barrel, request.clone()).await;
+
+        let sizes: Vec<usize> = batches.iter().map(|batch| batch.pages.len()).collect();
+        assert_eq!(sizes, vec![SEARCH_BATCH_SIZE, SEARCH_BATCH_SIZE, 1]);
+        assert!(
+            batches
+                .iter()
+                .all(|batch| batch.total == (SEARCH_BATCH_SIZE * 2 + 1) as u64)
+        );
+
+        // Batches hold the same pages, in the same order, as the unary search
+        let streamed: Vec<_> = batches.into_iter().flat_map(|batch| batch.pages).collect();
+        let response = barrel.search(Request::new(request)).await.unwrap();
+        assert_eq!(streamed, response.into_inner().pages);
+    }
+
+    #[tokio::test]
+    async fn test_search_limit() {
+        let total = DEFAULT_SEARCH_LIMIT as usize + 10;
+        let barrel = create_barrel(total).await;
+
+        let search = |offset, limit| {
+            barrel.search(Request::new(SearchRequest {
+                words: vec!["rust".to_string()],
+                offset,
+                limit,
+                ..Default::default()
+            }))
+        };
+
+        // Searches without a limit are capped, but still count every match
+        let response = search(0, 0).await.unwrap().into_inner();
+        assert_eq!(response.pages.len(), DEFAULT_SEARCH_LIMIT as usize);
+        assert_eq!(response.total, total as u64);
+
+        let response = search(5, 0).await.unwrap().into_inner();
+        assert_eq!(response.pages.len(), DEFAULT_SEARCH_LIMIT as usize);
+
+        let response = search(total as u32 - 3, 5).await.unwrap().into_inner();
+        assert_eq!(response.pages.len(), 3);
+
+        // An offset past the end yields no pages, but still the total
+        let response = search(total as u32 + 10, 5).await.unwrap().into_inner();
+        assert!(response.pages.is_empty());
+        assert_eq!(response.total, total as u64);
+    }
+
+    #[tokio::test]
+    async fn test_search_stream_no_results() {
+        let barrel = create_barrel(1).await;
+
+        let request = SearchRequest {
+            words: vec!["missing".to_string()],
+            ..Default::default()
+        };
+        let batches = collect_search_stream(&barrel, request).await;
 
-        Ok(Response::new(BarrelStatusResponse { status }))
+        assert_eq!(batches.len(), 1);
+        assert!(batches[0].pages.is_empty());
+        assert_eq!(batches[0].total, 0);
+        assert_eq!(batches[0].status(), crate::proto::Status::Success);
     }
 }
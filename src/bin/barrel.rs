@@ -1,36 +1,89 @@
 use googol::{
     barrel::Barrel,
     debugv,
-    proto::barrel_service_server::BarrelServiceServer,
+    logger::init_logging,
     settings::{GoogolConfig, Load, barrel::BarrelConfig},
 };
 use log::{debug, error, info};
+use std::{future, sync::Arc, time::Duration};
+use tokio::signal;
 use tonic::transport::Server;
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    pretty_env_logger::init();
-
-    let settings = match GoogolConfig::default() {
-        Err(e) => {
-            error!("{:#?}", e);
+/// Resolves once the process is asked to stop, with Ctrl+C or, on Unix, `SIGTERM`.
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl+C: {}", e);
+            future::pending::<()>().await;
+        }
+    };
 
-            BarrelConfig::default()?
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                future::pending::<()>().await;
+            }
         }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
+    }
 
-        Ok(config) => config.barrel,
+    info!("Shutting down, waiting for in-flight requests...");
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (settings, load_error) = match <GoogolConfig as Load>::default() {
+        Err(e) => (<BarrelConfig as Load>::default()?, Some(e)),
+        Ok(config) => (config.barrel, None),
     };
+
+    let _logger = init_logging(settings.log_file.as_deref(), &settings.log_level)?;
+    if let Some(e) = load_error {
+        error!("{:#?}", e);
+    }
     debugv!(settings, debug);
 
-    let barrel = Barrel::new(&settings).await;
+    let barrel = Arc::new(Barrel::new(&settings).await);
     debugv!(barrel, debug);
 
+    Arc::clone(&barrel).spawn_saver(Duration::from_secs(settings.save_interval_secs));
+
+    let mut server = Server::builder();
+    if let Some(tls) = &settings.tls {
+        server = server.tls_config(tls.read()?)?;
+    }
+
     info!("Barrel listening at {}...", barrel.address);
+    info!(
+        "Largest gRPC message size: {} bytes",
+        settings.max_message_size
+    );
 
-    Server::builder()
-        .add_service(BarrelServiceServer::new(barrel))
-        .serve(settings.address)
+    // Requests being handled are completed before this returns
+    server
+        .add_service(
+            Arc::clone(&barrel).into_service(settings.compression, settings.max_message_size),
+        )
+        .serve_with_shutdown(settings.address, shutdown_signal())
         .await?;
 
+    let size_bytes = barrel.save().await?;
+    info!(
+        "Saved index to {} ({} bytes), shut down cleanly",
+        settings.filepath, size_bytes
+    );
+
     Ok(())
 }
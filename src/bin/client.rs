@@ -1,16 +1,30 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use googol::{
-    debugv,
+    debugv, gateway,
     proto::{
-        BacklinksRequest, EnqueueRequest, HealthRequest, OutlinksRequest, RealTimeStatusRequest,
-        SearchRequest, gateway_service_client::GatewayServiceClient,
+        self, AddBarrelRequest, BacklinksRequest, ClearQueueRequest, EnqueueRequest, HealthRequest,
+        IndexRequest, OutlinksRequest, RealTimeStatusRequest, RemoveBarrelRequest, SearchMode,
+        SearchRequest, Status as GoogolStatus, SuggestRequest,
+        gateway_service_client::GatewayServiceClient,
     },
-    settings::{GoogolConfig, Load, client::ClientConfig},
+    settings::{
+        GoogolConfig, Load,
+        client::ClientConfig,
+        tls::{self, TlsClientConfig},
+    },
+};
+use log::{debug, error, info};
+use rand::Rng;
+use serde::Serialize;
+use std::{fmt::Debug, net::SocketAddr, time::Duration};
+use tokio::{
+    io::{self, AsyncBufReadExt, BufReader},
+    time::sleep,
+};
+use tonic::{
+    Code, Request, Status,
+    transport::{Channel, Endpoint},
 };
-use log::{debug, error};
-use std::{net::SocketAddr, time::Duration};
-use tokio::time::sleep;
-use tonic::{Request, Status, transport::Channel};
 use url::Url;
 
 #[derive(Debug, Parser)]
@@ -33,11 +47,48 @@ struct Cli {
     )]
     retries: Option<usize>,
 
+    /// Longest wait between two connection attempts, in seconds
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Longest wait between two connection attempts, in seconds"
+    )]
+    max_delay: Option<u64>,
+
+    /// Format responses are printed in
+    #[arg(long, value_enum, default_value_t = Output::Human, global = true)]
+    output: Output,
+
     /// Subcommands for specific operations
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Output {
+    /// Debug formatted, for people to read
+    Human,
+    /// One JSON document per response, for scripts
+    Json,
+}
+
+impl Output {
+    /// Prints a response in this format. `label` is only shown to humans.
+    fn print<T>(self, label: &str, response: &T)
+    where
+        T: Debug + Serialize,
+    {
+        match self {
+            Output::Human => println!("{}: {:#?}", label, response),
+            Output::Json => match serde_json::to_string(response) {
+                Ok(json) => println!("{}", json),
+                Err(e) => error!("Failed to serialize response: {}", e),
+            },
+        }
+    }
+}
+
 #[derive(Debug, Subcommand)]
 enum Commands {
     /// Enqueue a URL for processing
@@ -46,11 +97,59 @@ enum Commands {
         url: Url,
     },
 
+    /// Index a page directly, without downloading it
+    Index {
+        /// URL of the page
+        url: Url,
+
+        /// Title of the page
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Words of the page, comma-separated
+        #[arg(long, value_delimiter = ',')]
+        words: Vec<String>,
+
+        /// URLs the page links to, comma-separated
+        #[arg(long, value_delimiter = ',')]
+        outlinks: Vec<Url>,
+    },
+
     /// Search for provided words
     Search {
         /// Words to search for
         #[arg(required = true)]
         words: Vec<String>,
+
+        /// Number of results to skip
+        #[arg(long, default_value_t = 0)]
+        offset: u32,
+
+        /// Maximum number of results to return (0 for the default of 50)
+        #[arg(long, default_value_t = 0)]
+        limit: u32,
+
+        /// Match pages containing any of the words instead of all of them
+        #[arg(long, conflicts_with = "phrase")]
+        any: bool,
+
+        /// Match pages containing the words consecutively, in the given order
+        #[arg(long)]
+        phrase: bool,
+
+        /// Match words exactly as written, if the barrels keep a case-sensitive index
+        #[arg(long)]
+        case_sensitive: bool,
+    },
+
+    /// Suggest the most common indexed words starting with a prefix
+    Suggest {
+        /// Start of the words to suggest
+        prefix: String,
+
+        /// Maximum number of words to suggest (0 for the default of 10)
+        #[arg(long, default_value_t = 0)]
+        limit: u32,
     },
 
     /// Consult backlinks or outlinks of a given page
@@ -59,11 +158,34 @@ enum Commands {
         consult_command: ConsultCommand,
     },
 
+    /// Add or remove barrels of the gateway
+    Barrels {
+        #[command(subcommand)]
+        barrels_command: BarrelsCommand,
+    },
+
+    /// Remove URLs pending in the queue
+    Queue {
+        #[command(subcommand)]
+        queue_command: QueueCommand,
+    },
+
     /// Get real-time status of the system
     RealTimeStatus,
 
     /// Perform a health check
     Health,
+
+    /// Read commands from stdin, reusing the connection to the gateway
+    Repl,
+}
+
+/// A command read by the REPL, parsed as the client's arguments without the binary name.
+#[derive(Debug, Parser)]
+#[command(no_binary_name = true, disable_version_flag = true)]
+struct ReplLine {
+    #[command(subcommand)]
+    command: Commands,
 }
 
 #[derive(Debug, Subcommand)]
@@ -79,151 +201,482 @@ enum ConsultCommand {
         /// The URL to check for outlinks
         url: Url,
     },
+
+    /// Get both backlinks and outlinks of the specified URL
+    Both {
+        /// The URL to check for backlinks and outlinks
+        url: Url,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum BarrelsCommand {
+    /// Add a barrel to the gateway
+    Add {
+        /// Address of the barrel
+        address: SocketAddr,
+    },
+
+    /// Remove a barrel from the gateway
+    Remove {
+        /// Address of the barrel
+        address: SocketAddr,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum QueueCommand {
+    /// Remove every pending URL, and forget the URLs seen so far
+    Clear,
+
+    /// Remove a pending URL
+    Remove {
+        /// URL to remove
+        url: Url,
+    },
 }
 
+/// Wait before the first retry, doubled after each failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Wait before retrying after `attempt` failed attempts, the first being 1.
+///
+/// The wait doubles with each attempt up to `max_delay`, and is then shortened by a random
+/// amount of up to half, so clients that failed together don't all retry together.
+fn backoff_delay(attempt: usize, max_delay: Duration) -> Duration {
+    let exponent = u32::try_from(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+    let delay = INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(exponent))
+        .min(max_delay);
+
+    delay.mul_f64(rand::rng().random_range(0.5..=1.0))
+}
+
+/// Connects to the gateway and calls `f` with the connection, retrying with backoff while the
+/// gateway can't be reached.
+///
+/// # Errors
+///
+/// Returns the error of `f` right away unless it is `Code::Unavailable`, as the gateway
+/// answered, or `Code::Unavailable` once `max_retries` attempts failed.
 async fn connect_with_backoff<ClientType, F, Fut>(
     max_retries: usize,
-    address: SocketAddr,
+    max_delay: Duration,
+    endpoint: &Endpoint,
+    compression: bool,
+    max_message_size: usize,
     f: F,
-) -> Result<ClientType, String>
+) -> Result<ClientType, Status>
 where
     F: Fn(usize, GatewayServiceClient<Channel>) -> Fut,
     Fut: Future<Output = Result<ClientType, Status>> + Send,
 {
     let mut attempt = 0;
-    let mut delay = Duration::from_millis(1000);
 
     loop {
-        let address = format!("http://{}", address);
-
-        if let Ok(client) = GatewayServiceClient::connect(address).await {
-            if let Ok(result) = f(attempt, client).await {
-                break Ok(result);
-            }
-        }
+        let error = match gateway::connect(endpoint.clone(), compression, max_message_size).await {
+            Ok(client) => match f(attempt, client).await {
+                Ok(result) => break Ok(result),
+                Err(e) if e.code() == Code::Unavailable => e.message().to_string(),
+                Err(e) => break Err(e),
+            },
+            Err(e) => e.to_string(),
+        };
 
         attempt += 1;
 
         if attempt >= max_retries {
-            break Err(String::from("Failed connecting"));
+            break Err(Status::unavailable(format!(
+                "Failed connecting after {} attempts: {}",
+                attempt, error
+            )));
         }
 
+        let delay = backoff_delay(attempt, max_delay);
         eprintln!(
-            "Connection attempt {}/{} failed, retrying in {:?}...",
-            attempt, max_retries, delay
+            "Connection attempt {}/{} failed ({}), retrying in {:?}...",
+            attempt, max_retries, error, delay
         );
 
         sleep(delay).await;
-
-        delay *= 2;
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    pretty_env_logger::init();
+/// Runs a single command on a connection to the gateway.
+///
+/// # Errors
+///
+/// Returns the `Status` of the first request that failed.
+async fn execute(
+    command: &Commands,
+    mut client: GatewayServiceClient<Channel>,
+    output: Output,
+) -> Result<(), Status> {
+    match command {
+        Commands::Enqueue { url } => {
+            let url = url.to_string();
 
-    let cli = Cli::parse();
-    debugv!(&cli);
+            let request = Request::new(EnqueueRequest { url });
 
-    let settings = match GoogolConfig::default() {
-        Err(e) => {
-            error!("{:#?}", e);
+            let response = client.enqueue_url(request).await?;
+            output.print("Response", &response.into_inner());
+        }
+        Commands::Index {
+            url,
+            title,
+            words,
+            outlinks,
+        } => {
+            let page = proto::Page {
+                url: url.to_string(),
+                title: title.clone().unwrap_or_default(),
+                ..Default::default()
+            };
+
+            // Words are indexed in lowercase, as the downloader does
+            let words = words
+                .iter()
+                .map(|word| word.trim().to_lowercase())
+                .filter(|word| !word.is_empty())
+                .collect();
+            let title_words = title
+                .iter()
+                .flat_map(|title| title.split_whitespace())
+                .map(str::to_lowercase)
+                .collect();
+
+            let request = Request::new(IndexRequest {
+                index: Some(proto::Index {
+                    page: Some(page),
+                    words,
+                    outlinks: outlinks.iter().map(Url::to_string).collect(),
+                    title_words,
+                }),
+                depth: 0,
+            });
+
+            let response = client.index(request).await?;
+
+            output.print("Response", &response.into_inner());
+        }
+        Commands::Search {
+            words,
+            offset,
+            limit,
+            any,
+            phrase,
+            case_sensitive,
+        } => {
+            let words = words.iter().filter(|w| !w.is_empty()).cloned().collect();
+
+            let request = Request::new(SearchRequest {
+                words,
+                offset: *offset,
+                limit: *limit,
+                mode: if *any {
+                    SearchMode::Any as i32
+                } else if *phrase {
+                    SearchMode::Phrase as i32
+                } else {
+                    SearchMode::All as i32
+                },
+                case_sensitive: *case_sensitive,
+            });
+
+            let response = client.search(request).await?.into_inner();
+
+            // Scripts tell the outcomes apart by the status of the response
+            match (output, response.status()) {
+                (Output::Json, _) => output.print("Response", &response),
+                (Output::Human, GoogolStatus::Success) if response.pages.is_empty() => {
+                    println!("No results found");
+                    if !response.did_you_mean.is_empty() {
+                        println!("Did you mean: {}", response.did_you_mean.join(" "));
+                    }
+                }
+                (Output::Human, GoogolStatus::Success) => output.print("Response", &response),
+                (Output::Human, status) => {
+                    eprintln!("Error searching: {}", status.as_str_name());
+                }
+            }
+        }
+        Commands::Suggest { prefix, limit } => {
+            let request = Request::new(SuggestRequest {
+                prefix: prefix.clone(),
+                limit: *limit,
+            });
 
-            ClientConfig::default()?
+            let response = client.suggest(request).await?;
+
+            output.print("Response", &response.into_inner());
         }
+        Commands::Consult { consult_command } => match consult_command {
+            ConsultCommand::Backlinks { url } => {
+                let request = Request::new(BacklinksRequest {
+                    url: url.to_string(),
+                });
 
-        Ok(config) => config.client,
-    };
-    debugv!(settings);
+                let response = client.consult_backlinks(request).await?.into_inner();
 
-    let retries = cli.retries.unwrap_or(settings.max_retries);
-    let address = cli.address.unwrap_or(settings.gateway);
+                output.print(&format!("Backlinks of {}", url), &response);
+            }
+            ConsultCommand::Outlinks { url } => {
+                let request = Request::new(OutlinksRequest {
+                    url: url.to_string(),
+                });
 
-    match &cli.command {
-        Commands::Enqueue { url } => {
-            connect_with_backoff(retries, address, async move |_, mut client| {
-                let url = url.to_string();
+                let response = client.consult_outlinks(request).await?.into_inner();
 
-                let request = Request::new(EnqueueRequest { url });
+                output.print(&format!("Outlinks of {}", url), &response);
+            }
+            ConsultCommand::Both { url } => {
+                let (mut backlinks_client, mut outlinks_client) = (client.clone(), client);
+                let url = url.to_string();
 
-                let response = client.enqueue_url(request).await?;
-                println!("Response: {:#?}", response.into_inner());
+                let (backlinks, outlinks) = tokio::join!(
+                    backlinks_client
+                        .consult_backlinks(Request::new(BacklinksRequest { url: url.clone() })),
+                    outlinks_client
+                        .consult_outlinks(Request::new(OutlinksRequest { url: url.clone() })),
+                );
 
-                Ok(())
-            })
-            .await?;
-        }
-        Commands::Search { words } => {
-            connect_with_backoff(retries, address, async move |_, mut client| {
-                let words = words.iter().filter(|w| !w.is_empty()).cloned().collect();
+                // Retry only if there is nothing to show
+                if let (Err(e), Err(_)) = (&backlinks, &outlinks) {
+                    return Err(e.clone());
+                }
 
-                let request = Request::new(SearchRequest { words });
+                match backlinks {
+                    Ok(response) => {
+                        output.print(&format!("Backlinks of {}", url), &response.into_inner())
+                    }
+                    Err(e) => eprintln!("Failed to get backlinks of {}: {}", url, e.message()),
+                }
+                match outlinks {
+                    Ok(response) => {
+                        output.print(&format!("Outlinks of {}", url), &response.into_inner())
+                    }
+                    Err(e) => eprintln!("Failed to get outlinks of {}: {}", url, e.message()),
+                }
+            }
+        },
+        Commands::Barrels { barrels_command } => match barrels_command {
+            BarrelsCommand::Add { address: barrel } => {
+                let request = Request::new(AddBarrelRequest {
+                    address: barrel.to_string(),
+                });
+                let response = client.add_barrel(request).await?;
+
+                output.print("Add barrel", &response.into_inner());
+            }
+            BarrelsCommand::Remove { address: barrel } => {
+                let request = Request::new(RemoveBarrelRequest {
+                    address: barrel.to_string(),
+                });
+                let response = client.remove_barrel(request).await?;
 
-                let response = client.search(request).await?;
+                output.print("Remove barrel", &response.into_inner());
+            }
+        },
+        Commands::Queue { queue_command } => {
+            let url = match queue_command {
+                QueueCommand::Clear => None,
+                QueueCommand::Remove { url } => Some(url.to_string()),
+            };
 
-                println!("Response: {:#?}", response.into_inner());
+            let request = Request::new(ClearQueueRequest { url });
+            let response = client.clear_queue(request).await?;
 
-                Ok(())
-            })
-            .await?;
+            output.print("Queue", &response.into_inner());
         }
-        Commands::Consult { consult_command } => match consult_command {
-            ConsultCommand::Backlinks { url } => {
-                connect_with_backoff(retries, address, async move |_, mut client| {
-                    let url = url.clone();
+        Commands::RealTimeStatus => {
+            let mut wait_for_update = false;
 
-                    let request = Request::new(BacklinksRequest {
-                        url: url.to_string(),
-                    });
+            loop {
+                let request = Request::new(RealTimeStatusRequest { wait_for_update });
+                let response = client.real_time_status(request).await?;
+                output.print("Status", &response.into_inner());
 
-                    let response = client.consult_backlinks(request).await?.into_inner();
+                // Only the first status is sent right away
+                wait_for_update = true;
+            }
+        }
+        Commands::Health => {
+            let request = Request::new(HealthRequest {});
+            let response = client.health(request).await?;
 
-                    println!("Backlinks of {}: {:#?}", url, response);
+            output.print("Health", &response.into_inner());
+        }
+        Commands::Repl => eprintln!("Already in the REPL"),
+    };
 
-                    Ok(())
-                })
-                .await?;
-            }
-            ConsultCommand::Outlinks { url } => {
-                connect_with_backoff(retries, address, async move |_, mut client| {
-                    let url = url.clone();
+    Ok(())
+}
 
-                    let request = Request::new(OutlinksRequest {
-                        url: url.to_string(),
-                    });
+/// Reads commands from stdin, one per line, until `quit` or the end of input (Ctrl-D).
+///
+/// Commands take the same arguments as the client's subcommands, quoted as in a shell. The
+/// connection to the gateway is reused between commands, and only opened again once dropped,
+/// in which case the command is retried.
+async fn repl(
+    max_retries: usize,
+    max_delay: Duration,
+    endpoint: &Endpoint,
+    compression: bool,
+    max_message_size: usize,
+    output: Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let connect = || {
+        connect_with_backoff(
+            max_retries,
+            max_delay,
+            endpoint,
+            compression,
+            max_message_size,
+            async |_, client| Ok(client),
+        )
+    };
 
-                    let response = client.consult_outlinks(request).await?.into_inner();
+    let mut client = connect().await.map_err(|e| e.message().to_string())?;
+    let mut lines = BufReader::new(io::stdin()).lines();
 
-                    println!("Outlinks of {}: {:#?}", url, response);
+    loop {
+        eprint!("googol> ");
+
+        let Some(line) = lines.next_line().await? else {
+            // End of input
+            eprintln!();
+            break;
+        };
+
+        let Some(args) = shlex::split(&line) else {
+            eprintln!("Unbalanced quotes");
+            continue;
+        };
+
+        match args.first().map(String::as_str) {
+            None => continue,
+            Some("quit" | "exit") => break,
+            Some(_) => {}
+        }
 
-                    Ok(())
-                })
-                .await?;
+        let command = match ReplLine::try_parse_from(args) {
+            Ok(line) => line.command,
+            Err(e) => {
+                // Also prints help, if requested
+                let _ = e.print();
+                continue;
             }
-        },
-        Commands::RealTimeStatus => {
-            connect_with_backoff(retries, address, async move |_, mut client| {
-                loop {
-                    let request = Request::new(RealTimeStatusRequest {});
-                    let response = client.real_time_status(request).await?;
-                    println!("Status: {:#?}", response.into_inner());
+        };
+
+        let result = match execute(&command, client.clone(), output).await {
+            Err(e) if e.code() == Code::Unavailable => {
+                eprintln!("Connection lost, reconnecting...");
+
+                match connect().await {
+                    Ok(reconnected) => {
+                        client = reconnected;
+                        execute(&command, client.clone(), output).await
+                    }
+                    Err(_) => Err(e),
                 }
-            })
-            .await?;
+            }
+            result => result,
+        };
+
+        if let Err(e) = result {
+            eprintln!("Error: {}", e.message());
         }
-        Commands::Health => {
-            connect_with_backoff(retries, address, async move |_, mut client| {
-                let request = Request::new(HealthRequest {});
-                let response = client.health(request).await?;
+    }
+
+    Ok(())
+}
 
-                println!("Health: {:?}", response.into_inner());
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    pretty_env_logger::init();
+
+    let cli = Cli::parse();
+    debugv!(&cli);
+
+    let settings = match <GoogolConfig as Load>::default() {
+        Err(e) => {
+            error!("{:#?}", e);
 
-                Ok(())
-            })
-            .await?;
+            <ClientConfig as Load>::default()?
         }
+
+        Ok(config) => config.client,
     };
+    debugv!(settings);
+
+    let retries = cli.retries.unwrap_or(settings.max_retries);
+    let max_delay = Duration::from_secs(cli.max_delay.unwrap_or(settings.max_backoff_secs));
+    let address = cli.address.unwrap_or(settings.gateway);
+    let gateway_tls = settings
+        .gateway_tls
+        .as_ref()
+        .map(TlsClientConfig::read)
+        .transpose()?;
+    let endpoint = tls::endpoint(address, gateway_tls.as_ref())?;
+    info!(
+        "Largest gRPC message size: {} bytes",
+        settings.max_message_size
+    );
+
+    let (compression, max_message_size) = (settings.compression, settings.max_message_size);
+    match &cli.command {
+        Commands::Repl => {
+            repl(
+                retries,
+                max_delay,
+                &endpoint,
+                compression,
+                max_message_size,
+                cli.output,
+            )
+            .await?
+        }
+        command => connect_with_backoff(
+            retries,
+            max_delay,
+            &endpoint,
+            compression,
+            max_message_size,
+            |_, client| execute(command, client, cli.output),
+        )
+        .await
+        .map_err(|e| e.message().to_string())?,
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay() {
+        let max_delay = Duration::from_secs(30);
+
+        for _ in 0..100 {
+            // Doubles from the initial wait, shortened by up to half
+            let delay = backoff_delay(1, max_delay);
+            assert!(delay >= INITIAL_BACKOFF / 2 && delay <= INITIAL_BACKOFF);
+
+            let delay = backoff_delay(3, max_delay);
+            assert!(delay >= INITIAL_BACKOFF * 2 && delay <= INITIAL_BACKOFF * 4);
+
+            // Capped, even after absurdly many attempts
+            for attempt in [10, 64, usize::MAX] {
+                let delay = backoff_delay(attempt, max_delay);
+                assert!(delay >= max_delay / 2 && delay <= max_delay);
+            }
+        }
+
+        // Clients retrying together are spread apart
+        let delays: std::collections::HashSet<_> =
+            (0..100).map(|_| backoff_delay(10, max_delay)).collect();
+        assert!(delays.len() > 1);
+    }
+}
@@ -1,126 +1,315 @@
+use base64::{Engine, engine::general_purpose};
+use clap::Parser;
+use config::ConfigError;
 use googol::{
     debugv,
     fishfish::{FishFish, domain::category::FishDomainCategory},
+    gateway,
+    host_limiter::HostLimiter,
+    logger::init_logging,
+    page::{PageBuilder, content_hash},
     proto::{
-        self, DequeueRequest, Index, IndexRequest, gateway_service_client::GatewayServiceClient,
+        self, DequeueBatchRequest, DequeueResponse, Index, IndexRequest, RequeueUrlsRequest,
+        SkipUrlRequest, gateway_service_client::GatewayServiceClient,
+    },
+    robots::RobotsCache,
+    settings::{
+        GoogolConfig, Load,
+        downloader::DownloaderConfig,
+        tls::{self, TlsClientConfig},
+        watch::FileWatcher,
     },
-    settings::{GoogolConfig, Load, downloader::DownloaderConfig},
 };
 use log::{debug, error, info, warn};
+use reqwest::{StatusCode, header::CONTENT_TYPE, redirect::Policy};
 use scraper::{Html, Selector};
-use std::{collections::HashSet, sync::Arc, time::Duration};
-use tokio::{sync::RwLock, task::JoinSet, time::sleep};
-use tonic::Request;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{sync::watch, task::JoinSet, time::sleep};
+use tonic::{
+    Request,
+    transport::{Channel, Endpoint},
+};
 use url::Url;
 
 const MIN_BACKOFF: Duration = Duration::from_secs(1);
 const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Number of times dequeued urls that weren't crawled are sent back to the gateway.
+const REQUEUE_ATTEMPTS: usize = 5;
+/// Maximum time spent fetching a favicon.
+const FAVICON_TIMEOUT: Duration = Duration::from_secs(3);
+/// Maximum number of characters of body text used as a summary.
+const SUMMARY_MAX_CHARS: usize = 200;
+
+#[derive(Debug, Parser)]
+struct Cli {
+    /// Add or remove workers whenever `threads` changes in this configuration file.
+    #[arg(long, value_name = "FILE")]
+    watch_config: Option<PathBuf>,
+}
 
 #[derive(Debug, Clone)]
 struct HtmlInfo {
     url: Url,
-    words: HashSet<String>,
+    words: Vec<String>,
+    title_words: HashSet<String>,
     outlinks: HashSet<Url>,
     title: Option<String>,
+    summary: Option<String>,
+    /// Icon linked by the page, fetched after the page itself.
+    favicon_url: Option<Url>,
     icon: Option<String>,
     category: Option<FishDomainCategory>,
+    language: Option<String>,
+    content_hash: Option<String>,
+}
+
+/// Whether the `Content-Type` header value is an HTML media type.
+fn is_html_content_type(content_type: &str) -> bool {
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    matches!(media_type.as_str(), "text/html" | "application/xhtml+xml")
+}
+
+/// Whether the first bytes of a body look like an HTML document.
+fn looks_like_html(body: &[u8]) -> bool {
+    let head = &body[..body.len().min(1024)];
+
+    // Binary content
+    if head.contains(&0) {
+        return false;
+    }
+
+    let head = String::from_utf8_lossy(head).to_lowercase();
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+
+    head.starts_with('<')
+        && ["<!doctype html", "<html", "<head", "<body"]
+            .iter()
+            .any(|tag| head.contains(tag))
+}
+
+/// Extracts a summary of the document at `url`: its meta description, its Open Graph
+/// description, or else the beginning of its body `text`.
+fn extract_summary(document: &Html, url: &Url, text: &str) -> Option<String> {
+    let meta_selector = Selector::parse(
+        r#"meta[name="description"], meta[name="Description"], meta[property="og:description"]"#,
+    )
+    .unwrap();
+
+    let metas: Vec<_> = document.select(&meta_selector).collect();
+
+    // The meta description takes precedence over the Open Graph one
+    let description = metas
+        .iter()
+        .filter(|meta| meta.value().attr("property").is_none())
+        .chain(metas.iter())
+        .filter_map(|meta| meta.value().attr("content"))
+        .map(|content| content.split_whitespace().collect::<Vec<_>>().join(" "))
+        .find(|content| !content.is_empty());
+
+    if description.is_some() {
+        return description;
+    }
+
+    PageBuilder::default()
+        .url(url.clone())
+        .summarize_from(text, SUMMARY_MAX_CHARS)
+        .build()
+        .ok()?
+        .summary
+}
+
+/// Text of the document's body, empty if it has none.
+fn body_text(document: &Html) -> String {
+    let body_selector = Selector::parse("body").unwrap();
+
+    match document.select(&body_selector).next() {
+        Some(body) => body.text().collect::<Vec<_>>().join(" "),
+        None => String::new(),
+    }
+}
+
+/// Splits text into lowercase words, leaving out stop words and those with symbols.
+///
+/// Words are kept in order, repeated ones included, so that phrases can be searched.
+fn extract_words(text: &str, stop_words: &HashSet<String>) -> Vec<String> {
+    text.split_whitespace()
+        .map(|w| w.to_lowercase())
+        .filter(|w| !w.is_empty())
+        .filter(|w| !stop_words.contains(w.as_str()))
+        .filter(|w| w.chars().all(|c| c.is_alphanumeric()))
+        .collect()
+}
+
+/// Detects the language of a document as an ISO 639-1 code, from its `<html lang>` attribute
+/// or else guessed from `text`.
+///
+/// Returns `None` if the attribute is unusable and the guess isn't reliable.
+fn detect_language(document: &Html, text: &str) -> Option<String> {
+    let html_selector = Selector::parse("html[lang]").unwrap();
+    let declared = document
+        .select(&html_selector)
+        .next()
+        .and_then(|html| html.value().attr("lang"))
+        // Only the primary subtag, e.g. `pt` of `pt-BR`
+        .and_then(|lang| lang.split(['-', '_']).next())
+        .map(|lang| lang.trim().to_lowercase())
+        .and_then(|lang| match lang.len() {
+            2 if lang.chars().all(|c| c.is_ascii_alphabetic()) => Some(lang),
+            3 => isolang::Language::from_639_3(&lang)?
+                .to_639_1()
+                .map(String::from),
+            _ => None,
+        });
+
+    declared.or_else(|| {
+        let info = whatlang::detect(text).filter(|info| info.is_reliable())?;
+
+        isolang::Language::from_639_3(info.lang().code())?
+            .to_639_1()
+            .map(String::from)
+    })
 }
 
 impl HtmlInfo {
-    pub async fn new(url_str: &str, stop_words: &HashSet<String>) -> Result<Self, HtmlError> {
+    pub async fn new(
+        client: &reqwest::Client,
+        url_str: &str,
+        settings: &DownloaderConfig,
+    ) -> Result<Self, HtmlError> {
         // Parse the URL
         let url = Url::parse(url_str).map_err(|_| HtmlError::InvalidUrl)?;
 
         // Fetch the webpage asynchronously
-        let response = reqwest::get(url.as_str()).await?;
-        let body = response.text().await?;
-
-        // Parse HTML
-        let document = Html::parse_document(&body);
-
-        // Extract title
-        let title_selector = Selector::parse("title").unwrap();
-        let title = document
-            .select(&title_selector)
-            .next()
-            .map(|t| t.inner_html());
-
-        // Extract all words
-        let body_selector = Selector::parse("body").unwrap();
-        let words: HashSet<String> = match document.select(&body_selector).next() {
-            Some(body) => body
-                .text()
-                .collect::<Vec<_>>()
-                .join(" ")
-                .split_whitespace()
-                .map(|w| w.to_lowercase())
-                .filter(|w| !w.is_empty())
-                .filter(|w| !stop_words.contains(w.as_str()))
-                .filter(|w| w.chars().all(|c| c.is_alphanumeric()))
-                .collect(),
-            None => HashSet::new(),
-        };
+        let response = client.get(url.as_str()).send().await?;
 
-        // Extract all outlinks
-        let link_selector = Selector::parse("a").unwrap();
-        let outlinks: HashSet<Url> = document
-            .select(&link_selector)
-            .filter_map(|element| element.value().attr("href"))
-            .filter_map(|href| match url.join(href) {
-                Ok(outlink) => Some(outlink),
-                Err(e) => {
-                    error!("Error invalid join url: {}/{}: {}", url, href, e);
+        if !response.status().is_success() {
+            return Err(HtmlError::HttpStatus(response.status()));
+        }
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .map(|value| value.to_str().unwrap_or_default().to_string());
 
-                    None
+        let body = match content_type {
+            Some(content_type) if is_html_content_type(&content_type) => response.text().await?,
+            Some(content_type) => return Err(HtmlError::UnsupportedContentType(content_type)),
+            // Without a header, guess from the body itself
+            None => {
+                let bytes = response.bytes().await?;
+
+                if !looks_like_html(&bytes) {
+                    return Err(HtmlError::UnsupportedContentType(String::from("unknown")));
                 }
-            })
-            .collect();
-
-        // Extract favicon URL
-        let favicon_selector =
-            Selector::parse(r#"link[rel="icon"], link[rel="shortcut icon"]"#).unwrap();
-        let favicon_url = document
-            .select(&favicon_selector)
-            .next()
-            .and_then(|link| link.value().attr("href"))
-            .and_then(|href| url.join(href).ok());
-        debug!("favicon_url = {:#?}", favicon_url);
-
-        let icon = None;
-        //let icon = match favicon_url {
-        //    // Fetch favicon bytes
-        //    Some(favicon_url) => match reqwest::get(favicon_url.as_str()).await {
-        //        Ok(resp) => match resp.bytes().await {
-        //            Ok(bytes) => Some(general_purpose::STANDARD.encode(bytes)),
-        //            Err(_) => None,
-        //        },
-        //        Err(_) => None,
-        //    },
-        //    None => None,
-        //};
+
+                String::from_utf8_lossy(&bytes).into_owned()
+            }
+        };
+
+        // The parsed document can't be held across an await, so it only lives in this scope
+        let (title, summary, words, title_words, outlinks, favicon_url, language, content_hash) = {
+            // Parse HTML
+            let document = Html::parse_document(&body);
+
+            // Extract title
+            let title_selector = Selector::parse("title").unwrap();
+            let title_element = document.select(&title_selector).next();
+            let title = title_element.map(|t| t.inner_html());
+            let title_text = title_element
+                .map(|t| t.text().collect::<Vec<_>>().join(" "))
+                .unwrap_or_default();
+
+            let text = body_text(&document);
+            let summary = extract_summary(&document, &url, &text);
+
+            let language = detect_language(&document, &text);
+            let content_hash = content_hash(&text);
+            let stop_words = settings.stop_words_for(language.as_deref());
+
+            // Extract all words
+            let words = extract_words(&text, stop_words);
+            let title_words = extract_words(&title_text, stop_words).into_iter().collect();
+
+            // Extract all outlinks
+            let link_selector = Selector::parse("a").unwrap();
+            let outlinks: HashSet<Url> = document
+                .select(&link_selector)
+                .filter_map(|element| element.value().attr("href"))
+                .filter_map(|href| match url.join(href) {
+                    Ok(outlink) => Some(outlink),
+                    Err(e) => {
+                        error!("Error invalid join url: {}/{}: {}", url, href, e);
+
+                        None
+                    }
+                })
+                .collect();
+
+            // Extract favicon URL
+            let favicon_selector =
+                Selector::parse(r#"link[rel="icon"], link[rel="shortcut icon"]"#).unwrap();
+            let favicon_url = document
+                .select(&favicon_selector)
+                .next()
+                .and_then(|link| link.value().attr("href"))
+                .and_then(|href| url.join(href).ok());
+            debug!("favicon_url = {:#?}", favicon_url);
+
+            (
+                title,
+                summary,
+                words,
+                title_words,
+                outlinks,
+                favicon_url,
+                language,
+                content_hash,
+            )
+        };
 
         Ok(Self {
             url,
             words,
+            title_words,
             outlinks,
             title,
-            icon,
+            summary,
+            favicon_url,
+            icon: None,
             category: None,
+            language,
+            content_hash,
         })
     }
 }
 
 impl From<HtmlInfo> for proto::Page {
+    /// Converts the crawled page into its protocol buffer representation, like
+    /// [`googol::page::Page`] does.
     fn from(val: HtmlInfo) -> Self {
         proto::Page {
             url: val.url.to_string(),
             title: val.title.unwrap_or_default(),
-            summary: String::from(""),
+            summary: val.summary.unwrap_or_default(),
             icon: val.icon.unwrap_or_default(),
-            category: val
-                .category
-                .unwrap_or(FishDomainCategory::Unknown)
-                .to_string(),
+            category: match val.category {
+                Some(fish_category) => fish_category.to_string(),
+                None => "".to_string(),
+            },
+            rank: None,
+            language: val.language.unwrap_or_default(),
+            content_hash: val.content_hash.unwrap_or_default(),
         }
     }
 }
@@ -129,14 +318,41 @@ impl From<HtmlInfo> for proto::Page {
 #[allow(dead_code)]
 enum HtmlError {
     InvalidUrl,
+    Timeout,
+    HttpStatus(StatusCode),
+    UnsupportedContentType(String),
     ReqwestError(reqwest::Error),
     UrlParseError(url::ParseError),
     MissingTitle,
 }
 
+impl HtmlError {
+    /// Whether fetching the page again later may succeed.
+    ///
+    /// Transient errors are worth backing off on, while permanent ones (e.g. an invalid url
+    /// or a 404) only concern the page itself.
+    fn is_transient(&self) -> bool {
+        match self {
+            HtmlError::Timeout => true,
+            HtmlError::HttpStatus(status) => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            HtmlError::ReqwestError(e) => e.is_connect() || e.is_timeout() || e.is_request(),
+            HtmlError::InvalidUrl
+            | HtmlError::UnsupportedContentType(_)
+            | HtmlError::UrlParseError(_)
+            | HtmlError::MissingTitle => false,
+        }
+    }
+}
+
 impl From<reqwest::Error> for HtmlError {
     fn from(err: reqwest::Error) -> Self {
-        HtmlError::ReqwestError(err)
+        if err.is_timeout() {
+            HtmlError::Timeout
+        } else {
+            HtmlError::ReqwestError(err)
+        }
     }
 }
 
@@ -146,109 +362,655 @@ impl From<url::ParseError> for HtmlError {
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    pretty_env_logger::init();
+/// State shared by the downloader tasks.
+struct Crawler {
+    settings: DownloaderConfig,
+    fishfish: FishFish,
+    robots: RobotsCache,
+    host_limiter: HostLimiter,
+    http_client: reqwest::Client,
+}
+
+impl Crawler {
+    /// Fetches the favicon as a base64 data URI, from `favicon_url` or else `/favicon.ico` at
+    /// the host root. Like pages, favicons are fetched within the limits of their host.
+    async fn fetch_icon(&self, favicon_url: Option<Url>, url: &Url) -> Option<String> {
+        let max_crawl_delay = Duration::from_secs(self.settings.max_crawl_delay_secs);
+        let candidates = favicon_url.into_iter().chain(url.join("/favicon.ico").ok());
+
+        for candidate in candidates {
+            let crawl_delay = self
+                .robots
+                .crawl_delay(&candidate)
+                .await
+                .map(|crawl_delay| crawl_delay.min(max_crawl_delay));
+            let _permit = self.host_limiter.acquire(&candidate, crawl_delay).await;
+
+            let request = self
+                .http_client
+                .get(candidate.as_str())
+                .timeout(FAVICON_TIMEOUT);
+
+            let response = match request.send().await {
+                Ok(response) if response.status().is_success() => response,
+                Ok(response) => {
+                    debug!("No favicon at {}: {}", candidate, response.status());
+                    continue;
+                }
+                Err(e) => {
+                    debug!("Failed to fetch favicon {}: {}", candidate, e);
+                    continue;
+                }
+            };
+
+            let content_type = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or("image/x-icon")
+                .to_string();
+
+            match response.bytes().await {
+                Ok(bytes) if !bytes.is_empty() => {
+                    return Some(format!(
+                        "data:{};base64,{}",
+                        content_type,
+                        general_purpose::STANDARD.encode(bytes)
+                    ));
+                }
+                Ok(_) => debug!("Empty favicon at {}", candidate),
+                Err(e) => debug!("Failed to read favicon {}: {}", candidate, e),
+            }
+        }
+
+        None
+    }
+
+    /// Fetches a dequeued url and indexes it through the gateway, or reports it as skipped.
+    ///
+    /// # Returns
+    /// `false` if the failure may affect other urls too, so the task should back off.
+    async fn crawl(
+        &self,
+        task_id: usize,
+        client: &mut GatewayServiceClient<Channel>,
+        url: String,
+        depth: u32,
+    ) -> bool {
+        let max_crawl_delay = Duration::from_secs(self.settings.max_crawl_delay_secs);
+
+        // Invalid urls are reported by `HtmlInfo::new`
+        let skip_reason = match Url::parse(&url) {
+            Ok(url) if !self.robots.is_allowed(&url).await => {
+                Some(String::from("Disallowed by robots.txt"))
+            }
+            Ok(url) => match self.robots.crawl_delay(&url).await {
+                Some(crawl_delay) if crawl_delay > max_crawl_delay => Some(format!(
+                    "Crawl-delay of {:?} exceeds the maximum of {:?}",
+                    crawl_delay, max_crawl_delay
+                )),
+                _ => None,
+            },
+            Err(_) => None,
+        };
+
+        if let Some(reason) = skip_reason {
+            info!("[task-{}] Skipping {}: {}", task_id, url, reason);
+
+            let request = Request::new(SkipUrlRequest { url, reason });
+
+            if let Err(e) = client.skip_url(request).await {
+                error!("[task-{}] Failing reporting skipped url: {}", task_id, e);
+            }
 
-    let fishfish = Arc::new(RwLock::new(FishFish::new()));
+            true
+        } else {
+            // Only wait on this url's host, other tasks keep fetching
+            let permit = match Url::parse(&url) {
+                Ok(url) => {
+                    // Clamped in case robots.txt was refetched since checked
+                    let crawl_delay = self
+                        .robots
+                        .crawl_delay(&url)
+                        .await
+                        .map(|crawl_delay| crawl_delay.min(max_crawl_delay));
+
+                    Some(self.host_limiter.acquire(&url, crawl_delay).await)
+                }
+                Err(_) => None,
+            };
+
+            let html_info = HtmlInfo::new(&self.http_client, &url, &self.settings).await;
+            drop(permit);
+
+            match html_info {
+                Ok(mut html_info) => {
+                    debug!("html_info = {:#?}", html_info);
+
+                    // Only once the page's permit is released, as it may share its host
+                    html_info.icon = self
+                        .fetch_icon(html_info.favicon_url.clone(), &html_info.url)
+                        .await;
+
+                    // Looked up before the conversion, so the indexed page carries it
+                    html_info.category = {
+                        if let Some(host) = html_info.url.host() {
+                            let host = host.to_owned();
+                            Some(self.fishfish.domain_category(&host).await)
+                        } else {
+                            None
+                        }
+                    };
+                    debugv!(html_info.category);
+
+                    let page = Some(html_info.clone().into());
+
+                    let words = html_info.words.clone();
+                    let title_words: Vec<String> = html_info.title_words.iter().cloned().collect();
+                    let outlinks: Vec<String> = html_info
+                        .outlinks
+                        .iter()
+                        .map(|outlink| outlink.to_string())
+                        .collect();
+
+                    let index = Some(Index {
+                        page,
+                        words,
+                        outlinks,
+                        title_words,
+                    });
+                    debug!("index = {:#?}", index);
+
+                    client
+                        .index(Request::new(IndexRequest { index, depth }))
+                        .await
+                        .unwrap();
+
+                    true
+                }
+                // Only back off if the failure isn't specific to this page
+                Err(e) if e.is_transient() => {
+                    error!("[task-{}] Failing fetching {}: {:?}", task_id, url, e);
+                    false
+                }
+                Err(e) => {
+                    warn!("[task-{}] Skipping {}: {:?}", task_id, url, e);
 
-    let settings = match GoogolConfig::default() {
-        Err(e) => {
-            error!("{:#?}", e);
+                    let request = Request::new(SkipUrlRequest {
+                        url,
+                        reason: format!("{:?}", e),
+                    });
 
-            DownloaderConfig::default()?
+                    if let Err(e) = client.skip_url(request).await {
+                        error!("[task-{}] Failing reporting skipped url: {}", task_id, e);
+                    }
+
+                    true
+                }
+            }
         }
+    }
+}
 
-        Ok(config) => config.downloader,
-    };
-    debugv!(settings, debug);
+/// Worker tasks crawling through the gateway, which can be added or removed while running.
+struct Workers {
+    join_set: JoinSet<()>,
+    /// Stop signal of each running worker, along with its task id, oldest first.
+    running: Vec<(usize, watch::Sender<bool>)>,
+    /// Task id of the last spawned worker.
+    last_task_id: usize,
+    endpoint: Endpoint,
+    crawler: Arc<Crawler>,
+}
 
-    let gateway_address = format!("http://{}", settings.gateway);
+impl Workers {
+    fn new(endpoint: Endpoint, crawler: Arc<Crawler>) -> Self {
+        Self {
+            join_set: JoinSet::new(),
+            running: vec![],
+            last_task_id: 0,
+            endpoint,
+            crawler,
+        }
+    }
 
-    info!("Connecting to gateway: {}...", &gateway_address);
+    /// Spawns or stops workers until `count` of them are running.
+    ///
+    /// Stopped workers finish the page they are crawling, and give the rest of their batch
+    /// back to the gateway before exiting.
+    fn resize(&mut self, count: usize) {
+        // Forget the workers that already exited
+        while self.join_set.try_join_next().is_some() {}
 
-    let mut join_set = JoinSet::new();
+        if count != self.running.len() {
+            info!("Resizing workers from {} to {}", self.running.len(), count);
+        }
 
-    for task_id in 1..=settings.threads {
-        let address = gateway_address.clone();
-        let stop_words = settings.stop_words.clone();
-        let fishfish = Arc::clone(&fishfish);
+        while self.running.len() < count {
+            self.last_task_id += 1;
+            let (stop, stopped) = watch::channel(false);
+
+            self.join_set.spawn(work(
+                self.last_task_id,
+                self.endpoint.clone(),
+                Arc::clone(&self.crawler),
+                stopped,
+            ));
+            self.running.push((self.last_task_id, stop));
+        }
+
+        // The newest workers are stopped first
+        while self.running.len() > count {
+            if let Some((task_id, stop)) = self.running.pop() {
+                info!("[task-{}] Stopping...", task_id);
+                stop.send_replace(true);
+            }
+        }
+    }
 
-        join_set.spawn(async move {
-            let mut interval = MIN_BACKOFF;
-            loop {
-                let success = match GatewayServiceClient::connect(address.clone()).await {
+    /// Waits for every worker to exit.
+    async fn join(self) {
+        self.join_set.join_all().await;
+    }
+}
+
+/// Dequeues batches of urls from the gateway and crawls them, until told to stop.
+///
+/// # Arguments
+/// * `task_id` - Id of the worker in logs.
+/// * `endpoint` - The gateway to crawl through.
+/// * `crawler` - State shared by the workers.
+/// * `stop` - Set to `true` when the worker should exit, after its current page.
+async fn work(
+    task_id: usize,
+    endpoint: Endpoint,
+    crawler: Arc<Crawler>,
+    mut stop: watch::Receiver<bool>,
+) {
+    let compression = crawler.settings.compression;
+    let max_message_size = crawler.settings.max_message_size;
+    let batch_size = crawler.settings.batch_size;
+
+    let mut interval = MIN_BACKOFF;
+    while !*stop.borrow() {
+        let success = match gateway::connect(endpoint.clone(), compression, max_message_size).await
+        {
+            Err(e) => {
+                error!(
+                    "[task-{}] Error connecting to {}: {}",
+                    task_id,
+                    endpoint.uri(),
+                    e
+                );
+                false
+            }
+            Ok(mut client) => {
+                // Not interrupted when stopping, so dequeued urls always reach the worker
+                let request = Request::new(DequeueBatchRequest { count: batch_size });
+
+                match client.dequeue_batch(request).await {
                     Err(e) => {
-                        error!("[task-{}] Error connecting to {}: {}", task_id, address, e);
+                        error!("[task-{}] Failing dequeuing urls: {}", task_id, e);
                         false
                     }
-                    Ok(mut client) => {
-                        let request = Request::new(DequeueRequest {});
-
-                        match client.dequeue_url(request).await {
-                            Err(e) => {
-                                error!("[task-{}] Failing dequeuing url: {}", task_id, e);
-                                false
-                            }
-                            Ok(response) => {
-                                info!("[task-{}] RESPONSE = {:#?}", task_id, response);
-
-                                let response = response.into_inner();
-
-                                match HtmlInfo::new(&response.url, &stop_words).await {
-                                    Ok(mut html_info) => {
-                                        debug!("html_info = {:#?}", html_info);
-
-                                        let page = Some(html_info.clone().into());
-
-                                        let words: Vec<String> = html_info.words.iter().cloned().collect();
-                                        let outlinks: Vec<String> = html_info.outlinks.iter().cloned().map(|outlink| outlink.to_string()).collect();
-
-                                        let index = Some(Index { page, words, outlinks });
-                                        debug!("index = {:#?}", index);
-
-                                       html_info.category = {
-                                            let mut fishfish = fishfish.write().await;
-                                            if let Some(host) = html_info.url.host() {
-                                                let host = host.to_owned();
-                                                Some(fishfish.domain_category(&host).await)
-                                            } else {
-                                                None
-                                            }
-                                        };
-                                        debugv!(html_info.category);
-
-                                        client
-                                            .index(Request::new(IndexRequest { index }))
-                                            .await
-                                            .unwrap();
-
-                                        true
-                                    },
-                                    Err(_) => todo!(),
-                                }
-                            }
+                    Ok(response) => {
+                        info!("[task-{}] RESPONSE = {:#?}", task_id, response);
+
+                        // Every url is crawled or requeued, as they are no longer in the queue
+                        let mut urls = response.into_inner().urls.into_iter();
+                        let mut success = true;
+                        while !*stop.borrow()
+                            && let Some(entry) = urls.next()
+                        {
+                            success &= crawler
+                                .crawl(task_id, &mut client, entry.url, entry.depth)
+                                .await;
                         }
+
+                        requeue(task_id, &mut client, urls.collect()).await;
+
+                        success
                     }
-                };
-
-                if success {
-                    interval = MIN_BACKOFF;
-                } else {
-                    interval = (interval * 2).min(MAX_BACKOFF).max(MIN_BACKOFF);
-                    warn!(
-                        "[task{}] Failing connecting to gateway {}. Trying connecting in {} seconds...",
-                        task_id,
-                        address,
-                        interval.as_secs()
-                    );
-                    sleep(interval).await;
                 }
             }
-        });
+        };
+
+        if success {
+            interval = MIN_BACKOFF;
+        } else {
+            interval = (interval * 2).min(MAX_BACKOFF).max(MIN_BACKOFF);
+            warn!(
+                "[task{}] Failing crawling through gateway {}. Trying again in {} seconds...",
+                task_id,
+                endpoint.uri(),
+                interval.as_secs()
+            );
+
+            tokio::select! {
+                _ = sleep(interval) => {}
+                _ = stop.changed() => {}
+            }
+        }
+    }
+
+    info!("[task-{}] Stopped", task_id);
+}
+
+/// Gives dequeued urls that weren't crawled back to the gateway.
+async fn requeue(
+    task_id: usize,
+    client: &mut GatewayServiceClient<Channel>,
+    urls: Vec<DequeueResponse>,
+) {
+    if urls.is_empty() {
+        return;
+    }
+
+    info!("[task-{}] Requeuing {} urls", task_id, urls.len());
+
+    // Retried, as the urls are lost to the gateway otherwise
+    let mut interval = MIN_BACKOFF;
+    for attempt in 1..=REQUEUE_ATTEMPTS {
+        let request = Request::new(RequeueUrlsRequest { urls: urls.clone() });
+        match client.requeue_urls(request).await {
+            Ok(_) => return,
+            Err(e) if attempt < REQUEUE_ATTEMPTS => {
+                warn!(
+                    "[task-{}] Failing requeuing urls, trying again in {} seconds: {}",
+                    task_id,
+                    interval.as_secs(),
+                    e
+                );
+                sleep(interval).await;
+                interval = (interval * 2).min(MAX_BACKOFF);
+            }
+            Err(e) => {
+                let urls: Vec<String> = urls.iter().map(|entry| entry.url.clone()).collect();
+                error!(
+                    "[task-{}] Failing requeuing urls {:?}: {}",
+                    task_id, urls, e
+                );
+            }
+        }
+    }
+}
+
+/// Loads the downloader configuration from `path`, which may hold either a whole
+/// `GoogolConfig` or just a `DownloaderConfig`.
+fn load_downloader_config(path: &Path) -> Result<DownloaderConfig, ConfigError> {
+    let path = path.to_string_lossy();
+
+    GoogolConfig::load(&path)
+        .map(|config| config.downloader)
+        .or_else(|googol_error| {
+            DownloaderConfig::load(&path).map_err(|downloader_error| {
+                ConfigError::Message(format!(
+                    "not a googol config ({}), nor a downloader config ({})",
+                    googol_error, downloader_error
+                ))
+            })
+        })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    debugv!(&cli);
+
+    let (settings, load_error) = match <GoogolConfig as Load>::default() {
+        Err(e) => (<DownloaderConfig as Load>::default()?, Some(e)),
+        Ok(config) => (config.downloader, None),
+    };
+
+    let _logger = init_logging(settings.log_file.as_deref(), &settings.log_level)?;
+    if let Some(e) = load_error {
+        error!("{:#?}", e);
     }
+    debugv!(settings, debug);
 
-    join_set.join_all().await;
+    let http_client = reqwest::Client::builder()
+        .user_agent(&settings.user_agent)
+        .timeout(Duration::from_secs(settings.request_timeout_secs))
+        .redirect(Policy::limited(settings.max_redirects))
+        .build()?;
+
+    let crawler = Arc::new(Crawler {
+        settings: settings.clone(),
+        fishfish: FishFish::new().with_timeout(Duration::from_secs(settings.request_timeout_secs)),
+        robots: RobotsCache::new(
+            &settings.user_agent,
+            Duration::from_secs(settings.robots_ttl_secs),
+            Duration::from_secs(settings.request_timeout_secs),
+        ),
+        host_limiter: HostLimiter::new(
+            settings.max_per_host,
+            Duration::from_millis(settings.min_delay_ms),
+        ),
+        http_client,
+    });
+
+    let gateway_tls = settings
+        .gateway_tls
+        .as_ref()
+        .map(TlsClientConfig::read)
+        .transpose()?;
+    let endpoint = tls::endpoint(settings.gateway, gateway_tls.as_ref())?;
+
+    info!("Connecting to gateway: {}...", endpoint.uri());
+    info!(
+        "Largest gRPC message size: {} bytes",
+        settings.max_message_size
+    );
+
+    let mut workers = Workers::new(endpoint, crawler);
+    workers.resize(settings.threads);
+
+    if let Some(path) = &cli.watch_config {
+        match FileWatcher::new(path) {
+            Err(e) => error!("Failed to watch {}: {}", path.display(), e),
+            Ok(mut watcher) => {
+                while watcher.changed().await {
+                    match load_downloader_config(path) {
+                        Ok(config) => {
+                            info!("Reloading threads from {}", path.display());
+                            workers.resize(config.threads);
+                        }
+                        Err(e) => error!(
+                            "Failed to reload {}, keeping the previous threads: {}",
+                            path.display(),
+                            e
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    workers.join().await;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(html: &str) -> Option<String> {
+        let document = Html::parse_document(html);
+        let url = "https://example.com".parse().unwrap();
+
+        extract_summary(&document, &url, &body_text(&document))
+    }
+
+    #[test]
+    fn test_summary_meta_description() {
+        let html = r#"<html><head>
+                <meta property="og:description" content="Open Graph">
+                <meta name="description" content="  A   description ">
+            </head><body>Body text</body></html>"#;
+
+        assert_eq!(summary(html), Some("A description".to_string()));
+    }
+
+    #[test]
+    fn test_summary_og_description() {
+        let html = r#"<html><head>
+                <meta name="description" content="">
+                <meta property="og:description" content="Open Graph">
+            </head><body>Body text</body></html>"#;
+
+        assert_eq!(summary(html), Some("Open Graph".to_string()));
+    }
+
+    #[test]
+    fn test_summary_body_text() {
+        let body = "word ".repeat(100);
+
+        let summary = summary(&format!("<html><body><p>{}</p></body></html>", body)).unwrap();
+
+        assert!(summary.chars().count() <= SUMMARY_MAX_CHARS + 1);
+        assert!(summary.starts_with("word word"));
+        assert!(summary.ends_with("word…"));
+    }
+
+    #[test]
+    fn test_error_is_transient() {
+        assert!(HtmlError::Timeout.is_transient());
+        assert!(HtmlError::HttpStatus(StatusCode::INTERNAL_SERVER_ERROR).is_transient());
+        assert!(HtmlError::HttpStatus(StatusCode::SERVICE_UNAVAILABLE).is_transient());
+        assert!(HtmlError::HttpStatus(StatusCode::TOO_MANY_REQUESTS).is_transient());
+
+        assert!(!HtmlError::InvalidUrl.is_transient());
+        assert!(!HtmlError::HttpStatus(StatusCode::NOT_FOUND).is_transient());
+        assert!(!HtmlError::HttpStatus(StatusCode::FORBIDDEN).is_transient());
+        assert!(!HtmlError::UrlParseError(url::ParseError::EmptyHost).is_transient());
+    }
+
+    #[test]
+    fn test_extract_words() {
+        let stop_words = HashSet::from([String::from("the")]);
+
+        assert_eq!(
+            extract_words("The Rust  Book, 2nd edition of the Rust book", &stop_words),
+            vec!["rust", "2nd", "edition", "of", "rust", "book"]
+        );
+    }
+
+    #[test]
+    fn test_is_html_content_type() {
+        assert!(is_html_content_type("text/html"));
+        assert!(is_html_content_type("text/html; charset=UTF-8"));
+        assert!(is_html_content_type("application/xhtml+xml"));
+        assert!(is_html_content_type("TEXT/HTML"));
+
+        assert!(!is_html_content_type("application/pdf"));
+        assert!(!is_html_content_type("image/png"));
+        assert!(!is_html_content_type("text/plain"));
+    }
+
+    #[test]
+    fn test_looks_like_html() {
+        assert!(looks_like_html(b"<!DOCTYPE html><html></html>"));
+        assert!(looks_like_html(b"\xef\xbb\xbf  <html lang=\"en\">"));
+        assert!(looks_like_html(b"<head><title>Title</title></head>"));
+
+        assert!(!looks_like_html(b"%PDF-1.7"));
+        assert!(!looks_like_html(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        assert!(!looks_like_html(b"plain text"));
+        assert!(!looks_like_html(b""));
+    }
+
+    #[test]
+    fn test_summary_empty() {
+        assert_eq!(summary("<html><head></head><body>  </body></html>"), None);
+    }
+
+    #[test]
+    fn test_detect_language_html_lang() {
+        let detect = |lang: &str| {
+            let document = Html::parse_document(&format!(
+                "<html lang=\"{}\"><body>The quick brown fox</body></html>",
+                lang
+            ));
+
+            detect_language(&document, "")
+        };
+
+        assert_eq!(detect("en"), Some("en".to_string()));
+        assert_eq!(detect("pt-BR"), Some("pt".to_string()));
+        assert_eq!(detect("PT_pt"), Some("pt".to_string()));
+        assert_eq!(detect("por"), Some("pt".to_string()));
+    }
+
+    #[test]
+    fn test_detect_language_from_text() {
+        let text = "Os motores de busca percorrem a internet para encontrar páginas novas, e \
+                    guardam as palavras de cada uma num índice que depois é consultado quando \
+                    alguém faz uma pesquisa.";
+
+        // Guessed when the attribute is missing or unusable
+        for html in ["<html>", "<html lang=\"\">", "<html lang=\"x-klingon\">"] {
+            let document = Html::parse_document(&format!("{}<body>{}</body></html>", html, text));
+
+            assert_eq!(detect_language(&document, text), Some("pt".to_string()));
+        }
+
+        let document = Html::parse_document("<html><body></body></html>");
+        assert_eq!(detect_language(&document, ""), None);
+    }
+
+    #[test]
+    fn test_html_info_into_proto_page() {
+        let html_info = HtmlInfo {
+            url: "https://example.com/page".parse().unwrap(),
+            words: vec![],
+            title_words: HashSet::new(),
+            outlinks: HashSet::new(),
+            title: Some("Title".to_string()),
+            summary: Some("A page about things".to_string()),
+            favicon_url: None,
+            icon: Some("data:image/png;base64,".to_string()),
+            category: Some(FishDomainCategory::Safe),
+            language: Some("en".to_string()),
+            content_hash: Some("abc".to_string()),
+        };
+
+        let page: proto::Page = html_info.clone().into();
+        assert_eq!(
+            page,
+            proto::Page {
+                url: "https://example.com/page".to_string(),
+                title: "Title".to_string(),
+                summary: "A page about things".to_string(),
+                icon: "data:image/png;base64,".to_string(),
+                category: "safe".to_string(),
+                rank: None,
+                language: "en".to_string(),
+                content_hash: "abc".to_string(),
+            }
+        );
+
+        // Missing fields are left empty, as by `page::Page`
+        let page: proto::Page = HtmlInfo {
+            title: None,
+            summary: None,
+            icon: None,
+            category: None,
+            language: None,
+            content_hash: None,
+            ..html_info
+        }
+        .into();
+        assert_eq!(
+            (
+                page.title,
+                page.summary,
+                page.icon,
+                page.category,
+                page.language,
+                page.content_hash
+            ),
+            Default::default()
+        );
+    }
+}
@@ -1,35 +1,41 @@
 use clap::Parser;
 use googol::{
     debugv,
-    gateway::Gateway,
-    proto::gateway_service_server::GatewayServiceServer,
+    gateway::{Gateway, load_balancer::LoadBalancer},
+    logger::init_logging,
     settings::{GoogolConfig, Load, gateway::GatewayConfig},
 };
 use log::{debug, error, info};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tokio::signal;
 use tonic::transport::Server;
 
+/// Time between health checks of the barrels.
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
 #[derive(Debug, Parser)]
 struct Cli {
     #[arg(short, long)]
     interactive: bool,
+    /// Reload the barrels and domains filter whenever this configuration file changes.
+    #[arg(long, value_name = "FILE")]
+    watch_config: Option<PathBuf>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    pretty_env_logger::init();
-
     let cli = Cli::parse();
     debugv!(&cli);
 
-    let settings = match GoogolConfig::default() {
-        Err(e) => {
-            error!("{:#?}", e);
-
-            GatewayConfig::default()?
-        }
-
-        Ok(config) => config.gateway,
+    let (settings, load_error) = match <GoogolConfig as Load>::default() {
+        Err(e) => (<GatewayConfig as Load>::default()?, Some(e)),
+        Ok(config) => (config.gateway, None),
     };
+
+    let _logger = init_logging(settings.log_file.as_deref(), &settings.log_level)?;
+    if let Some(e) = load_error {
+        error!("{:#?}", e);
+    }
     debugv!(settings, debug);
 
     let gateway = Gateway::from(&settings)
@@ -37,11 +43,65 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_interactive(cli.interactive);
     debugv!(gateway, debug);
 
+    if let Some(barrel_tls) = &settings.barrel_tls {
+        gateway
+            .load_balancer
+            .lock()
+            .await
+            .set_tls(Some(barrel_tls.read()?));
+    }
+
+    let gateway = Arc::new(gateway);
+
+    LoadBalancer::spawn_health_checker(Arc::clone(&gateway.load_balancer), HEALTH_CHECK_INTERVAL);
+
+    Arc::clone(&gateway).spawn_index_cache_flusher(
+        Duration::from_secs(settings.index_cache.retry_interval_secs),
+        Duration::from_secs(settings.index_cache.max_retry_interval_secs),
+    );
+
+    if let Some(recrawl) = &settings.recrawl {
+        Arc::clone(&gateway).spawn_recrawler(
+            Duration::from_secs(recrawl.check_interval_secs),
+            Duration::from_secs(recrawl.interval_secs),
+            recrawl.batch_size,
+        );
+    }
+
+    if let Some(address) = settings.metrics_address {
+        Arc::clone(&gateway).spawn_metrics_server(address).await?;
+    }
+
+    if let Some(path) = &cli.watch_config
+        && let Err(e) = Arc::clone(&gateway).spawn_config_watcher(path)
+    {
+        error!("Failed to watch {}: {}", path.display(), e);
+    }
+
+    let mut server = Server::builder();
+    if let Some(tls) = &settings.tls {
+        server = server.tls_config(tls.read()?)?;
+    }
+
     info!("Gateway listening at {}...", gateway.address);
-    Server::builder()
-        .add_service(GatewayServiceServer::new(gateway))
-        .serve(settings.address)
+    info!(
+        "Largest gRPC message size: {} bytes",
+        settings.max_message_size
+    );
+    server
+        .add_service(
+            Arc::clone(&gateway).into_service(settings.compression, settings.max_message_size),
+        )
+        .serve_with_shutdown(settings.address, async {
+            if let Err(e) = signal::ctrl_c().await {
+                error!("Failed to listen for shutdown signal: {}", e);
+            }
+            info!("Shutting down...");
+        })
         .await?;
 
+    // Keep pending urls, and what was already crawled, for the next boot
+    gateway.queue.lock().await.save(&settings.queue_filepath)?;
+
     Ok(())
 }
@@ -1,28 +1,100 @@
-use actix_web::{App, HttpRequest, HttpServer, Responder, get, middleware, post, web};
+use actix_cors::Cors;
+use actix_web::{
+    App, HttpRequest, HttpResponse, HttpServer, Responder,
+    body::MessageBody,
+    dev::{RequestHead, ServiceRequest, ServiceResponse},
+    get,
+    http::{
+        StatusCode,
+        header::{self, ContentType, HeaderValue},
+    },
+    middleware::{self, Next, from_fn},
+    post,
+    web::{self, Bytes},
+};
 use actix_ws::Message;
-use futures::StreamExt;
+use futures::{StreamExt, stream};
 use googol::{
-    debugv, page,
+    debugv, gateway,
+    logger::init_logging,
+    page,
     proto::{
-        EnqueueRequest, HealthRequest, RealTimeStatusRequest, SearchRequest, Status,
+        BacklinksRequest, EnqueueRequest, HealthRequest, HealthResponse, OutlinksRequest,
+        RealTimeStatusRequest, SearchRequest, SearchResponse, Status, SuggestRequest,
         gateway_service_client::GatewayServiceClient,
     },
-    settings::{GoogolConfig, Load, web_server::WebServerConfig},
+    rate_limiter::RateLimiter,
+    settings::{
+        GoogolConfig, Load,
+        tls::{self, TlsClientConfig},
+        web_server::WebServerConfig,
+    },
 };
 use log::{debug, error, info};
 use serde::Deserialize;
 use serde_json::json;
-use std::net::SocketAddr;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 use tonic::{
-    Request,
-    transport::{Channel, Error},
+    Code, Request,
+    transport::{Channel, Endpoint, Error},
 };
+use url::Url;
+
+/// Checks if `origin` is the origin the request was sent to.
+fn is_same_origin(origin: &HeaderValue, request: &RequestHead) -> bool {
+    let Some(host) = request.headers().get(header::HOST) else {
+        return false;
+    };
+
+    origin
+        .to_str()
+        .ok()
+        .and_then(|origin| Url::parse(origin).ok())
+        .is_some_and(|origin| host == &origin[url::Position::BeforeHost..url::Position::AfterPort])
+}
+
+/// Builds the CORS middleware, allowing same-origin requests and those from `allowed_origins`.
+///
+/// Preflight `OPTIONS` requests are answered by the middleware for every route.
+fn cors(allowed_origins: &[String]) -> Cors {
+    let allowed_origins = allowed_origins.to_vec();
+
+    Cors::default()
+        .allowed_origin_fn(move |origin, request| {
+            allowed_origins
+                .iter()
+                .any(|allowed| allowed == "*" || origin == allowed.as_str())
+                || is_same_origin(origin, request)
+        })
+        .allowed_methods(["GET", "POST"])
+        .allowed_headers([header::CONTENT_TYPE, header::ACCEPT])
+        .max_age(3600)
+}
+
+/// How to reach the gateway.
+#[derive(Debug, Clone)]
+struct Gateway {
+    endpoint: Endpoint,
+    /// Whether requests are gzip-compressed.
+    compression: bool,
+    /// Largest request sent or response received, in bytes.
+    max_message_size: usize,
+}
 
-async fn get_grpc_client(
-    gateway_address: SocketAddr,
-) -> Result<GatewayServiceClient<Channel>, Error> {
-    let gateway_address = format!("http://{}", gateway_address);
-    GatewayServiceClient::connect(gateway_address).await
+async fn get_grpc_client(gateway: Gateway) -> Result<GatewayServiceClient<Channel>, Error> {
+    gateway::connect(
+        gateway.endpoint,
+        gateway.compression,
+        gateway.max_message_size,
+    )
+    .await
 }
 
 #[get("/")]
@@ -32,17 +104,164 @@ async fn index(req: HttpRequest) -> &'static str {
     "Hello world!"
 }
 
+/// Search page served at `/` instead of `index` with `serve_ui`. Bundled in the binary, so it
+/// is served wherever the web server runs from.
+const UI_INDEX: &str = include_str!("../../static/ui/index.html");
+const UI_SCRIPT: &str = include_str!("../../static/ui/script.js");
+const UI_STYLE: &str = include_str!("../../static/ui/style.css");
+
+#[get("/")]
+async fn ui_index() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(UI_INDEX)
+}
+
+#[get("/ui/script.js")]
+async fn ui_script() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/javascript; charset=utf-8")
+        .body(UI_SCRIPT)
+}
+
+#[get("/ui/style.css")]
+async fn ui_style() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/css; charset=utf-8")
+        .body(UI_STYLE)
+}
+
+/// Serves the search page and its assets.
+fn ui(config: &mut web::ServiceConfig) {
+    config
+        .service(ui_index)
+        .service(ui_script)
+        .service(ui_style);
+}
+
+/// Answers with 429 Too Many Requests once the client's rate limit is exceeded, if the app has a
+/// `RateLimiter`. Clients are told apart by the address of their connection.
+async fn rate_limit(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let limited = request
+        .app_data::<web::Data<RateLimiter>>()
+        .zip(request.peer_addr())
+        .and_then(|(limiter, peer)| limiter.check(peer.ip()).err());
+
+    match limited {
+        None => next
+            .call(request)
+            .await
+            .map(ServiceResponse::map_into_left_body),
+        Some(retry_after) => {
+            debug!("Rate limited {:?}", request.peer_addr());
+
+            let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, "Too many requests");
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(retry_after.as_secs_f64().ceil() as u64),
+            );
+
+            Ok(request.into_response(response).map_into_right_body())
+        }
+    }
+}
+
+/// Builds an error response with a JSON body holding the error detail.
+fn error_response(status_code: StatusCode, error: impl Display) -> HttpResponse {
+    HttpResponse::build(status_code).json(json!({"error": error.to_string()}))
+}
+
+/// Responds to a failure connecting to the gateway.
+fn connection_error_response(e: Error) -> HttpResponse {
+    error!("Failed to connect to gateway: {}", e);
+    error_response(StatusCode::BAD_GATEWAY, e)
+}
+
+/// Responds to a gRPC call to the gateway that failed.
+fn grpc_error_response(e: tonic::Status) -> HttpResponse {
+    error!("Gateway request failed: {}", e);
+
+    let status_code = match e.code() {
+        Code::InvalidArgument => StatusCode::BAD_REQUEST,
+        Code::Unavailable => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+
+    error_response(status_code, e.message())
+}
+
+/// Responds with how many barrels the gateway can reach, as unavailable if there are none.
+fn health_response(health: &HealthResponse) -> HttpResponse {
+    let (status_code, status) = match health.online_barrels {
+        0 => (StatusCode::SERVICE_UNAVAILABLE, "degraded"),
+        _ => (StatusCode::OK, "healthy"),
+    };
+
+    HttpResponse::build(status_code).json(json!({
+        "status": status,
+        "online_barrels": health.online_barrels,
+        "total_barrels": health.total_barrels,
+    }))
+}
+
+/// Responds with the pages of a search, as an empty list if the search found nothing.
+///
+/// Searches that failed, as opposed to finding no page, are responded with an error.
+fn search_response(response: SearchResponse) -> HttpResponse {
+    match response.status() {
+        Status::Success => {
+            let results: Vec<page::web_server::Page> = response
+                .pages
+                .into_iter()
+                .map(page::web_server::Page::from)
+                .collect();
+
+            debug!("{:#?}", results);
+
+            HttpResponse::Ok().json(results)
+        }
+        status => status_error_response(status, "searching"),
+    }
+}
+
+/// Maps the status of a gateway response to an HTTP status code.
+fn status_code(status: Status) -> StatusCode {
+    match status {
+        Status::Success => StatusCode::OK,
+        Status::InvalidUrl
+        | Status::BlacklistedUrl
+        | Status::MaxDepthExceeded
+        | Status::MaliciousUrl => StatusCode::BAD_REQUEST,
+        Status::AlreadyIndexedUrl => StatusCode::CONFLICT,
+        Status::UnavailableBarrels | Status::QueueFull => StatusCode::SERVICE_UNAVAILABLE,
+        Status::Error => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Responds to a gateway response whose status is not a success.
+fn status_error_response(status: Status, action: &str) -> HttpResponse {
+    error_response(
+        status_code(status),
+        format!("Error {}: {}", action, status.as_str_name()),
+    )
+}
+
 #[get("/health")]
-async fn health_handler(gateway_address: web::Data<SocketAddr>) -> impl Responder {
-    let gateway_address = *gateway_address.into_inner();
-
-    web::Json(match get_grpc_client(gateway_address).await {
-        Err(e) => json!({"error": e.to_string()}),
-        Ok(mut client) => match client.health(Request::new(HealthRequest {})).await {
-            Err(e) => json!({"error": e.to_string()}),
-            Ok(_) => json!({"status": "healthy"}),
-        },
-    })
+async fn health_handler(gateway: web::Data<Gateway>) -> HttpResponse {
+    let gateway = gateway.get_ref().clone();
+
+    let mut client = match get_grpc_client(gateway).await {
+        Ok(client) => client,
+        Err(e) => return connection_error_response(e),
+    };
+
+    match client.health(Request::new(HealthRequest {})).await {
+        Err(e) => grpc_error_response(e),
+        Ok(response) => health_response(&response.into_inner()),
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -50,96 +269,300 @@ struct EnqueueInput {
     url: String,
 }
 
-#[post("/enqueue")]
+#[post("/enqueue", wrap = "from_fn(rate_limit)")]
 async fn enqueue_handler(
-    gateway_address: web::Data<SocketAddr>,
+    gateway: web::Data<Gateway>,
     item: web::Json<EnqueueInput>,
-) -> impl Responder {
+) -> HttpResponse {
     debugv!(item);
 
-    let gateway_address = *gateway_address.into_inner();
+    if let Err(e) = Url::parse(&item.url) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid url `{}`: {}", item.url, e),
+        );
+    }
 
-    let json = web::Json(match get_grpc_client(gateway_address).await {
-        Err(e) => json!({"error": e.to_string()}),
-        Ok(mut client) => {
-            let request = Request::new(EnqueueRequest {
-                url: item.url.clone(),
-            });
+    let gateway = gateway.get_ref().clone();
 
-            match client.enqueue_url(request).await {
-                Err(e) => json!({"error": e.to_string()}),
-                Ok(_) => json!({"message": "Enqueued"}),
-            }
-        }
+    let mut client = match get_grpc_client(gateway).await {
+        Ok(client) => client,
+        Err(e) => return connection_error_response(e),
+    };
+
+    let request = Request::new(EnqueueRequest {
+        url: item.url.clone(),
     });
-    debugv!(json);
 
-    json
+    match client.enqueue_url(request).await {
+        Err(e) => grpc_error_response(e),
+        Ok(response) => match response.into_inner().status() {
+            Status::Success => HttpResponse::Ok().json(json!({"message": "Enqueued"})),
+            status => status_error_response(status, "enqueueing"),
+        },
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct SearchBody {
     words: Vec<String>,
+    #[serde(default)]
+    offset: u32,
+    #[serde(default)]
+    limit: u32,
 }
 
 #[derive(Debug, Deserialize)]
 struct SearchParams {
     words: String,
+    #[serde(default)]
+    offset: u32,
+    #[serde(default)]
+    limit: u32,
 }
 
-#[get("/search")]
+/// Builds the search request from either a JSON body or query parameters.
+fn search_request(
+    input: web::Either<web::Json<SearchBody>, web::Query<SearchParams>>,
+) -> SearchRequest {
+    let (words, offset, limit) = match input {
+        web::Either::Left(json) => {
+            let body = json.into_inner();
+            (body.words, body.offset, body.limit)
+        }
+        web::Either::Right(params) => {
+            let params = params.into_inner();
+            let words = params
+                .words
+                .split(',')
+                .filter(|word| !word.is_empty())
+                .map(|word| word.to_string())
+                .collect();
+            (words, params.offset, params.limit)
+        }
+    };
+    debugv!(words);
+
+    SearchRequest {
+        words,
+        offset,
+        limit,
+        ..Default::default()
+    }
+}
+
+#[get("/search", wrap = "from_fn(rate_limit)")]
 async fn search_handler(
-    gateway_address: web::Data<SocketAddr>,
+    gateway: web::Data<Gateway>,
     input: web::Either<web::Json<SearchBody>, web::Query<SearchParams>>,
-) -> impl Responder {
+) -> HttpResponse {
     debugv!(input, debug);
 
-    let gateway_address = *gateway_address.into_inner();
-
-    let words = match input {
-        web::Either::Left(json) => json.into_inner().words,
-        web::Either::Right(params) => params
-            .into_inner()
-            .words
-            .split(',')
-            .filter(|word| !word.is_empty())
-            .map(|word| word.to_string())
-            .collect(),
+    let gateway = gateway.get_ref().clone();
+
+    let mut client = match get_grpc_client(gateway).await {
+        Ok(client) => client,
+        Err(e) => return connection_error_response(e),
     };
-    debugv!(words);
 
-    let json = web::Json(match get_grpc_client(gateway_address).await {
-        Err(e) => json!({"error": e.to_string()}),
-        Ok(mut client) => {
-            let request = SearchRequest { words };
+    let request = search_request(input);
+
+    match client.search(request).await {
+        Ok(response) => search_response(response.into_inner()),
+        Err(e) => grpc_error_response(e),
+    }
+}
+
+/// Streams search results as newline delimited JSON, one array of pages per batch, so pages
+/// can be rendered before the whole result set arrives.
+///
+/// The status code is chosen from the first batch. Errors in later batches are sent as a line
+/// holding a JSON error.
+#[get("/search/stream", wrap = "from_fn(rate_limit)")]
+async fn search_stream_handler(
+    gateway: web::Data<Gateway>,
+    input: web::Either<web::Json<SearchBody>, web::Query<SearchParams>>,
+) -> HttpResponse {
+    debugv!(input, debug);
+
+    let gateway = gateway.get_ref().clone();
+
+    let mut client = match get_grpc_client(gateway).await {
+        Ok(client) => client,
+        Err(e) => return connection_error_response(e),
+    };
+
+    let mut batches = match client.search_stream(search_request(input)).await {
+        Ok(response) => response.into_inner(),
+        Err(e) => return grpc_error_response(e),
+    };
 
-            match client.search(request).await {
+    let first = match batches.next().await {
+        None => return error_response(StatusCode::BAD_GATEWAY, "Empty search stream"),
+        Some(Err(e)) => return grpc_error_response(e),
+        Some(Ok(batch)) if batch.status() != Status::Success => {
+            return status_error_response(batch.status(), "searching");
+        }
+        Some(Ok(batch)) => batch,
+    };
+
+    let lines = stream::once(async { Ok(first) })
+        .chain(batches)
+        .map(|batch| {
+            let json = match batch {
                 Err(e) => json!({"error": e.to_string()}),
-                Ok(response) => {
-                    let response = response.into_inner();
-
-                    match response.status() {
-                        Status::Success => {
-                            let results: Vec<page::web_server::Page> = response
-                                .pages
-                                .iter()
-                                .cloned()
-                                .map(page::web_server::Page::from)
-                                .collect();
-
-                            debug!("{:#?}", results);
-
-                            json!(results)
-                        }
-                        _ => json!({"error": "Error searching"}),
+                Ok(batch) => match batch.status() {
+                    Status::Success => {
+                        let results: Vec<page::web_server::Page> = batch
+                            .pages
+                            .into_iter()
+                            .map(page::web_server::Page::from)
+                            .collect();
+
+                        json!(results)
                     }
-                }
+                    _ => json!({"error": "Error searching"}),
+                },
+            };
+            debugv!(json);
+
+            Ok::<_, actix_web::Error>(Bytes::from(format!("{}\n", json)))
+        });
+
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(lines)
+}
+
+#[derive(Debug, Deserialize)]
+struct SuggestParams {
+    prefix: String,
+    #[serde(default)]
+    limit: u32,
+}
+
+/// Suggests the most common indexed words starting with `prefix`, e.g. to complete a search
+/// as it is typed.
+#[get("/suggest")]
+async fn suggest_handler(
+    gateway: web::Data<Gateway>,
+    params: web::Query<SuggestParams>,
+) -> HttpResponse {
+    debugv!(params, debug);
+
+    let gateway = gateway.get_ref().clone();
+
+    let mut client = match get_grpc_client(gateway).await {
+        Ok(client) => client,
+        Err(e) => return connection_error_response(e),
+    };
+
+    let params = params.into_inner();
+    let request = Request::new(SuggestRequest {
+        prefix: params.prefix,
+        limit: params.limit,
+    });
+
+    match client.suggest(request).await {
+        Err(e) => grpc_error_response(e),
+        Ok(response) => {
+            let response = response.into_inner();
+
+            match response.status() {
+                Status::Success => HttpResponse::Ok().json(response.suggestions),
+                status => status_error_response(status, "suggesting words"),
             }
         }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LinksParams {
+    url: Option<String>,
+}
+
+/// Validates the `url` query parameter of the links endpoints.
+///
+/// Returns the error detail if the parameter is missing or invalid.
+fn parse_url_param(params: web::Query<LinksParams>) -> Result<Url, String> {
+    let url = params
+        .into_inner()
+        .url
+        .ok_or_else(|| String::from("Missing url"))?;
+
+    Url::parse(&url).map_err(|e| format!("Invalid url `{}`: {}", url, e))
+}
+
+#[get("/backlinks")]
+async fn backlinks_handler(
+    gateway: web::Data<Gateway>,
+    params: web::Query<LinksParams>,
+) -> HttpResponse {
+    debugv!(params, debug);
+
+    let url = match parse_url_param(params) {
+        Ok(url) => url,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+
+    let gateway = gateway.get_ref().clone();
+
+    let mut client = match get_grpc_client(gateway).await {
+        Ok(client) => client,
+        Err(e) => return connection_error_response(e),
+    };
+
+    let request = Request::new(BacklinksRequest {
+        url: url.to_string(),
     });
-    debugv!(json);
 
-    json
+    match client.consult_backlinks(request).await {
+        Err(e) => grpc_error_response(e),
+        Ok(response) => {
+            let response = response.into_inner();
+
+            match response.status() {
+                Status::Success => HttpResponse::Ok().json(response.backlinks),
+                status => status_error_response(status, "consulting backlinks"),
+            }
+        }
+    }
+}
+
+#[get("/outlinks")]
+async fn outlinks_handler(
+    gateway: web::Data<Gateway>,
+    params: web::Query<LinksParams>,
+) -> HttpResponse {
+    debugv!(params, debug);
+
+    let url = match parse_url_param(params) {
+        Ok(url) => url,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+
+    let gateway = gateway.get_ref().clone();
+
+    let mut client = match get_grpc_client(gateway).await {
+        Ok(client) => client,
+        Err(e) => return connection_error_response(e),
+    };
+
+    let request = Request::new(OutlinksRequest {
+        url: url.to_string(),
+    });
+
+    match client.consult_outlinks(request).await {
+        Err(e) => grpc_error_response(e),
+        Ok(response) => {
+            let response = response.into_inner();
+
+            match response.status() {
+                Status::Success => HttpResponse::Ok().json(response.outlinks),
+                status => status_error_response(status, "consulting outlinks"),
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Hash, Eq, PartialEq)]
@@ -158,19 +581,93 @@ pub enum ClientMessage {
     Unsubscribe { topic: Topic },
 }
 
+/// Sends every status update of the gateway to the session, until `cancelled` is set or the
+/// session is closed.
+async fn stream_status(gateway: Gateway, session: actix_ws::Session, cancelled: Arc<AtomicBool>) {
+    send_status_updates(gateway, session, &cancelled).await;
+
+    // Allow subscribing again if the stream stopped on its own
+    cancelled.store(true, Ordering::Relaxed);
+}
+
+async fn send_status_updates(
+    gateway: Gateway,
+    mut session: actix_ws::Session,
+    cancelled: &AtomicBool,
+) {
+    let mut client = match get_grpc_client(gateway).await {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to connect to gateway: {}", e);
+            return;
+        }
+    };
+
+    let mut wait_for_update = false;
+
+    while !cancelled.load(Ordering::Relaxed) {
+        let request = Request::new(RealTimeStatusRequest { wait_for_update });
+        // Only the first status is sent right away
+        wait_for_update = true;
+
+        let response = match client.real_time_status(request).await {
+            Ok(response) => response.into_inner(),
+            Err(e) => {
+                error!("Failed to get real time status: {}", e);
+                return;
+            }
+        };
+
+        // The subscription may have been cancelled while waiting for an update
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let json = json!({
+            "top10_searches": response.top10_searches,
+            "avg_response_time_ms": response.avg_response_time_ms,
+            "p50_response_time_ms": response.p50_response_time_ms,
+            "p95_response_time_ms": response.p95_response_time_ms,
+            "p99_response_time_ms": response.p99_response_time_ms,
+            "barrels": response
+                .barrels
+                .iter()
+                .map(|barrel| {
+                    json!({
+                        "online": barrel.online,
+                        "address": barrel.address,
+                        "index_size_bytes": barrel.index_size_bytes,
+                    })
+                }).collect::<Vec<_>>(),
+            "queue": response.queue,
+        });
+        debug!("{:#?}", json);
+
+        if session.text(json.to_string()).await.is_err() {
+            // The session was closed
+            return;
+        }
+    }
+
+    debug!("Status subscription cancelled");
+}
+
 #[get("/ws")]
 async fn ws_handler(
-    gateway_address: web::Data<SocketAddr>,
+    gateway: web::Data<Gateway>,
     req: HttpRequest,
     body: web::Payload,
 ) -> actix_web::Result<impl Responder> {
     debugv!(req);
 
-    let gateway_address = *gateway_address.into_inner();
+    let gateway = gateway.get_ref().clone();
 
     let (response, mut session, mut msg_stream) = actix_ws::handle(&req, body)?;
 
     actix_web::rt::spawn(async move {
+        // Active subscriptions of the session, with the flag cancelling each one
+        let mut subscriptions: HashMap<Topic, Arc<AtomicBool>> = HashMap::new();
+
         while let Some(Ok(msg)) = msg_stream.next().await {
             match msg {
                 Message::Ping(bytes) => {
@@ -188,43 +685,37 @@ async fn ws_handler(
                             ClientMessage::Subscribe { topic } => {
                                 debug!("topic = {:#?}", topic);
 
+                                // Streams that failed mark themselves as cancelled
+                                if subscriptions
+                                    .get(&topic)
+                                    .is_some_and(|cancelled| !cancelled.load(Ordering::Relaxed))
+                                {
+                                    debug!("Already subscribed to {:?}", topic);
+                                    continue;
+                                }
+
+                                let cancelled = Arc::new(AtomicBool::new(false));
+
                                 match topic {
                                     Topic::Status => {
-                                        let mut client =
-                                            get_grpc_client(gateway_address).await.unwrap();
-
-                                        loop {
-                                            let request = Request::new(RealTimeStatusRequest {});
-                                            let response =
-                                                client.real_time_status(request).await.unwrap();
-                                            let response = response.into_inner();
-
-                                            let json = json!({
-                                                "top10_searches": response.top10_searches,
-                                                "avg_response_time_ms": response.avg_response_time_ms,
-                                                "barrels": response
-                                                    .barrels
-                                                    .iter()
-                                                    .map(|barrel| {
-                                                        json!({
-                                                            "online": barrel.online,
-                                                            "address": barrel.address,
-                                                            "index_size_bytes": barrel.index_size_bytes,
-                                                        })
-                                                    }).collect::<Vec<_>>(),
-                                                "queue": response.queue,
-                                            });
-                                            debug!("{:#?}", json);
-
-                                            session.text(json.to_string()).await.unwrap();
-                                        }
+                                        actix_web::rt::spawn(stream_status(
+                                            gateway.clone(),
+                                            session.clone(),
+                                            Arc::clone(&cancelled),
+                                        ));
                                     }
                                 }
+
+                                subscriptions.insert(topic, cancelled);
                             }
                             ClientMessage::Unsubscribe { topic } => {
                                 debug!("topic = {:#?}", topic);
 
-                                todo!()
+                                // The connection stays open, so the client may subscribe again
+                                match subscriptions.remove(&topic) {
+                                    Some(cancelled) => cancelled.store(true, Ordering::Relaxed),
+                                    None => debug!("Not subscribed to {:?}", topic),
+                                }
                             }
                         };
                     } else {
@@ -236,6 +727,10 @@ async fn ws_handler(
             }
         }
 
+        for cancelled in subscriptions.values() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+
         let _ = session.close(None).await;
     });
 
@@ -244,30 +739,66 @@ async fn ws_handler(
 
 #[actix_web::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    pretty_env_logger::init();
-
-    let settings = match GoogolConfig::default() {
-        Err(e) => {
-            error!("{:#?}", e);
+    let (settings, load_error) = match <GoogolConfig as Load>::default() {
+        Err(e) => (<WebServerConfig as Load>::default()?, Some(e)),
+        Ok(config) => (config.web_server, None),
+    };
 
-            WebServerConfig::default()?
-        }
+    let _logger = init_logging(settings.log_file.as_deref(), &settings.log_level)?;
+    if let Some(e) = load_error {
+        error!("{:#?}", e);
+    }
+    debugv!(settings, debug);
 
-        Ok(config) => config.web_server,
+    let gateway_tls = settings
+        .gateway_tls
+        .as_ref()
+        .map(TlsClientConfig::read)
+        .transpose()?;
+    let gateway = Gateway {
+        endpoint: tls::endpoint(settings.gateway_address, gateway_tls.as_ref())?,
+        compression: settings.compression,
+        max_message_size: settings.max_message_size,
     };
-    debugv!(settings, debug);
+    info!(
+        "Largest gRPC message size: {} bytes",
+        settings.max_message_size
+    );
+
+    // Shared by every worker, so clients are limited across them
+    let rate_limiter = settings.rate_limit.as_ref().map(|rate_limit| {
+        web::Data::new(RateLimiter::new(
+            rate_limit.requests_per_second,
+            rate_limit.burst,
+        ))
+    });
+
+    let serve_ui = settings.serve_ui;
 
     info!("Starting web-server at {}...", settings.address);
 
     HttpServer::new(move || {
-        let gateway_address = settings.gateway_address;
-
         App::new()
-            .app_data(web::Data::new(gateway_address))
+            .app_data(web::Data::new(gateway.clone()))
+            .configure(|config| {
+                if let Some(rate_limiter) = &rate_limiter {
+                    config.app_data(rate_limiter.clone());
+                }
+            })
+            .wrap(cors(&settings.allowed_origins))
             .wrap(middleware::Logger::default().log_target("@"))
             .wrap(middleware::Compress::default())
-            .service(index)
+            .configure(|config| match serve_ui {
+                true => ui(config),
+                false => {
+                    config.service(index);
+                }
+            })
             .service(search_handler)
+            .service(search_stream_handler)
+            .service(suggest_handler)
+            .service(backlinks_handler)
+            .service(outlinks_handler)
             .service(health_handler)
             .service(enqueue_handler)
             .service(ws_handler)
@@ -278,3 +809,221 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(query: &str) -> web::Query<LinksParams> {
+        web::Query::<LinksParams>::from_query(query).unwrap()
+    }
+
+    #[test]
+    fn test_parse_url_param() {
+        assert_eq!(
+            parse_url_param(query("url=https%3A%2F%2Fexample.com%2Fpage")).unwrap(),
+            Url::parse("https://example.com/page").unwrap()
+        );
+
+        assert_eq!(parse_url_param(query("")).unwrap_err(), "Missing url");
+        assert!(parse_url_param(query("url=not-a-url")).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_cors() {
+        use actix_web::{test, test::TestRequest};
+
+        let app = test::init_service(
+            App::new()
+                .wrap(cors(&["http://localhost:3000".to_string()]))
+                .service(index),
+        )
+        .await;
+
+        let preflight = |origin: &str| {
+            TestRequest::default()
+                .method(actix_web::http::Method::OPTIONS)
+                .uri("/")
+                .insert_header((header::HOST, "localhost:8080"))
+                .insert_header((header::ORIGIN, origin))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+                .to_request()
+        };
+
+        let allowed = test::call_service(&app, preflight("http://localhost:3000")).await;
+        assert_eq!(allowed.status(), StatusCode::OK);
+        assert_eq!(
+            allowed
+                .headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "http://localhost:3000"
+        );
+
+        let same_origin = test::call_service(&app, preflight("http://localhost:8080")).await;
+        assert_eq!(same_origin.status(), StatusCode::OK);
+
+        let denied = test::call_service(&app, preflight("http://evil.com")).await;
+        assert!(
+            denied
+                .headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .is_none()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_ui() {
+        use actix_web::{test, test::TestRequest};
+
+        let app = test::init_service(App::new().configure(ui)).await;
+
+        for (uri, content_type, content) in [
+            ("/", "text/html; charset=utf-8", "/ui/script.js"),
+            (
+                "/ui/script.js",
+                "text/javascript; charset=utf-8",
+                "/search?",
+            ),
+            ("/ui/style.css", "text/css; charset=utf-8", "#results"),
+        ] {
+            let response = test::call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            assert_eq!(
+                response.headers().get(header::CONTENT_TYPE).unwrap(),
+                content_type
+            );
+
+            let body = test::read_body(response).await;
+            assert!(String::from_utf8_lossy(&body).contains(content), "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_rate_limit() {
+        use actix_web::{test, test::TestRequest};
+
+        let gateway = Gateway {
+            endpoint: Endpoint::from_static("http://127.0.0.1:1"),
+            compression: false,
+            max_message_size: googol::settings::DEFAULT_MAX_MESSAGE_SIZE,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(gateway))
+                .app_data(web::Data::new(RateLimiter::new(1., 3)))
+                .service(enqueue_handler)
+                .service(search_handler)
+                .service(health_handler),
+        )
+        .await;
+
+        let client = "10.0.0.1:1234".parse().unwrap();
+        let enqueue = |peer| {
+            TestRequest::post()
+                .uri("/enqueue")
+                .peer_addr(peer)
+                .set_json(json!({"url": "not a url"}))
+                .to_request()
+        };
+
+        // Rejected by the handler until the burst is exhausted
+        for _ in 0..3 {
+            let response = test::call_service(&app, enqueue(client)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+
+        let response = test::call_service(&app, enqueue(client)).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "1");
+
+        // Searches share the same limit
+        let search = TestRequest::get()
+            .uri("/search?words=rust")
+            .peer_addr(client)
+            .to_request();
+        let response = test::call_service(&app, search).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Other clients, and health checks, aren't limited
+        let response = test::call_service(&app, enqueue("10.0.0.2:1234".parse().unwrap())).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let health = TestRequest::get()
+            .uri("/health")
+            .peer_addr(client)
+            .to_request();
+        let response = test::call_service(&app, health).await;
+        assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[test]
+    fn test_status_code() {
+        assert_eq!(status_code(Status::Success), StatusCode::OK);
+        assert_eq!(status_code(Status::InvalidUrl), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            status_code(Status::UnavailableBarrels),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            status_code(Status::Error),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn test_grpc_error_response() {
+        assert_eq!(
+            grpc_error_response(tonic::Status::invalid_argument("bad")).status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            grpc_error_response(tonic::Status::unavailable("down")).status(),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(
+            grpc_error_response(tonic::Status::internal("oops")).status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn test_health_response() {
+        let health = |online_barrels| HealthResponse {
+            online_barrels,
+            total_barrels: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            health_response(&health(0)).status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(health_response(&health(1)).status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_search_response() {
+        let search = |status: Status| SearchResponse {
+            status: status as i32,
+            ..Default::default()
+        };
+
+        // Finding nothing is not an error
+        let response = search_response(search(Status::Success));
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "[]");
+
+        assert_eq!(
+            search_response(search(Status::Error)).status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            search_response(search(Status::UnavailableBarrels)).status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+}
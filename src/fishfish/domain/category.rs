@@ -21,13 +21,15 @@ pub enum FishDomainCategory {
 }
 
 impl FishDomainCategory {
+    /// Whether domains of this category must not be crawled.
+    pub fn is_malicious(&self) -> bool {
+        matches!(self, Self::Malware | Self::Phishing)
+    }
+
     pub fn from_string(s: String) -> Option<Self> {
         match s.len() {
             0 => None,
-            _ => match s.parse::<Self>() {
-                Ok(fish_domain) => Some(fish_domain),
-                Err(_) => None,
-            },
+            _ => s.parse::<Self>().ok(),
         }
     }
 }
@@ -81,6 +83,16 @@ mod tests {
         assert_eq!(Unknown.to_string(), "unknown");
     }
 
+    #[test]
+    fn test_category_is_malicious() {
+        use FishDomainCategory::*;
+
+        assert!(Malware.is_malicious());
+        assert!(Phishing.is_malicious());
+        assert!(!Safe.is_malicious());
+        assert!(!Unknown.is_malicious());
+    }
+
     #[test]
     fn test_category_from_string() {
         use FishDomainCategory::*;
@@ -4,16 +4,50 @@
 
 use crate::{debugv, errorv};
 use domain::{FishDomain, category::FishDomainCategory};
-use log::{debug, error};
-use std::collections::HashMap;
+use log::{debug, error, warn};
+use reqwest::StatusCode;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 use url::Host;
 
 pub mod domain;
 
+/// Default maximum time to wait for the FishFish API.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default base URL of the FishFish API.
+pub const DEFAULT_ENDPOINT: &str = "https://api.fishfish.gg";
+
+/// Time a failed lookup is remembered, during which the host is `Unknown` without asking the
+/// API again.
+pub const FAILURE_TTL: Duration = Duration::from_secs(60);
+
+/// Maximum number of hosts [`FishFish::domain_categories`] requests from the API at once.
+pub const MAX_LOOKUPS: usize = 32;
+
+/// Cached answer of the API for a host.
+#[derive(Debug, Clone)]
+enum Cached {
+    /// The domain info, or `None` if the API doesn't know the host.
+    Known(Option<FishDomain>),
+    /// The request failed at this time.
+    Failed(Instant),
+}
+
 /// Represents the main structure managing host to fish domain mappings.
+///
+/// The cache is only locked to read or record answers, so lookups of several hosts run
+/// concurrently.
 #[derive(Debug)]
 pub struct FishFish {
-    host2domain: HashMap<Host, Option<FishDomain>>,
+    client: reqwest::Client,
+    endpoint: String,
+    timeout: Duration,
+    host2domain: Mutex<HashMap<Host, Cached>>,
 }
 
 impl Default for FishFish {
@@ -26,14 +60,65 @@ impl FishFish {
     /// Creates a new instance of `FishFish`.
     pub fn new() -> Self {
         Self {
-            host2domain: HashMap::new(),
+            client: reqwest::Client::new(),
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            host2domain: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Sets the maximum time to wait for the API.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Time after which a request is abandoned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::fishfish::FishFish;
+    /// use std::time::Duration;
+    ///
+    /// let fishfish = FishFish::new().with_timeout(Duration::from_secs(1));
+    /// ```
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the base URL of the API, e.g. to use a self-hosted mirror or a test server.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - The base URL. Trailing slashes are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::fishfish::FishFish;
+    ///
+    /// let fishfish = FishFish::new().with_endpoint("http://localhost:8000/");
+    /// ```
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Caches the domain info of a host, so it is never requested from the API.
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - The host the domain info belongs to.
+    /// * `domain` - The domain info.
+    pub fn insert(&self, host: Host, domain: FishDomain) {
+        self.host2domain().insert(host, Cached::Known(Some(domain)));
+    }
+
     /// Retrieves the category of the domain associated with the given host.
     ///
     /// This method first checks the cache; if the domain info is not cached,
-    /// it performs an HTTP request to fetch data from the API.
+    /// it performs an HTTP request to fetch data from the API. Hosts unknown to the API are
+    /// cached too, while failed requests are retried once [`FAILURE_TTL`] has passed.
     ///
     /// # Arguments
     ///
@@ -41,52 +126,339 @@ impl FishFish {
     ///
     /// # Returns
     ///
-    /// A `FishDomainCategory` indicating the category of the domain.
-    pub async fn domain_category(&mut self, host: &Host) -> FishDomainCategory {
-        match self.host2domain.get(host) {
-            Some(fish_domain) => match fish_domain {
-                Some(fish_domain) => fish_domain.category,
-                None => FishDomainCategory::Unknown,
-            },
-            None => {
-                let url = format!("https://api.fishfish.gg/v1/domains/{}", host);
+    /// A `FishDomainCategory` indicating the category of the domain. `Unknown` if the API
+    /// could not be reached.
+    pub async fn domain_category(&self, host: &Host) -> FishDomainCategory {
+        match self.cached(host) {
+            Some(category) => category,
+            None => self.lookup(host).await,
+        }
+    }
+
+    /// Retrieves the categories of several hosts, requesting the uncached ones concurrently
+    /// and each only once.
+    ///
+    /// At most [`MAX_LOOKUPS`] hosts are requested, the following uncached ones being
+    /// `Unknown`, so a request with many hosts can't wait on the API for long.
+    ///
+    /// # Returns
+    ///
+    /// The category of every host, as by [`FishFish::domain_category`].
+    pub async fn domain_categories<I>(&self, hosts: I) -> HashMap<Host, FishDomainCategory>
+    where
+        I: IntoIterator<Item = Host>,
+    {
+        let mut categories = HashMap::new();
+        let mut uncached = vec![];
+        let mut seen = HashSet::new();
 
-                let response = reqwest::get(url).await.expect("Failed to send request");
+        for host in hosts {
+            if !seen.insert(host.clone()) {
+                continue;
+            }
 
-                if !response.status().is_success() {
-                    self.host2domain.insert(host.clone(), None);
-                    return FishDomainCategory::Unknown;
+            match self.cached(&host) {
+                Some(category) => {
+                    categories.insert(host, category);
                 }
+                None => uncached.push(host),
+            }
+        }
 
-                let response = response.text().await.unwrap();
+        if uncached.len() > MAX_LOOKUPS {
+            warn!(
+                "Categorizing only {} of {} uncached hosts",
+                MAX_LOOKUPS,
+                uncached.len()
+            );
 
-                debugv!(response);
+            for host in uncached.split_off(MAX_LOOKUPS) {
+                categories.insert(host, FishDomainCategory::Unknown);
+            }
+        }
 
-                match serde_json::from_str::<FishDomain>(&response) {
-                    Ok(domain) => {
-                        self.host2domain.insert(host.clone(), Some(domain.clone()));
+        let looked_up = futures::future::join_all(uncached.iter().map(|host| self.lookup(host)));
+        categories.extend(uncached.iter().cloned().zip(looked_up.await));
 
-                        domain.category
-                    }
-                    Err(e) => {
-                        errorv!(e);
+        categories
+    }
 
-                        FishDomainCategory::Unknown
-                    }
-                }
+    /// The cache, recovered if a thread panicked while holding it.
+    fn host2domain(&self) -> std::sync::MutexGuard<'_, HashMap<Host, Cached>> {
+        self.host2domain.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the cached category of `host`, or `None` if it must be requested.
+    fn cached(&self, host: &Host) -> Option<FishDomainCategory> {
+        match self.host2domain().get(host)? {
+            Cached::Known(fish_domain) => Some(
+                fish_domain
+                    .as_ref()
+                    .map_or(FishDomainCategory::Unknown, |fish_domain| {
+                        fish_domain.category
+                    }),
+            ),
+            Cached::Failed(failed) if failed.elapsed() < FAILURE_TTL => {
+                Some(FishDomainCategory::Unknown)
+            }
+            Cached::Failed(_) => None,
+        }
+    }
+
+    /// Requests the category of `host` from the API, and caches the answer.
+    async fn lookup(&self, host: &Host) -> FishDomainCategory {
+        let (cached, category) = match self.fetch(host).await {
+            Ok(fish_domain) => {
+                let category = fish_domain
+                    .as_ref()
+                    .map_or(FishDomainCategory::Unknown, |fish_domain| {
+                        fish_domain.category
+                    });
+
+                (Cached::Known(fish_domain), category)
+            }
+            Err(e) => {
+                warn!("Failed to categorize {}: {}", host, e);
+
+                (Cached::Failed(Instant::now()), FishDomainCategory::Unknown)
+            }
+        };
+
+        self.host2domain().insert(host.clone(), cached);
+
+        category
+    }
+
+    /// Requests the domain info of `host` from the API.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Some(domain))` if the API knows the host.
+    /// - `Ok(None)` if it does not.
+    /// - `Err(FishFishError)` if the request failed, and may succeed later.
+    async fn fetch(&self, host: &Host) -> Result<Option<FishDomain>, FishFishError> {
+        let url = format!("{}/v1/domains/{}", self.endpoint, host);
+
+        let response = self.client.get(&url).timeout(self.timeout).send().await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => return Ok(None),
+            status if !status.is_success() => return Err(FishFishError::HttpStatus(status)),
+            _ => {}
+        }
+
+        let response = response.text().await?;
+
+        debugv!(response);
+
+        match serde_json::from_str::<FishDomain>(&response) {
+            Ok(domain) => Ok(Some(domain)),
+            Err(e) => {
+                errorv!(e);
+
+                Ok(None)
             }
         }
     }
 }
 
-//#[tokio::test]
-//async fn test_fishfish_new() {
-//    let mut fishfish = FishFish::new();
-//
-//    for (domain, category) in [("stieamcommunitiy.com", FishDomainCategory::Phishing)]
-//        .iter()
-//        .map(|(d, c)| (Host::parse(d).unwrap(), c))
-//    {
-//        assert_eq!(fishfish.domain_category(&domain).await, *category);
-//    }
-//}
+/// Errors of requests to the FishFish API.
+#[derive(Debug)]
+pub enum FishFishError {
+    /// The request failed or timed out.
+    Request(reqwest::Error),
+    /// The API answered with an unexpected status.
+    HttpStatus(StatusCode),
+}
+
+impl fmt::Display for FishFishError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FishFishError::Request(e) => write!(f, "{}", e),
+            FishFishError::HttpStatus(status) => write!(f, "API answered {}", status),
+        }
+    }
+}
+
+impl std::error::Error for FishFishError {}
+
+impl From<reqwest::Error> for FishFishError {
+    fn from(e: reqwest::Error) -> Self {
+        FishFishError::Request(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Serves `response` to every request for a domain, returning the server's URL and request
+    /// counter. Requests to other paths are answered with a 400.
+    async fn spawn_api(response: &'static str) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let counter = Arc::clone(&requests);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+
+                let mut buffer = [0; 1024];
+                let read = stream.read(&mut buffer).await.unwrap_or_default();
+
+                let response = if buffer[..read].starts_with(b"GET /v1/domains/") {
+                    response
+                } else {
+                    "HTTP/1.1 400 Bad Request\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        (format!("http://{}", address), requests)
+    }
+
+    #[tokio::test]
+    async fn test_server_error_yields_unknown() {
+        let (endpoint, requests) = spawn_api(
+            "HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+        )
+        .await;
+        let fishfish = FishFish::new().with_endpoint(&endpoint);
+        let host = Host::parse("example.com").unwrap();
+
+        assert_eq!(
+            fishfish.domain_category(&host).await,
+            FishDomainCategory::Unknown
+        );
+
+        // Failures are cached for a while, so the API isn't hammered while down
+        fishfish.domain_category(&host).await;
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // And the host is requested again afterwards
+        if let Some(failed) = Instant::now().checked_sub(FAILURE_TTL) {
+            fishfish
+                .host2domain()
+                .insert(host.clone(), Cached::Failed(failed));
+            fishfish.domain_category(&host).await;
+            assert_eq!(requests.load(Ordering::SeqCst), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_domain_categories() {
+        let (endpoint, requests) =
+            spawn_api("HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                .await;
+        let fishfish = FishFish::new().with_endpoint(&endpoint);
+        let host = |i: usize| Host::parse(&format!("{}.example.com", i)).unwrap();
+
+        // Each host is requested once, and no more than the limit
+        let hosts = (0..MAX_LOOKUPS + 10).chain(0..5).map(host);
+        let categories = fishfish.domain_categories(hosts).await;
+
+        assert_eq!(categories.len(), MAX_LOOKUPS + 10);
+        assert!(
+            categories
+                .values()
+                .all(|category| *category == FishDomainCategory::Unknown)
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), MAX_LOOKUPS);
+    }
+
+    #[tokio::test]
+    async fn test_not_found_is_cached() {
+        let (endpoint, requests) =
+            spawn_api("HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                .await;
+        let fishfish = FishFish::new().with_endpoint(&endpoint);
+        let host = Host::parse("example.com").unwrap();
+
+        assert_eq!(
+            fishfish.domain_category(&host).await,
+            FishDomainCategory::Unknown
+        );
+        fishfish.domain_category(&host).await;
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_yields_unknown() {
+        // Accepts connections but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+        let fishfish = FishFish::new()
+            .with_endpoint(&endpoint)
+            .with_timeout(Duration::from_millis(100));
+        let host = Host::parse("example.com").unwrap();
+
+        assert_eq!(
+            fishfish.domain_category(&host).await,
+            FishDomainCategory::Unknown
+        );
+    }
+
+    #[tokio::test]
+    async fn test_phishing_response() {
+        let (endpoint, _) = spawn_api(concat!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\nconnection: close\r\n",
+            "content-length: 90\r\n\r\n",
+            r#"{"added":0,"category":"phishing","checked":0,"description":"","domain":null,"target":null}"#,
+        ))
+        .await;
+        // Trailing slashes don't end up in the request path
+        let fishfish = FishFish::new().with_endpoint(&format!("{}/", endpoint));
+        let host = Host::parse("stieamcommunitiy.com").unwrap();
+
+        assert_eq!(
+            fishfish.domain_category(&host).await,
+            FishDomainCategory::Phishing
+        );
+    }
+
+    #[test]
+    fn test_endpoint_trailing_slashes() {
+        assert_eq!(FishFish::new().endpoint, DEFAULT_ENDPOINT);
+        assert_eq!(
+            FishFish::new()
+                .with_endpoint("http://localhost:8000//")
+                .endpoint,
+            "http://localhost:8000"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cached_domain_category() {
+        let fishfish = FishFish::new();
+        let host = Host::parse("stieamcommunitiy.com").unwrap();
+
+        fishfish.insert(
+            host.clone(),
+            FishDomain {
+                added: 0,
+                category: FishDomainCategory::Phishing,
+                checked: 0,
+                description: String::new(),
+                domain: Some(host.to_string()),
+                target: None,
+            },
+        );
+
+        // Cached hosts are not requested from the API
+        assert_eq!(
+            fishfish.domain_category(&host).await,
+            FishDomainCategory::Phishing
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! Disk-backed cache of index requests that could not be delivered to any barrel.
+//!
+//! Requests are stored as length-delimited protobuf messages, in the order they were received.
+//! The cache is bounded: once full, the oldest request is dropped to make room for a new one.
+//!
+//! Requests taken out to be delivered stay in the file until the undelivered ones are put back,
+//! so a crash in the middle of a delivery attempt loses nothing.
+
+use crate::proto::IndexRequest;
+use log::{error, warn};
+use prost::Message;
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Default maximum number of cached index requests.
+pub const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// A bounded FIFO of pending `IndexRequest`s, persisted to disk on every change.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::{gateway::cache::IndexCache, proto::IndexRequest};
+///
+/// // In-memory cache, not persisted
+/// let mut cache = IndexCache::default();
+/// cache.push(IndexRequest::default()).unwrap();
+/// assert_eq!(cache.len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct IndexCache {
+    /// File the cache is persisted to. `None` keeps the cache in memory only.
+    filepath: Option<PathBuf>,
+    /// Maximum number of requests kept in the cache.
+    max_entries: usize,
+    /// Pending requests, oldest first.
+    pending: VecDeque<IndexRequest>,
+    /// Requests taken out by `drain`, persisted until they are given back to `requeue`.
+    in_flight: Vec<IndexRequest>,
+}
+
+impl Default for IndexCache {
+    fn default() -> Self {
+        Self {
+            filepath: None,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            pending: VecDeque::new(),
+            in_flight: Vec::new(),
+        }
+    }
+}
+
+impl IndexCache {
+    /// Loads the cache from `filepath`, or creates an empty one if the file does not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `filepath` - File the cache is persisted to.
+    /// * `max_entries` - Maximum number of requests kept in the cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the file exists but cannot be read or decoded.
+    pub fn load<P>(filepath: P, max_entries: usize) -> Result<Self, io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut pending = VecDeque::new();
+
+        match fs::read(&filepath) {
+            Ok(bytes) => {
+                let mut buffer = bytes.as_slice();
+
+                while !buffer.is_empty() {
+                    let request = IndexRequest::decode_length_delimited(&mut buffer)
+                        .map_err(|e| io::Error::other(format!("Deserialization error: {}", e)))?;
+                    pending.push_back(request);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let mut cache = Self {
+            filepath: Some(filepath.as_ref().to_path_buf()),
+            max_entries,
+            pending,
+            in_flight: Vec::new(),
+        };
+        cache.truncate();
+
+        Ok(cache)
+    }
+
+    /// Number of pending requests, not counting those being delivered.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether there are no pending requests.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Returns the oldest pending request without removing it.
+    pub fn front(&self) -> Option<&IndexRequest> {
+        self.pending.front()
+    }
+
+    /// Adds a request to the cache and persists it.
+    ///
+    /// If the cache is full, the oldest request is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the cache cannot be written to disk.
+    pub fn push(&mut self, request: IndexRequest) -> Result<(), io::Error> {
+        self.pending.push_back(request);
+        self.truncate();
+        self.save()
+    }
+
+    /// Takes every pending request out of the cache, oldest first, to attempt delivering them.
+    ///
+    /// They stay in the file until the undelivered ones are given back to `requeue`. Only one
+    /// delivery attempt may be running at a time: until then, nothing is taken out.
+    pub fn drain(&mut self) -> Vec<IndexRequest> {
+        if !self.in_flight.is_empty() {
+            return vec![];
+        }
+
+        self.in_flight = self.pending.drain(..).collect();
+        self.in_flight.clone()
+    }
+
+    /// Ends a delivery attempt started by `drain`, putting the `undelivered` requests back in
+    /// front of the cache, and persists it without the delivered ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the cache cannot be written to disk.
+    pub fn requeue(&mut self, undelivered: Vec<IndexRequest>) -> Result<(), io::Error> {
+        self.in_flight.clear();
+
+        for request in undelivered.into_iter().rev() {
+            self.pending.push_front(request);
+        }
+        self.truncate();
+        self.save()
+    }
+
+    /// Drops the oldest requests until the cache fits in `max_entries`.
+    fn truncate(&mut self) {
+        while self.pending.len() > self.max_entries {
+            self.pending.pop_front();
+            warn!(
+                "Index cache full ({} entries), dropping oldest request",
+                self.max_entries
+            );
+        }
+    }
+
+    /// Writes the cache to disk, replacing the previous file atomically.
+    fn save(&self) -> Result<(), io::Error> {
+        let Some(filepath) = &self.filepath else {
+            return Ok(());
+        };
+
+        let mut bytes = Vec::new();
+        for request in self.in_flight.iter().chain(&self.pending) {
+            request
+                .encode_length_delimited(&mut bytes)
+                .map_err(|e| io::Error::other(format!("Serialization error: {}", e)))?;
+        }
+
+        let mut temp_filepath = filepath.clone().into_os_string();
+        temp_filepath.push(".tmp");
+
+        File::create(&temp_filepath)
+            .and_then(|mut file| {
+                file.write_all(&bytes)?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&temp_filepath, filepath))
+            .inspect_err(|e| error!("Failed to write to file {}: {}", filepath.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::{Index, Page};
+
+    fn create_request(url: &str) -> IndexRequest {
+        IndexRequest {
+            index: Some(Index {
+                page: Some(Page {
+                    url: url.to_string(),
+                    ..Default::default()
+                }),
+                words: vec!["rust".to_string()],
+                outlinks: vec![],
+                title_words: vec![],
+            }),
+            depth: 0,
+        }
+    }
+
+    #[test]
+    fn test_persistence() {
+        let filepath = ".test_index_cache.bin";
+
+        let mut cache = IndexCache::load(filepath, 10).unwrap();
+        cache.push(create_request("https://example.com/1")).unwrap();
+        cache.push(create_request("https://example.com/2")).unwrap();
+        cache.push(create_request("https://example.com/3")).unwrap();
+
+        // Delivered requests are not cached anymore
+        let mut requests = cache.drain();
+        let undelivered = requests.split_off(2);
+        cache.requeue(undelivered).unwrap();
+
+        let cache = IndexCache::load(filepath, 10).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(
+            cache.front(),
+            Some(&create_request("https://example.com/3"))
+        );
+
+        fs::remove_file(filepath).expect("Failed to delete temp file");
+    }
+
+    #[test]
+    fn test_bounded() {
+        let mut cache = IndexCache {
+            max_entries: 2,
+            ..Default::default()
+        };
+
+        for i in 0..5 {
+            cache
+                .push(create_request(&format!("https://example.com/{}", i)))
+                .unwrap();
+        }
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(
+            cache.front(),
+            Some(&create_request("https://example.com/3"))
+        );
+    }
+
+    #[test]
+    fn test_drain_and_requeue() {
+        let filepath = ".test_index_cache_requeue.bin";
+
+        let mut cache = IndexCache::load(filepath, 10).unwrap();
+        for i in 0..3 {
+            cache
+                .push(create_request(&format!("https://example.com/{}", i)))
+                .unwrap();
+        }
+
+        let requests = cache.drain();
+        assert_eq!(requests.len(), 3);
+        assert!(cache.is_empty());
+        // Nothing else is taken out until the attempt ends
+        assert!(cache.drain().is_empty());
+
+        // Requests being delivered are still persisted along with new ones
+        cache.push(create_request("https://example.com/3")).unwrap();
+        assert_eq!(IndexCache::load(filepath, 10).unwrap().len(), 4);
+
+        // Undelivered requests go back in front, in order
+        cache
+            .requeue(vec![requests[0].clone(), requests[2].clone()])
+            .unwrap();
+
+        let cache = IndexCache::load(filepath, 10).unwrap();
+        assert_eq!(
+            cache.pending,
+            [0, 2, 3]
+                .map(|i| create_request(&format!("https://example.com/{}", i)))
+                .to_vec()
+        );
+
+        fs::remove_file(filepath).expect("Failed to delete temp file");
+    }
+
+    #[test]
+    fn test_load_nonexistent_file() {
+        let cache = IndexCache::load("nonexistent_index_cache.bin", 10).unwrap();
+
+        assert!(cache.is_empty());
+    }
+}
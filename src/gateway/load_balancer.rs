@@ -1,44 +1,95 @@
 use super::status::ResponseTime;
+use crate::proto::HealthRequest;
 use crate::{
     address::Address,
     proto::{BarrelStatus, barrel_service_client::BarrelServiceClient},
+    settings::{DEFAULT_MAX_MESSAGE_SIZE, tls},
+};
+use futures::future::{BoxFuture, join_all};
+use log::{error, info, warn};
+use std::{collections::HashSet, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{
+    sync::Mutex as AsyncMutex,
+    task::JoinHandle,
+    time::{Instant, sleep, timeout},
 };
-use futures::future::BoxFuture;
-use log::error;
-use std::{collections::HashSet, net::SocketAddr};
-use tokio::time::Instant;
 use tonic::{
-    Response, Status,
-    transport::{Channel, Error},
+    Code, Response, Status,
+    codec::CompressionEncoding,
+    transport::{Channel, ClientTlsConfig, Endpoint, Error},
 };
 
+/// Default time a barrel has to answer a request.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Default)]
 pub struct Barrel {
     pub address: Address,
     pub online: bool,
     pub index_size_bytes: usize,
+    /// Client reused across requests, until a request fails.
+    client: Option<BarrelServiceClient<Channel>>,
+    /// Connects over TLS if set.
+    tls: Option<ClientTlsConfig>,
+    /// Whether requests are gzip-compressed.
+    compression: bool,
+    /// Largest request sent or response received, in bytes.
+    max_message_size: usize,
 }
 
 impl Barrel {
-    fn new(address: SocketAddr) -> Self {
+    fn new(address: SocketAddr, load_balancer: &LoadBalancer) -> Self {
         Self {
             address: Address::new(address),
             online: false,
             index_size_bytes: 0,
+            client: None,
+            tls: load_balancer.tls.clone(),
+            compression: load_balancer.compression,
+            max_message_size: load_balancer.max_message_size,
         }
     }
 
     fn mark_failure(&mut self) {
         self.online = false;
+        self.client = None;
     }
 
     fn mark_success(&mut self) {
         self.online = true;
     }
 
-    async fn connect(&self) -> Result<BarrelServiceClient<Channel>, Error> {
-        let address = format!("http://{}", self.address);
-        BarrelServiceClient::connect(address).await
+    /// Returns the cached client, connecting first if there is none.
+    async fn connect(
+        &mut self,
+        deadline: Duration,
+    ) -> Result<BarrelServiceClient<Channel>, String> {
+        if let Some(client) = &self.client {
+            return Ok(client.clone());
+        }
+
+        let endpoint = self.endpoint().map_err(|e| e.to_string())?;
+        let client = match timeout(deadline, BarrelServiceClient::connect(endpoint)).await {
+            Ok(client) => client.map_err(|e| e.to_string())?,
+            Err(_) => return Err(format!("timed out after {:?}", deadline)),
+        };
+
+        // Responses are compressed whenever the barrel is configured to
+        let client = client
+            .accept_compressed(CompressionEncoding::Gzip)
+            .max_decoding_message_size(self.max_message_size)
+            .max_encoding_message_size(self.max_message_size);
+        let client = match self.compression {
+            true => client.send_compressed(CompressionEncoding::Gzip),
+            false => client,
+        };
+        self.client = Some(client.clone());
+
+        Ok(client)
+    }
+
+    fn endpoint(&self) -> Result<Endpoint, Error> {
+        tls::endpoint(self.address.as_socket_addr(), self.tls.as_ref())
     }
 
     fn get_status(&self) -> BarrelStatus {
@@ -50,17 +101,62 @@ impl Barrel {
     }
 }
 
-#[derive(Debug, Default)]
+/// Decides which barrel `LoadBalancer::send_until` tries first.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Cycle through the barrels, one request at a time.
+    #[default]
+    RoundRobin,
+    /// Prefer the online barrel reporting the smallest index.
+    LeastIndexSize,
+}
+
+#[derive(Debug)]
 pub struct LoadBalancer {
     pub barrels: Vec<Barrel>,
+    strategy: Strategy,
+    /// Barrel the next round-robin request starts from.
+    next: usize,
+    /// Time a barrel has to connect, and then to answer a request.
+    timeout: Duration,
+    /// Barrels are connected to over TLS if set.
+    tls: Option<ClientTlsConfig>,
+    /// Whether requests to the barrels are gzip-compressed.
+    compression: bool,
+    /// Largest request sent to or response received from a barrel, in bytes.
+    max_message_size: usize,
+}
+
+impl Default for LoadBalancer {
+    fn default() -> Self {
+        Self {
+            barrels: vec![],
+            strategy: Strategy::default(),
+            next: 0,
+            timeout: DEFAULT_TIMEOUT,
+            tls: None,
+            compression: false,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+        }
+    }
 }
 
+/// Result of sending a request to the barrels.
 #[derive(Debug)]
 pub enum LBResult<T> {
+    /// The responses, with the number of barrels that couldn't be reached.
     Ok(T, usize, ResponseTime),
+    /// No barrel answered successfully. Holds the number of barrels that couldn't be reached,
+    /// so any other barrel answered with an error.
     Offline(usize),
 }
 
+/// Whether `status` means the barrel couldn't be reached, rather than it answering with an
+/// error.
+fn is_unreachable(status: &Status) -> bool {
+    matches!(status.code(), Code::Unavailable | Code::Unknown)
+}
+
 impl<'a> IntoIterator for &'a mut LoadBalancer {
     type Item = &'a mut Barrel;
     type IntoIter = std::slice::IterMut<'a, Barrel>;
@@ -71,12 +167,215 @@ impl<'a> IntoIterator for &'a mut LoadBalancer {
 }
 
 impl LoadBalancer {
+    /// Creates a `LoadBalancer` over `barrels`.
+    ///
+    /// `barrels` may be empty, in which case every request is answered with
+    /// `LBResult::Offline(0)` until barrels are added with [`LoadBalancer::add_barrel`].
     pub fn new(barrels: &HashSet<SocketAddr>) -> Self {
-        assert!(!barrels.is_empty());
+        let mut load_balancer = Self::default();
+        for address in barrels {
+            load_balancer.add_barrel(*address);
+        }
 
-        Self {
-            barrels: barrels.iter().map(|addr| Barrel::new(*addr)).collect(),
+        load_balancer
+    }
+
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets the time a barrel has to connect, and then to answer a request, before being
+    /// considered offline.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets whether requests to the barrels, including those added later, are gzip-compressed.
+    /// Compressed responses are accepted either way.
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        for barrel in &mut self.barrels {
+            barrel.compression = compression;
+            barrel.client = None;
+        }
+        self.compression = compression;
+        self
+    }
+
+    /// Sets the largest request sent to or response received from the barrels, including
+    /// those added later, in bytes.
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        for barrel in &mut self.barrels {
+            barrel.max_message_size = max_message_size;
+            barrel.client = None;
         }
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Largest request sent to or response received from the barrels, in bytes.
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+
+    /// Connects to every barrel over TLS, see [`LoadBalancer::set_tls`].
+    pub fn with_tls(mut self, tls: ClientTlsConfig) -> Self {
+        self.set_tls(Some(tls));
+        self
+    }
+
+    /// Sets whether barrels, including those added later, are connected to over TLS.
+    ///
+    /// Open connections are dropped, so the next requests use the new setting.
+    pub fn set_tls(&mut self, tls: Option<ClientTlsConfig>) {
+        for barrel in &mut self.barrels {
+            barrel.tls = tls.clone();
+            barrel.client = None;
+        }
+        self.tls = tls;
+    }
+
+    /// Adds a barrel, initially offline until it answers a request or a health check.
+    ///
+    /// # Returns
+    /// `false` if a barrel with this address already exists, in which case nothing changes.
+    pub fn add_barrel(&mut self, address: SocketAddr) -> bool {
+        if self
+            .barrels
+            .iter()
+            .any(|barrel| barrel.address.as_socket_addr() == address)
+        {
+            return false;
+        }
+
+        let barrel = Barrel::new(address, self);
+        self.barrels.push(barrel);
+
+        true
+    }
+
+    /// Removes the barrel with the given address.
+    ///
+    /// # Returns
+    /// `false` if no barrel has this address.
+    pub fn remove_barrel(&mut self, address: &SocketAddr) -> bool {
+        let len = self.barrels.len();
+
+        self.barrels
+            .retain(|barrel| barrel.address.as_socket_addr() != *address);
+
+        self.barrels.len() != len
+    }
+
+    /// Index of the barrel the next request should start from, according to the strategy.
+    fn select(&mut self) -> usize {
+        if self.barrels.is_empty() {
+            return 0;
+        }
+
+        match self.strategy {
+            Strategy::RoundRobin => {
+                let selected = self.next % self.barrels.len();
+                self.next = (selected + 1) % self.barrels.len();
+                selected
+            }
+            Strategy::LeastIndexSize => self
+                .barrels
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, barrel)| (!barrel.online, barrel.index_size_bytes))
+                .map_or(0, |(i, _)| i),
+        }
+    }
+
+    /// Calls the `health` RPC of every barrel concurrently and updates their `online` status.
+    ///
+    /// The lock is only held to read the barrels and to record the results, so requests are
+    /// not blocked while the barrels are being checked.
+    ///
+    /// # Arguments
+    /// * `load_balancer` - The shared load balancer.
+    /// * `deadline` - Time a barrel has to answer before being considered offline.
+    pub async fn check_health(load_balancer: &AsyncMutex<Self>, deadline: Duration) {
+        let endpoints: Vec<_> = load_balancer
+            .lock()
+            .await
+            .barrels
+            .iter()
+            .map(|barrel| (barrel.address.as_socket_addr(), barrel.endpoint()))
+            .collect();
+
+        let results = join_all(endpoints.into_iter().map(|(address, endpoint)| async move {
+            let health = async {
+                let mut client = BarrelServiceClient::connect(endpoint.ok()?).await.ok()?;
+                client.health(HealthRequest {}).await.ok()
+            };
+
+            (
+                address,
+                matches!(timeout(deadline, health).await, Ok(Some(_))),
+            )
+        }))
+        .await;
+
+        load_balancer.lock().await.apply_health(results);
+    }
+
+    /// Records the results of a health check.
+    ///
+    /// Results are matched by address, since barrels may have been added or removed while
+    /// the check was running. Results for barrels that are gone are ignored.
+    fn apply_health(&mut self, results: Vec<(SocketAddr, bool)>) {
+        for (address, online) in results {
+            let Some(barrel) = self
+                .barrels
+                .iter_mut()
+                .find(|barrel| barrel.address.as_socket_addr() == address)
+            else {
+                continue;
+            };
+
+            match (barrel.online, online) {
+                (false, true) => info!("Barrel {} is back online", barrel.address),
+                (true, false) => warn!("Barrel {} went offline", barrel.address),
+                _ => {}
+            }
+
+            if online {
+                barrel.mark_success();
+            } else {
+                barrel.mark_failure();
+            }
+        }
+    }
+
+    /// Spawns a background task that checks the health of every barrel each `interval`.
+    ///
+    /// # Arguments
+    /// * `load_balancer` - The shared load balancer.
+    /// * `interval` - Time between health checks.
+    ///
+    /// # Returns
+    /// The `JoinHandle` of the spawned task.
+    pub fn spawn_health_checker(
+        load_balancer: Arc<AsyncMutex<Self>>,
+        interval: Duration,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                Self::check_health(&load_balancer, interval).await;
+                sleep(interval).await;
+            }
+        })
+    }
+
+    /// Addresses of every barrel, online or not.
+    pub fn addresses(&self) -> Vec<SocketAddr> {
+        self.barrels
+            .iter()
+            .map(|barrel| barrel.address.as_socket_addr())
+            .collect()
     }
 
     pub fn get_barrels_status(&self) -> Vec<BarrelStatus> {
@@ -86,35 +385,81 @@ impl LoadBalancer {
             .collect()
     }
 
-    pub async fn broadcast<F, T>(&mut self, mut f: F) -> LBResult<Vec<T>>
+    pub async fn broadcast<F, T>(&mut self, f: F) -> LBResult<Vec<T>>
     where
         F: FnMut(
                 &mut Barrel,
                 BarrelServiceClient<Channel>,
             ) -> BoxFuture<'static, Result<Response<T>, Status>>
             + Send,
+    {
+        self.broadcast_and_inspect(f, |_, _| {}).await
+    }
+
+    /// Like `broadcast`, but calls `inspect` with each barrel and its response, e.g. to record
+    /// what the barrel reported.
+    pub async fn broadcast_and_inspect<F, I, T>(
+        &mut self,
+        mut f: F,
+        mut inspect: I,
+    ) -> LBResult<Vec<T>>
+    where
+        F: FnMut(
+                &mut Barrel,
+                BarrelServiceClient<Channel>,
+            ) -> BoxFuture<'static, Result<Response<T>, Status>>
+            + Send,
+        I: FnMut(&mut Barrel, &T) + Send,
     {
         let mut offline = 0;
         let mut responses = vec![];
         let mut avg_response = ResponseTime::default();
 
-        for barrel in self.into_iter() {
-            let start_instant = Instant::now();
+        let start_instant = Instant::now();
 
-            match barrel.connect().await {
-                Ok(client) => {
-                    if let Ok(response) = f(barrel, client).await {
-                        barrel.mark_success();
-                        avg_response.new_sample(start_instant);
-                        responses.push(response.into_inner());
-                    } else {
-                        barrel.mark_failure();
-                        offline += 1;
-                    }
-                }
+        let deadline = self.timeout;
+        let clients = join_all(
+            self.barrels
+                .iter_mut()
+                .map(|barrel| barrel.connect(deadline)),
+        )
+        .await;
+
+        // Requests are built one barrel at a time, but awaited concurrently
+        let requests: Vec<_> = self
+            .barrels
+            .iter_mut()
+            .zip(clients)
+            .map(|(barrel, client)| match client {
+                Ok(client) => Some(f(barrel, client)),
                 Err(e) => {
-                    barrel.mark_failure();
                     error!("Error connecting to {}: {}", barrel.address, e);
+                    None
+                }
+            })
+            .collect();
+
+        let results = join_all(requests.into_iter().map(|request| async move {
+            let response = timeout(deadline, request?).await.ok()?;
+            Some((response, start_instant.elapsed()))
+        }))
+        .await;
+
+        for (barrel, result) in self.barrels.iter_mut().zip(results) {
+            match result {
+                Some((Ok(response), elapsed)) => {
+                    barrel.mark_success();
+                    avg_response.add_sample(elapsed);
+                    let response = response.into_inner();
+                    inspect(barrel, &response);
+                    responses.push(response);
+                }
+                Some((Err(e), _)) if !is_unreachable(&e) => {
+                    barrel.mark_success();
+                    error!("Barrel {} failed: {}", barrel.address, e);
+                }
+                _ => {
+                    barrel.mark_failure();
                     offline += 1;
                 }
             }
@@ -123,11 +468,70 @@ impl LoadBalancer {
         if !responses.is_empty() {
             LBResult::Ok(responses, offline, avg_response)
         } else {
-            let offline = self.barrels.len();
             LBResult::Offline(offline)
         }
     }
 
+    /// Like `broadcast_and_inspect`, but only sends the request to the barrel at `address`.
+    ///
+    /// # Returns
+    /// `LBResult::Offline` if the barrel failed to answer, or if no barrel has this address.
+    pub async fn send_to_and_inspect<F, I, T>(
+        &mut self,
+        address: SocketAddr,
+        f: F,
+        inspect: I,
+    ) -> LBResult<T>
+    where
+        F: FnOnce(
+                &mut Barrel,
+                BarrelServiceClient<Channel>,
+            ) -> BoxFuture<'static, Result<Response<T>, Status>>
+            + Send,
+        I: FnOnce(&mut Barrel, &T) + Send,
+    {
+        let deadline = self.timeout;
+        let Some(barrel) = self
+            .barrels
+            .iter_mut()
+            .find(|barrel| barrel.address.as_socket_addr() == address)
+        else {
+            error!("Unknown barrel {}", address);
+            return LBResult::Offline(0);
+        };
+
+        let start_time = Instant::now();
+
+        let client = match barrel.connect(deadline).await {
+            Ok(client) => client,
+            Err(e) => {
+                barrel.mark_failure();
+                error!("Error connecting to {}: {}", barrel.address, e);
+                return LBResult::Offline(1);
+            }
+        };
+
+        match timeout(deadline, f(barrel, client)).await {
+            Ok(Ok(response)) => {
+                barrel.mark_success();
+                let mut response_time = ResponseTime::default();
+                response_time.new_sample(start_time);
+                let response = response.into_inner();
+                inspect(barrel, &response);
+                LBResult::Ok(response, 0, response_time)
+            }
+            Ok(Err(e)) if !is_unreachable(&e) => {
+                barrel.mark_success();
+                error!("Barrel {} failed: {}", barrel.address, e);
+                LBResult::Offline(0)
+            }
+            _ => {
+                barrel.mark_failure();
+                LBResult::Offline(1)
+            }
+        }
+    }
+
     pub async fn send_until<T, F>(&mut self, mut f: F) -> LBResult<T>
     where
         F: FnMut(BarrelServiceClient<Channel>) -> BoxFuture<'static, Result<Response<T>, Status>>
@@ -137,17 +541,39 @@ impl LoadBalancer {
         let mut offline = 0;
         let mut avg_response = ResponseTime::default();
 
-        for barrel in &mut self.barrels.iter_mut() {
+        // Start from the selected barrel, falling back to the following ones
+        let start = self.select();
+        let len = self.barrels.len();
+
+        for i in 0..len {
+            let barrel = &mut self.barrels[(start + i) % len];
             let start_time = Instant::now();
 
-            match barrel.connect().await {
-                Ok(client) => {
-                    if let Ok(response) = f(client).await {
+            match barrel.connect(self.timeout).await {
+                Ok(client) => match timeout(self.timeout, f(client)).await {
+                    Ok(Ok(response)) => {
                         barrel.mark_success();
                         avg_response.new_sample(start_time);
                         return LBResult::Ok(response.into_inner(), offline, avg_response);
                     }
-                }
+                    Ok(Err(e)) if !is_unreachable(&e) => {
+                        barrel.mark_success();
+                        error!("Barrel {} failed: {}", barrel.address, e);
+                    }
+                    Ok(Err(e)) => {
+                        barrel.mark_failure();
+                        error!("Error reaching {}: {}", barrel.address, e);
+                        offline += 1;
+                    }
+                    Err(_) => {
+                        barrel.mark_failure();
+                        warn!(
+                            "Barrel {} timed out after {:?}",
+                            barrel.address, self.timeout
+                        );
+                        offline += 1;
+                    }
+                },
                 Err(e) => {
                     barrel.mark_failure();
                     error!("Error connecting to {}: {}", barrel.address, e);
@@ -156,7 +582,465 @@ impl LoadBalancer {
             }
         }
 
-        let offline = self.barrels.len();
         LBResult::Offline(offline)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::barrel_service_server::BarrelServiceServer;
+    use crate::settings::tls::{TlsClientConfig, TlsServerConfig};
+    use std::collections::HashMap;
+    use tokio::net::TcpListener;
+    use tonic::transport::{Server, server::TcpIncoming};
+
+    fn create_load_balancer(strategy: Strategy) -> LoadBalancer {
+        let barrels = ["127.0.0.1:1", "127.0.0.1:2", "127.0.0.1:3"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+
+        LoadBalancer::new(&barrels).with_strategy(strategy)
+    }
+
+    #[test]
+    fn test_select_round_robin() {
+        let mut load_balancer = create_load_balancer(Strategy::RoundRobin);
+
+        let selected: Vec<usize> = (0..4).map(|_| load_balancer.select()).collect();
+
+        assert_eq!(selected, vec![0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn test_select_least_index_size() {
+        let mut load_balancer = create_load_balancer(Strategy::LeastIndexSize);
+
+        for (barrel, size) in load_balancer.barrels.iter_mut().zip([30, 10, 20]) {
+            barrel.online = true;
+            barrel.index_size_bytes = size;
+        }
+        assert_eq!(load_balancer.select(), 1);
+
+        // Offline barrels are only picked if no barrel is online
+        load_balancer.barrels[1].online = false;
+        assert_eq!(load_balancer.select(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_check_health_marks_unreachable_offline() {
+        let load_balancer = AsyncMutex::new(create_load_balancer(Strategy::RoundRobin));
+
+        for barrel in load_balancer.lock().await.into_iter() {
+            barrel.online = true;
+        }
+
+        LoadBalancer::check_health(&load_balancer, Duration::from_secs(1)).await;
+
+        assert!(
+            load_balancer
+                .lock()
+                .await
+                .get_barrels_status()
+                .iter()
+                .all(|status| !status.online)
+        );
+    }
+
+    #[test]
+    fn test_apply_health_by_address() {
+        let mut load_balancer = create_load_balancer(Strategy::RoundRobin);
+        let first: SocketAddr = "127.0.0.1:1".parse().unwrap();
+        let third: SocketAddr = "127.0.0.1:3".parse().unwrap();
+
+        // The first barrel was removed and a new one added while the check was running.
+        assert!(load_balancer.remove_barrel(&first));
+        assert!(load_balancer.add_barrel("127.0.0.1:4".parse().unwrap()));
+
+        load_balancer.apply_health(vec![(first, false), (third, true)]);
+
+        let online: HashMap<_, _> = load_balancer
+            .get_barrels_status()
+            .into_iter()
+            .map(|status| (status.address, status.online))
+            .collect();
+
+        assert_eq!(online.len(), 3);
+        assert!(online[&third.to_string()]);
+        assert!(!online[&"127.0.0.1:2".to_string()]);
+        assert!(!online[&"127.0.0.1:4".to_string()]);
+    }
+
+    /// Serves a barrel on a random local port.
+    async fn spawn_barrel() -> SocketAddr {
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let address = incoming.local_addr().unwrap();
+
+        tokio::spawn(
+            Server::builder()
+                .add_service(BarrelServiceServer::new(crate::barrel::Barrel::default()))
+                .serve_with_incoming(incoming),
+        );
+
+        address
+    }
+
+    /// Serves a barrel on a random local port, compressing responses if `compression` is set
+    /// and refusing messages larger than `max_message_size` bytes.
+    async fn spawn_compressed_barrel(compression: bool, max_message_size: usize) -> SocketAddr {
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let address = incoming.local_addr().unwrap();
+
+        tokio::spawn(
+            Server::builder()
+                .add_service(
+                    Arc::new(crate::barrel::Barrel::default())
+                        .into_service(compression, max_message_size),
+                )
+                .serve_with_incoming(incoming),
+        );
+
+        address
+    }
+
+    #[tokio::test]
+    async fn test_compression() {
+        for (client_compression, server_compression) in
+            [(true, true), (true, false), (false, true), (false, false)]
+        {
+            let address =
+                spawn_compressed_barrel(server_compression, DEFAULT_MAX_MESSAGE_SIZE).await;
+            let mut load_balancer = LoadBalancer::new(&[address].into_iter().collect())
+                .with_compression(client_compression);
+
+            let mut client = load_balancer.barrels[0]
+                .connect(DEFAULT_TIMEOUT)
+                .await
+                .unwrap();
+            let response = client
+                .search(crate::proto::SearchRequest {
+                    words: vec!["word".to_string(); 1000],
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+
+            // Either side may compress, without breaking the other
+            let encoding = response.metadata().get("grpc-encoding");
+            match server_compression {
+                true => assert_eq!(encoding.unwrap(), "gzip"),
+                false => assert!(encoding.is_none()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_message_size() {
+        let address = spawn_compressed_barrel(false, DEFAULT_MAX_MESSAGE_SIZE).await;
+
+        // About 6 MB, over tonic's default limit of 4 MiB
+        let index = |mut client: BarrelServiceClient<Channel>| -> BoxFuture<_> {
+            Box::pin(async move {
+                client
+                    .index(crate::proto::IndexRequest {
+                        index: Some(crate::proto::Index {
+                            page: Some(crate::proto::Page {
+                                url: "https://example.com/".to_string(),
+                                ..Default::default()
+                            }),
+                            words: vec!["word".to_string(); 1_000_000],
+                            ..Default::default()
+                        }),
+                        depth: 0,
+                    })
+                    .await
+            })
+        };
+
+        let mut load_balancer = LoadBalancer::new(&[address].into_iter().collect());
+        assert!(matches!(
+            load_balancer.send_until(index).await,
+            LBResult::Ok(..)
+        ));
+
+        // Refused by the client with the previous limit, while the barrel stays online
+        let mut load_balancer = LoadBalancer::new(&[address].into_iter().collect())
+            .with_max_message_size(4 * 1024 * 1024);
+        assert!(matches!(
+            load_balancer.send_until(index).await,
+            LBResult::Offline(0)
+        ));
+    }
+
+    /// Serves a barrel over mutual TLS on a random local port.
+    async fn spawn_tls_barrel() -> SocketAddr {
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let address = incoming.local_addr().unwrap();
+
+        let tls = TlsServerConfig {
+            cert: "examples/tls/server.pem".into(),
+            key: "examples/tls/server.key".into(),
+            client_ca: Some("examples/tls/ca.pem".into()),
+        };
+
+        tokio::spawn(
+            Server::builder()
+                .tls_config(tls.read().unwrap())
+                .unwrap()
+                .add_service(BarrelServiceServer::new(crate::barrel::Barrel::default()))
+                .serve_with_incoming(incoming),
+        );
+
+        address
+    }
+
+    #[tokio::test]
+    async fn test_tls() {
+        let address = spawn_tls_barrel().await;
+        let mut tls = TlsClientConfig {
+            ca: "examples/tls/ca.pem".into(),
+            domain: None,
+            cert: Some("examples/tls/client.pem".into()),
+            key: Some("examples/tls/client.key".into()),
+        };
+
+        let health = |mut client: BarrelServiceClient<Channel>| -> BoxFuture<_> {
+            Box::pin(async move { client.health(HealthRequest {}).await })
+        };
+
+        let mut load_balancer = LoadBalancer::new(&[address].into_iter().collect())
+            .with_timeout(Duration::from_secs(2))
+            .with_tls(tls.read().unwrap());
+        assert!(matches!(
+            load_balancer.send_until(health).await,
+            LBResult::Ok(..)
+        ));
+
+        // Plaintext is refused
+        load_balancer.set_tls(None);
+        assert!(matches!(
+            load_balancer.send_until(health).await,
+            LBResult::Offline(1)
+        ));
+
+        // And so is a client without a certificate
+        tls.cert = None;
+        tls.key = None;
+        load_balancer.set_tls(Some(tls.read().unwrap()));
+        assert!(matches!(
+            load_balancer.send_until(health).await,
+            LBResult::Offline(1)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_concurrently() {
+        let delays = [400, 200, 200].map(Duration::from_millis);
+
+        let mut addresses = vec![];
+        for _ in &delays {
+            addresses.push(spawn_barrel().await);
+        }
+
+        let delays: HashMap<String, Duration> = addresses
+            .iter()
+            .map(|address| address.to_string())
+            .zip(delays)
+            .collect();
+
+        let mut load_balancer = LoadBalancer::new(&addresses.into_iter().collect());
+
+        let start = Instant::now();
+        let result = load_balancer
+            .broadcast(|barrel, mut client| {
+                let delay = delays[&barrel.address.to_string()];
+                Box::pin(async move {
+                    sleep(delay).await;
+                    client.health(HealthRequest {}).await
+                })
+            })
+            .await;
+        let elapsed = start.elapsed();
+
+        match result {
+            LBResult::Ok(responses, offline, response_time) => {
+                assert_eq!(responses.len(), 3);
+                assert_eq!(offline, 0);
+                assert_eq!(response_time.count, 3);
+            }
+            LBResult::Offline(_) => panic!("Every barrel should be online"),
+        }
+
+        // Bounded by the slowest barrel, not the sum of all of them
+        assert!(elapsed >= Duration::from_millis(400));
+        assert!(elapsed < Duration::from_millis(700), "took {:?}", elapsed);
+
+        assert!(load_balancer.barrels.iter().all(|barrel| barrel.online));
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_marks_offline_barrels() {
+        let online = spawn_barrel().await;
+        let offline: SocketAddr = "127.0.0.1:1".parse().unwrap();
+
+        let mut load_balancer = LoadBalancer::new(&[online, offline].into_iter().collect());
+
+        let result = load_balancer
+            .broadcast(|_, mut client| {
+                Box::pin(async move { client.health(HealthRequest {}).await })
+            })
+            .await;
+
+        assert!(matches!(result, LBResult::Ok(ref responses, 1, _) if responses.len() == 1));
+
+        for barrel in &load_balancer.barrels {
+            assert_eq!(barrel.online, barrel.address.as_socket_addr() == online);
+        }
+    }
+
+    /// Accepts connections on a random local port, but never answers.
+    async fn spawn_hanging_barrel() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut connections = vec![];
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+
+        address
+    }
+
+    #[tokio::test]
+    async fn test_send_until_timeout() {
+        let address = spawn_hanging_barrel().await;
+
+        let mut load_balancer = LoadBalancer::new(&[address].into_iter().collect())
+            .with_timeout(Duration::from_millis(200));
+        load_balancer.barrels[0].online = true;
+
+        let start = Instant::now();
+        let result = load_balancer
+            .send_until(|mut client| Box::pin(async move { client.health(HealthRequest {}).await }))
+            .await;
+
+        assert!(matches!(result, LBResult::Offline(1)));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(!load_balancer.barrels[0].online);
+    }
+
+    #[tokio::test]
+    async fn test_empty() {
+        let mut load_balancer = LoadBalancer::new(&HashSet::new());
+
+        let health = |mut client: BarrelServiceClient<Channel>| -> BoxFuture<_> {
+            Box::pin(async move { client.health(HealthRequest {}).await })
+        };
+
+        assert!(matches!(
+            load_balancer.send_until(health).await,
+            LBResult::Offline(0)
+        ));
+        assert!(matches!(
+            load_balancer.broadcast(|_, client| health(client)).await,
+            LBResult::Offline(0)
+        ));
+
+        // Until barrels are added
+        let address = spawn_barrel().await;
+        assert!(load_balancer.add_barrel(address));
+        assert!(matches!(
+            load_balancer.send_until(health).await,
+            LBResult::Ok(..)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_until_error() {
+        let online = spawn_barrel().await;
+        let offline: SocketAddr = "127.0.0.1:1".parse().unwrap();
+
+        let mut load_balancer = LoadBalancer::new(&[online, offline].into_iter().collect());
+
+        let failing = |_| -> BoxFuture<Result<Response<()>, Status>> {
+            Box::pin(async { Err(Status::internal("failed")) })
+        };
+
+        // The barrel that answered with an error isn't counted as offline
+        assert!(matches!(
+            load_balancer.send_until(failing).await,
+            LBResult::Offline(1)
+        ));
+        assert!(matches!(
+            load_balancer.broadcast(|_, client| failing(client)).await,
+            LBResult::Offline(1)
+        ));
+
+        for barrel in &load_balancer.barrels {
+            assert_eq!(barrel.online, barrel.address.as_socket_addr() == online);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_timeout() {
+        let slow = spawn_barrel().await;
+        let fast = spawn_barrel().await;
+
+        let mut load_balancer = LoadBalancer::new(&[slow, fast].into_iter().collect())
+            .with_timeout(Duration::from_millis(200));
+
+        let start = Instant::now();
+        let result = load_balancer
+            .broadcast(|barrel, mut client| {
+                // The slow barrel answers after the timeout
+                let delay = if barrel.address.as_socket_addr() == slow {
+                    Duration::from_secs(5)
+                } else {
+                    Duration::ZERO
+                };
+                Box::pin(async move {
+                    sleep(delay).await;
+                    client.health(HealthRequest {}).await
+                })
+            })
+            .await;
+
+        assert!(matches!(result, LBResult::Ok(ref responses, 1, _) if responses.len() == 1));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        for barrel in &load_balancer.barrels {
+            assert_eq!(barrel.online, barrel.address.as_socket_addr() == fast);
+        }
+    }
+
+    #[test]
+    fn test_add_remove_barrel() {
+        let mut load_balancer = create_load_balancer(Strategy::RoundRobin);
+        let address: SocketAddr = "127.0.0.1:4".parse().unwrap();
+
+        assert!(load_balancer.add_barrel(address));
+        assert_eq!(load_balancer.barrels.len(), 4);
+        assert!(!load_balancer.barrels[3].online);
+
+        // Duplicates are ignored
+        assert!(!load_balancer.add_barrel(address));
+        assert_eq!(load_balancer.barrels.len(), 4);
+
+        assert!(load_balancer.remove_barrel(&address));
+        assert_eq!(load_balancer.barrels.len(), 3);
+
+        // Unknown barrels are not removed
+        assert!(!load_balancer.remove_barrel(&address));
+        assert_eq!(load_balancer.barrels.len(), 3);
+    }
+
+    #[test]
+    fn test_select_empty() {
+        let mut load_balancer = LoadBalancer::default();
+
+        assert_eq!(load_balancer.select(), 0);
+    }
+}
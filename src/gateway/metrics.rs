@@ -0,0 +1,342 @@
+//! Prometheus metrics of the gateway, served in the text exposition format over HTTP.
+//!
+//! Counters and the response time histogram are updated as requests are handled, while gauges
+//! are sampled whenever the metrics are scraped.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use googol::gateway::metrics::GatewayMetrics;
+//!
+//! let metrics = GatewayMetrics::default();
+//! metrics.record_enqueues(2);
+//!
+//! let text = metrics.render(5, 1);
+//! assert!(text.contains("googol_gateway_enqueues_total 2"));
+//! assert!(text.contains("googol_gateway_queue_length 5"));
+//! ```
+
+use super::status::ResponseTime;
+use log::{debug, error};
+use prometheus::{Encoder, Histogram, HistogramOpts, IntCounter, IntGauge, Registry, TextEncoder};
+use std::{fmt, future::Future, io, sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Semaphore,
+    time::timeout,
+};
+
+/// Upper bounds, in seconds, of the search response time buckets.
+const SEARCH_BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+/// Maximum size of a request head. Anything past it is ignored.
+const MAX_REQUEST_BYTES: usize = 8192;
+/// Time a connection has to send its request and receive the response before being closed.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum number of connections answered at once. Further ones wait to be accepted.
+const MAX_CONNECTIONS: usize = 16;
+
+/// Counters, gauges and histograms of the gateway, registered in their own registry.
+#[derive(Clone)]
+pub struct GatewayMetrics {
+    registry: Registry,
+    searches: IntCounter,
+    enqueues: IntCounter,
+    queue_length: IntGauge,
+    online_barrels: IntGauge,
+    search_response_time: Histogram,
+}
+
+impl fmt::Debug for GatewayMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GatewayMetrics")
+            .field("searches", &self.searches.get())
+            .field("enqueues", &self.enqueues.get())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for GatewayMetrics {
+    fn default() -> Self {
+        let searches = IntCounter::new(
+            "googol_gateway_searches_total",
+            "Searches answered by the barrels",
+        )
+        .unwrap();
+        let enqueues = IntCounter::new(
+            "googol_gateway_enqueues_total",
+            "Urls added to the queue, by clients or as outlinks of indexed pages",
+        )
+        .unwrap();
+        let queue_length =
+            IntGauge::new("googol_gateway_queue_length", "Urls waiting to be crawled").unwrap();
+        let online_barrels =
+            IntGauge::new("googol_gateway_online_barrels", "Barrels currently online").unwrap();
+        let search_response_time = Histogram::with_opts(
+            HistogramOpts::new(
+                "googol_gateway_search_response_time_seconds",
+                "Time the barrels took to answer a search",
+            )
+            .buckets(SEARCH_BUCKETS.to_vec()),
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        registry.register(Box::new(searches.clone())).unwrap();
+        registry.register(Box::new(enqueues.clone())).unwrap();
+        registry.register(Box::new(queue_length.clone())).unwrap();
+        registry.register(Box::new(online_barrels.clone())).unwrap();
+        registry
+            .register(Box::new(search_response_time.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            searches,
+            enqueues,
+            queue_length,
+            online_barrels,
+            search_response_time,
+        }
+    }
+}
+
+impl GatewayMetrics {
+    /// Counts a search, and the time the barrels took to answer it.
+    pub fn record_search(&self, response_time: &ResponseTime) {
+        self.searches.inc();
+
+        if response_time.count > 0 {
+            self.search_response_time
+                .observe(f64::from(response_time.miliseconds) / 1000.);
+        }
+    }
+
+    /// Counts `count` urls added to the queue.
+    pub fn record_enqueues(&self, count: usize) {
+        self.enqueues.inc_by(count as u64);
+    }
+
+    /// Renders every metric in the Prometheus text format, after sampling the gauges.
+    ///
+    /// # Arguments
+    /// * `queue_length` - Number of urls waiting to be crawled.
+    /// * `online_barrels` - Number of barrels currently online.
+    pub fn render(&self, queue_length: usize, online_barrels: usize) -> String {
+        self.queue_length.set(queue_length as i64);
+        self.online_barrels.set(online_barrels as i64);
+
+        let mut buffer = vec![];
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!("Failed to encode metrics: {}", e);
+        }
+
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+}
+
+/// Answers `GET /metrics` with the text returned by `render`, and anything else with a 404.
+///
+/// Each connection is answered in its own task, and closed after a single response. Slow
+/// connections are closed after `REQUEST_TIMEOUT`, and at most `MAX_CONNECTIONS` are answered
+/// at once.
+///
+/// # Arguments
+/// * `listener` - The bound listener to accept scrapes from.
+/// * `render` - Renders the metrics of a scrape.
+pub async fn serve<F, Fut>(listener: TcpListener, render: F)
+where
+    F: Fn() -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = String> + Send,
+{
+    serve_with(listener, render, REQUEST_TIMEOUT, MAX_CONNECTIONS).await
+}
+
+/// Same as [`serve`], with the given timeout and maximum number of connections.
+async fn serve_with<F, Fut>(
+    listener: TcpListener,
+    render: F,
+    request_timeout: Duration,
+    max_connections: usize,
+) where
+    F: Fn() -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = String> + Send,
+{
+    let connections = Arc::new(Semaphore::new(max_connections));
+
+    loop {
+        let permit = Arc::clone(&connections)
+            .acquire_owned()
+            .await
+            .expect("The semaphore is never closed");
+
+        let stream = match listener.accept().await {
+            Ok((stream, peer)) => {
+                debug!("Metrics scraped by {}", peer);
+                stream
+            }
+            Err(e) => {
+                error!("Failed to accept metrics connection: {}", e);
+                continue;
+            }
+        };
+
+        let render = render.clone();
+        tokio::spawn(async move {
+            match timeout(request_timeout, respond(stream, render)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => error!("Failed to answer metrics request: {}", e),
+                Err(_) => debug!("Metrics request timed out"),
+            }
+
+            drop(permit);
+        });
+    }
+}
+
+/// Reads a request head from `stream` and writes back the response.
+async fn respond<F, Fut>(mut stream: TcpStream, render: F) -> io::Result<()>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = String>,
+{
+    let mut buffer = vec![0; MAX_REQUEST_BYTES];
+    let mut len = 0;
+
+    // Only the request line matters, but the whole head is read before answering
+    while len < buffer.len() && !buffer[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+        match stream.read(&mut buffer[len..]).await? {
+            0 => break,
+            read => len += read,
+        }
+    }
+
+    let head = String::from_utf8_lossy(&buffer[..len]);
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+
+    let (status, content_type, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            TextEncoder::new().format_type().to_string(),
+            render().await,
+        ),
+        _ => (
+            "404 Not Found",
+            String::from("text/plain"),
+            String::from("Not Found\n"),
+        ),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_search() {
+        let metrics = GatewayMetrics::default();
+
+        let mut response_time = ResponseTime::default();
+        response_time.add_sample(std::time::Duration::from_millis(30));
+        metrics.record_search(&response_time);
+        // Searches no barrel answered have no response time
+        metrics.record_search(&ResponseTime::default());
+
+        let text = metrics.render(0, 2);
+        assert!(text.contains("googol_gateway_searches_total 2"), "{}", text);
+        assert!(text.contains("googol_gateway_online_barrels 2"), "{}", text);
+        assert!(
+            text.contains("googol_gateway_search_response_time_seconds_count 1"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains(r#"googol_gateway_search_response_time_seconds_bucket{le="0.025"} 0"#),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains(r#"googol_gateway_search_response_time_seconds_bucket{le="0.05"} 1"#),
+            "{}",
+            text
+        );
+    }
+
+    #[tokio::test]
+    async fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, || async { String::from("metric 1\n") }));
+
+        let request = |path: &str| {
+            let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+
+            async move {
+                let mut stream = TcpStream::connect(address).await.unwrap();
+                stream.write_all(request.as_bytes()).await.unwrap();
+
+                let mut response = String::new();
+                stream.read_to_string(&mut response).await.unwrap();
+                response
+            }
+        };
+
+        let response = request("/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(
+            response.contains("text/plain; version=0.0.4"),
+            "{}",
+            response
+        );
+        assert!(response.ends_with("\r\n\r\nmetric 1\n"), "{}", response);
+
+        let response = request("/").await;
+        assert!(
+            response.starts_with("HTTP/1.1 404 Not Found"),
+            "{}",
+            response
+        );
+    }
+
+    #[tokio::test]
+    async fn test_serve_closes_slow_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve_with(
+            listener,
+            || async { String::from("metric 1\n") },
+            Duration::from_millis(100),
+            1,
+        ));
+
+        // Never sends its request, so it holds the only connection until it times out
+        let mut slow = TcpStream::connect(address).await.unwrap();
+
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+
+        let mut response = String::new();
+        timeout(Duration::from_secs(5), stream.read_to_string(&mut response))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+
+        let mut closed = String::new();
+        assert_eq!(slow.read_to_string(&mut closed).await.unwrap(), 0);
+    }
+}
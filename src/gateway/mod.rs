@@ -7,28 +7,69 @@
 use crate::{
     GoogolStatus,
     address::Address,
+    barrel::DEFAULT_SEARCH_LIMIT,
+    fishfish::FishFish,
     gateway::load_balancer::LoadBalancer,
     proto::{
-        BacklinksRequest, BacklinksResponse, BroadcastIndexRequest, BroadcastIndexResponse,
-        DequeueRequest, DequeueResponse, EnqueueRequest, EnqueueResponse, GatewayStatusRequest,
-        GatewayStatusResponse, HealthRequest, HealthResponse, IndexRequest, IndexResponse,
-        OutlinksRequest, OutlinksResponse, RealTimeStatusRequest, RealTimeStatusResponse,
-        RequestIndexRequest, RequestIndexResponse, SearchRequest, SearchResponse,
-        gateway_service_server::GatewayService,
+        AddBarrelRequest, AddBarrelResponse, BacklinksRequest, BacklinksResponse,
+        BroadcastIndexRequest, BroadcastIndexResponse, ClearQueueRequest, ClearQueueResponse,
+        DequeueBatchRequest, DequeueBatchResponse, DequeueRequest, DequeueResponse, EnqueueRequest,
+        EnqueueResponse, GatewayStatusRequest, GatewayStatusResponse, HealthRequest,
+        HealthResponse, IndexRequest, IndexResponse, ListIndexedPagesRequest, OutlinksRequest,
+        OutlinksResponse, Page, RealTimeStatusRequest, RealTimeStatusResponse, RemoveBarrelRequest,
+        RemoveBarrelResponse, RequestIndexRequest, RequestIndexResponse, RequeueUrlsRequest,
+        RequeueUrlsResponse, SearchRequest, SearchResponse, SkipUrlRequest, SkipUrlResponse,
+        SuggestRequest, SuggestResponse,
+        barrel_service_client::BarrelServiceClient,
+        gateway_service_client::GatewayServiceClient,
+        gateway_service_server::{GatewayService, GatewayServiceServer},
+    },
+    settings::{
+        GoogolConfig, Load,
+        gateway::{GatewayConfig, SearchScope, Sharding},
+        watch::FileWatcher,
     },
-    settings::gateway::GatewayConfig,
     wait_for_enter,
 };
+use cache::IndexCache;
+use chrono::Utc;
+use futures::{
+    TryStreamExt,
+    future::BoxFuture,
+    stream::{self, BoxStream},
+};
 use load_balancer::LBResult;
-use log::{debug, error};
+use log::{debug, error, info, warn};
+use metrics::GatewayMetrics;
+use prost::Message;
 use queue::Queue;
-use status::GatewayStatus;
-use tokio::sync::{Mutex as AsyncMutex, Notify};
-use tonic::{Request, Response, Status};
+use status::{GatewayStatus, ResponseTime};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    net::SocketAddr,
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    net::TcpListener,
+    sync::{Mutex as AsyncMutex, Notify},
+    task::JoinHandle,
+    time::sleep,
+};
+use tonic::{
+    Code, Request, Response, Status,
+    codec::CompressionEncoding,
+    transport::{self, Channel, Endpoint},
+};
 use url::Url;
 
+pub mod cache;
 pub mod load_balancer;
+pub mod metrics;
 pub mod queue;
+pub mod sharding;
 pub mod status;
 
 /// Represents notifications used for signaling status changes and queue updates.
@@ -50,14 +91,23 @@ pub struct Gateway {
     /// Queue managing URLs to crawl.
     pub queue: AsyncMutex<Queue>,
     /// Load balancer managing connections to barrels.
-    pub load_balancer: AsyncMutex<LoadBalancer>,
+    pub load_balancer: Arc<AsyncMutex<LoadBalancer>>,
     /// Current status of the gateway.
     pub status: AsyncMutex<GatewayStatus>,
     /// Notifications for status and queue updates.
     pub notification: Notification,
     /// Toggle interactive mode to wait for user input
     pub interactive: bool,
-    // TODO: Add caching mechanisms.
+    /// Index requests waiting for a barrel to come online.
+    pub index_cache: AsyncMutex<IndexCache>,
+    /// Categorizes hosts to reject phishing and malware urls. Disabled if not set.
+    pub fishfish: Option<FishFish>,
+    /// Barrels searches are sent to.
+    pub search_scope: SearchScope,
+    /// Barrels indexed pages are sent to.
+    pub sharding: Sharding,
+    /// Prometheus metrics, served by `spawn_metrics_server`.
+    pub metrics: GatewayMetrics,
 }
 
 impl Gateway {
@@ -161,6 +211,70 @@ impl Gateway {
         self
     }
 
+    /// Enables rejecting phishing and malware urls, categorized by `fishfish`.
+    ///
+    /// # Arguments
+    /// * `fishfish` - The `FishFish` instance.
+    ///
+    /// # Returns
+    /// The updated `Gateway` instance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use googol::{fishfish::FishFish, gateway::Gateway};
+    ///
+    /// let gw = Gateway::create().with_fishfish(FishFish::new());
+    /// ```
+    pub fn with_fishfish(mut self, fishfish: FishFish) -> Self {
+        self.fishfish = Some(fishfish);
+        self
+    }
+
+    /// Sets the barrels searches are sent to.
+    ///
+    /// # Arguments
+    /// * `search_scope` - The `SearchScope` to assign.
+    ///
+    /// # Returns
+    /// The updated `Gateway` instance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use googol::{gateway::Gateway, settings::gateway::SearchScope};
+    ///
+    /// let gw = Gateway::create().with_search_scope(SearchScope::All);
+    /// ```
+    pub fn with_search_scope(mut self, search_scope: SearchScope) -> Self {
+        self.search_scope = search_scope;
+        self
+    }
+
+    /// Sets the barrels indexed pages are sent to.
+    ///
+    /// Sharding by host only makes sense along with searching every barrel.
+    ///
+    /// # Arguments
+    /// * `sharding` - The `Sharding` to assign.
+    ///
+    /// # Returns
+    /// The updated `Gateway` instance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use googol::{gateway::Gateway, settings::gateway::{SearchScope, Sharding}};
+    ///
+    /// let gw = Gateway::create()
+    ///     .with_search_scope(SearchScope::All)
+    ///     .with_sharding(Sharding::Host);
+    /// ```
+    pub fn with_sharding(mut self, sharding: Sharding) -> Self {
+        self.sharding = sharding;
+        self
+    }
+
     /// Creates a Gateway from a configuration.
     ///
     /// # Arguments
@@ -172,7 +286,7 @@ impl Gateway {
     /// # Examples
     ///
     /// ```
-    /// use googol::{settings::gateway::{GatewayConfig, DomainsFilter}, gateway::Gateway, address::Address};
+    /// use googol::{settings::gateway::{GatewayConfig, DomainsFilter, IndexCacheConfig, SearchScope, Sharding, UrlNormalization}, gateway::Gateway, address::Address};
     /// use std::collections::VecDeque;
     ///
     /// let config = GatewayConfig {
@@ -180,382 +294,3002 @@ impl Gateway {
     ///     queue: VecDeque::new(),
     ///     barrels: ["127.0.0.1:50052"].iter().map(|a| a.parse().unwrap()).collect(),
     ///     domains_filter: DomainsFilter::default(),
+    ///     index_cache: IndexCacheConfig::default(),
+    ///     max_depth: None,
+    ///     queue_filepath: ".gateway-queue.json".to_string(),
+    ///     queue_max_size: None,
+    ///     fishfish: false,
+    ///     fishfish_timeout_secs: 5,
+    ///     tls: None,
+    ///     barrel_tls: None,
+    ///     compression: true,
+    ///     max_message_size: 64 * 1024 * 1024,
+    ///     search_scope: SearchScope::Single,
+    ///     sharding: Sharding::Replicate,
+    ///     url_normalization: UrlNormalization::default(),
+    ///     metrics_address: None,
+    ///     recrawl: None,
+    ///     log_file: None,
+    ///     log_level: "info".to_string(),
     /// };
     /// let gw = Gateway::from(&config);
     /// ```
     pub async fn from(config: &GatewayConfig) -> Self {
-        Self::create()
+        if config.barrels.is_empty() {
+            warn!(
+                "No barrels configured, searches and indexing are unavailable until one is added"
+            );
+        }
+
+        let index_cache =
+            IndexCache::load(&config.index_cache.filepath, config.index_cache.max_entries)
+                .unwrap_or_else(|e| {
+                    error!(
+                        "Failed to load index cache {}: {}",
+                        config.index_cache.filepath, e
+                    );
+                    IndexCache::default()
+                });
+
+        let queue = Queue::load(&config.queue_filepath).unwrap_or_else(|e| {
+            error!("Failed to load queue {}: {}", config.queue_filepath, e);
+            Queue::create()
+        });
+
+        let gateway = if config.fishfish {
+            let timeout = Duration::from_secs(config.fishfish_timeout_secs);
+            Self::create().with_fishfish(FishFish::new().with_timeout(timeout))
+        } else {
+            Self::create()
+        };
+
+        gateway
             .with_address(Address::new(config.address))
-            .with_load_balancer(LoadBalancer::new(&config.barrels))
+            .with_search_scope(config.search_scope)
+            .with_sharding(config.sharding)
+            .with_load_balancer(
+                LoadBalancer::new(&config.barrels)
+                    .with_compression(config.compression)
+                    .with_max_message_size(config.max_message_size),
+            )
             .await
-            .with_queue(Queue::create().with_domains_filter(&config.domains_filter))
+            .with_queue(
+                queue
+                    .with_domains_filter(&config.domains_filter)
+                    .with_url_normalization(&config.url_normalization)
+                    .with_max_depth(config.max_depth)
+                    .with_max_size(config.queue_max_size),
+            )
+            .await
+            .with_index_cache(index_cache)
             .await
     }
-}
 
-/// Implementation of the gRPC GatewayService trait for the Gateway.
-#[tonic::async_trait]
-impl GatewayService for Gateway {
-    /// Handles broadcasting an index to barrels.
+    /// Sets the pending index cache for the Gateway asynchronously.
     ///
     /// # Arguments
-    /// * `request` - The gRPC request containing `BroadcastIndexRequest`.
+    /// * `index_cache` - The `IndexCache` instance.
     ///
     /// # Returns
-    /// A response with `BroadcastIndexResponse`.
-    async fn broadcast_index(
-        &self,
-        request: Request<BroadcastIndexRequest>,
-    ) -> Result<Response<BroadcastIndexResponse>, Status> {
-        debug!("{:#?}", request);
-
-        unimplemented!()
+    /// The updated `Gateway`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use googol::gateway::{Gateway, cache::IndexCache};
+    ///
+    /// let gw = Gateway::create().with_index_cache(IndexCache::default());
+    /// ```
+    pub async fn with_index_cache(self, index_cache: IndexCache) -> Self {
+        *self.index_cache.lock().await = index_cache;
+        self
     }
 
-    /// Consults backlinks from the load balancer.
+    /// Checks if FishFish categorizes the URL's host as phishing or malware.
     ///
     /// # Arguments
-    /// * `request` - The gRPC request containing `BacklinksRequest`.
+    /// * `url` - The URL to check.
     ///
     /// # Returns
-    /// A response with `BacklinksResponse`.
-    async fn consult_backlinks(
-        &self,
-        request: Request<BacklinksRequest>,
-    ) -> Result<Response<BacklinksResponse>, Status> {
-        debug!("{:#?}", request);
+    /// `true` if the URL must not be crawled. Always `false` if FishFish is disabled.
+    async fn is_malicious(&self, url: &Url) -> bool {
+        let (Some(fishfish), Some(host)) = (&self.fishfish, url.host()) else {
+            return false;
+        };
 
-        let request = request.into_inner();
+        let category = fishfish.domain_category(&host.to_owned()).await;
 
-        // Send request to load balancer, retrying until success or offline.
-        let (status, backlinks) = match self
-            .load_balancer
-            .lock()
-            .await
-            .send_until(|mut client| {
-                let request = request.clone();
-                Box::pin(async move { client.consult_backlinks(request).await })
-            })
-            .await
-        {
-            LBResult::Ok(response, _, _) => (response.status, response.backlinks),
-            LBResult::Offline(_) => (GoogolStatus::UnavailableBarrels as i32, vec![]),
-        };
+        if category.is_malicious() {
+            warn!("Rejected {} url {}", category, url);
+        }
 
-        Ok(Response::new(BacklinksResponse { status, backlinks }))
+        category.is_malicious()
     }
 
-    /// Consults outlinks from the load balancer.
-    ///
-    /// # Arguments
-    /// * `request` - The gRPC request containing `OutlinksRequest`.
+    /// Drops the URLs whose host FishFish categorizes as phishing or malware, looking each
+    /// host up once and concurrently.
     ///
     /// # Returns
-    /// A response with `OutlinksResponse`.
-    async fn consult_outlinks(
-        &self,
-        request: Request<OutlinksRequest>,
-    ) -> Result<Response<OutlinksResponse>, Status> {
-        debug!("{:#?}", request);
+    /// The URLs that may be crawled. All of them if FishFish is disabled.
+    async fn reject_malicious(&self, urls: Vec<Url>) -> Vec<Url> {
+        let Some(fishfish) = &self.fishfish else {
+            return urls;
+        };
 
-        let request = request.into_inner();
+        let hosts = urls
+            .iter()
+            .filter_map(|url| url.host().map(|host| host.to_owned()));
+        let categories = fishfish.domain_categories(hosts).await;
+
+        urls.into_iter()
+            .filter(|url| {
+                let category = url.host().and_then(|host| categories.get(&host.to_owned()));
+
+                match category {
+                    Some(category) if category.is_malicious() => {
+                        warn!("Rejected {} url {}", category, url);
+                        false
+                    }
+                    _ => true,
+                }
+            })
+            .collect()
+    }
 
-        let (status, outlinks) = match self
-            .load_balancer
-            .lock()
-            .await
+    /// Searches the first barrel that answers.
+    ///
+    /// # Returns
+    /// The response of the barrel, without duplicate pages. A barrel that found nothing
+    /// answers with `GoogolStatus::Success` and no pages.
+    async fn search_single(&self, request: &SearchRequest) -> SearchResponse {
+        let mut load_balancer = self.load_balancer.lock().await;
+        let barrels = load_balancer.addresses().len();
+
+        // Pagination is forwarded to the barrel as is
+        let result = load_balancer
             .send_until(|mut client| {
                 let request = request.clone();
-                Box::pin(async move { client.consult_outlinks(request).await })
+                Box::pin(async move { client.search(request).await })
             })
-            .await
-        {
-            LBResult::Ok(response, _, _) => (response.status, response.outlinks),
-            LBResult::Offline(_) => (GoogolStatus::UnavailableBarrels as i32, vec![]),
-        };
+            .await;
+        drop(load_balancer);
 
-        Ok(Response::new(OutlinksResponse { status, outlinks }))
-    }
+        match result {
+            LBResult::Ok(response, _, response_time) => {
+                self.record_search(&request.words, &response_time).await;
 
-    /// Dequeues a URL from the queue, waiting if necessary.
-    ///
-    /// # Arguments
-    /// * `request` - The gRPC request containing `DequeueRequest`.
-    ///
-    /// # Returns
-    /// A response with `DequeueResponse`.
-    async fn dequeue_url(
-        &self,
-        request: Request<DequeueRequest>,
-    ) -> Result<Response<DequeueResponse>, Status> {
-        debug!("{:#?}", request);
+                // Duplicates in other pages of results can't be known, so only these are counted
+                let pages = dedup_pages(response.pages.clone());
+                let duplicates = (response.pages.len() - pages.len()) as u64;
 
-        // Wait until a URL is available in the queue.
-        let url = loop {
-            if let Some(url) = self.queue.lock().await.dequeue() {
-                break url;
+                SearchResponse {
+                    pages,
+                    total: response.total.saturating_sub(duplicates),
+                    ..response
+                }
             }
-
-            // Wait for notification that a URL has been enqueued.
-            self.notification.queue.notified().await;
+            LBResult::Offline(offline) => SearchResponse {
+                status: unanswered_status(offline, barrels) as i32,
+                ..Default::default()
+            },
         }
-        .to_string();
-
-        // Notify status listeners of queue change.
-        self.notification.status.notify_waiters();
-
-        Ok(Response::new(DequeueResponse { url }))
     }
 
-    /// Enqueues a URL into the queue.
+    /// Searches every online barrel, merging their pages. Offline barrels are skipped.
     ///
-    /// # Arguments
-    /// * `request` - The gRPC request containing `EnqueueRequest`.
+    /// Pages are merged by rank when barrels replicate the index. When sharded by host, each
+    /// barrel ranks its pages from its own backlinks and word statistics, so ranks of different
+    /// barrels can't be compared, and the pages of each barrel are taken in turns instead.
     ///
     /// # Returns
-    /// A response with `EnqueueResponse`.
-    async fn enqueue_url(
-        &self,
-        request: Request<EnqueueRequest>,
-    ) -> Result<Response<EnqueueResponse>, Status> {
-        debug!("{:#?}", request);
+    /// The merged responses, without duplicate pages. Corrections of the words are only kept
+    /// if no barrel found a page.
+    async fn search_all(&self, request: &SearchRequest) -> SearchResponse {
+        // Any barrel may hold the best pages, so each one is asked for every page up to the
+        // requested ones, and the merged results are paginated here
+        let limit = match request.limit {
+            0 => DEFAULT_SEARCH_LIMIT,
+            limit => limit,
+        };
+        let barrel_request = SearchRequest {
+            offset: 0,
+            limit: request.offset.saturating_add(limit),
+            ..request.clone()
+        };
 
-        let request = request.into_inner();
+        let mut load_balancer = self.load_balancer.lock().await;
+        let barrels = load_balancer.addresses().len();
 
-        // Parse URL and enqueue if valid.
-        let (status, queue) = match Url::parse(&request.url) {
-            Err(e) => {
-                error!("Invalid url: `{}`: {}", &request.url, e);
-                (GoogolStatus::InvalidUrl, vec![])
+        let result = load_balancer
+            .broadcast(|_, mut client| {
+                let request = barrel_request.clone();
+                Box::pin(async move { client.search(request).await })
+            })
+            .await;
+        drop(load_balancer);
+
+        match result {
+            LBResult::Ok(responses, _, response_time) => {
+                self.record_search(&request.words, &response_time).await;
+
+                let success = GoogolStatus::Success as i32;
+                let status = match responses.iter().find(|response| response.status == success) {
+                    Some(_) => success,
+                    None => responses[0].status,
+                };
+
+                let total: u64 = responses.iter().map(|response| response.total).sum();
+                let did_you_mean = match total {
+                    0 => responses
+                        .iter()
+                        .map(|response| &response.did_you_mean)
+                        .find(|did_you_mean| !did_you_mean.is_empty())
+                        .cloned()
+                        .unwrap_or_default(),
+                    _ => vec![],
+                };
+
+                let pages_by_barrel: Vec<_> = responses
+                    .into_iter()
+                    .map(|response| response.pages)
+                    .collect();
+                let received: usize = pages_by_barrel.iter().map(Vec::len).sum();
+
+                // Pages indexed by several barrels are only counted once, if they were received
+                let pages = match self.sharding {
+                    Sharding::Replicate => dedup_pages(pages_by_barrel.concat()),
+                    Sharding::Host => interleave_pages(pages_by_barrel),
+                };
+                let total = total.saturating_sub((received - pages.len()) as u64);
+
+                let pages = pages
+                    .into_iter()
+                    .skip(request.offset as usize)
+                    .take(limit as usize)
+                    .collect();
+
+                SearchResponse {
+                    status,
+                    pages,
+                    total,
+                    did_you_mean,
+                }
+            }
+            LBResult::Offline(offline) => SearchResponse {
+                status: unanswered_status(offline, barrels) as i32,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Normalizes the urls of an index, as the queue does, so barrels index each page once.
+    ///
+    /// Invalid urls are left as they are.
+    async fn normalize_index(&self, index: &mut crate::proto::Index) {
+        let queue = self.queue.lock().await;
+        let normalize = |url: &mut String| {
+            if let Ok(parsed) = Url::parse(url) {
+                *url = queue.normalize(parsed).to_string();
             }
-            Ok(url) => self.queue.lock().await.enqueue(url),
         };
 
-        // Notify status listeners if enqueue succeeded.
-        if status == GoogolStatus::Success {
-            self.notification.status.notify_waiters();
+        if let Some(page) = &mut index.page {
+            normalize(&mut page.url);
         }
+        index.outlinks.iter_mut().for_each(normalize);
+    }
 
-        let status = status as i32;
+    /// Dequeues up to `count` URLs, waiting only while the queue is empty.
+    async fn wait_dequeue(&self, count: usize) -> Vec<(Url, u32)> {
+        loop {
+            // Registered before checking the queue, so URLs enqueued in between aren't missed
+            let notified = self.notification.queue.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let entries = self.queue.lock().await.dequeue_batch(count);
+            if !entries.is_empty() {
+                // Notify status listeners of queue change.
+                self.notification.status.notify_waiters();
 
-        Ok(Response::new(EnqueueResponse { status, queue }))
+                return entries;
+            }
+
+            // Wait for notification that a URL has been enqueued.
+            notified.await;
+        }
     }
 
-    /// Checks the health of the gateway.
+    /// Sends an index request to every barrel, or only to the barrel responsible for the
+    /// page's host when sharding by host.
+    ///
+    /// A sharded page is never sent to another barrel, even if its own is offline, so that
+    /// each page stays in a single barrel.
     ///
     /// # Arguments
-    /// * `request` - The gRPC request containing `HealthRequest`.
+    /// * `request` - The `IndexRequest` to send.
     ///
     /// # Returns
-    /// A response with `HealthResponse`.
-    async fn health(
-        &self,
-        request: Request<HealthRequest>,
-    ) -> Result<Response<HealthResponse>, Status> {
-        debug!("{:#?}", request);
-
-        let response = HealthResponse {
-            status: format!("OK: Online. Listening at {}...", self.address),
+    /// The number of barrels that received the request.
+    ///
+    /// # Errors
+    /// The error of a barrel that refused the request as too large, if no barrel received it.
+    /// Sending it again would fail the same way.
+    async fn send_index_request(&self, request: &IndexRequest) -> Result<usize, Status> {
+        let too_large = Arc::new(std::sync::Mutex::new(None));
+
+        let send = |_: &mut load_balancer::Barrel, mut client: BarrelServiceClient<Channel>| {
+            let request = request.clone();
+            let too_large = Arc::clone(&too_large);
+
+            Box::pin(async move {
+                client.index(request).await.inspect_err(|e| {
+                    if is_too_large(e) {
+                        *too_large.lock().unwrap_or_else(|e| e.into_inner()) = Some(e.clone());
+                    }
+                })
+            }) as BoxFuture<'static, _>
+        };
+        let inspect = |barrel: &mut load_balancer::Barrel, response: &IndexResponse| {
+            barrel.index_size_bytes = response.size_bytes as usize
         };
 
-        if self.interactive {
-            wait_for_enter!("Press Enter to send \x1b[32m{:#?}\x1b[0m...", &response);
+        let mut load_balancer = self.load_balancer.lock().await;
+
+        // Refused by the client without being sent, with an error telling nothing about why
+        let size = request.encoded_len();
+        if size > load_balancer.max_message_size() {
+            return Err(Status::out_of_range(format!(
+                "Index request of {} bytes exceeds the maximum message size of {} bytes",
+                size,
+                load_balancer.max_message_size()
+            )));
         }
 
-        Ok(Response::new(response))
+        let shard = match self.sharding {
+            Sharding::Replicate => None,
+            Sharding::Host => request
+                .index
+                .as_ref()
+                .and_then(|index| index.page.as_ref())
+                .and_then(|page| Url::parse(&page.url).ok())
+                .and_then(|url| {
+                    sharding::shard(sharding::shard_key(&url), &load_balancer.addresses())
+                }),
+        };
+
+        let received = match shard {
+            Some(address) => match load_balancer
+                .send_to_and_inspect(address, send, inspect)
+                .await
+            {
+                LBResult::Ok(..) => 1,
+                LBResult::Offline(_) => 0,
+            },
+            None => match load_balancer.broadcast_and_inspect(send, inspect).await {
+                LBResult::Ok(responses, _, _) => responses.len(),
+                LBResult::Offline(_) => 0,
+            },
+        };
+
+        match too_large.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            Some(status) if received == 0 => Err(status),
+            _ => Ok(received),
+        }
     }
 
-    /// Performs an index operation.
+    /// Attempts to deliver the cached index requests, oldest first.
     ///
-    /// # Arguments
-    /// * `request` - The gRPC request containing `IndexRequest`.
+    /// Each request is acknowledged on its own: the cache is persisted with only the still
+    /// pending requests. The attempt stops at the first request no barrel accepted, which is
+    /// put back in the cache along with the following ones, as they would likely fail the same
+    /// way, each only after the barrels time out. Requests refused as too large are dropped
+    /// instead. The cache isn't locked while delivering, so new requests can be cached in the
+    /// meantime.
     ///
     /// # Returns
-    /// A response with `IndexResponse`.
-    async fn index(
-        &self,
-        request: Request<IndexRequest>,
-    ) -> Result<Response<IndexResponse>, Status> {
-        debug!("{:#?}", request);
+    /// The number of delivered requests.
+    pub async fn flush_index_cache(&self) -> usize {
+        let requests = self.index_cache.lock().await.drain();
+        let attempted = requests.len();
+
+        let mut requests = requests.into_iter();
+        let mut undelivered = vec![];
+        let mut dropped = 0;
+        for request in requests.by_ref() {
+            match self.send_index_request(&request).await {
+                Ok(0) => {
+                    undelivered.push(request);
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("Dropping cached index request: {}", e);
+                    dropped += 1;
+                }
+            }
+        }
+        undelivered.extend(requests);
 
-        let request = request.into_inner();
+        let delivered = attempted - undelivered.len() - dropped;
+        let failed = undelivered.len();
 
-        // If outlinks are provided, enqueue them.
-        if let Some(index) = &request.index {
-            let mut queue = self.queue.lock().await;
+        if let Err(e) = self.index_cache.lock().await.requeue(undelivered) {
+            error!("Failed to persist index cache: {}", e);
+        }
 
-            for url in index.outlinks.iter().map(|url| Url::parse(url).unwrap()) {
-                queue.enqueue(url);
-            }
+        if delivered > 0 {
+            info!("Delivered {} cached index requests", delivered);
+        }
+        if failed > 0 {
+            warn!("Failed to deliver {} cached index requests", failed);
         }
 
-        // Broadcast index to barrels.
-        let online = match self
+        delivered
+    }
+
+    /// Enqueues again up to `batch_size` pages the barrels last indexed more than `interval`
+    /// ago, least recently indexed first.
+    ///
+    /// Pages already being recrawled are skipped, unless they were enqueued more than
+    /// `interval` ago and never indexed since.
+    ///
+    /// # Returns
+    /// The number of enqueued pages.
+    pub async fn recrawl_stale_pages(&self, interval: Duration, batch_size: u32) -> usize {
+        let stale_before = Utc::now() - interval;
+        let request = ListIndexedPagesRequest {
+            indexed_before: Some(stale_before.timestamp()),
+            limit: batch_size,
+            offset: 0,
+        };
+
+        // Barrels may hold different pages, so every one of them is asked
+        let responses = match self
             .load_balancer
             .lock()
             .await
             .broadcast(|_, mut client| {
-                let request = request.clone();
-
-                Box::pin(async move {
-                    // Send index request to each barrel.
-
-                    //if let Ok(response) = response {
-                    //    let response = response.into_inner();
-                    //
-                    //    barrel.index_size_bytes = response.size_bytes as usize;
-                    //}
-
-                    // Additional response handling can be added here.
-                    client.index(request).await
-                })
+                Box::pin(async move { client.list_indexed_pages(request).await })
             })
             .await
         {
-            LBResult::Ok(responses, _, _) => responses.len(),
-            LBResult::Offline(_) => 0,
+            LBResult::Ok(responses, _, _) => responses,
+            LBResult::Offline(_) => {
+                warn!("No barrels online, not recrawling");
+                return 0;
+            }
         };
 
-        if online == 0 {
-            // TODO: Handle caching for later index sending if all barrels are offline.
+        let urls: HashSet<Url> = responses
+            .into_iter()
+            .flat_map(|response| response.pages)
+            .filter_map(|page| {
+                Url::parse(&page.url)
+                    .inspect_err(|e| error!("Invalid indexed url `{}`: {}", page.url, e))
+                    .ok()
+            })
+            .collect();
+
+        let mut queue = self.queue.lock().await;
+        let enqueued = urls
+            .into_iter()
+            .filter(|url| queue.enqueue_recrawl(url.clone(), stale_before) == GoogolStatus::Success)
+            .count();
+        drop(queue);
+
+        if enqueued > 0 {
+            info!("Enqueued {} pages to recrawl", enqueued);
+
+            self.metrics.record_enqueues(enqueued);
+            self.notification.queue.notify_waiters();
+            self.notification.status.notify_waiters();
         }
 
-        Ok(Response::new(IndexResponse { size_bytes: 0 }))
+        enqueued
     }
 
-    /// Retrieves real-time status information.
+    /// Records a search answered by a barrel in the gateway status.
     ///
     /// # Arguments
-    /// * `request` - The gRPC request containing `RealTimeStatusRequest`.
-    ///
-    /// # Returns
-    /// A response with `RealTimeStatusResponse`.
-    async fn real_time_status(
-        &self,
-        request: Request<RealTimeStatusRequest>,
-    ) -> Result<Response<RealTimeStatusResponse>, Status> {
-        debug!("{:#?}", request);
+    /// * `words` - The searched words, counted in the top searches.
+    /// * `response_time` - The time the barrel took to answer.
+    async fn record_search(&self, words: &[String], response_time: &ResponseTime) {
+        self.metrics.record_search(response_time);
 
-        // Wait for status update notification.
-        self.notification.status.notified().await;
+        let mut status = self.status.lock().await;
 
-        // Gather current system statuses.
-        let barrels = self.load_balancer.lock().await.get_barrels_status();
-        let queue = self.queue.lock().await.into_vec();
-        let status = self.status.lock().await;
+        // Update response time and top searches.
+        status.response_time.update(response_time);
 
-        // Compute average response time.
-        let avg_response_time_ms = status.response_time.miliseconds;
+        for word in words {
+            status.top_searches.add_search(word);
+        }
 
-        // Collect top 10 searches.
-        let top10_searches = status
-            .top_searches
-            .top_n(10)
+        // Notify waiting tasks about status update.
+        self.notification.status.notify_waiters();
+    }
+
+    /// Spawns a background task that periodically flushes the index cache, backing off while
+    /// requests keep failing.
+    ///
+    /// # Arguments
+    /// * `interval` - Time between flush attempts.
+    /// * `max_interval` - Maximum time between flush attempts when backing off.
+    ///
+    /// # Returns
+    /// The `JoinHandle` of the spawned task.
+    pub fn spawn_index_cache_flusher(
+        self: Arc<Self>,
+        interval: Duration,
+        max_interval: Duration,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut delay = interval;
+
+            loop {
+                sleep(delay).await;
+
+                if !self.index_cache.lock().await.is_empty() {
+                    self.flush_index_cache().await;
+                }
+
+                let pending = !self.index_cache.lock().await.is_empty();
+                delay = next_flush_delay(delay, interval, max_interval, pending);
+            }
+        })
+    }
+
+    /// Spawns a background task that periodically enqueues stale pages to be recrawled, with
+    /// [`Gateway::recrawl_stale_pages`].
+    ///
+    /// # Arguments
+    /// * `check_interval` - Time between checks for stale pages.
+    /// * `interval` - Age after which an indexed page is recrawled.
+    /// * `batch_size` - Maximum number of pages enqueued by each check.
+    ///
+    /// # Returns
+    /// The `JoinHandle` of the spawned task.
+    pub fn spawn_recrawler(
+        self: Arc<Self>,
+        check_interval: Duration,
+        interval: Duration,
+        batch_size: u32,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                sleep(check_interval).await;
+
+                self.recrawl_stale_pages(interval, batch_size).await;
+            }
+        })
+    }
+
+    /// Applies the hot-reloadable parts of a configuration: the barrels of the load balancer,
+    /// and the domains filter and url normalization of the queue. Everything else, like the
+    /// listening address, is left alone.
+    ///
+    /// # Arguments
+    /// * `config` - The reloaded configuration.
+    pub async fn reload_config(&self, config: &GatewayConfig) {
+        let barrels_changed = {
+            let mut load_balancer = self.load_balancer.lock().await;
+
+            let removed: Vec<_> = load_balancer
+                .barrels
+                .iter()
+                .map(|barrel| barrel.address.as_socket_addr())
+                .filter(|address| !config.barrels.contains(address))
+                .collect();
+            for address in &removed {
+                load_balancer.remove_barrel(address);
+                info!("Removed barrel {}", address);
+            }
+
+            let mut added = false;
+            for address in &config.barrels {
+                if load_balancer.add_barrel(*address) {
+                    info!("Added barrel {}", address);
+                    added = true;
+                }
+            }
+
+            added || !removed.is_empty()
+        };
+
+        {
+            let mut queue = self.queue.lock().await;
+            queue.set_domains_filter(&config.domains_filter);
+            queue.set_url_normalization(&config.url_normalization);
+        }
+
+        if barrels_changed {
+            self.notification.status.notify_waiters();
+        }
+    }
+
+    /// Wraps the gateway in its gRPC service.
+    ///
+    /// Compressed requests are always accepted, while responses are only compressed if
+    /// `compression` is set and the client accepts them. Requests and responses larger than
+    /// `max_message_size` bytes are refused.
+    pub fn into_service(
+        self: Arc<Self>,
+        compression: bool,
+        max_message_size: usize,
+    ) -> GatewayServiceServer<Self> {
+        let service = GatewayServiceServer::from_arc(self)
+            .accept_compressed(CompressionEncoding::Gzip)
+            .max_decoding_message_size(max_message_size)
+            .max_encoding_message_size(max_message_size);
+
+        match compression {
+            true => service.send_compressed(CompressionEncoding::Gzip),
+            false => service,
+        }
+    }
+
+    /// Renders the metrics in the Prometheus text format, sampling the queue length and the
+    /// online barrels.
+    pub async fn render_metrics(&self) -> String {
+        let queue_length = self.queue.lock().await.len();
+        let online_barrels = self
+            .load_balancer
+            .lock()
+            .await
+            .barrels
             .iter()
-            .map(|(word, _)| word)
-            .cloned()
-            .collect();
+            .filter(|barrel| barrel.online)
+            .count();
 
-        Ok(Response::new(RealTimeStatusResponse {
-            top10_searches,
-            barrels,
-            avg_response_time_ms,
-            queue,
+        self.metrics.render(queue_length, online_barrels)
+    }
+
+    /// Spawns a background task serving the metrics over HTTP at `/metrics`.
+    ///
+    /// # Arguments
+    /// * `address` - The address to listen on, separate from the gRPC one.
+    ///
+    /// # Returns
+    /// The `JoinHandle` of the spawned task, or an error if `address` can't be bound.
+    pub async fn spawn_metrics_server(
+        self: Arc<Self>,
+        address: SocketAddr,
+    ) -> io::Result<JoinHandle<()>> {
+        let listener = TcpListener::bind(address).await?;
+        info!("Serving metrics at http://{}/metrics", address);
+
+        Ok(tokio::spawn(metrics::serve(listener, move || {
+            let gateway = Arc::clone(&self);
+            async move { gateway.render_metrics().await }
+        })))
+    }
+
+    /// Spawns a background task that reloads the configuration whenever `path` changes.
+    ///
+    /// The file may hold either a whole `GoogolConfig` or just a `GatewayConfig`. Malformed
+    /// edits are logged and ignored, keeping the previous configuration.
+    ///
+    /// # Arguments
+    /// * `path` - The configuration file to watch.
+    ///
+    /// # Returns
+    /// The `JoinHandle` of the spawned task, or an error if the file can't be watched.
+    pub fn spawn_config_watcher(
+        self: Arc<Self>,
+        path: impl AsRef<Path>,
+    ) -> notify::Result<JoinHandle<()>> {
+        let path = path.as_ref().to_path_buf();
+        let mut watcher = FileWatcher::new(&path)?;
+
+        Ok(tokio::spawn(async move {
+            while watcher.changed().await {
+                match load_gateway_config(&path) {
+                    Ok(config) => {
+                        info!("Reloading configuration from {}", path.display());
+                        self.reload_config(&config).await;
+                    }
+                    Err(e) => error!(
+                        "Failed to reload {}, keeping the previous configuration: {}",
+                        path.display(),
+                        e
+                    ),
+                }
+            }
         }))
     }
+}
 
-    /// Requests an index operation.
+/// Connects to the gateway at `endpoint`, e.g. one built with [`crate::settings::tls::endpoint`].
+///
+/// # Arguments
+/// * `endpoint` - The gateway to connect to.
+/// * `compression` - Whether requests are gzip-compressed. Compressed responses are accepted
+///   either way.
+/// * `max_message_size` - Largest request sent or response received, in bytes.
+pub async fn connect(
+    endpoint: Endpoint,
+    compression: bool,
+    max_message_size: usize,
+) -> Result<GatewayServiceClient<Channel>, transport::Error> {
+    let client = GatewayServiceClient::connect(endpoint)
+        .await?
+        .accept_compressed(CompressionEncoding::Gzip)
+        .max_decoding_message_size(max_message_size)
+        .max_encoding_message_size(max_message_size);
+
+    Ok(match compression {
+        true => client.send_compressed(CompressionEncoding::Gzip),
+        false => client,
+    })
+}
+
+/// Returns the time to wait before the next index cache flush: `interval` once every request
+/// was delivered, otherwise `delay` doubled, up to `max_interval`.
+fn next_flush_delay(
+    delay: Duration,
+    interval: Duration,
+    max_interval: Duration,
+    pending: bool,
+) -> Duration {
+    if pending {
+        (delay * 2).min(max_interval).max(interval)
+    } else {
+        interval
+    }
+}
+
+/// Keeps the highest-ranked page of each url, ordered by decreasing rank.
+///
+/// Unranked pages rank last, and pages of equal rank keep their relative order, so results
+/// already ordered by relevance stay that way.
+fn dedup_pages(pages: Vec<Page>) -> Vec<Page> {
+    let rank = |page: &Page| page.rank.unwrap_or(f64::NEG_INFINITY);
+
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut deduped: Vec<Page> = Vec::with_capacity(pages.len());
+
+    for page in pages {
+        match positions.get(&page.url) {
+            Some(&position) => {
+                if rank(&page) > rank(&deduped[position]) {
+                    deduped[position] = page;
+                }
+            }
+            None => {
+                positions.insert(page.url.clone(), deduped.len());
+                deduped.push(page);
+            }
+        }
+    }
+
+    deduped.sort_by(|a, b| rank(b).total_cmp(&rank(a)));
+
+    deduped
+}
+
+/// Merges the pages of several barrels, each ranked by its own barrel, by taking them in
+/// turns: the first page of every barrel, then the second one, and so on.
+///
+/// Pages appearing several times are only kept the first time.
+fn interleave_pages(pages_by_barrel: Vec<Vec<Page>>) -> Vec<Page> {
+    let mut urls = HashSet::new();
+    let mut merged = vec![];
+
+    let mut barrels: Vec<_> = pages_by_barrel.into_iter().map(Vec::into_iter).collect();
+    loop {
+        let round: Vec<Page> = barrels.iter_mut().filter_map(Iterator::next).collect();
+        if round.is_empty() {
+            return merged;
+        }
+
+        merged.extend(
+            round
+                .into_iter()
+                .filter(|page| urls.insert(page.url.clone())),
+        );
+    }
+}
+
+/// Status of a request no barrel answered successfully.
+///
+/// # Arguments
+/// * `offline` - Number of barrels that couldn't be reached.
+/// * `barrels` - Number of barrels the request was sent to.
+///
+/// # Returns
+/// `GoogolStatus::Error` if some barrel answered with an error, or
+/// `GoogolStatus::UnavailableBarrels` if none could be reached.
+fn unanswered_status(offline: usize, barrels: usize) -> GoogolStatus {
+    match offline < barrels {
+        true => GoogolStatus::Error,
+        false => GoogolStatus::UnavailableBarrels,
+    }
+}
+
+/// Whether `status` means a message exceeded the maximum message size, of either the gateway
+/// or the barrel.
+fn is_too_large(status: &Status) -> bool {
+    matches!(status.code(), Code::OutOfRange | Code::ResourceExhausted)
+}
+
+/// Loads the gateway's configuration from a `GoogolConfig` file, or a `GatewayConfig` one.
+fn load_gateway_config(path: &Path) -> Result<GatewayConfig, config::ConfigError> {
+    let path = path.to_string_lossy();
+
+    GoogolConfig::load(&path)
+        .map(|config| config.gateway)
+        .or_else(|googol_error| {
+            GatewayConfig::load(&path).map_err(|gateway_error| {
+                config::ConfigError::Message(format!(
+                    "not a googol config ({}), nor a gateway config ({})",
+                    googol_error, gateway_error
+                ))
+            })
+        })
+}
+
+/// Implementation of the gRPC GatewayService trait for the Gateway.
+#[tonic::async_trait]
+impl GatewayService for Gateway {
+    /// Adds a barrel to the load balancer, without restarting the gateway.
     ///
     /// # Arguments
-    /// * `request` - The gRPC request containing `RequestIndexRequest`.
+    /// * `request` - The gRPC request containing `AddBarrelRequest`.
     ///
     /// # Returns
-    /// A response with `RequestIndexResponse`.
-    async fn request_index(
+    /// A response with `AddBarrelResponse`.
+    async fn add_barrel(
         &self,
-        request: Request<RequestIndexRequest>,
-    ) -> Result<Response<RequestIndexResponse>, Status> {
+        request: Request<AddBarrelRequest>,
+    ) -> Result<Response<AddBarrelResponse>, Status> {
         debug!("{:#?}", request);
 
-        unimplemented!()
+        let request = request.into_inner();
+
+        let address: SocketAddr = request.address.parse().map_err(|e| {
+            error!("Invalid address provided: {}", e);
+            Status::invalid_argument(format!("Invalid address: {}", request.address))
+        })?;
+
+        let (added, barrels) = {
+            let mut load_balancer = self.load_balancer.lock().await;
+            let added = load_balancer.add_barrel(address);
+            (added, load_balancer.get_barrels_status())
+        };
+
+        if added {
+            info!("Added barrel {}", address);
+            self.notification.status.notify_waiters();
+        }
+
+        Ok(Response::new(AddBarrelResponse { added, barrels }))
     }
 
-    /// Performs a search operation.
+    /// Handles broadcasting an index to barrels.
+    ///
+    /// The request is sent to every barrel. The broadcast succeeds only if every reachable
+    /// barrel merged the index; unreachable barrels are reported in `offline`.
     ///
     /// # Arguments
-    /// * `request` - The gRPC request containing `SearchRequest`.
+    /// * `request` - The gRPC request containing `BroadcastIndexRequest`.
     ///
     /// # Returns
-    /// A response with `SearchResponse`.
-    async fn search(
+    /// A response with `BroadcastIndexResponse`.
+    async fn broadcast_index(
         &self,
-        request: Request<SearchRequest>,
-    ) -> Result<Response<SearchResponse>, Status> {
+        request: Request<BroadcastIndexRequest>,
+    ) -> Result<Response<BroadcastIndexResponse>, Status> {
         debug!("{:#?}", request);
 
         let request = request.into_inner();
 
-        // Send search request to load balancer.
-        let (status, pages) = match self
+        let response = match self
             .load_balancer
             .lock()
             .await
-            .send_until(|mut client| {
+            .broadcast(|_, mut client| {
                 let request = request.clone();
-                Box::pin(async move { client.search(request).await })
+                Box::pin(async move { client.broadcast_index(request).await })
             })
             .await
         {
-            LBResult::Ok(response, _, response_time) => {
-                let mut status = self.status.lock().await;
+            LBResult::Ok(responses, offline, _) => {
+                let success = responses.iter().all(|response| response.success);
+
+                let status = if success {
+                    GoogolStatus::Success as i32
+                } else {
+                    GoogolStatus::Error as i32
+                };
+
+                BroadcastIndexResponse {
+                    success,
+                    status,
+                    offline: offline as u64,
+                }
+            }
+            LBResult::Offline(offline) => BroadcastIndexResponse {
+                success: false,
+                status: GoogolStatus::UnavailableBarrels as i32,
+                offline: offline as u64,
+            },
+        };
+
+        Ok(Response::new(response))
+    }
+
+    /// Removes a pending url from the queue, or clears the whole queue if no url is given.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `ClearQueueRequest`.
+    ///
+    /// # Returns
+    /// A response with `ClearQueueResponse`. Removing a url that is not pending is not an
+    /// error, but removes nothing.
+    async fn clear_queue(
+        &self,
+        request: Request<ClearQueueRequest>,
+    ) -> Result<Response<ClearQueueResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let request = request.into_inner();
 
-                // Update response time and top searches.
-                status.response_time.update(&response_time);
+        let (status, removed, queue) = {
+            let mut queue = self.queue.lock().await;
+
+            let (status, removed) = match request.url.as_deref().map(Url::parse) {
+                None => {
+                    let removed = queue.len();
+                    queue.clear();
+                    info!("Cleared queue of {} urls", removed);
 
-                for word in &request.words {
-                    status.top_searches.add_search(word);
+                    (GoogolStatus::Success, removed)
                 }
+                Some(Err(e)) => {
+                    error!("Invalid url: `{}`: {}", request.url.unwrap_or_default(), e);
+                    (GoogolStatus::InvalidUrl, 0)
+                }
+                Some(Ok(url)) => {
+                    let removed = queue.remove(&url);
+                    if removed {
+                        info!("Removed {} from the queue", url);
+                    }
 
-                // Notify waiting tasks about status update.
-                self.notification.status.notify_waiters();
+                    (GoogolStatus::Success, removed as usize)
+                }
+            };
 
-                (response.status, response.pages)
-            }
-            LBResult::Offline(_) => (GoogolStatus::UnavailableBarrels as i32, vec![]),
+            (status, removed, queue.into_vec())
         };
 
-        Ok(Response::new(SearchResponse { status, pages }))
+        if removed > 0 {
+            self.notification.status.notify_waiters();
+        }
+
+        Ok(Response::new(ClearQueueResponse {
+            status: status as i32,
+            removed: removed as u64,
+            queue,
+        }))
     }
 
-    /// Retrieves overall gateway status.
+    /// Consults backlinks from the load balancer.
     ///
     /// # Arguments
-    /// * `request` - The gRPC request containing `GatewayStatusRequest`.
+    /// * `request` - The gRPC request containing `BacklinksRequest`.
     ///
     /// # Returns
-    /// A response with `GatewayStatusResponse`.
-    async fn status(
+    /// A response with `BacklinksResponse`.
+    async fn consult_backlinks(
         &self,
-        request: Request<GatewayStatusRequest>,
-    ) -> Result<Response<GatewayStatusResponse>, Status> {
+        request: Request<BacklinksRequest>,
+    ) -> Result<Response<BacklinksResponse>, Status> {
         debug!("{:#?}", request);
 
-        unimplemented!()
+        let request = request.into_inner();
+
+        // Send request to load balancer, retrying until success or offline.
+        let (status, backlinks) = match self
+            .load_balancer
+            .lock()
+            .await
+            .send_until(|mut client| {
+                let request = request.clone();
+                Box::pin(async move { client.consult_backlinks(request).await })
+            })
+            .await
+        {
+            LBResult::Ok(response, _, _) => (response.status, response.backlinks),
+            LBResult::Offline(_) => (GoogolStatus::UnavailableBarrels as i32, vec![]),
+        };
+
+        Ok(Response::new(BacklinksResponse { status, backlinks }))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Consults outlinks from the load balancer.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `OutlinksRequest`.
+    ///
+    /// # Returns
+    /// A response with `OutlinksResponse`.
+    async fn consult_outlinks(
+        &self,
+        request: Request<OutlinksRequest>,
+    ) -> Result<Response<OutlinksResponse>, Status> {
+        debug!("{:#?}", request);
 
-    #[test]
-    fn test_gateway_default_interactive_off() {
-        let gateway = Gateway::default();
+        let request = request.into_inner();
 
-        assert!(!gateway.interactive);
+        let (status, outlinks) = match self
+            .load_balancer
+            .lock()
+            .await
+            .send_until(|mut client| {
+                let request = request.clone();
+                Box::pin(async move { client.consult_outlinks(request).await })
+            })
+            .await
+        {
+            LBResult::Ok(response, _, _) => (response.status, response.outlinks),
+            LBResult::Offline(_) => (GoogolStatus::UnavailableBarrels as i32, vec![]),
+        };
 
-        let gateway = gateway.with_interactive(true);
+        Ok(Response::new(OutlinksResponse { status, outlinks }))
+    }
 
-        assert!(gateway.interactive);
+    /// Dequeues a URL from the queue, waiting if necessary.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `DequeueRequest`.
+    ///
+    /// # Returns
+    /// A response with `DequeueResponse`.
+    async fn dequeue_url(
+        &self,
+        request: Request<DequeueRequest>,
+    ) -> Result<Response<DequeueResponse>, Status> {
+        debug!("{:#?}", request);
+
+        // Wait until a URL is available in the queue.
+        let (url, depth) = self.wait_dequeue(1).await.remove(0);
+
+        Ok(Response::new(DequeueResponse {
+            url: url.to_string(),
+            depth,
+        }))
+    }
+
+    /// Dequeues up to `count` URLs from the queue, waiting only while it is empty.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `DequeueBatchRequest`.
+    ///
+    /// # Returns
+    /// A response with `DequeueBatchResponse`, holding between one and `count` URLs.
+    async fn dequeue_batch(
+        &self,
+        request: Request<DequeueBatchRequest>,
+    ) -> Result<Response<DequeueBatchResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let count = request.into_inner().count.max(1) as usize;
+
+        let urls = self
+            .wait_dequeue(count)
+            .await
+            .into_iter()
+            .map(|(url, depth)| DequeueResponse {
+                url: url.to_string(),
+                depth,
+            })
+            .collect();
+
+        Ok(Response::new(DequeueBatchResponse { urls }))
+    }
+
+    /// Enqueues a URL into the queue.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `EnqueueRequest`.
+    ///
+    /// # Returns
+    /// A response with `EnqueueResponse`.
+    async fn enqueue_url(
+        &self,
+        request: Request<EnqueueRequest>,
+    ) -> Result<Response<EnqueueResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let request = request.into_inner();
+
+        // Parse URL and enqueue if valid.
+        let (status, queue) = match Url::parse(&request.url) {
+            Err(e) => {
+                error!("Invalid url: `{}`: {}", &request.url, e);
+                (GoogolStatus::InvalidUrl, vec![])
+            }
+            Ok(url) if self.is_malicious(&url).await => (
+                GoogolStatus::MaliciousUrl,
+                self.queue.lock().await.into_vec(),
+            ),
+            Ok(url) => {
+                let mut queue = self.queue.lock().await;
+                (queue.enqueue(url), queue.into_vec())
+            }
+        };
+
+        // Notify dequeuers and status listeners if enqueue succeeded.
+        if status == GoogolStatus::Success {
+            self.metrics.record_enqueues(1);
+            self.notification.queue.notify_waiters();
+            self.notification.status.notify_waiters();
+        }
+
+        let status = status as i32;
+
+        Ok(Response::new(EnqueueResponse { status, queue }))
+    }
+
+    /// Checks the health of the gateway, and how many barrels it can reach.
+    ///
+    /// The status is degraded if no barrel is online, as nothing can be searched nor indexed.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `HealthRequest`.
+    ///
+    /// # Returns
+    /// A response with `HealthResponse`.
+    async fn health(
+        &self,
+        request: Request<HealthRequest>,
+    ) -> Result<Response<HealthResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let (online_barrels, total_barrels) = {
+            let load_balancer = self.load_balancer.lock().await;
+            let online = load_balancer.barrels.iter().filter(|b| b.online).count();
+
+            (online as u64, load_balancer.barrels.len() as u64)
+        };
+
+        let status = match online_barrels {
+            0 => format!(
+                "DEGRADED: No barrels online. Listening at {}...",
+                self.address
+            ),
+            _ => format!("OK: Online. Listening at {}...", self.address),
+        };
+
+        let response = HealthResponse {
+            status,
+            online_barrels,
+            total_barrels,
+        };
+
+        if self.interactive {
+            wait_for_enter!("Press Enter to send \x1b[32m{:#?}\x1b[0m...", &response);
+        }
+
+        Ok(Response::new(response))
+    }
+
+    /// Performs an index operation.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `IndexRequest`.
+    ///
+    /// # Returns
+    /// A response with `IndexResponse`.
+    async fn index(
+        &self,
+        request: Request<IndexRequest>,
+    ) -> Result<Response<IndexResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let mut request = request.into_inner();
+
+        if let Some(index) = &mut request.index {
+            self.normalize_index(index).await;
+        }
+
+        // If outlinks are provided, enqueue them one level deeper than the indexed page.
+        if let Some(index) = &request.index {
+            let mut outlinks = vec![];
+
+            for url in &index.outlinks {
+                match Url::parse(url) {
+                    Err(e) => error!("Invalid outlink `{}`: {}", url, e),
+                    Ok(url) => outlinks.push(url),
+                }
+            }
+            let outlinks = self.reject_malicious(outlinks).await;
+
+            let mut queue = self.queue.lock().await;
+            let depth = request.depth.saturating_add(1);
+
+            // Recrawled pages can be enqueued again once indexed, and no longer requeued
+            if let Some(Ok(url)) = index.page.as_ref().map(|page| Url::parse(&page.url)) {
+                queue.finish_recrawl(&url);
+                queue.release(&url);
+            }
+
+            let mut enqueued = 0;
+            for url in outlinks {
+                if queue.enqueue_with_depth(url, depth) == GoogolStatus::Success {
+                    enqueued += 1;
+                }
+            }
+
+            if enqueued > 0 {
+                self.metrics.record_enqueues(enqueued);
+                self.notification.queue.notify_waiters();
+            }
+        }
+
+        // Broadcast index to barrels, keeping the request for later if no barrel received it.
+        // Requests too large for the barrels would never be received, so they are dropped.
+        match self.send_index_request(&request).await {
+            Ok(0) => {
+                warn!("No barrels online, caching index request");
+
+                if let Err(e) = self.index_cache.lock().await.push(request) {
+                    error!("Failed to cache index request: {}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => error!("Dropping index request: {}", e),
+        }
+
+        Ok(Response::new(IndexResponse { size_bytes: 0 }))
+    }
+
+    /// Retrieves real-time status information.
+    ///
+    /// The current status is returned right away, unless the request asks to wait for the next
+    /// status change. Clients polling in a loop should only wait after their first call.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `RealTimeStatusRequest`.
+    ///
+    /// # Returns
+    /// A response with `RealTimeStatusResponse`.
+    async fn real_time_status(
+        &self,
+        request: Request<RealTimeStatusRequest>,
+    ) -> Result<Response<RealTimeStatusResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let request = request.into_inner();
+
+        // Wait for status update notification.
+        if request.wait_for_update {
+            self.notification.status.notified().await;
+        }
+
+        // Gather current system statuses.
+        let barrels = self.load_balancer.lock().await.get_barrels_status();
+        let queue = self.queue.lock().await.into_vec();
+        let status = self.status.lock().await;
+
+        // Compute average response time.
+        let avg_response_time_ms = status.response_time.miliseconds;
+
+        // Collect top 10 searches.
+        let top10_searches = status
+            .top_searches
+            .top_n(10)
+            .iter()
+            .map(|(word, _)| word)
+            .cloned()
+            .collect();
+
+        Ok(Response::new(RealTimeStatusResponse {
+            top10_searches,
+            barrels,
+            avg_response_time_ms,
+            queue,
+            p50_response_time_ms: status.response_time.percentile(0.5),
+            p95_response_time_ms: status.response_time.percentile(0.95),
+            p99_response_time_ms: status.response_time.percentile(0.99),
+        }))
+    }
+
+    /// Removes a barrel from the load balancer, without restarting the gateway.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `RemoveBarrelRequest`.
+    ///
+    /// # Returns
+    /// A response with `RemoveBarrelResponse`.
+    async fn remove_barrel(
+        &self,
+        request: Request<RemoveBarrelRequest>,
+    ) -> Result<Response<RemoveBarrelResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let request = request.into_inner();
+
+        let address: SocketAddr = request.address.parse().map_err(|e| {
+            error!("Invalid address provided: {}", e);
+            Status::invalid_argument(format!("Invalid address: {}", request.address))
+        })?;
+
+        let (removed, barrels) = {
+            let mut load_balancer = self.load_balancer.lock().await;
+            let removed = load_balancer.remove_barrel(&address);
+            (removed, load_balancer.get_barrels_status())
+        };
+
+        if removed {
+            info!("Removed barrel {}", address);
+            self.notification.status.notify_waiters();
+        }
+
+        Ok(Response::new(RemoveBarrelResponse { removed, barrels }))
+    }
+
+    /// Requests an index operation.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `RequestIndexRequest`.
+    ///
+    /// # Returns
+    /// A response with `RequestIndexResponse`.
+    async fn request_index(
+        &self,
+        request: Request<RequestIndexRequest>,
+    ) -> Result<Response<RequestIndexResponse>, Status> {
+        debug!("{:#?}", request);
+
+        unimplemented!()
+    }
+
+    /// Puts dequeued URLs back in the queue, at their crawl depth, e.g. when a downloader
+    /// worker shuts down before crawling them. Invalid URLs are ignored.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `RequeueUrlsRequest`.
+    ///
+    /// # Returns
+    /// A response with `RequeueUrlsResponse`.
+    async fn requeue_urls(
+        &self,
+        request: Request<RequeueUrlsRequest>,
+    ) -> Result<Response<RequeueUrlsResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let mut requeued = 0;
+        {
+            let mut queue = self.queue.lock().await;
+
+            for entry in request.into_inner().urls {
+                match Url::parse(&entry.url) {
+                    Ok(url) if queue.requeue(&url) => requeued += 1,
+                    Ok(url) => warn!("Not requeuing {}, as it isn't dequeued", url),
+                    Err(e) => error!("Invalid url: `{}`: {}", entry.url, e),
+                }
+            }
+        }
+
+        if requeued > 0 {
+            info!("Requeued {} urls", requeued);
+            self.notification.queue.notify_waiters();
+            self.notification.status.notify_waiters();
+        }
+
+        Ok(Response::new(RequeueUrlsResponse {}))
+    }
+
+    /// Performs a search operation.
+    ///
+    /// Depending on the gateway's `search_scope`, the first barrel that answers is searched,
+    /// or every online barrel is, merging their results. Pages are unique by url either way.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `SearchRequest`.
+    ///
+    /// # Returns
+    /// A response with `SearchResponse`.
+    async fn search(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> Result<Response<SearchResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let request = request.into_inner();
+
+        let response = match self.search_scope {
+            SearchScope::Single => self.search_single(&request).await,
+            SearchScope::All => self.search_all(&request).await,
+        };
+
+        Ok(Response::new(response))
+    }
+
+    type SearchStreamStream = BoxStream<'static, Result<SearchResponse, Status>>;
+
+    /// Searches like `search`, forwarding the barrel's batches of pages as they arrive.
+    ///
+    /// Pages already sent in an earlier batch are left out. When searching every barrel, their
+    /// pages must be merged by rank first, so they are sent in a single batch, as by `search`.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `SearchRequest`.
+    ///
+    /// # Returns
+    /// A response with the stream of `SearchResponse` batches. If no barrel answered, the
+    /// stream holds a single empty batch with the status of the failure.
+    async fn search_stream(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> Result<Response<Self::SearchStreamStream>, Status> {
+        debug!("{:#?}", request);
+
+        let request = request.into_inner();
+
+        if self.search_scope == SearchScope::All {
+            let response = self.search_all(&request).await;
+
+            return Ok(Response::new(Box::pin(stream::once(async move {
+                Ok(response)
+            }))));
+        }
+
+        let mut load_balancer = self.load_balancer.lock().await;
+        let barrels = load_balancer.addresses().len();
+
+        let result = load_balancer
+            .send_until(|mut client| {
+                let request = request.clone();
+                Box::pin(async move { client.search_stream(request).await })
+            })
+            .await;
+        drop(load_balancer);
+
+        let stream: Self::SearchStreamStream = match result {
+            LBResult::Ok(batches, _, response_time) => {
+                self.record_search(&request.words, &response_time).await;
+
+                let mut sent = HashSet::new();
+                Box::pin(batches.map_ok(move |mut response| {
+                    response.pages.retain(|page| sent.insert(page.url.clone()));
+                    response
+                }))
+            }
+            LBResult::Offline(offline) => Box::pin(stream::once(async move {
+                Ok(SearchResponse {
+                    status: unanswered_status(offline, barrels) as i32,
+                    ..Default::default()
+                })
+            })),
+        };
+
+        Ok(Response::new(stream))
+    }
+
+    /// Records a URL a downloader skipped instead of indexing.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `SkipUrlRequest`.
+    ///
+    /// # Returns
+    /// A response with `SkipUrlResponse`.
+    async fn skip_url(
+        &self,
+        request: Request<SkipUrlRequest>,
+    ) -> Result<Response<SkipUrlResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let request = request.into_inner();
+
+        info!("Skipped url {}: {}", request.url, request.reason);
+
+        if let Ok(url) = Url::parse(&request.url) {
+            let mut queue = self.queue.lock().await;
+            queue.finish_recrawl(&url);
+            queue.release(&url);
+        }
+
+        self.status.lock().await.skipped_urls += 1;
+        self.notification.status.notify_waiters();
+
+        Ok(Response::new(SkipUrlResponse {}))
+    }
+
+    /// Retrieves overall gateway status.
+    ///
+    /// Unlike `real_time_status`, this returns a snapshot immediately.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `GatewayStatusRequest`.
+    ///
+    /// # Returns
+    /// A response with `GatewayStatusResponse`.
+    async fn status(
+        &self,
+        request: Request<GatewayStatusRequest>,
+    ) -> Result<Response<GatewayStatusResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let barrels = self.load_balancer.lock().await.get_barrels_status();
+        let online_barrels = barrels.iter().filter(|barrel| barrel.online).count();
+        let offline_barrels = barrels.len() - online_barrels;
+
+        let queue_length = self.queue.lock().await.len();
+        let status = self.status.lock().await;
+
+        Ok(Response::new(GatewayStatusResponse {
+            status: format!("OK: Online. Listening at {}...", self.address),
+            queue_length: queue_length as u64,
+            online_barrels: online_barrels as u64,
+            offline_barrels: offline_barrels as u64,
+            avg_response_time_ms: status.response_time.miliseconds,
+            total_searches: status.top_searches.total() as u64,
+            skipped_urls: status.skipped_urls as u64,
+            p50_response_time_ms: status.response_time.percentile(0.5),
+            p95_response_time_ms: status.response_time.percentile(0.95),
+            p99_response_time_ms: status.response_time.percentile(0.99),
+        }))
+    }
+
+    /// Suggests words starting with a prefix, from the load balancer.
+    ///
+    /// # Arguments
+    /// * `request` - The gRPC request containing `SuggestRequest`.
+    ///
+    /// # Returns
+    /// A response with `SuggestResponse`.
+    async fn suggest(
+        &self,
+        request: Request<SuggestRequest>,
+    ) -> Result<Response<SuggestResponse>, Status> {
+        debug!("{:#?}", request);
+
+        let request = request.into_inner();
+
+        // Send request to load balancer, retrying until success or offline.
+        let (status, suggestions) = match self
+            .load_balancer
+            .lock()
+            .await
+            .send_until(|mut client| {
+                let request = request.clone();
+                Box::pin(async move { client.suggest(request).await })
+            })
+            .await
+        {
+            LBResult::Ok(response, _, _) => (response.status, response.suggestions),
+            LBResult::Offline(_) => (GoogolStatus::UnavailableBarrels as i32, vec![]),
+        };
+
+        Ok(Response::new(SuggestResponse {
+            status,
+            suggestions,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::barrel_service_server::{BarrelService, BarrelServiceServer};
+    use crate::{
+        fishfish::domain::{FishDomain, category::FishDomainCategory},
+        proto::Index,
+        settings::gateway::DomainsFilter,
+    };
+    use futures::StreamExt;
+    use std::collections::HashSet;
+    use tonic::transport::{Server, server::TcpIncoming};
+    use url::Host;
+
+    /// Barrel answering every search with the same pages, and accepting index requests except
+    /// for the rejected urls. Every indexed url is listed as stale.
+    #[derive(Default)]
+    struct StubBarrel {
+        pages: Vec<Page>,
+        did_you_mean: Vec<String>,
+        rejected_urls: Arc<std::sync::Mutex<HashSet<String>>>,
+        /// Urls of the pages received by `index`.
+        received_urls: Arc<std::sync::Mutex<Vec<String>>>,
+        indexed_urls: Vec<String>,
+        /// Answers searches with an error instead.
+        failing: bool,
+    }
+
+    #[tonic::async_trait]
+    impl BarrelService for StubBarrel {
+        async fn broadcast_index(
+            &self,
+            _: Request<BroadcastIndexRequest>,
+        ) -> Result<Response<BroadcastIndexResponse>, Status> {
+            Err(Status::unimplemented("stub"))
+        }
+
+        async fn consult_backlinks(
+            &self,
+            _: Request<BacklinksRequest>,
+        ) -> Result<Response<BacklinksResponse>, Status> {
+            Err(Status::unimplemented("stub"))
+        }
+
+        async fn consult_outlinks(
+            &self,
+            _: Request<OutlinksRequest>,
+        ) -> Result<Response<OutlinksResponse>, Status> {
+            Err(Status::unimplemented("stub"))
+        }
+
+        async fn health(
+            &self,
+            _: Request<HealthRequest>,
+        ) -> Result<Response<HealthResponse>, Status> {
+            Ok(Response::new(HealthResponse::default()))
+        }
+
+        async fn index(
+            &self,
+            request: Request<IndexRequest>,
+        ) -> Result<Response<IndexResponse>, Status> {
+            let url = request
+                .into_inner()
+                .index
+                .and_then(|index| index.page)
+                .map(|page| page.url)
+                .unwrap_or_default();
+
+            if self.rejected_urls.lock().unwrap().contains(&url) {
+                return Err(Status::unavailable("rejected"));
+            }
+
+            self.received_urls.lock().unwrap().push(url);
+
+            Ok(Response::new(IndexResponse::default()))
+        }
+
+        async fn list_indexed_pages(
+            &self,
+            request: Request<ListIndexedPagesRequest>,
+        ) -> Result<Response<crate::proto::ListIndexedPagesResponse>, Status> {
+            let limit = request.into_inner().limit as usize;
+
+            Ok(Response::new(crate::proto::ListIndexedPagesResponse {
+                pages: self
+                    .indexed_urls
+                    .iter()
+                    .take(limit)
+                    .map(|url| crate::proto::IndexedPage {
+                        url: url.clone(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            }))
+        }
+
+        async fn search(
+            &self,
+            _: Request<SearchRequest>,
+        ) -> Result<Response<SearchResponse>, Status> {
+            if self.failing {
+                return Err(Status::internal("stub"));
+            }
+
+            Ok(Response::new(SearchResponse {
+                status: GoogolStatus::Success as i32,
+                pages: self.pages.clone(),
+                total: self.pages.len() as u64,
+                did_you_mean: self.did_you_mean.clone(),
+            }))
+        }
+
+        type SearchStreamStream = BoxStream<'static, Result<SearchResponse, Status>>;
+
+        /// Sends each page in its own batch.
+        async fn search_stream(
+            &self,
+            _: Request<SearchRequest>,
+        ) -> Result<Response<Self::SearchStreamStream>, Status> {
+            let batches = self.pages.clone().into_iter().map(|page| SearchResponse {
+                status: GoogolStatus::Success as i32,
+                pages: vec![page],
+                ..Default::default()
+            });
+
+            Ok(Response::new(Box::pin(stream::iter(batches).map(Ok))))
+        }
+
+        async fn status(
+            &self,
+            _: Request<crate::proto::BarrelStatusRequest>,
+        ) -> Result<Response<crate::proto::BarrelStatusResponse>, Status> {
+            Err(Status::unimplemented("stub"))
+        }
+
+        async fn suggest(
+            &self,
+            _: Request<SuggestRequest>,
+        ) -> Result<Response<SuggestResponse>, Status> {
+            Err(Status::unimplemented("stub"))
+        }
+    }
+
+    /// Serves a stub barrel on a random local port.
+    async fn spawn_stub_barrel(stub: StubBarrel) -> SocketAddr {
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let address = incoming.local_addr().unwrap();
+
+        tokio::spawn(
+            Server::builder()
+                .add_service(BarrelServiceServer::new(stub))
+                .serve_with_incoming(incoming),
+        );
+
+        address
+    }
+
+    #[tokio::test]
+    async fn test_search_dedups_pages() {
+        let page = |url: &str, rank| Page {
+            url: url.to_string(),
+            rank,
+            ..Default::default()
+        };
+        let stub = StubBarrel {
+            pages: vec![
+                page("https://a.com/", Some(3.0)),
+                page("https://b.com/", Some(2.0)),
+                page("https://a.com/", Some(1.0)),
+                page("https://c.com/", None),
+                page("https://b.com/", Some(4.0)),
+                page("https://d.com/", None),
+            ],
+            ..Default::default()
+        };
+
+        let address = spawn_stub_barrel(stub).await;
+        let gateway = Gateway::create()
+            .with_load_balancer(LoadBalancer::new(&HashSet::from([address])))
+            .await;
+
+        let response = gateway
+            .search(Request::new(SearchRequest {
+                words: vec!["rust".to_string()],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let pages: Vec<_> = response
+            .pages
+            .iter()
+            .map(|page| (page.url.as_str(), page.rank))
+            .collect();
+        assert_eq!(
+            pages,
+            [
+                ("https://b.com/", Some(4.0)),
+                ("https://a.com/", Some(3.0)),
+                ("https://c.com/", None),
+                ("https://d.com/", None),
+            ]
+        );
+        assert_eq!(response.total, 4);
+    }
+
+    #[tokio::test]
+    async fn test_search_all_barrels() {
+        let page = |url: &str, rank| Page {
+            url: url.to_string(),
+            rank: Some(rank),
+            ..Default::default()
+        };
+        let first = StubBarrel {
+            pages: vec![page("https://a.com/", 5.0), page("https://c.com/", 1.0)],
+            ..Default::default()
+        };
+        let second = StubBarrel {
+            pages: vec![page("https://b.com/", 3.0), page("https://a.com/", 2.0)],
+            ..Default::default()
+        };
+
+        let barrels = HashSet::from([
+            spawn_stub_barrel(first).await,
+            spawn_stub_barrel(second).await,
+            // Offline barrels are skipped
+            "127.0.0.1:1".parse().unwrap(),
+        ]);
+        let gateway = Gateway::create()
+            .with_load_balancer(LoadBalancer::new(&barrels))
+            .await
+            .with_search_scope(SearchScope::All);
+
+        let search = |offset, limit| {
+            gateway.search(Request::new(SearchRequest {
+                words: vec!["rust".to_string()],
+                offset,
+                limit,
+                ..Default::default()
+            }))
+        };
+
+        let response = search(0, 0).await.unwrap().into_inner();
+        let urls: Vec<_> = response
+            .pages
+            .iter()
+            .map(|page| page.url.as_str())
+            .collect();
+        assert_eq!(response.status, GoogolStatus::Success as i32);
+        assert_eq!(urls, ["https://a.com/", "https://b.com/", "https://c.com/"]);
+        assert_eq!(response.total, 3);
+
+        // Pagination applies to the merged results
+        let response = search(1, 1).await.unwrap().into_inner();
+        let urls: Vec<_> = response
+            .pages
+            .iter()
+            .map(|page| page.url.as_str())
+            .collect();
+        assert_eq!(urls, ["https://b.com/"]);
+    }
+
+    /// Urls of the pages streamed by `search_stream`, in the order they were received.
+    async fn search_stream_urls(gateway: &Gateway) -> Vec<String> {
+        let stream = gateway
+            .search_stream(Request::new(SearchRequest {
+                words: vec!["rust".to_string()],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        stream
+            .flat_map(|batch| stream::iter(batch.unwrap().pages))
+            .map(|page| page.url)
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_search_stream_dedups_pages() {
+        let page = |url: &str| Page {
+            url: url.to_string(),
+            ..Default::default()
+        };
+        let stub = StubBarrel {
+            pages: vec![
+                page("https://a.com/"),
+                page("https://b.com/"),
+                page("https://a.com/"),
+            ],
+            ..Default::default()
+        };
+
+        let gateway = Gateway::create()
+            .with_load_balancer(LoadBalancer::new(&HashSet::from([
+                spawn_stub_barrel(stub).await
+            ])))
+            .await;
+
+        assert_eq!(
+            search_stream_urls(&gateway).await,
+            ["https://a.com/", "https://b.com/"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_stream_all_barrels() {
+        let page = |url: &str, rank| Page {
+            url: url.to_string(),
+            rank: Some(rank),
+            ..Default::default()
+        };
+        let first = StubBarrel {
+            pages: vec![page("https://a.com/", 5.0), page("https://c.com/", 1.0)],
+            ..Default::default()
+        };
+        let second = StubBarrel {
+            pages: vec![page("https://b.com/", 3.0), page("https://a.com/", 2.0)],
+            ..Default::default()
+        };
+
+        let barrels = HashSet::from([
+            spawn_stub_barrel(first).await,
+            spawn_stub_barrel(second).await,
+        ]);
+        let gateway = Gateway::create()
+            .with_load_balancer(LoadBalancer::new(&barrels))
+            .await
+            .with_search_scope(SearchScope::All);
+
+        // Pages of every barrel are merged, as by `search`
+        assert_eq!(
+            search_stream_urls(&gateway).await,
+            ["https://a.com/", "https://b.com/", "https://c.com/"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_sharded_interleaves_barrels() {
+        let page = |url: &str, rank| Page {
+            url: url.to_string(),
+            rank: Some(rank),
+            ..Default::default()
+        };
+        // Ranks of different shards aren't comparable, so the lower ones aren't left last
+        let first = StubBarrel {
+            pages: vec![page("https://a.com/", 50.0), page("https://c.com/", 40.0)],
+            ..Default::default()
+        };
+        let second = StubBarrel {
+            pages: vec![page("https://b.com/", 2.0), page("https://d.com/", 1.0)],
+            ..Default::default()
+        };
+
+        let barrels = HashSet::from([
+            spawn_stub_barrel(first).await,
+            spawn_stub_barrel(second).await,
+        ]);
+        let gateway = Gateway::create()
+            .with_load_balancer(LoadBalancer::new(&barrels))
+            .await
+            .with_search_scope(SearchScope::All)
+            .with_sharding(Sharding::Host);
+
+        let urls = search_stream_urls(&gateway).await;
+
+        // Each barrel's order is kept, taking one page of each in turns
+        let position = |url: &str| urls.iter().position(|u| u == url).unwrap();
+        assert_eq!(urls.len(), 4);
+        assert!(position("https://a.com/") < position("https://c.com/"));
+        assert!(position("https://b.com/") < position("https://d.com/"));
+        assert_eq!(
+            position("https://a.com/").max(position("https://b.com/")),
+            1
+        );
+    }
+
+    #[test]
+    fn test_interleave_pages() {
+        let page = |url: &str| Page {
+            url: url.to_string(),
+            ..Default::default()
+        };
+
+        let pages = interleave_pages(vec![
+            vec![page("a"), page("c"), page("e")],
+            vec![page("b"), page("a")],
+            vec![],
+        ]);
+        let urls: Vec<_> = pages.iter().map(|page| page.url.as_str()).collect();
+
+        assert_eq!(urls, ["a", "b", "c", "e"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_all_barrels_did_you_mean() {
+        let corrected = || StubBarrel {
+            did_you_mean: vec!["rust".to_string()],
+            ..Default::default()
+        };
+        let found = StubBarrel {
+            pages: vec![Page {
+                url: "https://a.com/".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let search = |barrels: HashSet<SocketAddr>| async move {
+            Gateway::create()
+                .with_load_balancer(LoadBalancer::new(&barrels))
+                .await
+                .with_search_scope(SearchScope::All)
+                .search(Request::new(SearchRequest {
+                    words: vec!["rsut".to_string()],
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner()
+        };
+
+        let barrels = HashSet::from([
+            spawn_stub_barrel(corrected()).await,
+            spawn_stub_barrel(StubBarrel::default()).await,
+        ]);
+        assert_eq!(search(barrels).await.did_you_mean, ["rust"]);
+
+        // Corrections are dropped if any barrel found a page
+        let barrels = HashSet::from([
+            spawn_stub_barrel(corrected()).await,
+            spawn_stub_barrel(found).await,
+        ]);
+        assert!(search(barrels).await.did_you_mean.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_all_barrels_offline() {
+        let gateway = create_gateway_with_unreachable_barrel()
+            .await
+            .with_search_scope(SearchScope::All);
+
+        let response = gateway
+            .search(Request::new(SearchRequest {
+                words: vec!["rust".to_string()],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.status, GoogolStatus::UnavailableBarrels as i32);
+        assert!(response.pages.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_no_results() {
+        for scope in [SearchScope::Single, SearchScope::All] {
+            let address = spawn_stub_barrel(StubBarrel::default()).await;
+            let gateway = Gateway::create()
+                .with_load_balancer(LoadBalancer::new(&HashSet::from([address])))
+                .await
+                .with_search_scope(scope);
+
+            let response = gateway
+                .search(Request::new(SearchRequest {
+                    words: vec!["rust".to_string()],
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+
+            assert_eq!(response.status, GoogolStatus::Success as i32);
+            assert!(response.pages.is_empty());
+            assert_eq!(response.total, 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_barrel_error() {
+        let failing = StubBarrel {
+            failing: true,
+            ..Default::default()
+        };
+        let barrels = HashSet::from([
+            spawn_stub_barrel(failing).await,
+            "127.0.0.1:1".parse().unwrap(),
+        ]);
+
+        for scope in [SearchScope::Single, SearchScope::All] {
+            let gateway = Gateway::create()
+                .with_load_balancer(LoadBalancer::new(&barrels))
+                .await
+                .with_search_scope(scope);
+
+            let response = gateway
+                .search(Request::new(SearchRequest {
+                    words: vec!["rust".to_string()],
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+
+            // A barrel was reached, so it isn't reported as unavailable
+            assert_eq!(response.status, GoogolStatus::Error as i32);
+            assert!(response.pages.is_empty());
+        }
+    }
+
+    /// Creates a gateway whose only barrel is not listening.
+    async fn create_gateway_with_unreachable_barrel() -> Gateway {
+        let barrels = ["127.0.0.1:1"].iter().map(|a| a.parse().unwrap()).collect();
+
+        Gateway::create()
+            .with_load_balancer(LoadBalancer::new(&barrels))
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_barrels() {
+        let gateway = create_gateway_with_unreachable_barrel().await;
+
+        let response = gateway
+            .health(Request::new(HealthRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.status.starts_with("DEGRADED"));
+        assert_eq!((response.online_barrels, response.total_barrels), (0, 1));
+
+        gateway.load_balancer.lock().await.barrels[0].online = true;
+
+        let response = gateway
+            .health(Request::new(HealthRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.status.starts_with("OK"));
+        assert_eq!((response.online_barrels, response.total_barrels), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_status_default_gateway() {
+        let gateway = Gateway::default();
+
+        gateway
+            .queue
+            .lock()
+            .await
+            .enqueue(Url::parse("https://example.com").unwrap());
+        gateway.status.lock().await.top_searches.add_search("rust");
+
+        let request = Request::new(GatewayStatusRequest {});
+        let response = gateway.status(request).await.unwrap().into_inner();
+
+        assert_eq!(response.queue_length, 1);
+        assert_eq!(response.online_barrels, 0);
+        assert_eq!(response.offline_barrels, 0);
+        assert_eq!(response.avg_response_time_ms, 0.0);
+        assert_eq!(response.p99_response_time_ms, 0.0);
+        assert_eq!(response.total_searches, 1);
+    }
+
+    #[tokio::test]
+    async fn test_status_response_time_percentiles() {
+        let gateway = Gateway::default();
+
+        for miliseconds in [10, 10, 10, 1000] {
+            let mut response_time = ResponseTime::default();
+            response_time.add_sample(Duration::from_millis(miliseconds));
+            gateway
+                .record_search(&["rust".to_string()], &response_time)
+                .await;
+        }
+
+        let request = Request::new(GatewayStatusRequest {});
+        let response = gateway.status(request).await.unwrap().into_inner();
+
+        let close = |actual: f32, expected: f32| (actual - expected).abs() / expected < 0.02;
+        assert!(
+            close(response.avg_response_time_ms, 257.5),
+            "{:?}",
+            response
+        );
+        assert!(close(response.p50_response_time_ms, 10.), "{:?}", response);
+        assert!(
+            close(response.p99_response_time_ms, 1000.),
+            "{:?}",
+            response
+        );
+    }
+
+    #[tokio::test]
+    async fn test_real_time_status_initial_snapshot() {
+        let gateway = Gateway::default();
+        gateway.status.lock().await.top_searches.add_search("rust");
+
+        // The first call returns the current status without waiting for a change
+        let request = Request::new(RealTimeStatusRequest::default());
+        let response = tokio::time::timeout(
+            Duration::from_millis(100),
+            gateway.real_time_status(request),
+        )
+        .await
+        .expect("Initial snapshot should not wait")
+        .unwrap()
+        .into_inner();
+
+        assert_eq!(response.top10_searches, vec!["rust"]);
+    }
+
+    #[tokio::test]
+    async fn test_real_time_status_waits_for_update() {
+        let gateway = Gateway::default();
+
+        let request = Request::new(RealTimeStatusRequest {
+            wait_for_update: true,
+        });
+        let waiting = tokio::time::timeout(
+            Duration::from_millis(100),
+            gateway.real_time_status(request),
+        )
+        .await;
+        assert!(waiting.is_err());
+
+        let request = Request::new(RealTimeStatusRequest {
+            wait_for_update: true,
+        });
+        let (response, _) = tokio::join!(gateway.real_time_status(request), async {
+            sleep(Duration::from_millis(50)).await;
+            gateway.notification.status.notify_waiters();
+        });
+        assert!(response.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_search_stream_barrels_offline() {
+        use futures::StreamExt;
+
+        let gateway = create_gateway_with_unreachable_barrel().await;
+
+        let request = Request::new(SearchRequest {
+            words: vec!["rust".to_string()],
+            ..Default::default()
+        });
+        let batches: Vec<_> = gateway
+            .search_stream(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .collect()
+            .await;
+
+        assert_eq!(batches.len(), 1);
+        let batch = batches[0].as_ref().unwrap();
+        assert_eq!(batch.status, GoogolStatus::UnavailableBarrels as i32);
+        assert!(batch.pages.is_empty());
+
+        // Failed searches don't count towards the top searches
+        assert_eq!(gateway.status.lock().await.top_searches.total(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_index_cached_when_barrels_offline() {
+        let gateway = create_gateway_with_unreachable_barrel().await;
+
+        let request = IndexRequest::default();
+        gateway.index(Request::new(request.clone())).await.unwrap();
+
+        assert_eq!(gateway.index_cache.lock().await.front(), Some(&request));
+
+        // Barrel is still offline, so nothing is delivered nor dropped
+        assert_eq!(gateway.flush_index_cache().await, 0);
+        assert_eq!(gateway.index_cache.lock().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_flush_index_cache_retries_failed() {
+        let filepath = ".test_gateway_flush_index_cache.bin";
+        let request = |url: &str| IndexRequest {
+            index: Some(Index {
+                page: Some(Page {
+                    url: url.to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            depth: 0,
+        };
+
+        let stub = StubBarrel::default();
+        let rejected_urls = Arc::clone(&stub.rejected_urls);
+        let received_urls = Arc::clone(&stub.received_urls);
+        rejected_urls
+            .lock()
+            .unwrap()
+            .insert("https://b.com/".to_string());
+
+        let address = spawn_stub_barrel(stub).await;
+        let mut index_cache = IndexCache::load(filepath, 10).unwrap();
+        for url in ["https://a.com/", "https://b.com/", "https://c.com/"] {
+            index_cache.push(request(url)).unwrap();
+        }
+        let gateway = Gateway::create()
+            .with_load_balancer(LoadBalancer::new(&HashSet::from([address])))
+            .await
+            .with_index_cache(index_cache)
+            .await;
+
+        // The barrel fails one request mid-flush, which is kept for the next attempt along with
+        // the following ones, without trying them
+        assert_eq!(gateway.flush_index_cache().await, 1);
+        let cache = IndexCache::load(filepath, 10).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.front(), Some(&request("https://b.com/")));
+        assert_eq!(*received_urls.lock().unwrap(), ["https://a.com/"]);
+
+        rejected_urls.lock().unwrap().clear();
+
+        assert_eq!(gateway.flush_index_cache().await, 2);
+        assert!(gateway.index_cache.lock().await.is_empty());
+        assert!(IndexCache::load(filepath, 10).unwrap().is_empty());
+
+        std::fs::remove_file(filepath).expect("Failed to delete temp file");
+    }
+
+    #[tokio::test]
+    async fn test_index_too_large_dropped() {
+        let request = |url: &str, words: usize| IndexRequest {
+            index: Some(Index {
+                page: Some(Page {
+                    url: url.to_string(),
+                    ..Default::default()
+                }),
+                words: vec!["word".to_string(); words],
+                ..Default::default()
+            }),
+            depth: 0,
+        };
+
+        // Too large for the gateway, so it would never be sent
+        let address = spawn_stub_barrel(StubBarrel::default()).await;
+        let gateway = Gateway::create()
+            .with_load_balancer(
+                LoadBalancer::new(&HashSet::from([address])).with_max_message_size(1024),
+            )
+            .await;
+        gateway
+            .index(Request::new(request("https://a.com/", 1000)))
+            .await
+            .unwrap();
+        assert!(gateway.index_cache.lock().await.is_empty());
+
+        // Too large for the barrel
+        let stub = StubBarrel::default();
+        let received_urls = Arc::clone(&stub.received_urls);
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let address = incoming.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(BarrelServiceServer::new(stub).max_decoding_message_size(1024))
+                .serve_with_incoming(incoming),
+        );
+        let gateway = Gateway::create()
+            .with_load_balancer(LoadBalancer::new(&HashSet::from([address])))
+            .await;
+        gateway
+            .index(Request::new(request("https://a.com/", 1000)))
+            .await
+            .unwrap();
+        assert!(gateway.index_cache.lock().await.is_empty());
+
+        // Cached requests too large are dropped without holding back the others
+        {
+            let mut index_cache = gateway.index_cache.lock().await;
+            index_cache.push(request("https://b.com/", 1000)).unwrap();
+            index_cache.push(request("https://c.com/", 1)).unwrap();
+        }
+        assert_eq!(gateway.flush_index_cache().await, 1);
+        assert!(gateway.index_cache.lock().await.is_empty());
+        assert_eq!(*received_urls.lock().unwrap(), ["https://c.com/"]);
+    }
+
+    #[tokio::test]
+    async fn test_index_sharded_by_host() {
+        let stubs = [StubBarrel::default(), StubBarrel::default()];
+        let received: Vec<_> = stubs
+            .iter()
+            .map(|stub| Arc::clone(&stub.received_urls))
+            .collect();
+
+        let mut addresses = vec![];
+        for stub in stubs {
+            addresses.push(spawn_stub_barrel(stub).await);
+        }
+
+        let gateway = Gateway::create()
+            .with_load_balancer(LoadBalancer::new(&addresses.iter().copied().collect()))
+            .await
+            .with_search_scope(SearchScope::All)
+            .with_sharding(Sharding::Host);
+
+        let urls: Vec<_> = (0..20)
+            .flat_map(|i| {
+                [
+                    format!("https://host{}.com/", i),
+                    format!("https://host{}.com/page", i),
+                ]
+            })
+            .collect();
+        for url in &urls {
+            let request = IndexRequest {
+                index: Some(Index {
+                    page: Some(Page {
+                        url: url.clone(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                depth: 0,
+            };
+            gateway.index(Request::new(request)).await.unwrap();
+        }
+
+        // Each page reaches only the barrel responsible for its host
+        for (address, received) in addresses.iter().zip(&received) {
+            let expected: Vec<_> = urls
+                .iter()
+                .filter(|url| {
+                    let url = Url::parse(url).unwrap();
+                    sharding::shard(sharding::shard_key(&url), &addresses) == Some(*address)
+                })
+                .cloned()
+                .collect();
+
+            assert!(!expected.is_empty());
+            assert_eq!(*received.lock().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_next_flush_delay() {
+        let interval = Duration::from_secs(30);
+        let max_interval = Duration::from_secs(100);
+
+        let delay = next_flush_delay(interval, interval, max_interval, true);
+        assert_eq!(delay, Duration::from_secs(60));
+        let delay = next_flush_delay(delay, interval, max_interval, true);
+        assert_eq!(delay, max_interval);
+        let delay = next_flush_delay(delay, interval, max_interval, true);
+        assert_eq!(delay, max_interval);
+
+        // Back to the interval once everything was delivered
+        assert_eq!(
+            next_flush_delay(delay, interval, max_interval, false),
+            interval
+        );
+    }
+
+    #[tokio::test]
+    async fn test_index_normalizes_urls() {
+        let gateway = create_gateway_with_unreachable_barrel().await;
+
+        let request = IndexRequest {
+            index: Some(Index {
+                page: Some(Page {
+                    url: "https://Example.com/page#section".to_string(),
+                    ..Default::default()
+                }),
+                outlinks: vec![
+                    "https://example.com/other?utm_source=feed".to_string(),
+                    "not a url".to_string(),
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        gateway.index(Request::new(request)).await.unwrap();
+
+        // The request sent to the barrels, cached as they are offline
+        let cache = gateway.index_cache.lock().await;
+        let index = cache.front().unwrap().index.as_ref().unwrap();
+        assert_eq!(index.page.as_ref().unwrap().url, "https://example.com/page");
+        assert_eq!(index.outlinks, ["https://example.com/other", "not a url"]);
+
+        assert_eq!(
+            gateway.queue.lock().await.into_vec(),
+            vec!["https://example.com/other"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_index_skips_invalid_outlinks() {
+        let gateway = Gateway::default();
+
+        let request = IndexRequest {
+            index: Some(Index {
+                outlinks: vec![
+                    "https://example.com/a".to_string(),
+                    "::garbage::".to_string(),
+                    String::new(),
+                    "https://example.com/b".to_string(),
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(gateway.index(Request::new(request)).await.is_ok());
+
+        assert_eq!(
+            gateway.queue.lock().await.into_vec(),
+            vec!["https://example.com/a", "https://example.com/b"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metrics_count_enqueues() {
+        let gateway = Gateway::default();
+
+        gateway
+            .enqueue_url(Request::new(EnqueueRequest {
+                url: "https://example.com/a".to_string(),
+            }))
+            .await
+            .unwrap();
+
+        // Only the new outlink is counted
+        let request = IndexRequest {
+            index: Some(Index {
+                outlinks: vec![
+                    "https://example.com/a".to_string(),
+                    "https://example.com/b".to_string(),
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        gateway.index(Request::new(request)).await.unwrap();
+
+        let text = gateway.render_metrics().await;
+        assert!(text.contains("googol_gateway_enqueues_total 2"), "{}", text);
+        assert!(text.contains("googol_gateway_queue_length 2"), "{}", text);
+        assert!(text.contains("googol_gateway_online_barrels 0"), "{}", text);
+    }
+
+    #[tokio::test]
+    async fn test_index_skips_blacklisted_outlinks() {
+        let domains_filter = DomainsFilter {
+            whitelist: HashSet::default(),
+            blacklist: [Host::parse("bad.com").unwrap()].into_iter().collect(),
+            ..Default::default()
+        };
+        let gateway = Gateway::create()
+            .with_queue(Queue::create().with_domains_filter(&domains_filter))
+            .await;
+
+        let request = IndexRequest {
+            index: Some(Index {
+                outlinks: vec![
+                    "https://example.com".to_string(),
+                    "https://bad.com/page".to_string(),
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        gateway.index(Request::new(request)).await.unwrap();
+
+        assert_eq!(
+            gateway.queue.lock().await.into_vec(),
+            vec!["https://example.com/"]
+        );
+    }
+
+    /// Creates a FishFish instance knowing `host` as `category`, without querying the API.
+    fn create_fishfish(host: &str, category: FishDomainCategory) -> FishFish {
+        let fishfish = FishFish::new();
+
+        fishfish.insert(
+            Host::parse(host).unwrap(),
+            FishDomain {
+                added: 0,
+                category,
+                checked: 0,
+                description: String::new(),
+                domain: Some(host.to_string()),
+                target: None,
+            },
+        );
+
+        fishfish
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_rejects_malicious_urls() {
+        let gateway = Gateway::create().with_fishfish(create_fishfish(
+            "phishing.com",
+            FishDomainCategory::Phishing,
+        ));
+
+        let request = Request::new(EnqueueRequest {
+            url: "https://phishing.com/login".to_string(),
+        });
+        let response = gateway.enqueue_url(request).await.unwrap().into_inner();
+
+        assert_eq!(response.status, GoogolStatus::MaliciousUrl as i32);
+        assert!(gateway.queue.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_index_skips_malicious_outlinks() {
+        let gateway = Gateway::create()
+            .with_fishfish(create_fishfish("malware.com", FishDomainCategory::Malware));
+
+        let request = IndexRequest {
+            index: Some(Index {
+                outlinks: vec![
+                    "https://malware.com/download".to_string(),
+                    "not a url".to_string(),
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        gateway.index(Request::new(request)).await.unwrap();
+
+        assert!(gateway.queue.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_index_drops_outlinks_at_max_depth() {
+        let gateway = Gateway::create()
+            .with_queue(Queue::create().with_max_depth(Some(1)))
+            .await;
+
+        let create_request = |depth| IndexRequest {
+            index: Some(Index {
+                outlinks: vec![format!("https://example.com/{}", depth)],
+                ..Default::default()
+            }),
+            depth,
+        };
+
+        // Outlinks of a page below the maximum depth are queued
+        gateway
+            .index(Request::new(create_request(0)))
+            .await
+            .unwrap();
+        // Outlinks of a page at the maximum depth are dropped
+        gateway
+            .index(Request::new(create_request(1)))
+            .await
+            .unwrap();
+
+        let mut queue = gateway.queue.lock().await;
+        assert_eq!(
+            queue.dequeue(),
+            Some((Url::parse("https://example.com/0").unwrap(), 1))
+        );
+        assert_eq!(queue.dequeue(), None);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_index_unreachable_barrels() {
+        let gateway = create_gateway_with_unreachable_barrel().await;
+
+        let request = Request::new(BroadcastIndexRequest {
+            index_json_serialized: "{}".to_string(),
+        });
+
+        let response = gateway.broadcast_index(request).await.unwrap().into_inner();
+
+        assert!(!response.success);
+        assert_eq!(response.status, GoogolStatus::UnavailableBarrels as i32);
+        assert_eq!(response.offline, 1);
+    }
+
+    #[tokio::test]
+    async fn test_add_remove_barrel() {
+        let gateway = create_gateway_with_unreachable_barrel().await;
+
+        let request = AddBarrelRequest {
+            address: "127.0.0.1:2".to_string(),
+        };
+        let response = gateway
+            .add_barrel(Request::new(request.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.added);
+        assert_eq!(response.barrels.len(), 2);
+        assert!(response.barrels.iter().all(|barrel| !barrel.online));
+
+        let response = gateway
+            .add_barrel(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!response.added);
+
+        let request = RemoveBarrelRequest {
+            address: "127.0.0.1:1".to_string(),
+        };
+        let response = gateway
+            .remove_barrel(Request::new(request.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.removed);
+        assert_eq!(response.barrels.len(), 1);
+        assert_eq!(response.barrels[0].address, "127.0.0.1:2");
+
+        let response = gateway
+            .remove_barrel(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!response.removed);
+    }
+
+    #[tokio::test]
+    async fn test_add_barrel_invalid_address() {
+        let gateway = Gateway::default();
+
+        let request = Request::new(AddBarrelRequest {
+            address: "not an address".to_string(),
+        });
+
+        let status = gateway.add_barrel(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_clear_queue() {
+        let gateway = Gateway::default();
+        for url in ["https://a.com", "https://b.com", "https://c.com"] {
+            gateway.queue.lock().await.enqueue(Url::parse(url).unwrap());
+        }
+
+        let clear_queue = |url: Option<&str>| {
+            gateway.clear_queue(Request::new(ClearQueueRequest {
+                url: url.map(String::from),
+            }))
+        };
+
+        let response = clear_queue(Some("https://a.com"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.status, GoogolStatus::Success as i32);
+        assert_eq!(response.removed, 1);
+        assert_eq!(response.queue, ["https://b.com/", "https://c.com/"]);
+
+        // Not pending anymore
+        let response = clear_queue(Some("https://a.com"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.status, GoogolStatus::Success as i32);
+        assert_eq!(response.removed, 0);
+
+        let response = clear_queue(Some("not a url")).await.unwrap().into_inner();
+        assert_eq!(response.status, GoogolStatus::InvalidUrl as i32);
+
+        let response = clear_queue(None).await.unwrap().into_inner();
+        assert_eq!(response.removed, 2);
+        assert!(response.queue.is_empty());
+        assert!(gateway.queue.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_batch() {
+        let gateway = Arc::new(Gateway::default());
+        let dequeue_batch = |count| {
+            let gateway = Arc::clone(&gateway);
+            tokio::spawn(async move {
+                gateway
+                    .dequeue_batch(Request::new(DequeueBatchRequest { count }))
+                    .await
+                    .unwrap()
+                    .into_inner()
+                    .urls
+            })
+        };
+
+        // Waits while the queue is empty, until a URL is enqueued
+        let pending = dequeue_batch(3);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!pending.is_finished());
+
+        gateway
+            .enqueue_url(Request::new(EnqueueRequest {
+                url: "https://a.com".to_string(),
+            }))
+            .await
+            .unwrap();
+
+        let urls = tokio::time::timeout(Duration::from_secs(1), pending)
+            .await
+            .expect("dequeue_batch was not woken up")
+            .unwrap();
+        assert_eq!(urls.len(), 1);
+        assert_eq!(urls[0].url, "https://a.com/");
+
+        // Returns at most `count` URLs, without waiting for more
+        for url in ["https://b.com", "https://c.com", "https://d.com"] {
+            gateway.queue.lock().await.enqueue(Url::parse(url).unwrap());
+        }
+        assert_eq!(dequeue_batch(2).await.unwrap().len(), 2);
+        assert_eq!(dequeue_batch(2).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_requeue_urls() {
+        let gateway = Gateway::default();
+        let url = Url::parse("https://example.com/").unwrap();
+
+        gateway
+            .queue
+            .lock()
+            .await
+            .enqueue_with_depth(url.clone(), 2);
+        let dequeued = gateway.queue.lock().await.dequeue_batch(1);
+        assert_eq!(dequeued, [(url.clone(), 2)]);
+
+        let request = Request::new(RequeueUrlsRequest {
+            urls: vec![
+                DequeueResponse {
+                    url: url.to_string(),
+                    depth: 2,
+                },
+                DequeueResponse {
+                    url: "not a url".to_string(),
+                    depth: 0,
+                },
+            ],
+        });
+        gateway.requeue_urls(request).await.unwrap();
+
+        // Put back at its depth, even though it was already seen
+        assert_eq!(gateway.queue.lock().await.dequeue(), Some((url.clone(), 2)));
+        assert!(gateway.queue.lock().await.is_empty());
+
+        // Urls that weren't dequeued, or were crawled since, aren't put back
+        gateway
+            .skip_url(Request::new(SkipUrlRequest {
+                url: url.to_string(),
+                reason: "Crawled".to_string(),
+            }))
+            .await
+            .unwrap();
+        let request = Request::new(RequeueUrlsRequest {
+            urls: vec![
+                DequeueResponse {
+                    url: url.to_string(),
+                    depth: 0,
+                },
+                DequeueResponse {
+                    url: "https://phishing.example/".to_string(),
+                    depth: 0,
+                },
+            ],
+        });
+        gateway.requeue_urls(request).await.unwrap();
+        assert!(gateway.queue.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_skip_url() {
+        let gateway = Gateway::default();
+
+        let request = Request::new(SkipUrlRequest {
+            url: "https://example.com/private".to_string(),
+            reason: "Disallowed by robots.txt".to_string(),
+        });
+        gateway.skip_url(request).await.unwrap();
+
+        let request = Request::new(GatewayStatusRequest {});
+        let response = gateway.status(request).await.unwrap().into_inner();
+
+        assert_eq!(response.skipped_urls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_recrawl_stale_pages() {
+        let stub = StubBarrel {
+            indexed_urls: ["https://a.com/", "https://b.com/", "https://c.com/"]
+                .map(String::from)
+                .to_vec(),
+            ..Default::default()
+        };
+        let address = spawn_stub_barrel(stub).await;
+        let gateway = Gateway::create()
+            .with_load_balancer(LoadBalancer::new(&HashSet::from([address])))
+            .await;
+        let interval = Duration::from_secs(60);
+
+        assert_eq!(gateway.recrawl_stale_pages(interval, 2).await, 2);
+        assert_eq!(gateway.queue.lock().await.len(), 2);
+
+        // Pages being recrawled aren't enqueued again, even once dequeued
+        let entries = gateway.wait_dequeue(2).await;
+        assert_eq!(gateway.recrawl_stale_pages(interval, 3).await, 1);
+
+        // Until they are indexed or skipped
+        let (indexed, skipped) = (&entries[0].0, &entries[1].0);
+        gateway
+            .index(Request::new(IndexRequest {
+                index: Some(crate::proto::Index {
+                    page: Some(Page {
+                        url: indexed.to_string(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                depth: 0,
+            }))
+            .await
+            .unwrap();
+        gateway
+            .skip_url(Request::new(SkipUrlRequest {
+                url: skipped.to_string(),
+                reason: "Not found".to_string(),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(gateway.recrawl_stale_pages(interval, 3).await, 2);
+    }
+
+    #[test]
+    fn test_gateway_default_interactive_off() {
+        let gateway = Gateway::default();
+
+        assert!(!gateway.interactive);
+
+        let gateway = gateway.with_interactive(true);
+
+        assert!(gateway.interactive);
+    }
+
+    /// Addresses of the barrels in the gateway's load balancer.
+    async fn barrel_addresses(gateway: &Gateway) -> HashSet<SocketAddr> {
+        gateway
+            .load_balancer
+            .lock()
+            .await
+            .barrels
+            .iter()
+            .map(|barrel| barrel.address.as_socket_addr())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_reload_config() {
+        let gateway = create_gateway_with_unreachable_barrel().await;
+        let url = Url::parse("https://bad.com").unwrap();
+
+        let config = GatewayConfig {
+            barrels: ["127.0.0.1:2", "127.0.0.1:3"]
+                .iter()
+                .map(|a| a.parse().unwrap())
+                .collect(),
+            domains_filter: DomainsFilter {
+                whitelist: HashSet::new(),
+                blacklist: HashSet::from([Host::parse("bad.com").unwrap()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        gateway.reload_config(&config).await;
+
+        assert_eq!(barrel_addresses(&gateway).await, config.barrels);
+        assert_eq!(
+            gateway.queue.lock().await.enqueue(url),
+            GoogolStatus::BlacklistedUrl
+        );
+    }
+
+    #[tokio::test]
+    async fn test_config_watcher() {
+        let directory =
+            std::env::temp_dir().join(format!("googol-config-watcher-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("gateway.toml");
+        let config = |barrel: &str| {
+            format!(
+                "address = \"127.0.0.1:50051\"\nqueue = []\nbarrels = [\"{}\"]\n[domains_filter]\nwhitelist = []\nblacklist = []\n",
+                barrel
+            )
+        };
+        std::fs::write(&path, config("127.0.0.1:1")).unwrap();
+
+        let gateway = Arc::new(create_gateway_with_unreachable_barrel().await);
+        let watcher = Arc::clone(&gateway).spawn_config_watcher(&path).unwrap();
+        let unreachable = HashSet::from(["127.0.0.1:1".parse().unwrap()]);
+
+        // Malformed edits keep the previous configuration
+        std::fs::write(&path, "barrels = [").unwrap();
+        sleep(Duration::from_millis(300)).await;
+        assert_eq!(barrel_addresses(&gateway).await, unreachable);
+
+        std::fs::write(&path, config("127.0.0.1:2")).unwrap();
+        let reloaded = HashSet::from(["127.0.0.1:2".parse().unwrap()]);
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while barrel_addresses(&gateway).await != reloaded {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "Config not reloaded"
+            );
+            sleep(Duration::from_millis(50)).await;
+        }
+
+        watcher.abort();
+        std::fs::remove_dir_all(&directory).unwrap();
     }
 }
@@ -1,13 +1,117 @@
-use std::collections::{HashSet, VecDeque};
+use chrono::{DateTime, Utc};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap, HashSet},
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+};
 use url::Url;
 
-use crate::{GoogolStatus, settings::gateway::DomainsFilter};
+use crate::{
+    GoogolStatus,
+    settings::gateway::{DomainsFilter, UrlNormalization},
+};
+
+/// Computes the priority of a URL discovered at the given crawl depth.
+///
+/// URLs with a higher priority are dequeued first. URLs with the same priority are dequeued in
+/// the order they were enqueued.
+pub type Priority = fn(url: &Url, depth: u32, domains_filter: &DomainsFilter) -> i64;
+
+/// Default `Priority`: whitelisted domains first, then shallower depths.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::{gateway::queue::default_priority, settings::gateway::DomainsFilter};
+/// use url::Url;
+///
+/// let filter = DomainsFilter::default();
+/// let url = Url::parse("https://example.com").unwrap();
+///
+/// assert!(default_priority(&url, 0, &filter) > default_priority(&url, 1, &filter));
+/// ```
+pub fn default_priority(url: &Url, depth: u32, domains_filter: &DomainsFilter) -> i64 {
+    let whitelisted = domains_filter.is_whitelisted(url) as i64;
+
+    (whitelisted << 32) - depth as i64
+}
+
+/// A pending URL in the queue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    url: Url,
+    depth: u32,
+    priority: i64,
+    /// Insertion order, used to keep equal priorities FIFO.
+    sequence: u64,
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.priority, Reverse(self.sequence)).cmp(&(other.priority, Reverse(other.sequence)))
+    }
+}
 
-#[derive(Debug, Default)]
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// On-disk representation of a `Queue`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct QueueData {
+    /// Pending URLs and their depth, in the order they will be dequeued.
+    pending: Vec<(Url, u32)>,
+    seen: HashSet<Url>,
+    #[serde(default)]
+    recrawling: HashMap<Url, DateTime<Utc>>,
+    #[serde(default)]
+    leased: HashMap<Url, u32>,
+}
+
+#[derive(Debug)]
 pub struct Queue {
-    queue: VecDeque<Url>,
+    /// Pending URLs, highest priority first.
+    queue: BinaryHeap<Entry>,
     seen: HashSet<Url>,
+    /// Indexed URLs enqueued again to be recrawled, and when. They are not enqueued again
+    /// until recrawled, or until the recrawl itself is stale.
+    recrawling: HashMap<Url, DateTime<Utc>>,
+    /// Dequeued URLs that are neither indexed nor skipped yet, and their depth. Only these can
+    /// be requeued.
+    leased: HashMap<Url, u32>,
     domains_filter: DomainsFilter,
+    /// Rules URLs are rewritten with before being enqueued.
+    url_normalization: UrlNormalization,
+    /// URLs deeper than this are rejected. `None` means no limit.
+    max_depth: Option<u32>,
+    /// URLs are rejected while this many are pending. `None` means no limit.
+    max_size: Option<usize>,
+    priority: Priority,
+    /// Sequence number of the next enqueued URL.
+    sequence: u64,
+}
+
+impl Default for Queue {
+    fn default() -> Self {
+        Self {
+            queue: BinaryHeap::new(),
+            seen: HashSet::new(),
+            recrawling: HashMap::new(),
+            leased: HashMap::new(),
+            domains_filter: DomainsFilter::default(),
+            url_normalization: UrlNormalization::default(),
+            max_depth: None,
+            max_size: None,
+            priority: default_priority,
+            sequence: 0,
+        }
+    }
 }
 
 impl Queue {
@@ -16,43 +120,357 @@ impl Queue {
     }
 
     pub fn with_domains_filter(mut self, domains_filter: &DomainsFilter) -> Self {
+        self.set_domains_filter(domains_filter);
+        self
+    }
+
+    /// Replaces the domains filter, reprioritizing the pending URLs.
+    ///
+    /// Pending URLs the new filter rejects stay in the queue; only new URLs are filtered.
+    pub fn set_domains_filter(&mut self, domains_filter: &DomainsFilter) {
         self.domains_filter = domains_filter.clone();
+        self.reprioritize();
+    }
+
+    pub fn with_url_normalization(mut self, url_normalization: &UrlNormalization) -> Self {
+        self.set_url_normalization(url_normalization);
         self
     }
 
+    /// Replaces the rules URLs are normalized with. Pending and seen URLs are kept as is.
+    pub fn set_url_normalization(&mut self, url_normalization: &UrlNormalization) {
+        self.url_normalization = url_normalization.clone();
+    }
+
+    /// Normalizes a URL the way it would be before being enqueued.
+    pub fn normalize(&self, url: Url) -> Url {
+        self.url_normalization.normalize(url)
+    }
+
+    /// Sets the function deciding the order URLs are dequeued in.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::gateway::queue::Queue;
+    ///
+    /// // Prefer shorter URLs, regardless of depth
+    /// let queue = Queue::create().with_priority(|url, _, _| -(url.as_str().len() as i64));
+    /// ```
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self.reprioritize();
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: Option<u32>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the maximum number of pending URLs. `None` means no limit.
+    pub fn with_max_size(mut self, max_size: Option<usize>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Whether no more URLs can be enqueued until some are dequeued.
+    pub fn is_full(&self) -> bool {
+        self.max_size.is_some_and(|max_size| self.len() >= max_size)
+    }
+
+    /// Enqueues a seed URL, at depth 0.
     #[allow(private_interfaces)]
-    pub fn enqueue(&mut self, url: Url) -> (GoogolStatus, Vec<String>) {
+    pub fn enqueue(&mut self, url: Url) -> GoogolStatus {
+        self.enqueue_with_depth(url, 0)
+    }
+
+    /// Enqueues a URL discovered at the given crawl `depth`, once normalized.
+    ///
+    /// URLs deeper than the configured maximum depth are rejected with
+    /// `GoogolStatus::MaxDepthExceeded`. New URLs are rejected with `GoogolStatus::QueueFull`
+    /// while the queue is full, but URLs already seen are still reported as such.
+    ///
+    /// Use [`Queue::into_vec`] to list the pending URLs afterwards, if needed.
+    #[allow(private_interfaces)]
+    pub fn enqueue_with_depth(&mut self, url: Url, depth: u32) -> GoogolStatus {
+        let url = self.normalize(url);
+
+        if self.max_depth.is_some_and(|max_depth| depth > max_depth) {
+            return GoogolStatus::MaxDepthExceeded;
+        }
+
+        if !self.domains_filter.is_allowed(&url) {
+            return GoogolStatus::BlacklistedUrl;
+        }
+
         if self.seen.contains(&url) {
-            return (GoogolStatus::AlreadyIndexedUrl, self.into_vec());
+            return GoogolStatus::AlreadyIndexedUrl;
         }
 
-        self.queue.push_back(url.clone());
+        if self.is_full() {
+            return GoogolStatus::QueueFull;
+        }
+
+        self.push(url.clone(), depth);
+        self.seen.insert(url);
+
+        GoogolStatus::Success
+    }
+
+    /// Puts back a dequeued URL that wasn't crawled, at the depth it was dequeued at.
+    ///
+    /// Only URLs dequeued and not released since with [`Queue::release`] are requeued, so
+    /// URLs that were never accepted can't be slipped in. As the URL was already accepted, it
+    /// is neither checked against the seen URLs nor rejected while the queue is full, but it
+    /// is dropped if the domains filter or the maximum depth reject it by now.
+    ///
+    /// # Returns
+    /// `true` if the URL was requeued, `false` otherwise.
+    pub fn requeue(&mut self, url: &Url) -> bool {
+        let url = self.normalize(url.clone());
+
+        let Some(depth) = self.leased.remove(&url) else {
+            return false;
+        };
+
+        if self.max_depth.is_some_and(|max_depth| depth > max_depth)
+            || !self.domains_filter.is_allowed(&url)
+        {
+            return false;
+        }
+
+        self.push(url.clone(), depth);
         self.seen.insert(url);
 
-        (GoogolStatus::Success, self.into_vec())
+        true
+    }
+
+    /// Marks a dequeued URL as crawled, once indexed or skipped, so it can't be requeued.
+    ///
+    /// # Returns
+    /// `true` if the URL was dequeued and not released yet, `false` otherwise.
+    pub fn release(&mut self, url: &Url) -> bool {
+        let url = self.normalize(url.clone());
+
+        self.leased.remove(&url).is_some()
+    }
+
+    /// Enqueues an already indexed URL again so it is recrawled, as a seed at depth 0.
+    ///
+    /// Unlike [`Queue::enqueue`], URLs already seen are accepted. Instead, a URL is only
+    /// enqueued once until [`Queue::finish_recrawl`] is called for it, so it isn't crawled by
+    /// several downloaders at once. A recrawl that never finished, e.g. because its downloader
+    /// went away, is retried if it was enqueued before `stale_before`.
+    #[allow(private_interfaces)]
+    pub fn enqueue_recrawl(&mut self, url: Url, stale_before: DateTime<Utc>) -> GoogolStatus {
+        let url = self.normalize(url);
+
+        if !self.domains_filter.is_allowed(&url) {
+            return GoogolStatus::BlacklistedUrl;
+        }
+
+        if self
+            .recrawling
+            .get(&url)
+            .is_some_and(|enqueued| *enqueued >= stale_before)
+            || self.queue.iter().any(|entry| entry.url == url)
+        {
+            return GoogolStatus::AlreadyIndexedUrl;
+        }
+
+        if self.is_full() {
+            return GoogolStatus::QueueFull;
+        }
+
+        self.push(url.clone(), 0);
+        self.seen.insert(url.clone());
+        self.recrawling.insert(url, Utc::now());
+
+        GoogolStatus::Success
+    }
+
+    /// Marks a URL as recrawled, once indexed or skipped, so it can be enqueued for a recrawl
+    /// again.
+    ///
+    /// # Returns
+    /// `true` if the URL was being recrawled, `false` otherwise.
+    pub fn finish_recrawl(&mut self, url: &Url) -> bool {
+        let url = self.normalize(url.clone());
+
+        self.recrawling.remove(&url).is_some()
+    }
+
+    /// Pushes a URL to the queue, without any check.
+    fn push(&mut self, url: Url, depth: u32) {
+        let priority = (self.priority)(&url, depth, &self.domains_filter);
+
+        self.queue.push(Entry {
+            url,
+            depth,
+            priority,
+            sequence: self.sequence,
+        });
+        self.sequence += 1;
+    }
+
+    /// Dequeues the URL with the highest priority, along with its crawl depth.
+    ///
+    /// The URL can be requeued until it is released.
+    pub fn dequeue(&mut self) -> Option<(Url, u32)> {
+        let entry = self.queue.pop()?;
+        self.leased.insert(entry.url.clone(), entry.depth);
+
+        Some((entry.url, entry.depth))
     }
 
-    pub fn dequeue(&mut self) -> Option<Url> {
-        self.queue.pop_front()
+    /// Dequeues up to `count` URLs, highest priority first, along with their crawl depth.
+    ///
+    /// Fewer URLs are returned if fewer are pending.
+    pub fn dequeue_batch(&mut self, count: usize) -> Vec<(Url, u32)> {
+        std::iter::from_fn(|| self.dequeue()).take(count).collect()
     }
 
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Pending URLs, in the order they will be dequeued.
     pub fn into_vec(&self) -> Vec<String> {
-        self.queue.iter().map(|url| url.to_string()).collect()
+        let mut entries: Vec<&Entry> = self.queue.iter().collect();
+        entries.sort_by(|a, b| b.cmp(a));
+
+        entries.iter().map(|entry| entry.url.to_string()).collect()
+    }
+
+    /// Removes every pending URL, and forgets the URLs seen so far.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.seen.clear();
+        self.recrawling.clear();
+        self.leased.clear();
+    }
+
+    /// Removes a pending URL, once normalized, so it is neither crawled nor seen.
+    ///
+    /// # Returns
+    /// `true` if the URL was pending, `false` otherwise.
+    pub fn remove(&mut self, url: &Url) -> bool {
+        let url = self.normalize(url.clone());
+
+        let len = self.queue.len();
+        self.queue.retain(|entry| entry.url != url);
+
+        let removed = self.queue.len() < len;
+        if removed {
+            self.seen.remove(&url);
+            self.recrawling.remove(&url);
+        }
+
+        removed
     }
 
     pub fn clear_seen(&mut self) {
         self.seen.clear();
 
-        for url in &self.queue {
-            self.seen.insert(url.clone());
+        for entry in &self.queue {
+            self.seen.insert(entry.url.clone());
         }
     }
+
+    /// Saves the pending URLs and the seen set to `filepath`, replacing the previous file
+    /// atomically.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the queue cannot be serialized or written.
+    pub fn save<P>(&self, filepath: P) -> Result<(), io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let filepath = filepath.as_ref();
+
+        let mut entries: Vec<&Entry> = self.queue.iter().collect();
+        entries.sort_by(|a, b| b.cmp(a));
+
+        let data = QueueData {
+            pending: entries
+                .iter()
+                .map(|entry| (entry.url.clone(), entry.depth))
+                .collect(),
+            seen: self.seen.clone(),
+            recrawling: self.recrawling.clone(),
+            leased: self.leased.clone(),
+        };
+
+        let json = serde_json::to_string(&data)
+            .map_err(|e| io::Error::other(format!("Serialization error: {}", e)))?;
+
+        let mut temp_filepath = filepath.to_path_buf().into_os_string();
+        temp_filepath.push(".tmp");
+
+        File::create(&temp_filepath)
+            .and_then(|mut file| {
+                file.write_all(json.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&temp_filepath, filepath))
+            .inspect_err(|e| error!("Failed to write to file {}: {}", filepath.display(), e))
+    }
+
+    /// Loads a queue saved with `save`, or creates an empty one if the file does not exist.
+    ///
+    /// Pending URLs keep their order and depth, and the seen set is restored so already
+    /// crawled URLs are not enqueued again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the file exists but cannot be read or deserialized.
+    pub fn load<P>(filepath: P) -> Result<Self, io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let data: QueueData = match fs::read_to_string(filepath) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| io::Error::other(format!("Deserialization error: {}", e)))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => QueueData::default(),
+            Err(e) => return Err(e),
+        };
+
+        let mut queue = Self::default();
+
+        for (url, depth) in data.pending {
+            queue.push(url, depth);
+        }
+        queue.seen.extend(data.seen);
+        queue.recrawling.extend(data.recrawling);
+        queue.leased.extend(data.leased);
+
+        Ok(queue)
+    }
+
+    /// Recomputes the priority of every pending URL.
+    fn reprioritize(&mut self) {
+        let entries = std::mem::take(&mut self.queue);
+
+        self.queue = entries
+            .into_iter()
+            .map(|entry| Entry {
+                priority: (self.priority)(&entry.url, entry.depth, &self.domains_filter),
+                ..entry
+            })
+            .collect();
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use url::Url;
+    use url::{Host, Url};
 
     #[test]
     fn test_enqueue_and_duplicate() {
@@ -60,12 +478,14 @@ mod tests {
         let url = Url::parse("https://example.com").unwrap();
 
         // Enqueue a new URL
-        let (status, list) = queue.enqueue(url.clone());
+        let status = queue.enqueue(url.clone());
+        let list = queue.into_vec();
         assert_eq!(status, GoogolStatus::Success);
         assert_eq!(list, vec![url.as_str()]);
 
         // Enqueue the same URL again should return AlreadyIndexedUrl
-        let (status_dup, list_dup) = queue.enqueue(url.clone());
+        let status_dup = queue.enqueue(url.clone());
+        let list_dup = queue.into_vec();
         assert_eq!(status_dup, GoogolStatus::AlreadyIndexedUrl);
         assert_eq!(list_dup, vec![url.as_str()]);
 
@@ -73,6 +493,268 @@ mod tests {
         assert_eq!(queue.into_vec(), vec![url.as_str()]);
     }
 
+    fn create_domains_filter(whitelist: &[&str], blacklist: &[&str]) -> DomainsFilter {
+        DomainsFilter {
+            whitelist: whitelist.iter().map(|d| Host::parse(d).unwrap()).collect(),
+            blacklist: blacklist.iter().map(|d| Host::parse(d).unwrap()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_enqueue_normalizes_urls() {
+        let mut queue = Queue::default();
+
+        let status = queue.enqueue(Url::parse("https://example.com/page").unwrap());
+        assert_eq!(status, GoogolStatus::Success);
+
+        for duplicate in [
+            "https://example.com/page#section",
+            "https://EXAMPLE.com:443/page?utm_source=feed",
+        ] {
+            let status = queue.enqueue(Url::parse(duplicate).unwrap());
+            assert_eq!(status, GoogolStatus::AlreadyIndexedUrl);
+        }
+
+        let mut queue = Queue::default().with_url_normalization(&UrlNormalization {
+            strip_trailing_slash: true,
+            ..Default::default()
+        });
+        queue.enqueue(Url::parse("https://example.com/page/#top").unwrap());
+        assert_eq!(queue.into_vec(), vec!["https://example.com/page"]);
+    }
+
+    #[test]
+    fn test_enqueue_blacklisted() {
+        let mut queue =
+            Queue::create().with_domains_filter(&create_domains_filter(&[], &["bad.com"]));
+
+        let status = queue.enqueue(Url::parse("https://bad.com/page").unwrap());
+        let list = queue.into_vec();
+        assert_eq!(status, GoogolStatus::BlacklistedUrl);
+        assert!(list.is_empty());
+
+        // Blacklisted URLs are not marked as seen
+        assert!(queue.seen.is_empty());
+    }
+
+    #[test]
+    fn test_enqueue_empty_whitelist_allows_all() {
+        let mut queue = Queue::create().with_domains_filter(&create_domains_filter(&[], &[]));
+
+        let status = queue.enqueue(Url::parse("https://example.com").unwrap());
+        assert_eq!(status, GoogolStatus::Success);
+
+        let status = queue.enqueue(Url::parse("https://other.com").unwrap());
+        assert_eq!(status, GoogolStatus::Success);
+    }
+
+    #[test]
+    fn test_enqueue_whitelist() {
+        let mut queue =
+            Queue::create().with_domains_filter(&create_domains_filter(&["example.com"], &[]));
+
+        let status = queue.enqueue(Url::parse("https://example.com/page").unwrap());
+        assert_eq!(status, GoogolStatus::Success);
+
+        let status = queue.enqueue(Url::parse("https://other.com").unwrap());
+        let list = queue.into_vec();
+        assert_eq!(status, GoogolStatus::BlacklistedUrl);
+        assert_eq!(list, vec!["https://example.com/page"]);
+    }
+
+    #[test]
+    fn test_enqueue_blacklist_overrides_whitelist() {
+        let mut queue = Queue::create()
+            .with_domains_filter(&create_domains_filter(&["example.com"], &["example.com"]));
+
+        let status = queue.enqueue(Url::parse("https://example.com").unwrap());
+        assert_eq!(status, GoogolStatus::BlacklistedUrl);
+    }
+
+    #[test]
+    fn test_enqueue_max_depth() {
+        let mut queue = Queue::create().with_max_depth(Some(1));
+
+        let seed = Url::parse("https://example.com").unwrap();
+        let child = Url::parse("https://example.com/child").unwrap();
+        let grandchild = Url::parse("https://example.com/child/grandchild").unwrap();
+
+        assert_eq!(queue.enqueue(seed.clone()), GoogolStatus::Success);
+        assert_eq!(
+            queue.enqueue_with_depth(child.clone(), 1),
+            GoogolStatus::Success
+        );
+        assert_eq!(
+            queue.enqueue_with_depth(grandchild, 2),
+            GoogolStatus::MaxDepthExceeded
+        );
+
+        assert_eq!(queue.dequeue(), Some((seed, 0)));
+        assert_eq!(queue.dequeue(), Some((child, 1)));
+        assert_eq!(queue.dequeue(), None);
+    }
+
+    #[test]
+    fn test_enqueue_max_size() {
+        let mut queue = Queue::default().with_max_size(Some(2));
+        let url = |i| Url::parse(&format!("https://example.com/{}", i)).unwrap();
+
+        assert_eq!(queue.enqueue(url(0)), GoogolStatus::Success);
+        assert_eq!(queue.enqueue(url(1)), GoogolStatus::Success);
+        assert!(queue.is_full());
+
+        // Full, but duplicates are still told apart
+        assert_eq!(queue.enqueue(url(2)), GoogolStatus::QueueFull);
+        assert_eq!(queue.enqueue(url(0)), GoogolStatus::AlreadyIndexedUrl);
+        assert_eq!(queue.len(), 2);
+
+        // Dequeuing frees space, and rejected URLs were not marked as seen
+        queue.dequeue();
+        assert_eq!(queue.enqueue(url(2)), GoogolStatus::Success);
+        assert_eq!(queue.enqueue(url(3)), GoogolStatus::QueueFull);
+    }
+
+    #[test]
+    fn test_requeue() {
+        let mut queue = Queue::default().with_max_size(Some(1));
+        let url = |i| Url::parse(&format!("https://example.com/{}", i)).unwrap();
+
+        queue.enqueue_with_depth(url(0), 3);
+        let (dequeued, depth) = queue.dequeue().unwrap();
+        assert_eq!(queue.enqueue(url(1)), GoogolStatus::Success);
+
+        // Put back even though seen and the queue is full, and still seen afterwards
+        assert_eq!(depth, 3);
+        assert!(queue.requeue(&dequeued));
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.enqueue(url(0)), GoogolStatus::AlreadyIndexedUrl);
+        assert_eq!(queue.dequeue_batch(2), [(url(1), 0), (url(0), 3)]);
+
+        // But only once per dequeue, and not once crawled
+        assert!(queue.release(&url(1)));
+        assert!(!queue.requeue(&url(1)));
+        assert!(queue.requeue(&url(0)));
+        assert!(!queue.requeue(&url(0)));
+
+        // Nor if never dequeued
+        assert!(!queue.requeue(&url(2)));
+        assert!(!queue.requeue(&Url::parse("https://phishing.example/").unwrap()));
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn test_requeue_filtered() {
+        let mut queue = Queue::default();
+        let url = Url::parse("https://example.com/").unwrap();
+
+        queue.enqueue(url.clone());
+        queue.dequeue();
+
+        // Rejected by a filter reloaded since it was dequeued
+        let domains_filter = DomainsFilter {
+            blacklist: [Host::Domain("example.com".to_string())].into(),
+            ..Default::default()
+        };
+        queue.set_domains_filter(&domains_filter);
+        assert!(!queue.requeue(&url));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_enqueue_unlimited_depth() {
+        let mut queue = Queue::default();
+
+        let url = Url::parse("https://example.com").unwrap();
+
+        assert_eq!(
+            queue.enqueue_with_depth(url.clone(), u32::MAX),
+            GoogolStatus::Success
+        );
+        assert_eq!(queue.dequeue(), Some((url, u32::MAX)));
+    }
+
+    #[test]
+    fn test_dequeue_shallower_first() {
+        let mut queue = Queue::default();
+
+        let deep = Url::parse("https://example.com/deep").unwrap();
+        let shallow = Url::parse("https://example.com/shallow").unwrap();
+
+        queue.enqueue_with_depth(deep.clone(), 2);
+        queue.enqueue_with_depth(shallow.clone(), 1);
+
+        assert_eq!(
+            queue.into_vec(),
+            vec![shallow.to_string(), deep.to_string()]
+        );
+        assert_eq!(queue.dequeue(), Some((shallow, 1)));
+        assert_eq!(queue.dequeue(), Some((deep, 2)));
+    }
+
+    #[test]
+    fn test_default_priority_whitelisted_first() {
+        let filter = create_domains_filter(&["example.com"], &[]);
+
+        let whitelisted = Url::parse("https://example.com/deep").unwrap();
+        let other = Url::parse("https://other.com").unwrap();
+
+        assert!(default_priority(&whitelisted, 3, &filter) > default_priority(&other, 0, &filter));
+    }
+
+    #[test]
+    fn test_with_priority_reorders_pending() {
+        let mut queue = Queue::default();
+
+        let url1 = Url::parse("https://example.com/1").unwrap();
+        let url2 = Url::parse("https://example.com/2").unwrap();
+
+        queue.enqueue(url1.clone());
+        queue.enqueue(url2.clone());
+        assert_eq!(queue.into_vec(), vec![url1.to_string(), url2.to_string()]);
+
+        // Prefer the greatest path
+        let queue = queue.with_priority(|url, _, _| url.path().as_bytes()[1] as i64);
+        assert_eq!(queue.into_vec(), vec![url2.to_string(), url1.to_string()]);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let filepath = ".test_queue.json";
+
+        let crawled = Url::parse("https://example.com/crawled").unwrap();
+        let pending1 = Url::parse("https://example.com/1").unwrap();
+        let pending2 = Url::parse("https://example.com/2").unwrap();
+
+        let mut queue = Queue::default();
+        queue.enqueue(crawled.clone());
+        queue.enqueue_with_depth(pending1.clone(), 1);
+        queue.enqueue_with_depth(pending2.clone(), 1);
+        queue.dequeue();
+
+        queue.save(filepath).unwrap();
+        let mut queue = Queue::load(filepath).unwrap();
+        fs::remove_file(filepath).expect("Failed to delete temp file");
+
+        // Already crawled URLs are still deduplicated
+        let status = queue.enqueue(crawled);
+        let list = queue.into_vec();
+        assert_eq!(status, GoogolStatus::AlreadyIndexedUrl);
+        assert_eq!(list, vec![pending1.to_string(), pending2.to_string()]);
+
+        assert_eq!(queue.dequeue(), Some((pending1, 1)));
+        assert_eq!(queue.dequeue(), Some((pending2, 1)));
+        assert_eq!(queue.dequeue(), None);
+    }
+
+    #[test]
+    fn test_load_nonexistent_file() {
+        let queue = Queue::load("nonexistent_queue.json").unwrap();
+
+        assert!(queue.is_empty());
+        assert!(queue.seen.is_empty());
+    }
+
     #[test]
     fn test_dequeue() {
         let mut queue = Queue::default();
@@ -85,13 +767,13 @@ mod tests {
 
         // Dequeue should return url1 first
         let dequeued = queue.dequeue();
-        assert_eq!(dequeued, Some(url1));
+        assert_eq!(dequeued, Some((url1, 0)));
         // Now only url2 remains
         assert_eq!(queue.into_vec(), vec![url2.to_string()]);
 
         // Dequeue remaining URL
         let dequeued2 = queue.dequeue();
-        assert_eq!(dequeued2, Some(url2));
+        assert_eq!(dequeued2, Some((url2, 0)));
         // Queue should now be empty
         assert_eq!(queue.into_vec(), Vec::<String>::new());
 
@@ -99,6 +781,27 @@ mod tests {
         assert_eq!(queue.dequeue(), None);
     }
 
+    #[test]
+    fn test_dequeue_batch() {
+        let mut queue = Queue::default();
+        for i in 0..3 {
+            queue.enqueue_with_depth(Url::parse(&format!("https://a.com/{}", i)).unwrap(), i);
+        }
+
+        let batch = queue.dequeue_batch(2);
+        assert_eq!(
+            batch,
+            vec![
+                (Url::parse("https://a.com/0").unwrap(), 0),
+                (Url::parse("https://a.com/1").unwrap(), 1),
+            ]
+        );
+
+        // Only what is left, without waiting for more
+        assert_eq!(queue.dequeue_batch(2).len(), 1);
+        assert!(queue.dequeue_batch(2).is_empty());
+    }
+
     #[test]
     fn test_into_vec() {
         let mut queue = Queue::default();
@@ -113,6 +816,43 @@ mod tests {
         assert_eq!(vec_representation, vec![url1.to_string(), url2.to_string()]);
     }
 
+    #[test]
+    fn test_clear() {
+        let mut queue = Queue::default();
+        let url = Url::parse("https://test.com").unwrap();
+
+        queue.enqueue(url.clone());
+        queue.clear();
+
+        assert!(queue.is_empty());
+        assert!(queue.seen.is_empty());
+
+        // Cleared URLs can be enqueued again
+        assert_eq!(queue.enqueue(url), GoogolStatus::Success);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut queue = Queue::default();
+        let a = Url::parse("https://a.com").unwrap();
+        let b = Url::parse("https://b.com").unwrap();
+
+        queue.enqueue(a.clone());
+        queue.enqueue(b.clone());
+
+        // URLs are matched once normalized, as when enqueued
+        assert!(queue.remove(&Url::parse("https://a.com/#top").unwrap()));
+        assert_eq!(queue.into_vec(), vec![b.to_string()]);
+        assert!(!queue.seen.contains(&a));
+        assert!(queue.seen.contains(&b));
+
+        // Removing a URL that is not pending changes nothing
+        assert!(!queue.remove(&a));
+        queue.dequeue();
+        assert!(!queue.remove(&b));
+        assert!(queue.seen.contains(&b));
+    }
+
     #[test]
     fn test_clear_seen() {
         let mut queue = Queue::default();
@@ -132,7 +872,8 @@ mod tests {
         assert!(!queue.seen.is_empty());
 
         // Enqueue same URL again after clearing
-        let (status, list) = queue.enqueue(url.clone());
+        let status = queue.enqueue(url.clone());
+        let list = queue.into_vec();
         assert_eq!(status, GoogolStatus::AlreadyIndexedUrl);
         assert_eq!(list, vec![url.to_string()]);
 
@@ -142,9 +883,47 @@ mod tests {
         assert!(queue.seen.is_empty());
 
         // Enqueue same URL again after clearing should succeed
-        let (status, list) = queue.enqueue(url.clone());
+        let status = queue.enqueue(url.clone());
+        let list = queue.into_vec();
         dbg!(queue);
         assert_eq!(status, GoogolStatus::Success);
         assert_eq!(list, vec![url.to_string()]);
     }
+
+    #[test]
+    fn test_enqueue_recrawl() {
+        let mut queue = Queue::default();
+        let url = Url::parse("https://example.com").unwrap();
+        let hour_ago = Utc::now() - chrono::Duration::hours(1);
+
+        queue.enqueue(url.clone());
+        queue.dequeue();
+
+        // Seen URLs can be recrawled, but only once at a time
+        assert_eq!(
+            queue.enqueue_recrawl(url.clone(), hour_ago),
+            GoogolStatus::Success
+        );
+        assert_eq!(queue.dequeue(), Some((url.clone(), 0)));
+        assert_eq!(
+            queue.enqueue_recrawl(url.clone(), hour_ago),
+            GoogolStatus::AlreadyIndexedUrl
+        );
+
+        // Unless the pending recrawl is stale
+        assert_eq!(
+            queue.enqueue_recrawl(url.clone(), Utc::now() + chrono::Duration::hours(1)),
+            GoogolStatus::Success
+        );
+        assert_eq!(
+            queue.enqueue_recrawl(url.clone(), Utc::now() + chrono::Duration::hours(1)),
+            GoogolStatus::AlreadyIndexedUrl,
+            "Pending URLs are never enqueued twice"
+        );
+        queue.dequeue();
+
+        assert!(queue.finish_recrawl(&url));
+        assert!(!queue.finish_recrawl(&url));
+        assert_eq!(queue.enqueue_recrawl(url, hour_ago), GoogolStatus::Success);
+    }
 }
@@ -0,0 +1,129 @@
+//! Assignment of pages to barrels by host, using rendezvous hashing.
+//!
+//! Each barrel is given a pseudo-random weight for every host, and the host belongs to the
+//! barrel with the highest weight. Adding a barrel only moves the hosts it now wins, and
+//! removing one only moves the hosts it owned, so pages are reshuffled as little as possible.
+//!
+//! The hash is computed here rather than with the standard library's hasher, whose output may
+//! change between Rust releases, so every gateway build agrees on the owner of a host.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use googol::gateway::sharding::shard;
+//! use std::net::SocketAddr;
+//!
+//! let barrels: Vec<SocketAddr> = vec!["127.0.0.1:50052".parse().unwrap()];
+//!
+//! assert_eq!(shard("example.com", &barrels), Some(barrels[0]));
+//! assert_eq!(shard("example.com", &[]), None);
+//! ```
+
+use std::net::SocketAddr;
+use url::Url;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Hashes bytes with FNV-1a, followed by the SplitMix64 finalizer so that similar inputs,
+/// such as addresses differing only in the port, get unrelated weights.
+fn hash(bytes: &[u8]) -> u64 {
+    let hash = bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    });
+
+    let hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// Weight of `barrel` for `key`. The key is owned by the barrel with the highest weight.
+fn weight(key: &str, barrel: &SocketAddr) -> u64 {
+    hash(format!("{}\0{}", key, barrel).as_bytes())
+}
+
+/// Picks the barrel responsible for `key`, usually a host.
+///
+/// The choice only depends on the key and the set of barrels, not on their order.
+///
+/// # Arguments
+/// * `key` - The key to place.
+/// * `barrels` - Addresses of every barrel, online or not.
+///
+/// # Returns
+/// The address of the responsible barrel, or `None` if there are no barrels.
+pub fn shard(key: &str, barrels: &[SocketAddr]) -> Option<SocketAddr> {
+    barrels
+        .iter()
+        .max_by_key(|barrel| (weight(key, barrel), **barrel))
+        .copied()
+}
+
+/// Key a page is sharded by: its host, or the whole url if it has none.
+pub fn shard_key(url: &Url) -> &str {
+    url.host_str().unwrap_or(url.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn barrels(count: u16) -> Vec<SocketAddr> {
+        (0..count)
+            .map(|i| SocketAddr::from(([127, 0, 0, 1], 50052 + i)))
+            .collect()
+    }
+
+    fn hosts() -> impl Iterator<Item = String> {
+        (0..1000).map(|i| format!("host{}.com", i))
+    }
+
+    #[test]
+    fn test_shard_is_deterministic() {
+        let barrels = barrels(3);
+        let mut reversed = barrels.clone();
+        reversed.reverse();
+
+        for host in hosts() {
+            assert_eq!(shard(&host, &barrels), shard(&host, &reversed));
+        }
+    }
+
+    #[test]
+    fn test_shard_spreads_hosts() {
+        let barrels = barrels(4);
+
+        for barrel in &barrels {
+            let owned = hosts()
+                .filter(|host| shard(host, &barrels) == Some(*barrel))
+                .count();
+
+            assert!((150..350).contains(&owned), "{} owns {}", barrel, owned);
+        }
+    }
+
+    #[test]
+    fn test_shard_minimizes_reshuffling() {
+        let before = barrels(4);
+        let after = barrels(5);
+        let added = after[4];
+
+        for host in hosts() {
+            let owner = shard(&host, &after).unwrap();
+
+            // Hosts only move to the added barrel
+            if owner != added {
+                assert_eq!(shard(&host, &before), Some(owner), "{}", host);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shard_key() {
+        let url = Url::parse("https://example.com/page").unwrap();
+        assert_eq!(shard_key(&url), "example.com");
+
+        let url = Url::parse("data:text/plain,googol").unwrap();
+        assert_eq!(shard_key(&url), "data:text/plain,googol");
+    }
+}
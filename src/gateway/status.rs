@@ -1,4 +1,7 @@
-use std::collections::VecDeque;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
 
 use tokio::time::Instant;
 use url::Url;
@@ -18,25 +21,90 @@ impl Queue {
     }
 }
 
+/// Relative error of the estimated response time percentiles.
+const PERCENTILE_ACCURACY: f64 = 0.01;
+/// Response times below this many milliseconds are counted as this fast.
+const MIN_TRACKED_MILISECONDS: f64 = 0.001;
+
+/// Mean and percentiles of response times.
+///
+/// Percentiles are estimated from logarithmic buckets, each spanning values within
+/// `PERCENTILE_ACCURACY` of each other, so memory doesn't grow with the number of samples:
+/// about 1100 buckets cover response times from a microsecond up to an hour.
 #[derive(Debug, Default)]
 pub struct ResponseTime {
     pub miliseconds: f32,
     pub count: usize,
+    /// Number of samples in each bucket, by index. Bucket `i` holds the response times in
+    /// `(γ^(i - 1), γ^i]` milliseconds.
+    buckets: BTreeMap<i32, u64>,
+}
+
+/// Growth factor `γ` between the bounds of consecutive buckets.
+fn gamma() -> f64 {
+    (1. + PERCENTILE_ACCURACY) / (1. - PERCENTILE_ACCURACY)
 }
 
 impl ResponseTime {
     pub fn new_sample(&mut self, start_instant: Instant) {
-        let duration = start_instant.elapsed().as_secs_f32() * 1000.;
+        self.add_sample(start_instant.elapsed());
+    }
+
+    pub fn add_sample(&mut self, duration: Duration) {
+        let duration = duration.as_secs_f32() * 1000.;
         let count = self.count as f32;
 
         self.miliseconds = ((self.miliseconds * count) + duration) / (count + 1.);
         self.count += 1;
+
+        let bucket = (f64::from(duration).max(MIN_TRACKED_MILISECONDS).ln() / gamma().ln()).ceil();
+        *self.buckets.entry(bucket as i32).or_default() += 1;
     }
 
     pub fn update(&mut self, response_time: &ResponseTime) {
+        let count = self.count + response_time.count;
+        if count == 0 {
+            return;
+        }
+
         self.miliseconds = ((self.miliseconds * self.count as f32)
             + (response_time.miliseconds * response_time.count as f32))
-            / (self.count + response_time.count) as f32;
+            / count as f32;
+        self.count = count;
+
+        for (bucket, samples) in &response_time.buckets {
+            *self.buckets.entry(*bucket).or_default() += samples;
+        }
+    }
+
+    /// Estimates the response time, in milliseconds, that a `quantile` of the samples don't
+    /// exceed, e.g. `0.99` for the 99th percentile.
+    ///
+    /// # Returns
+    /// The estimate, within `PERCENTILE_ACCURACY` of the actual value, or `0.0` without samples.
+    pub fn percentile(&self, quantile: f64) -> f32 {
+        let total: u64 = self.buckets.values().sum();
+        if total == 0 {
+            return 0.;
+        }
+
+        // Nearest rank, so the slowest samples show up in high percentiles of few samples
+        let rank = ((quantile.clamp(0., 1.) * total as f64).ceil() as u64).max(1) - 1;
+
+        let mut seen = 0;
+        let bucket = self
+            .buckets
+            .iter()
+            .find(|(_, samples)| {
+                seen += *samples;
+                seen > rank
+            })
+            .map(|(bucket, _)| *bucket)
+            .unwrap_or_default();
+
+        // Center of the bucket, relative to its bounds
+        let gamma = gamma();
+        (2. * gamma.powi(bucket) / (gamma + 1.)) as f32
     }
 }
 
@@ -44,4 +112,80 @@ impl ResponseTime {
 pub struct GatewayStatus {
     pub top_searches: TopSearches,
     pub response_time: ResponseTime,
+    /// Number of urls the downloaders skipped instead of indexing.
+    pub skipped_urls: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        let error = (actual - expected).abs() / expected;
+        assert!(
+            error <= PERCENTILE_ACCURACY as f32 + 0.005,
+            "{} is not close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let mut response_time = ResponseTime::default();
+        assert_eq!(response_time.percentile(0.5), 0.);
+
+        // Uniform from 1 to 1000 ms, shuffled
+        for i in 0..1000 {
+            let miliseconds = (i * 7919) % 1000 + 1;
+            response_time.add_sample(Duration::from_millis(miliseconds));
+        }
+
+        assert_close(response_time.miliseconds, 500.5);
+        assert_close(response_time.percentile(0.5), 500.);
+        assert_close(response_time.percentile(0.95), 950.);
+        assert_close(response_time.percentile(0.99), 990.);
+        assert_close(response_time.percentile(1.), 1000.);
+        // Memory is bounded by the buckets, not the samples
+        assert!(response_time.buckets.len() < 400);
+    }
+
+    #[test]
+    fn test_percentiles_tail() {
+        let mut response_time = ResponseTime::default();
+
+        // The mean hides a slow 2% of responses
+        for i in 0..1000 {
+            let miliseconds = if i % 50 == 0 { 2000 } else { 10 };
+            response_time.add_sample(Duration::from_millis(miliseconds));
+        }
+
+        assert_close(response_time.miliseconds, 49.8);
+        assert_close(response_time.percentile(0.5), 10.);
+        assert_close(response_time.percentile(0.95), 10.);
+        assert_close(response_time.percentile(0.99), 2000.);
+    }
+
+    #[test]
+    fn test_update() {
+        let mut total = ResponseTime::default();
+        let sample = |miliseconds| {
+            let mut response_time = ResponseTime::default();
+            response_time.add_sample(Duration::from_millis(miliseconds));
+            response_time
+        };
+
+        total.update(&ResponseTime::default());
+        assert_eq!(total.count, 0);
+        assert_eq!(total.miliseconds, 0.);
+
+        for miliseconds in [10, 20, 30, 1000] {
+            total.update(&sample(miliseconds));
+        }
+
+        assert_eq!(total.count, 4);
+        assert_close(total.miliseconds, 265.);
+        assert_close(total.percentile(0.5), 20.);
+        assert_close(total.percentile(1.), 1000.);
+    }
 }
@@ -0,0 +1,215 @@
+//! Politeness limits on requests sent to the same host.
+//!
+//! Each host gets its own semaphore, so waiting on a saturated host never blocks requests to
+//! other hosts. Requests to the same host are also spaced by a minimum delay, or by the host's
+//! own `Crawl-delay` if it is longer.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use googol::host_limiter::HostLimiter;
+//! use std::time::Duration;
+//! use url::Url;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let limiter = HostLimiter::new(2, Duration::from_millis(100));
+//!
+//!     let url = Url::parse("https://example.com").unwrap();
+//!     let _permit = limiter.acquire(&url, None).await;
+//!     // Fetch the page while holding the permit
+//! }
+//! ```
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::{
+    sync::{Mutex, OwnedSemaphorePermit, Semaphore},
+    time::{Instant, sleep_until},
+};
+use url::Url;
+
+/// Limits of a single host.
+#[derive(Debug)]
+struct Host {
+    semaphore: Arc<Semaphore>,
+    /// When the last request to the host was allowed.
+    last_request: Mutex<Option<Instant>>,
+}
+
+/// Permission to send a request to a host, released when dropped.
+#[derive(Debug)]
+pub struct HostPermit {
+    _permit: OwnedSemaphorePermit,
+}
+
+/// Limits the number of concurrent requests per host, and the rate they are sent at.
+#[derive(Debug)]
+pub struct HostLimiter {
+    max_per_host: usize,
+    min_delay: Duration,
+    hosts: Mutex<HashMap<String, Arc<Host>>>,
+}
+
+impl HostLimiter {
+    /// Creates a new `HostLimiter`.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_per_host` - Maximum number of concurrent requests to the same host. At least 1.
+    /// * `min_delay` - Minimum time between two requests to the same host.
+    pub fn new(max_per_host: usize, min_delay: Duration) -> Self {
+        Self {
+            max_per_host: max_per_host.max(1),
+            min_delay,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the time to wait between two requests to a host asking for `crawl_delay`, which
+    /// only overrides the minimum delay if it is longer.
+    fn delay(&self, crawl_delay: Option<Duration>) -> Duration {
+        crawl_delay.map_or(self.min_delay, |crawl_delay| {
+            crawl_delay.max(self.min_delay)
+        })
+    }
+
+    /// Waits until a request may be sent to the URL's host.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL about to be requested.
+    /// * `crawl_delay` - The `Crawl-delay` of the host's `robots.txt`, if any.
+    ///
+    /// # Returns
+    ///
+    /// A `HostPermit` to hold until the request is done.
+    pub async fn acquire(&self, url: &Url, crawl_delay: Option<Duration>) -> HostPermit {
+        let host = {
+            let mut hosts = self.hosts.lock().await;
+
+            Arc::clone(
+                hosts
+                    .entry(url.host_str().unwrap_or_default().to_string())
+                    .or_insert_with(|| {
+                        Arc::new(Host {
+                            semaphore: Arc::new(Semaphore::new(self.max_per_host)),
+                            last_request: Mutex::new(None),
+                        })
+                    }),
+            )
+        };
+
+        let permit = Arc::clone(&host.semaphore)
+            .acquire_owned()
+            .await
+            .expect("Host semaphores are never closed");
+
+        // Requests to the same host wait for each other's delay
+        let mut last_request = host.last_request.lock().await;
+        if let Some(last_request) = *last_request {
+            sleep_until(last_request + self.delay(crawl_delay)).await;
+        }
+        *last_request = Some(Instant::now());
+
+        HostPermit { _permit: permit }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::timeout;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_max_per_host() {
+        let limiter = HostLimiter::new(1, Duration::ZERO);
+
+        let permit = limiter.acquire(&url("https://example.com/1"), None).await;
+
+        // The host is saturated
+        let blocked = timeout(
+            Duration::from_millis(100),
+            limiter.acquire(&url("https://example.com/2"), None),
+        )
+        .await;
+        assert!(blocked.is_err());
+
+        // Other hosts are not blocked
+        let other = timeout(
+            Duration::from_millis(100),
+            limiter.acquire(&url("https://other.com"), None),
+        )
+        .await;
+        assert!(other.is_ok());
+
+        drop(permit);
+
+        let unblocked = timeout(
+            Duration::from_millis(100),
+            limiter.acquire(&url("https://example.com/2"), None),
+        )
+        .await;
+        assert!(unblocked.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_min_delay() {
+        let delay = Duration::from_millis(200);
+        let limiter = HostLimiter::new(2, delay);
+
+        let start = Instant::now();
+        let _first = limiter.acquire(&url("https://example.com/1"), None).await;
+        let _other = limiter.acquire(&url("https://other.com"), None).await;
+        assert!(start.elapsed() < delay);
+
+        let _second = limiter.acquire(&url("https://example.com/2"), None).await;
+        assert!(start.elapsed() >= delay);
+    }
+
+    #[test]
+    fn test_delay() {
+        let limiter = HostLimiter::new(1, Duration::from_secs(1));
+
+        assert_eq!(limiter.delay(None), Duration::from_secs(1));
+        // A longer crawl delay overrides the minimum delay, a shorter one doesn't
+        assert_eq!(
+            limiter.delay(Some(Duration::from_secs(10))),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            limiter.delay(Some(Duration::from_millis(500))),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_crawl_delay_per_host() {
+        let crawl_delay = Some(Duration::from_secs(10));
+        let limiter = HostLimiter::new(2, Duration::ZERO);
+
+        let _slow = limiter
+            .acquire(&url("https://slow.com/1"), crawl_delay)
+            .await;
+
+        // Only the host asking for the delay waits
+        let blocked = timeout(
+            Duration::from_millis(100),
+            limiter.acquire(&url("https://slow.com/2"), crawl_delay),
+        )
+        .await;
+        assert!(blocked.is_err());
+
+        for path in ["/1", "/2"] {
+            let other = timeout(
+                Duration::from_millis(100),
+                limiter.acquire(&url(&format!("https://other.com{}", path)), None),
+            )
+            .await;
+            assert!(other.is_ok());
+        }
+    }
+}
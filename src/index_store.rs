@@ -20,20 +20,327 @@
 //!     "https://link1.com".parse().unwrap(),
 //!     "https://link2.com".parse().unwrap()
 //! ];
-//! index_store.store(&page1, &words1, &outlinks_for_page1);
+//! index_store.store(&page1, &[], &words1, &outlinks_for_page1);
 //! ```
 //!
-//! Supports loading existing index data from files.
-
-use crate::page::Page;
+//! Supports loading existing index data from files, either as JSON or as a compact binary
+//! encoding (see [`StorageFormat`]). Files ending in `.gz` are transparently gzip-compressed.
+//! For backups, the index can also be streamed page by page as JSON Lines (see
+//! [`IndexStore::export_jsonl`]).
+
+use crate::{page::Page, proto};
+use bincode::Options;
+use chrono::{DateTime, Utc};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
 use log::error;
+use rust_stemmers::{Algorithm, Stemmer};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{self, Read, Write};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufRead, Read, Write};
+use std::ops::Bound;
 use std::path::{Path, PathBuf};
 use url::Url;
 
+/// Maximum edit distance between a word and its correction, see [`IndexStore::did_you_mean`].
+pub const MAX_CORRECTION_DISTANCE: usize = 2;
+/// Maximum number of indexed words compared to a word being corrected, to bound the cost of a
+/// correction on large vocabularies.
+pub const MAX_CORRECTION_CANDIDATES: usize = 10_000;
+/// Largest index loaded by [`IndexStore::load`], in bytes, once decompressed.
+///
+/// Guards against corrupted or malicious files, e.g. gzip bombs or binary files whose length
+/// prefixes claim huge collections.
+pub const MAX_INDEX_BYTES: u64 = 8 * 1024 * 1024 * 1024;
+
+/// On-disk format of a saved `IndexStore`.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::index_store::StorageFormat;
+///
+/// assert_eq!(StorageFormat::from_path("index.json"), StorageFormat::Json);
+/// assert_eq!(StorageFormat::from_path("index.bin"), StorageFormat::Binary);
+/// assert_eq!(StorageFormat::from_path("index.bin.gz"), StorageFormat::Binary);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StorageFormat {
+    /// Human readable JSON.
+    #[default]
+    Json,
+    /// Compact binary encoding using `bincode`.
+    ///
+    /// Unlike JSON, where fields added since a file was saved take their default value, bincode
+    /// isn't self-describing: files start with [`BINARY_VERSION`], and only files of the current
+    /// version are loaded.
+    Binary,
+}
+
+/// Start of the files saved in the binary format, followed by their [`BINARY_VERSION`].
+const BINARY_MAGIC: &[u8; 8] = b"GOOGOLIX";
+
+/// Version of the layout of binary files, to increase whenever the serialized fields of
+/// `IndexStore` change.
+pub const BINARY_VERSION: u32 = 1;
+
+impl StorageFormat {
+    /// Selects the format from the file extension.
+    ///
+    /// Files ending in `.bin` use the binary format, anything else is treated as JSON. A trailing
+    /// `.gz` is ignored, as compression is independent of the format.
+    pub fn from_path<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let path = match is_gzipped(path) {
+            true => Path::new(path.file_stem().unwrap_or_default()),
+            false => path,
+        };
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("bin") => Self::Binary,
+            _ => Self::Json,
+        }
+    }
+
+    /// Returns the other format, used as a fallback when loading.
+    fn other(self) -> Self {
+        match self {
+            Self::Json => Self::Binary,
+            Self::Binary => Self::Json,
+        }
+    }
+
+    /// Serializes an `IndexStore` in this format.
+    fn serialize(self, index_store: &IndexStore) -> Result<Vec<u8>, io::Error> {
+        match self {
+            Self::Json => serde_json::to_vec(index_store)
+                .map_err(|e| io::Error::other(format!("Serialization error: {}", e))),
+            Self::Binary => {
+                let mut bytes = BINARY_MAGIC.to_vec();
+                bytes.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+
+                bincode::serialize_into(&mut bytes, index_store)
+                    .map_err(|e| io::Error::other(format!("Serialization error: {}", e)))?;
+
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Deserializes an `IndexStore` from bytes in this format.
+    fn deserialize(self, bytes: &[u8]) -> Result<IndexStore, io::Error> {
+        let mut index_store: IndexStore = match self {
+            Self::Json => serde_json::from_slice(bytes)
+                .map_err(|e| io::Error::other(format!("Deserialization error: {}", e)))?,
+            Self::Binary => bincode::options()
+                .with_fixint_encoding()
+                .allow_trailing_bytes()
+                .with_limit(MAX_INDEX_BYTES)
+                .deserialize(binary_payload(bytes)?)
+                .map_err(|e| io::Error::other(format!("Deserialization error: {}", e)))?,
+        };
+        index_store.rebuild_case_sensitive_words();
+        index_store.rebuild_words_by_length();
+
+        Ok(index_store)
+    }
+}
+
+/// Strips the header of a binary file, checking it was saved with the current
+/// [`BINARY_VERSION`].
+fn binary_payload(bytes: &[u8]) -> Result<&[u8], io::Error> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let Some(bytes) = bytes.strip_prefix(BINARY_MAGIC) else {
+        return Err(invalid(
+            "Binary index has no version header, it was saved by an older release".to_string(),
+        ));
+    };
+
+    let version = bytes
+        .first_chunk()
+        .map(|version| u32::from_le_bytes(*version))
+        .ok_or_else(|| invalid("Binary index is truncated".to_string()))?;
+
+    if version != BINARY_VERSION {
+        return Err(invalid(format!(
+            "Binary index version {} is not supported, expected {}",
+            version, BINARY_VERSION
+        )));
+    }
+
+    Ok(&bytes[size_of::<u32>()..])
+}
+
+/// Whether an index file is gzip-compressed, which is the case for files ending in `.gz`.
+fn is_gzipped(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+/// Compresses bytes with gzip.
+fn gzip(bytes: &[u8]) -> Result<Vec<u8>, io::Error> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+/// Decompresses gzip-compressed bytes, up to [`MAX_INDEX_BYTES`].
+fn gunzip(bytes: &[u8]) -> Result<Vec<u8>, io::Error> {
+    read_limited(GzDecoder::new(bytes), MAX_INDEX_BYTES)
+}
+
+/// Reads everything from `reader`, failing with `InvalidData` once more than `limit` bytes
+/// were read.
+fn read_limited(reader: impl Read, limit: u64) -> Result<Vec<u8>, io::Error> {
+    let mut bytes = Vec::new();
+    reader
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut bytes)?;
+
+    if bytes.len() as u64 > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Index is larger than {} bytes", limit),
+        ));
+    }
+
+    Ok(bytes)
+}
+
+/// Sorts and clones only the pages from `offset` to `offset + limit` in the order of
+/// `compare`, leaving the rest unsorted.
+fn select_pages<F>(mut pages: Vec<&Page>, offset: usize, limit: usize, compare: F) -> Vec<Page>
+where
+    F: Fn(&&Page, &&Page) -> Ordering,
+{
+    let end = offset.saturating_add(limit);
+
+    if end < pages.len() {
+        pages.select_nth_unstable_by(end, &compare);
+        pages.truncate(end);
+    }
+    pages.sort_unstable_by(&compare);
+
+    pages.into_iter().skip(offset).cloned().collect()
+}
+
+/// Strategy used to measure the relevance of search results.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Ranking {
+    /// Rank pages by their number of backlinks.
+    #[default]
+    Backlinks,
+    /// Rank pages by their PageRank (see [`IndexStore::compute_pagerank`]).
+    PageRank {
+        /// Probability of following an outlink.
+        damping: f64,
+        /// Number of power iterations.
+        iterations: usize,
+    },
+}
+
+/// The score added to a ranked page for each searched word in its title.
+///
+/// Compared bit by bit, so that [`IndexStore`] stays [`Eq`]: the boost is a setting, never the
+/// result of arithmetic, so a `NaN` is simply unequal to any other boost but itself.
+#[derive(Debug, Default, Clone, Copy)]
+struct TitleBoost(f64);
+
+impl PartialEq for TitleBoost {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for TitleBoost {}
+
+/// A page along with its relevance score, ordered from the least to the most relevant.
+///
+/// Pages of equal score are ordered by URL, the smallest being the most relevant, as in
+/// [`IndexStore::rank_with`].
+#[derive(Clone)]
+struct Scored {
+    page: Page,
+    score: f64,
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.page.url.cmp(&self.page.url))
+    }
+}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Scored {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scored {}
+
+/// Summary statistics of an `IndexStore`.
+///
+/// Obtained with [`IndexStore::stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IndexStats {
+    /// Number of indexed pages.
+    pub indexed_pages: usize,
+    /// Number of distinct indexed words.
+    pub words: usize,
+    /// Total number of outlinks across all pages.
+    pub outlinks: usize,
+    /// Total number of backlinks across all pages.
+    pub backlinks: usize,
+    /// Size of the serialized index when it was last saved or loaded, in bytes.
+    pub size_bytes: usize,
+}
+
+impl From<IndexStats> for proto::IndexStats {
+    /// Converts `IndexStats` into its protocol buffer representation.
+    fn from(val: IndexStats) -> Self {
+        proto::IndexStats {
+            indexed_pages: val.indexed_pages as u64,
+            words: val.words as u64,
+            outlinks: val.outlinks as u64,
+            backlinks: val.backlinks as u64,
+            size_bytes: val.size_bytes as u64,
+        }
+    }
+}
+
+/// A page and everything indexed about it, as a line of [`IndexStore::export_jsonl`].
+#[derive(Debug, Serialize, Deserialize)]
+struct PageRecord {
+    page: Page,
+    /// Indexed words of the page, normalized.
+    words: HashSet<String>,
+    /// Positions of each indexed word within the page.
+    #[serde(default)]
+    positions: HashMap<String, Vec<usize>>,
+    /// Indexed words of the page's title.
+    #[serde(default)]
+    title_words: HashSet<String>,
+    /// Words of the page as written, if indexed case-sensitively.
+    #[serde(default)]
+    case_sensitive_words: HashSet<String>,
+    #[serde(default)]
+    outlinks: HashSet<Url>,
+    #[serde(default)]
+    backlinks: HashSet<Url>,
+}
+
 /// An index storage structure for managing web pages, their links, and search indices.
 ///
 /// The `IndexStore` maintains collections of pages, their associated URLs, inverted indices for search,
@@ -70,7 +377,7 @@ use url::Url;
 ///     .unwrap();
 /// let words = ["example", "page"];
 /// let outlinks = ["https://linked.com".parse().unwrap()];
-/// store.store(&page, &words, &outlinks);
+/// store.store(&page, &[], &words, &outlinks);
 /// ```
 ///
 /// Saving the index to disk:
@@ -95,8 +402,24 @@ pub struct IndexStore {
 
     /// Forward index: word (lowercase) to set of URLs containing the word.
     index: HashMap<String, HashSet<Url>>,
+    /// Words of the forward index by length in characters, sorted, rebuilt when loaded.
+    #[serde(skip)]
+    words_by_length: BTreeMap<usize, BTreeSet<String>>,
     /// Inverse index: URL to set of words associated with the page.
     invert_index: HashMap<Url, HashSet<String>>,
+    /// Positional index: URL to the positions of each word within the page.
+    #[serde(default)]
+    positions: HashMap<Url, HashMap<String, Vec<usize>>>,
+    /// Forward index of words as written, filled only if [`IndexStore::with_case_sensitive`]
+    /// is enabled.
+    #[serde(default)]
+    case_sensitive_index: HashMap<String, HashSet<Url>>,
+    /// URL to the words of the page in the case-sensitive index, rebuilt when loaded.
+    #[serde(skip)]
+    case_sensitive_words: HashMap<Url, HashSet<String>>,
+    /// Title index: URL to the words of the page's title, which are also in the forward index.
+    #[serde(default)]
+    titles: HashMap<Url, HashSet<String>>,
 
     /// Map from URL to set of URLs linking **to** the page (backlinks).
     backlinks: HashMap<Url, HashSet<Url>>,
@@ -109,6 +432,21 @@ pub struct IndexStore {
     /// Size of the serialized index in bytes.
     #[serde(skip)]
     size_bytes: usize,
+    /// Words (lowercase) never indexed by [`IndexStore::store`].
+    #[serde(skip)]
+    stop_words: HashSet<String>,
+    /// Whether words are reduced to their stem, both when indexed and when searched.
+    #[serde(skip)]
+    stemming: bool,
+    /// Whether stored words are also kept as written, for case-sensitive searches.
+    #[serde(skip)]
+    case_sensitive: bool,
+    /// Score added to a ranked page for each searched word in its title.
+    #[serde(skip)]
+    title_boost: TitleBoost,
+    /// Whether ranked search results with the same content are collapsed into one.
+    #[serde(skip)]
+    collapse_duplicates: bool,
 }
 
 impl IndexStore {
@@ -131,13 +469,217 @@ impl IndexStore {
         }
     }
 
+    /// Sets the words that are never indexed, whichever way pages are stored.
+    ///
+    /// Stop words are matched case-insensitively. Already indexed words are kept. Searches
+    /// leave stop words out too, so that they still match pages with the other words.
+    ///
+    /// # Arguments
+    ///
+    /// * `stop_words` - The words to leave out of the index.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::{index_store::IndexStore, page::PageBuilder};
+    ///
+    /// let mut store = IndexStore::default().with_stop_words(["the"].map(String::from).into());
+    ///
+    /// let page = PageBuilder::default()
+    ///     .url("https://example.com".parse().unwrap())
+    ///     .build()
+    ///     .unwrap();
+    /// store.store(&page, &[], &["The", "crab"], &[]);
+    ///
+    /// assert!(store.search(&["the"]).is_empty());
+    /// assert_eq!(store.search(&["crab"]).len(), 1);
+    /// assert_eq!(store.search(&["the", "crab"]).len(), 1);
+    /// ```
+    pub fn with_stop_words(mut self, stop_words: HashSet<String>) -> Self {
+        self.stop_words = stop_words
+            .into_iter()
+            .map(|word| word.to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Enables reducing words to their English stem, so that e.g. "running" matches "run".
+    ///
+    /// Stemming applies both to stored words and to searched ones. It is off by default, and
+    /// should stay the same for the whole life of an index, as words stored with the other
+    /// setting won't be found.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::{index_store::IndexStore, page::PageBuilder};
+    ///
+    /// let mut store = IndexStore::default().with_stemming(true);
+    ///
+    /// let page = PageBuilder::default()
+    ///     .url("https://example.com".parse().unwrap())
+    ///     .build()
+    ///     .unwrap();
+    /// store.store(&page, &[], &["run"], &[]);
+    ///
+    /// assert_eq!(store.search(&["running"]).len(), 1);
+    /// ```
+    pub fn with_stemming(mut self, stemming: bool) -> Self {
+        self.stemming = stemming;
+        self
+    }
+
+    /// Enables a second forward index of words as written, used by
+    /// [`IndexStore::search_case_sensitive`] and [`IndexStore::search_any_case_sensitive`].
+    ///
+    /// Words are kept both lowercased and as written, so the forward index takes roughly
+    /// twice the memory and disk space. Words in the case-sensitive index are never stemmed.
+    /// Only pages stored while it is enabled can be found case-sensitively.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::{index_store::IndexStore, page::PageBuilder};
+    ///
+    /// let mut store = IndexStore::default().with_case_sensitive(true);
+    ///
+    /// let page = PageBuilder::default()
+    ///     .url("https://example.com".parse().unwrap())
+    ///     .build()
+    ///     .unwrap();
+    /// store.store(&page, &[], &["Rust"], &[]);
+    ///
+    /// assert_eq!(store.search(&["rust"]).len(), 1);
+    /// assert!(store.search_case_sensitive(&["rust"]).is_empty());
+    /// assert_eq!(store.search_case_sensitive(&["Rust"]).len(), 1);
+    /// ```
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Sets the score added to a page ranked for a search, such as by
+    /// [`IndexStore::search_by_relevance`], for each searched word in its title. `0.0`, the
+    /// default, ignores titles.
+    ///
+    /// The boost adds to the score of the ranking, so its scale depends on it: ranking by
+    /// backlinks, a boost of `10.0` makes a title match worth as much as 10 backlinks.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::{index_store::IndexStore, page::PageBuilder};
+    ///
+    /// let mut store = IndexStore::default().with_title_boost(10.0);
+    ///
+    /// let page = PageBuilder::default()
+    ///     .url("https://example.com".parse().unwrap())
+    ///     .build()
+    ///     .unwrap();
+    /// store.store(&page, &["Rust"], &["language"], &[]);
+    ///
+    /// assert_eq!(store.search_by_relevance(&["rust"], 10)[0].rank, Some(10.0));
+    /// assert_eq!(store.search_by_relevance(&["language"], 10)[0].rank, Some(0.0));
+    /// ```
+    pub fn with_title_boost(mut self, title_boost: f64) -> Self {
+        self.title_boost = TitleBoost(title_boost);
+        self
+    }
+
+    /// Keeps only the most relevant of the pages with the same content hash (see
+    /// [`crate::page::content_hash`]) when ranking search results, such as by
+    /// [`IndexStore::search_by_relevance`]. Pages without a content hash are never collapsed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::{index_store::IndexStore, page::PageBuilder};
+    ///
+    /// let mut store = IndexStore::default().with_collapse_duplicates(true);
+    ///
+    /// for url in ["https://example.com", "https://mirror.example.com"] {
+    ///     let page = PageBuilder::default()
+    ///         .url(url.parse().unwrap())
+    ///         .content_hash("same")
+    ///         .build()
+    ///         .unwrap();
+    ///     store.store(&page, &[], &["rust"], &[]);
+    /// }
+    ///
+    /// assert_eq!(store.search(&["rust"]).len(), 2);
+    /// assert_eq!(store.search_by_relevance(&["rust"], 10).len(), 1);
+    /// ```
+    pub fn with_collapse_duplicates(mut self, collapse_duplicates: bool) -> Self {
+        self.collapse_duplicates = collapse_duplicates;
+        self
+    }
+
+    /// Lowercases a word and, if stemming is enabled, reduces it to its stem. Applied to
+    /// every stored and searched word, so both end up as the same term.
+    fn normalize(&self, word: &str) -> String {
+        let word = word.to_lowercase();
+
+        if self.stemming {
+            Stemmer::create(Algorithm::English).stem(&word).into_owned()
+        } else {
+            word
+        }
+    }
+
+    /// Fills the words of each page in the case-sensitive index, which aren't serialized.
+    fn rebuild_case_sensitive_words(&mut self) {
+        self.case_sensitive_words.clear();
+
+        for (word, urls) in &self.case_sensitive_index {
+            for url in urls {
+                self.case_sensitive_words
+                    .entry(url.clone())
+                    .or_default()
+                    .insert(word.clone());
+            }
+        }
+    }
+
+    /// Fills the words of the forward index by length, which aren't serialized.
+    fn rebuild_words_by_length(&mut self) {
+        self.words_by_length.clear();
+
+        for word in self.index.keys() {
+            self.words_by_length
+                .entry(word.chars().count())
+                .or_default()
+                .insert(word.clone());
+        }
+    }
+
+    /// Adds the page at `url` to the pages of `word` in the forward index.
+    fn index_word(&mut self, word: &str, url: &Url) {
+        match self.index.get_mut(word) {
+            Some(urls) => {
+                urls.insert(url.clone());
+            }
+            None => {
+                self.index
+                    .insert(word.to_string(), HashSet::from([url.clone()]));
+                self.words_by_length
+                    .entry(word.chars().count())
+                    .or_default()
+                    .insert(word.to_string());
+            }
+        }
+    }
+
     /// Loads an `IndexStore` from disk at the given path.
     ///
     /// If the file does not exist or cannot be read, it initializes a new `IndexStore`.
     ///
+    /// The format is picked with [`StorageFormat::from_path`]. If the file cannot be decoded in
+    /// that format, the other one is tried before giving up. Files ending in `.gz` are
+    /// decompressed first.
+    ///
     /// # Arguments
     ///
-    /// * `filepath` - Path to the JSON file containing serialized `IndexStore`.
+    /// * `filepath` - Path to the file containing serialized `IndexStore`.
     ///
     /// # Errors
     ///
@@ -147,15 +689,18 @@ impl IndexStore {
         P: AsRef<Path>,
     {
         match File::open(&filepath) {
-            Ok(mut file) => {
-                let mut json_str = String::new();
+            Ok(file) => {
+                let mut bytes = read_limited(file, MAX_INDEX_BYTES)?;
+                let size = bytes.len();
 
-                let size = file.read_to_string(&mut json_str)?;
-                let mut index_store: IndexStore = serde_json::from_str(&json_str).map_err(|e| {
-                    use io::{Error, ErrorKind};
+                if is_gzipped(filepath.as_ref()) {
+                    bytes = gunzip(&bytes)?;
+                }
 
-                    Error::new(ErrorKind::Other, format!("Deserialization error: {}", e))
-                })?;
+                let format = StorageFormat::from_path(&filepath);
+                let mut index_store = format
+                    .deserialize(&bytes)
+                    .or_else(|e| format.other().deserialize(&bytes).map_err(|_| e))?;
 
                 index_store.filepath = filepath.as_ref().to_path_buf();
                 index_store.size_bytes = size;
@@ -184,39 +729,102 @@ impl IndexStore {
     where
         S: AsRef<str>,
     {
-        if words.is_empty() {
+        self.search_in(&self.index, &self.query_terms(words))
+    }
+
+    /// Normalized terms of a search, leaving out the stop words.
+    fn query_terms<S>(&self, words: &[S]) -> Vec<String>
+    where
+        S: AsRef<str>,
+    {
+        words
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|word| !self.is_stop_word(word))
+            .map(|word| self.normalize(word))
+            .collect()
+    }
+
+    /// Terms of a case-sensitive search, as written, leaving out the stop words.
+    fn case_sensitive_terms<'a, S>(&self, words: &'a [S]) -> Vec<&'a str>
+    where
+        S: AsRef<str>,
+    {
+        words
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|word| !self.is_stop_word(word))
+            .collect()
+    }
+
+    /// Whether a word is left out of the index, see [`IndexStore::with_stop_words`].
+    fn is_stop_word(&self, word: &str) -> bool {
+        self.stop_words.contains(&word.to_lowercase())
+    }
+
+    /// Searches for pages containing all the specified words, exactly as written.
+    ///
+    /// Falls back to the case-insensitive [`IndexStore::search`] if the case-sensitive index
+    /// is not enabled with [`IndexStore::with_case_sensitive`].
+    ///
+    /// # Arguments
+    ///
+    /// * `words` - A slice of words to search for.
+    ///
+    /// # Returns
+    ///
+    /// A set of `Page` instances matching all words. Empty if no matches or input is empty.
+    pub fn search_case_sensitive<S>(&self, words: &[S]) -> HashSet<Page>
+    where
+        S: AsRef<str>,
+    {
+        if !self.case_sensitive {
+            return self.search(words);
+        }
+
+        self.search_in(
+            &self.case_sensitive_index,
+            &self.case_sensitive_terms(words),
+        )
+    }
+
+    /// Pages of `index` containing all the `terms`.
+    fn search_in<S>(&self, index: &HashMap<String, HashSet<Url>>, terms: &[S]) -> HashSet<Page>
+    where
+        S: AsRef<str>,
+    {
+        if terms.is_empty() {
             return HashSet::new();
         }
 
-        // Collect URL sets for each word (case-insensitive)
-        let sets_of_urls: Vec<&HashSet<Url>> = words
+        // Collect URL sets for each term
+        let mut sets_of_urls: Vec<&HashSet<Url>> = terms
             .iter()
-            .map(|w| w.as_ref().to_lowercase())
-            .filter_map(|word| self.index.get(&word))
+            .filter_map(|term| index.get(term.as_ref()))
             .collect();
 
-        // If any word isn't found, no pages contain all words
-        if sets_of_urls.len() < words.len() {
+        // If any term isn't found, no pages contain all terms
+        if sets_of_urls.len() < terms.len() {
             return HashSet::new();
         }
 
-        // Intersect all URL sets to find common pages
-        let intersection_urls = sets_of_urls
-            .iter()
-            .skip(1)
-            .fold(sets_of_urls[0].clone(), |acc, set| &acc & set);
+        // Only the urls of the smallest set can be in all of them, so the others are just
+        // looked up instead of being copied
+        sets_of_urls.sort_by_key(|urls| urls.len());
+        let (smallest, others) = sets_of_urls.split_first().expect("Terms are not empty");
 
         // Convert URLs to Pages
-        intersection_urls
+        smallest
             .iter()
+            .filter(|url| others.iter().all(|urls| urls.contains(*url)))
             .filter_map(|url| self.url2pages.get(url))
             .cloned()
             .collect()
     }
 
-    /// Searches for pages matching all words and sorts them by their backlink count (descending).
+    /// Searches for pages containing at least one of the specified words.
     ///
-    /// The most backlinks (popularity) pages appear first.
+    /// The search is case-insensitive. Words that are not indexed are ignored.
     ///
     /// # Arguments
     ///
@@ -224,89 +832,977 @@ impl IndexStore {
     ///
     /// # Returns
     ///
-    /// A vector of `Page` sorted by relevance (backlink count).
-    pub fn search_by_relevance<S>(&self, words: &[S]) -> Vec<Page>
+    /// A set of `Page` instances matching any word. Empty if no matches or input is empty.
+    pub fn search_any<S>(&self, words: &[S]) -> HashSet<Page>
     where
         S: AsRef<str>,
     {
-        let pages = self.search(words);
-
-        let mut pages_with_backlinks: Vec<(Page, usize)> = pages
-            .into_iter()
-            .map(|page| {
-                let backlink_count = self.backlinks.get(&page.url).map_or(0, |s| s.len());
-                (page, backlink_count)
-            })
-            .collect();
-
-        // Sort descending by backlink count
-        pages_with_backlinks.sort_by(|(_, a_size), (_, b_size)| b_size.cmp(a_size));
-
-        pages_with_backlinks
-            .into_iter()
-            .map(|(page, _)| page)
-            .collect()
+        self.search_any_in(&self.index, &self.query_terms(words))
     }
 
-    /// Stores a page and its associated data into the index.
+    /// Searches for pages containing at least one of the specified words, exactly as written.
     ///
-    /// Updates the inverted index, backlink relationships, and outlinks.
+    /// Falls back to the case-insensitive [`IndexStore::search_any`] if the case-sensitive
+    /// index is not enabled with [`IndexStore::with_case_sensitive`].
     ///
     /// # Arguments
     ///
-    /// * `page` - The `Page` to store.
-    /// * `words` - Words associated with the page.
-    /// * `outlinks` - Outgoing links from the page.
-    pub fn store<S>(&mut self, page: &Page, words: &[S], outlinks: &[Url])
+    /// * `words` - A slice of words to search for.
+    ///
+    /// # Returns
+    ///
+    /// A set of `Page` instances matching any word. Empty if no matches or input is empty.
+    pub fn search_any_case_sensitive<S>(&self, words: &[S]) -> HashSet<Page>
     where
         S: AsRef<str>,
     {
-        self.indexed_pages.insert(page.clone());
-        self.url2pages.insert(page.url.clone(), page.clone());
-
-        for word in words.iter().map(|word| word.as_ref().to_lowercase()) {
-            self.index
-                .entry(word.clone())
-                .or_default()
-                .insert(page.url.clone());
-
-            self.invert_index
-                .entry(page.url.clone())
-                .or_default()
-                .insert(word.clone());
+        if !self.case_sensitive {
+            return self.search_any(words);
         }
 
-        self.outlinks
-            .entry(page.url.clone())
-            .or_default()
-            .extend(outlinks.iter().cloned());
+        self.search_any_in(
+            &self.case_sensitive_index,
+            &self.case_sensitive_terms(words),
+        )
+    }
 
-        for outlink in outlinks {
-            self.backlinks
-                .entry(outlink.clone())
-                .or_default()
-                .insert(page.url.clone());
-        }
+    /// Pages of `index` containing at least one of the `terms`.
+    fn search_any_in<S>(&self, index: &HashMap<String, HashSet<Url>>, terms: &[S]) -> HashSet<Page>
+    where
+        S: AsRef<str>,
+    {
+        // Union of the URL sets of every term
+        let union_urls: HashSet<&Url> = terms
+            .iter()
+            .filter_map(|term| index.get(term.as_ref()))
+            .flatten()
+            .collect();
+
+        // Convert URLs to Pages
+        union_urls
+            .into_iter()
+            .filter_map(|url| self.url2pages.get(url))
+            .cloned()
+            .collect()
     }
 
-    /// Retrieves all backlinks (pages linking to the given URL).
+    /// Searches for pages matching all words and returns the `limit` ones with the most
+    /// backlinks (popularity), most relevant first. Pages with the words in their title are
+    /// boosted, see [`IndexStore::with_title_boost`].
     ///
     /// # Arguments
     ///
-    /// * `url` - The URL for which to retrieve backlinks.
+    /// * `words` - A slice of words to search for.
+    /// * `limit` - Maximum number of pages to return.
     ///
     /// # Returns
     ///
-    /// A set of URLs linking to the given URL. Empty if none.
-    pub fn consult_backlinks(&self, url: &Url) -> HashSet<Url> {
-        self.backlinks.get(url).cloned().unwrap_or_default()
+    /// A vector of `Page` sorted by relevance (backlink count).
+    pub fn search_by_relevance<S>(&self, words: &[S], limit: usize) -> Vec<Page>
+    where
+        S: AsRef<str>,
+    {
+        self.search_by_relevance_with(words, Ranking::Backlinks, limit)
     }
 
-    /// Retrieves all outlinks (pages linked from the given URL).
+    /// Searches for pages matching all words and returns the `limit` most relevant ones using
+    /// the given `Ranking`, boosting those with the words in their title.
     ///
     /// # Arguments
     ///
-    /// * `url` - The URL for which to retrieve outlinks.
+    /// * `words` - A slice of words to search for.
+    /// * `ranking` - How to measure the relevance of each page.
+    /// * `limit` - Maximum number of pages to return.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `Page` sorted by relevance (descending).
+    pub fn search_by_relevance_with<S>(
+        &self,
+        words: &[S],
+        ranking: Ranking,
+        limit: usize,
+    ) -> Vec<Page>
+    where
+        S: AsRef<str>,
+    {
+        self.rank_top_for(self.search(words), words, ranking, limit)
+    }
+
+    /// Sorts pages by their backlink count (descending).
+    ///
+    /// Ties are broken by URL, so the same set of pages always yields the same ordering.
+    ///
+    /// # Arguments
+    ///
+    /// * `pages` - The pages to sort, usually the result of a search.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `Page` sorted by relevance (backlink count).
+    pub fn rank_by_relevance(&self, pages: HashSet<Page>) -> Vec<Page> {
+        self.rank_with(pages, Ranking::Backlinks)
+    }
+
+    /// Sorts pages by relevance (descending) using the given `Ranking`.
+    ///
+    /// Ties are broken by URL, so the same set of pages always yields the same ordering.
+    ///
+    /// # Arguments
+    ///
+    /// * `pages` - The pages to sort, usually the result of a search.
+    /// * `ranking` - How to measure the relevance of each page.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `Page` sorted by relevance.
+    pub fn rank_with(&self, pages: HashSet<Page>, ranking: Ranking) -> Vec<Page> {
+        self.rank_for::<&str>(pages, &[], ranking)
+    }
+
+    /// Like [`IndexStore::rank_with`], boosting the pages with the searched words in their
+    /// title (see [`IndexStore::with_title_boost`]), and collapsing pages with the same content
+    /// if enabled (see [`IndexStore::with_collapse_duplicates`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `pages` - The pages to sort, usually the result of a search.
+    /// * `words` - The searched words.
+    /// * `ranking` - How to measure the relevance of each page.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `Page` sorted by relevance, ordered as by [`IndexStore::rank_top_for`].
+    pub fn rank_for<S>(&self, pages: HashSet<Page>, words: &[S], ranking: Ranking) -> Vec<Page>
+    where
+        S: AsRef<str>,
+    {
+        let words = words.iter().map(|word| self.normalize(word.as_ref()));
+        let scores = self.scores(ranking, words.collect());
+
+        let mut pages_with_scores: Vec<(Page, f64)> = pages
+            .into_iter()
+            .map(|page| {
+                let score = scores(&page.url);
+                (page, score)
+            })
+            .collect();
+
+        // Sort descending by score, breaking ties by URL for a stable ordering
+        pages_with_scores.sort_by(|(a_page, a_score), (b_page, b_score)| {
+            b_score
+                .total_cmp(a_score)
+                .then_with(|| a_page.url.cmp(&b_page.url))
+        });
+
+        // Only the most relevant page with each content is kept, which comes first
+        let mut seen = HashSet::new();
+        pages_with_scores.retain(|(page, _)| match &page.content_hash {
+            Some(hash) if self.collapse_duplicates => seen.insert(hash.clone()),
+            _ => true,
+        });
+
+        pages_with_scores
+            .into_iter()
+            .map(|(mut page, score)| {
+                page.rank = Some(score);
+                page
+            })
+            .collect()
+    }
+
+    /// Keeps the `limit` pages with the most backlinks, sorted by relevance (descending).
+    ///
+    /// # Arguments
+    ///
+    /// * `pages` - The pages to rank, usually the result of a search.
+    /// * `limit` - Maximum number of pages to keep.
+    ///
+    /// # Returns
+    ///
+    /// A vector of at most `limit` pages, ordered as by [`IndexStore::rank_by_relevance`].
+    pub fn rank_top_by_relevance(&self, pages: HashSet<Page>, limit: usize) -> Vec<Page> {
+        self.rank_top_with(pages, Ranking::Backlinks, limit)
+    }
+
+    /// Keeps the `limit` most relevant pages using the given `Ranking`, sorted by relevance
+    /// (descending).
+    ///
+    /// Unlike [`IndexStore::rank_with`], the pages are never all sorted: only the best ones seen
+    /// so far are kept in an ordered set of `limit` pages, which is much cheaper when many pages
+    /// match.
+    ///
+    /// # Arguments
+    ///
+    /// * `pages` - The pages to rank, usually the result of a search.
+    /// * `ranking` - How to measure the relevance of each page.
+    /// * `limit` - Maximum number of pages to keep.
+    ///
+    /// # Returns
+    ///
+    /// A vector of at most `limit` pages, ordered as by [`IndexStore::rank_with`].
+    pub fn rank_top_with(&self, pages: HashSet<Page>, ranking: Ranking, limit: usize) -> Vec<Page> {
+        self.rank_top_for::<&str>(pages, &[], ranking, limit)
+    }
+
+    /// Like [`IndexStore::rank_top_with`], boosting the pages with the searched words in their
+    /// title (see [`IndexStore::with_title_boost`]), and collapsing pages with the same content
+    /// if enabled (see [`IndexStore::with_collapse_duplicates`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `pages` - The pages to rank, usually the result of a search.
+    /// * `words` - The searched words.
+    /// * `ranking` - How to measure the relevance of each page.
+    /// * `limit` - Maximum number of pages to keep.
+    ///
+    /// # Returns
+    ///
+    /// A vector of at most `limit` pages, sorted by relevance (descending).
+    pub fn rank_top_for<S>(
+        &self,
+        pages: HashSet<Page>,
+        words: &[S],
+        ranking: Ranking,
+        limit: usize,
+    ) -> Vec<Page>
+    where
+        S: AsRef<str>,
+    {
+        let words = words.iter().map(|word| self.normalize(word.as_ref()));
+        let scores = self.scores(ranking, words.collect());
+
+        // The least relevant page kept comes first, to be evicted by a better one
+        let mut top: BTreeSet<Scored> = BTreeSet::new();
+        // The page kept for each content hash, if collapsing duplicates
+        let mut kept: HashMap<String, Scored> = HashMap::new();
+
+        for page in pages {
+            let scored = Scored {
+                score: scores(&page.url),
+                page,
+            };
+
+            let hash = match self.collapse_duplicates {
+                true => scored.page.content_hash.clone(),
+                false => None,
+            };
+
+            // A duplicate only replaces the page kept for its content if more relevant
+            if let Some(duplicate) = hash.as_ref().and_then(|hash| kept.get(hash)) {
+                if *duplicate >= scored {
+                    continue;
+                }
+                top.remove(duplicate);
+            }
+
+            if top.len() >= limit && top.first().is_none_or(|least| *least >= scored) {
+                continue;
+            }
+
+            if let Some(hash) = hash {
+                kept.insert(hash, scored.clone());
+            }
+            top.insert(scored);
+
+            if top.len() > limit
+                && let Some(Scored { page, .. }) = top.pop_first()
+                && let Some(hash) = page.content_hash
+            {
+                kept.remove(&hash);
+            }
+        }
+
+        top.into_iter()
+            .rev()
+            .map(|Scored { mut page, score }| {
+                page.rank = Some(score);
+                page
+            })
+            .collect()
+    }
+
+    /// Returns the function scoring the relevance of a URL with the given `Ranking`, boosted
+    /// for each of the (normalized) `words` in the page's title.
+    fn scores(&self, ranking: Ranking, words: HashSet<String>) -> Box<dyn Fn(&Url) -> f64 + '_> {
+        let scores = self.ranking_scores(ranking);
+
+        let TitleBoost(title_boost) = self.title_boost;
+        if title_boost == 0.0 || words.is_empty() {
+            return scores;
+        }
+
+        Box::new(move |url| {
+            let title_matches = self
+                .titles
+                .get(url)
+                .map_or(0, |title| words.intersection(title).count());
+
+            scores(url) + title_boost * title_matches as f64
+        })
+    }
+
+    /// Returns the function scoring the relevance of a URL with the given `Ranking` alone.
+    fn ranking_scores(&self, ranking: Ranking) -> Box<dyn Fn(&Url) -> f64 + '_> {
+        match ranking {
+            Ranking::Backlinks => {
+                Box::new(|url| self.backlinks.get(url).map_or(0, |s| s.len()) as f64)
+            }
+            Ranking::PageRank {
+                damping,
+                iterations,
+            } => {
+                let pagerank = self.compute_pagerank(damping, iterations);
+                Box::new(move |url| pagerank.get(url).copied().unwrap_or_default())
+            }
+        }
+    }
+
+    /// Computes the PageRank of every page in the link graph.
+    ///
+    /// Uses the power-iteration method over the outlink graph. The rank of dangling pages
+    /// (pages without outlinks, including linked pages that were never indexed) is
+    /// redistributed uniformly across all pages. Outlinks are stored as sets, so a page linking
+    /// to the same URL several times only counts once.
+    ///
+    /// # Arguments
+    ///
+    /// * `damping` - Probability of following an outlink, usually `0.85`.
+    /// * `iterations` - Number of power iterations to run.
+    ///
+    /// # Returns
+    ///
+    /// A map from URL to its PageRank. Ranks sum to 1. Empty if the graph is empty.
+    pub fn compute_pagerank(&self, damping: f64, iterations: usize) -> HashMap<Url, f64> {
+        // Every URL that is indexed, links somewhere or is linked to
+        let nodes: HashSet<&Url> = self
+            .url2pages
+            .keys()
+            .chain(self.outlinks.keys())
+            .chain(self.outlinks.values().flatten())
+            .collect();
+
+        if nodes.is_empty() {
+            return HashMap::new();
+        }
+
+        let total_nodes = nodes.len() as f64;
+
+        let mut ranks: HashMap<&Url, f64> =
+            nodes.iter().map(|url| (*url, 1.0 / total_nodes)).collect();
+
+        for _ in 0..iterations {
+            let dangling_rank: f64 = ranks
+                .iter()
+                .filter(|(url, _)| self.outlinks.get(*url).is_none_or(|o| o.is_empty()))
+                .map(|(_, rank)| rank)
+                .sum();
+
+            let base = (1.0 - damping) / total_nodes + damping * dangling_rank / total_nodes;
+
+            let mut next_ranks: HashMap<&Url, f64> = nodes.iter().map(|url| (*url, base)).collect();
+
+            for (url, outlinks) in self.outlinks.iter().filter(|(_, o)| !o.is_empty()) {
+                let share = damping * ranks[url] / outlinks.len() as f64;
+
+                for outlink in outlinks {
+                    *next_ranks.entry(outlink).or_default() += share;
+                }
+            }
+
+            ranks = next_ranks;
+        }
+
+        ranks
+            .into_iter()
+            .map(|(url, rank)| (url.clone(), rank))
+            .collect()
+    }
+
+    /// Searches for pages containing the words as an exact phrase.
+    ///
+    /// Only pages where the words appear consecutively, in the given order, are matched. The
+    /// search is case-insensitive. Stop words aren't indexed, so any word matches in their place.
+    ///
+    /// # Arguments
+    ///
+    /// * `words` - The words of the phrase, in order.
+    ///
+    /// # Returns
+    ///
+    /// A set of `Page` instances containing the phrase. Empty if no matches or input is empty.
+    pub fn search_phrase<S>(&self, words: &[S]) -> HashSet<Page>
+    where
+        S: AsRef<str>,
+    {
+        // Stop words still take up their position in the phrase
+        let terms: Vec<(usize, String)> = words
+            .iter()
+            .map(AsRef::as_ref)
+            .enumerate()
+            .filter(|(_, word)| !self.is_stop_word(word))
+            .map(|(offset, word)| (offset, self.normalize(word)))
+            .collect();
+
+        let Some((first_offset, first)) = terms.first() else {
+            return HashSet::new();
+        };
+
+        self.search(words)
+            .into_iter()
+            .filter(|page| {
+                let Some(positions) = self.positions.get(&page.url) else {
+                    return false;
+                };
+
+                let Some(starts) = positions.get(first) else {
+                    return false;
+                };
+
+                // Every following word must appear at its offset from the first one
+                starts.iter().any(|start| {
+                    terms.iter().skip(1).all(|(offset, word)| {
+                        positions
+                            .get(word)
+                            .is_some_and(|p| p.contains(&(start + offset - first_offset)))
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// Searches for pages matching all words and returns a single page of results.
+    ///
+    /// Results are ordered as in [`IndexStore::search_by_relevance`], so the same query always
+    /// yields the same pagination.
+    ///
+    /// # Arguments
+    ///
+    /// * `words` - A slice of words to search for.
+    /// * `offset` - Number of results to skip.
+    /// * `limit` - Maximum number of results to return.
+    ///
+    /// # Returns
+    ///
+    /// A tuple with the requested slice of `Page` and the total number of matches. The slice
+    /// is empty if `offset` is past the end.
+    pub fn search_paginated<S>(
+        &self,
+        words: &[S],
+        offset: usize,
+        limit: usize,
+    ) -> (Vec<Page>, usize)
+    where
+        S: AsRef<str>,
+    {
+        self.paginate(self.search(words), words, offset, limit)
+    }
+
+    /// Ranks the pages found by a search, as in [`IndexStore::search_paginated`], and returns a
+    /// single page of them. Only the pages up to the requested ones are ranked.
+    ///
+    /// # Arguments
+    ///
+    /// * `pages` - The pages found, e.g. by [`IndexStore::search_any`].
+    /// * `words` - The searched words.
+    /// * `offset` - Number of results to skip.
+    /// * `limit` - Maximum number of results to return.
+    ///
+    /// # Returns
+    ///
+    /// A tuple with the requested slice of `Page` and the total number of pages. The slice is
+    /// empty if `offset` is past the end.
+    pub fn paginate<S>(
+        &self,
+        pages: HashSet<Page>,
+        words: &[S],
+        offset: usize,
+        limit: usize,
+    ) -> (Vec<Page>, usize)
+    where
+        S: AsRef<str>,
+    {
+        let total = pages.len();
+
+        let pages = self
+            .rank_top_for(
+                pages,
+                words,
+                Ranking::Backlinks,
+                offset.saturating_add(limit),
+            )
+            .into_iter()
+            .skip(offset)
+            .collect();
+
+        (pages, total)
+    }
+
+    /// Searches for pages matching all words and ranks them by TF-IDF score.
+    ///
+    /// The term frequency of a word in a page is `1 / n`, where `n` is the number of distinct
+    /// words indexed for that page. The inverse document frequency is `ln(N / df)`, where `N` is
+    /// the number of indexed pages and `df` the number of pages containing the word. Words that
+    /// appear in every page therefore contribute nothing to the score.
+    ///
+    /// The TF-IDF score is blended with the page popularity as
+    /// `tfidf + backlink_weight * ln(1 + backlinks)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `words` - A slice of words to search for.
+    /// * `backlink_weight` - Weight of the backlink count in the final score. Use `0.0` to rank
+    ///   by TF-IDF alone.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `(Page, score)` sorted by descending score. Empty if no matches or input is
+    /// empty.
+    pub fn search_by_tfidf<S>(&self, words: &[S], backlink_weight: f64) -> Vec<(Page, f64)>
+    where
+        S: AsRef<str>,
+    {
+        let pages = self.search(words);
+
+        let total_pages = self.invert_index.len() as f64;
+
+        // Inverse document frequency of each query word
+        let idfs: Vec<(String, f64)> = words
+            .iter()
+            .map(|w| self.normalize(w.as_ref()))
+            .filter_map(|word| {
+                let df = self.index.get(&word)?.len() as f64;
+                let idf = (total_pages / df).ln().max(0.0);
+                Some((word, idf))
+            })
+            .collect();
+
+        let mut pages_with_scores: Vec<(Page, f64)> = pages
+            .into_iter()
+            .map(|page| {
+                let page_words = self.invert_index.get(&page.url);
+                let page_word_count = page_words.map_or(0, |w| w.len());
+
+                let tfidf: f64 = idfs
+                    .iter()
+                    .filter(|(word, _)| page_words.is_some_and(|w| w.contains(word)))
+                    .map(|(_, idf)| idf / page_word_count as f64)
+                    .sum();
+
+                let backlink_count = self.backlinks.get(&page.url).map_or(0, |s| s.len());
+                let score = tfidf + backlink_weight * (backlink_count as f64).ln_1p();
+
+                let page = Page {
+                    rank: Some(score),
+                    ..page
+                };
+
+                (page, score)
+            })
+            .collect();
+
+        // Sort descending by score, breaking ties by URL for a stable ordering
+        pages_with_scores.sort_by(|(a_page, a_score), (b_page, b_score)| {
+            b_score
+                .total_cmp(a_score)
+                .then_with(|| a_page.url.cmp(&b_page.url))
+        });
+
+        pages_with_scores
+    }
+
+    /// Stores a page and its associated data into the index.
+    ///
+    /// Updates the inverted index, backlink relationships, and outlinks. The order of `words`
+    /// is recorded in the positional index used by [`IndexStore::search_phrase`]. Title words
+    /// are indexed like the other words, and also recorded to boost the page in searches for
+    /// them (see [`IndexStore::with_title_boost`]). Stop words set with
+    /// [`IndexStore::with_stop_words`] are left out. If [`IndexStore::with_case_sensitive`]
+    /// is enabled, words are also indexed as written.
+    ///
+    /// Storing an already indexed page, e.g. once recrawled, replaces its words and outlinks.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - The `Page` to store.
+    /// * `title_words` - Words of the page's title.
+    /// * `words` - Words associated with the page.
+    /// * `outlinks` - Outgoing links from the page.
+    pub fn store<S>(&mut self, page: &Page, title_words: &[S], words: &[S], outlinks: &[Url])
+    where
+        S: AsRef<str>,
+    {
+        self.remove(&page.url);
+
+        self.indexed_pages.insert(page.clone());
+        self.url2pages.insert(page.url.clone(), page.clone());
+
+        if self.case_sensitive {
+            for word in title_words.iter().chain(words).map(AsRef::as_ref) {
+                if !self.is_stop_word(word) {
+                    self.case_sensitive_index
+                        .entry(word.to_string())
+                        .or_default()
+                        .insert(page.url.clone());
+                    self.case_sensitive_words
+                        .entry(page.url.clone())
+                        .or_default()
+                        .insert(word.to_string());
+                }
+            }
+        }
+
+        // Stop words are left out, but still count in the positions of the words after them
+        let words: Vec<(usize, String)> = words
+            .iter()
+            .map(|word| word.as_ref().to_lowercase())
+            .enumerate()
+            .filter(|(_, word)| !self.stop_words.contains(word))
+            .map(|(position, word)| (position, self.normalize(&word)))
+            .collect();
+
+        let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+        for (position, word) in &words {
+            positions.entry(word.clone()).or_default().push(*position);
+        }
+        self.positions.insert(page.url.clone(), positions);
+
+        for (_, word) in words {
+            self.index_word(&word, &page.url);
+
+            self.invert_index
+                .entry(page.url.clone())
+                .or_default()
+                .insert(word);
+        }
+
+        let title_words: HashSet<String> = title_words
+            .iter()
+            .map(|word| word.as_ref().to_lowercase())
+            .filter(|word| !self.stop_words.contains(word))
+            .map(|word| self.normalize(&word))
+            .collect();
+
+        for word in &title_words {
+            self.index_word(word, &page.url);
+
+            self.invert_index
+                .entry(page.url.clone())
+                .or_default()
+                .insert(word.clone());
+        }
+
+        if !title_words.is_empty() {
+            self.titles.insert(page.url.clone(), title_words);
+        }
+
+        self.outlinks
+            .entry(page.url.clone())
+            .or_default()
+            .extend(outlinks.iter().cloned());
+
+        for outlink in outlinks {
+            self.backlinks
+                .entry(outlink.clone())
+                .or_default()
+                .insert(page.url.clone());
+        }
+    }
+
+    /// Merges another index into this one.
+    ///
+    /// Pages, words and links of `other` are added to this index. Pages present in both are
+    /// replaced by the ones of `other`, as by [`IndexStore::store`]: they no longer match the
+    /// words, nor count the outlinks, they had only in this index.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The `IndexStore` to merge from.
+    pub fn merge(&mut self, other: IndexStore) {
+        for url in other.url2pages.keys() {
+            self.remove(url);
+        }
+
+        for page in other.indexed_pages {
+            self.indexed_pages.replace(page);
+        }
+        self.url2pages.extend(other.url2pages);
+        self.positions.extend(other.positions);
+        self.titles.extend(other.titles);
+
+        for (word, urls) in other.index {
+            for url in &urls {
+                self.index_word(&word, url);
+            }
+        }
+        for (word, urls) in other.case_sensitive_index {
+            self.case_sensitive_index
+                .entry(word)
+                .or_default()
+                .extend(urls);
+        }
+        for (url, words) in other.case_sensitive_words {
+            self.case_sensitive_words
+                .entry(url)
+                .or_default()
+                .extend(words);
+        }
+        for (url, words) in other.invert_index {
+            self.invert_index.entry(url).or_default().extend(words);
+        }
+        for (url, backlinks) in other.backlinks {
+            self.backlinks.entry(url).or_default().extend(backlinks);
+        }
+        for (url, outlinks) in other.outlinks {
+            self.outlinks.entry(url).or_default().extend(outlinks);
+        }
+    }
+
+    /// Removes a page and all its associated data from the index.
+    ///
+    /// The page is dropped from the inverted indices, and its outlinks are no longer counted as
+    /// backlinks of the pages it linked to. Words and backlink entries left empty are removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page to remove.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the page was indexed and has been removed, `false` otherwise.
+    pub fn remove(&mut self, url: &Url) -> bool {
+        let Some(page) = self.url2pages.remove(url) else {
+            return false;
+        };
+
+        self.indexed_pages.remove(&page);
+        self.positions.remove(url);
+        self.titles.remove(url);
+
+        for word in self.invert_index.remove(url).unwrap_or_default() {
+            if let Some(urls) = self.index.get_mut(&word) {
+                urls.remove(url);
+
+                if urls.is_empty() {
+                    self.index.remove(&word);
+
+                    let length = word.chars().count();
+                    if let Some(words) = self.words_by_length.get_mut(&length) {
+                        words.remove(&word);
+                        if words.is_empty() {
+                            self.words_by_length.remove(&length);
+                        }
+                    }
+                }
+            }
+        }
+
+        for word in self.case_sensitive_words.remove(url).unwrap_or_default() {
+            if let Some(urls) = self.case_sensitive_index.get_mut(&word) {
+                urls.remove(url);
+
+                if urls.is_empty() {
+                    self.case_sensitive_index.remove(&word);
+                }
+            }
+        }
+
+        for outlink in self.outlinks.remove(url).unwrap_or_default() {
+            if let Some(backlinks) = self.backlinks.get_mut(&outlink) {
+                backlinks.remove(url);
+
+                if backlinks.is_empty() {
+                    self.backlinks.remove(&outlink);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Computes summary statistics of the index.
+    ///
+    /// The index isn't serialized again, so the size is the one of its last save or load, as in
+    /// [`IndexStore::size_bytes`].
+    ///
+    /// # Returns
+    ///
+    /// The `IndexStats` of this index.
+    pub fn stats(&self) -> IndexStats {
+        IndexStats {
+            indexed_pages: self.url2pages.len(),
+            words: self.index.len(),
+            outlinks: self.outlinks.values().map(|urls| urls.len()).sum(),
+            backlinks: self.backlinks.values().map(|urls| urls.len()).sum(),
+            size_bytes: self.size_bytes,
+        }
+    }
+
+    /// Size of the index when it was last saved or loaded, in bytes.
+    ///
+    /// The index isn't serialized again, so changes since then aren't accounted for.
+    pub fn size_bytes(&self) -> usize {
+        self.size_bytes
+    }
+
+    /// Counts the pages containing a word.
+    ///
+    /// The word is normalized the same way as in [`IndexStore::search`], so the lookup is
+    /// case-insensitive.
+    ///
+    /// # Arguments
+    ///
+    /// * `word` - The word to look up.
+    ///
+    /// # Returns
+    ///
+    /// The number of indexed pages containing the word. `0` if none.
+    pub fn document_frequency(&self, word: &str) -> usize {
+        self.index
+            .get(&self.normalize(word))
+            .map_or(0, |urls| urls.len())
+    }
+
+    /// Iterates over every indexed word, in no particular order.
+    ///
+    /// # Returns
+    ///
+    /// Each word along with its document frequency (see [`IndexStore::document_frequency`]).
+    pub fn vocabulary(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.index
+            .iter()
+            .map(|(word, urls)| (word.as_str(), urls.len()))
+    }
+
+    /// Suggests a correction of a possibly misspelled word, e.g. for a search without results.
+    ///
+    /// The correction is the indexed word closest to `word` in Levenshtein distance, up to
+    /// [`MAX_CORRECTION_DISTANCE`] edits, among the words found in more pages than `word`
+    /// itself. Ties are broken by document frequency, then alphabetically.
+    ///
+    /// Only [`MAX_CORRECTION_CANDIDATES`] words of a similar length are compared, so on larger
+    /// vocabularies the closest word may be missed. They are taken from the words of the same
+    /// length first, then one character shorter or longer, and so on, each length
+    /// alphabetically, so the same vocabulary always yields the same correction.
+    ///
+    /// The word is normalized as in [`IndexStore::search`], so with stemming enabled the
+    /// correction is a stem.
+    ///
+    /// # Arguments
+    ///
+    /// * `word` - The word to correct.
+    ///
+    /// # Returns
+    ///
+    /// The correction, or `None` if no indexed word is close and more common.
+    pub fn did_you_mean(&self, word: &str) -> Option<String> {
+        let word = self.normalize(word);
+        if word.is_empty() {
+            return None;
+        }
+
+        let frequency = self.index.get(&word).map_or(0, |urls| urls.len());
+        let length = word.chars().count();
+
+        // The lengths closest to the word's first: 0, -1, +1, -2, +2...
+        let lengths = (0..=MAX_CORRECTION_DISTANCE).flat_map(|diff| {
+            [
+                length.checked_sub(diff),
+                (diff > 0).then_some(length + diff),
+            ]
+            .into_iter()
+            .flatten()
+        });
+
+        lengths
+            .filter_map(|length| self.words_by_length.get(&length))
+            .flatten()
+            .filter_map(|candidate| {
+                let candidate_frequency = self.index.get(candidate).map_or(0, |urls| urls.len());
+                (candidate_frequency > frequency)
+                    .then_some((candidate.as_str(), candidate_frequency))
+            })
+            .take(MAX_CORRECTION_CANDIDATES)
+            .filter_map(|(candidate, candidate_frequency)| {
+                let distance = strsim::levenshtein(&word, candidate);
+
+                (distance <= MAX_CORRECTION_DISTANCE).then_some((
+                    distance,
+                    Reverse(candidate_frequency),
+                    candidate,
+                ))
+            })
+            .min()
+            .map(|(_, _, candidate)| candidate.to_string())
+    }
+
+    /// Suggests the most common indexed words starting with `prefix`, e.g. to complete a
+    /// search as it is typed.
+    ///
+    /// The prefix is lowercased, so suggestions are case-insensitive, but never stemmed. With
+    /// stemming enabled, the suggestions are the stems themselves.
+    ///
+    /// Only the words starting with `prefix` are visited, looked up in the sorted words of each
+    /// length, so the longer the prefix, the cheaper the suggestions.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - The start of the words to suggest. Every word matches an empty prefix.
+    /// * `n` - Maximum number of words to suggest.
+    ///
+    /// # Returns
+    ///
+    /// The words along with their document frequency (see
+    /// [`IndexStore::document_frequency`]), most common first. Ties are sorted alphabetically.
+    pub fn suggest(&self, prefix: &str, n: usize) -> Vec<(String, usize)> {
+        let prefix = prefix.to_lowercase();
+        let prefix = prefix.as_str();
+
+        let mut words: Vec<_> = self
+            .words_by_length
+            .range(prefix.chars().count()..)
+            .flat_map(|(_, words)| {
+                words
+                    .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+                    .take_while(|word| word.starts_with(prefix))
+            })
+            .map(|word| {
+                let frequency = self.index.get(word).map_or(0, |urls| urls.len());
+                (word.as_str(), frequency)
+            })
+            .collect();
+
+        let compare = |a: &(&str, usize), b: &(&str, usize)| b.1.cmp(&a.1).then(a.0.cmp(b.0));
+        if n < words.len() {
+            words.select_nth_unstable_by(n, compare);
+            words.truncate(n);
+        }
+        words.sort_unstable_by(compare);
+
+        words
+            .into_iter()
+            .map(|(word, frequency)| (word.to_string(), frequency))
+            .collect()
+    }
+
+    /// Retrieves all backlinks (pages linking to the given URL).
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL for which to retrieve backlinks.
+    ///
+    /// # Returns
+    ///
+    /// A set of URLs linking to the given URL. Empty if none.
+    pub fn consult_backlinks(&self, url: &Url) -> HashSet<Url> {
+        self.backlinks.get(url).cloned().unwrap_or_default()
+    }
+
+    /// Retrieves all outlinks (pages linked from the given URL).
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL for which to retrieve outlinks.
     ///
     /// # Returns
     ///
@@ -315,36 +1811,324 @@ impl IndexStore {
         self.outlinks.get(url).cloned().unwrap_or_default()
     }
 
-    /// Saves the current index to disk.
-    ///
-    /// Serializes the index to JSON and writes it to the specified filepath.
-    ///
-    /// # Errors
-    ///
-    /// Returns an `io::Error` if serialization or file writing fails.
-    pub fn save(&mut self) -> Result<usize, io::Error> {
-        let json = serde_json::to_string(self).map_err(|e| {
-            use io::{Error, ErrorKind};
+    /// Groups the indexed pages with the same content, such as mirrors or print versions of a
+    /// page, by their content hash (see [`crate::page::content_hash`]).
+    ///
+    /// # Returns
+    ///
+    /// The URLs of each group of at least two pages sharing a content hash, sorted, with the
+    /// groups sorted by their first URL. Pages without a content hash are left out.
+    pub fn find_duplicates(&self) -> Vec<Vec<Url>> {
+        let mut groups: HashMap<&str, Vec<Url>> = HashMap::new();
+
+        for page in self.url2pages.values() {
+            if let Some(hash) = &page.content_hash {
+                groups.entry(hash).or_default().push(page.url.clone());
+            }
+        }
+
+        let mut duplicates: Vec<Vec<Url>> = groups
+            .into_values()
+            .filter(|urls| urls.len() > 1)
+            .map(|mut urls| {
+                urls.sort();
+                urls
+            })
+            .collect();
+        duplicates.sort();
+
+        duplicates
+    }
+
+    /// Lists a page of the indexed pages, sorted by URL.
+    ///
+    /// Only the requested pages are sorted and cloned, so listing is cheap even for large
+    /// indices.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Number of pages to skip.
+    /// * `limit` - Maximum number of pages to list.
+    ///
+    /// # Returns
+    ///
+    /// The pages, sorted by URL. Empty if `offset` is past the last page.
+    pub fn list_pages(&self, offset: usize, limit: usize) -> Vec<Page> {
+        let pages = self.url2pages.values().collect();
+
+        select_pages(pages, offset, limit, |a, b| a.url.cmp(&b.url))
+    }
+
+    /// Lists a page of the pages last indexed before `before`, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `before` - Only pages indexed before this instant are listed.
+    /// * `offset` - Number of pages to skip.
+    /// * `limit` - Maximum number of pages to list.
+    ///
+    /// # Returns
+    ///
+    /// The pages, least recently indexed first. Ties are sorted by URL.
+    pub fn pages_indexed_before(
+        &self,
+        before: DateTime<Utc>,
+        offset: usize,
+        limit: usize,
+    ) -> Vec<Page> {
+        let pages = self
+            .url2pages
+            .values()
+            .filter(|page| page.timestamp < before)
+            .collect();
+
+        select_pages(pages, offset, limit, |a, b| {
+            a.timestamp
+                .cmp(&b.timestamp)
+                .then_with(|| a.url.cmp(&b.url))
+        })
+    }
+
+    /// Saves the current index to disk.
+    ///
+    /// Serializes the index in the format matching the file extension (see
+    /// [`StorageFormat::from_path`]) and writes it to the specified filepath, gzip-compressed if
+    /// it ends in `.gz`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if serialization or file writing fails.
+    pub fn save(&mut self) -> Result<usize, io::Error> {
+        self.save_as(StorageFormat::from_path(&self.filepath))
+    }
+
+    /// Saves the current index to disk in the given format.
+    ///
+    /// The data is first written to a temporary file in the same directory, which is then
+    /// renamed over the target. A crash mid-write therefore never leaves a truncated index
+    /// behind: the previous file stays intact until the new one is complete.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The `StorageFormat` to serialize the index with.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if serialization or file writing fails.
+    pub fn save_as(&mut self, format: StorageFormat) -> Result<usize, io::Error> {
+        let bytes = self.encode(format)?;
+
+        let temp_filepath = self.temp_filepath();
+
+        let result = File::create(&temp_filepath)
+            .and_then(|mut file| {
+                file.write_all(&bytes)?;
+                file.sync_all()
+            })
+            .and_then(|_| Self::replace(&temp_filepath, &self.filepath));
+
+        match result {
+            Ok(_) => {
+                self.size_bytes = bytes.len();
+                Ok(bytes.len())
+            }
+            Err(e) => {
+                error!(
+                    "Failed to write to file {}: {}",
+                    &self.filepath.display(),
+                    e
+                );
+                let _ = fs::remove_file(&temp_filepath);
+                Err(e)
+            }
+        }
+    }
+
+    /// Writes the index as JSON Lines, one object per page with its words and links.
+    ///
+    /// Unlike [`IndexStore::save`], the index is never serialized as a whole, so large indices
+    /// can be backed up without holding a copy of them in memory. Pages are written in order of
+    /// their URLs.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Where the lines are written to. Wrap it in a `BufWriter` if unbuffered.
+    ///
+    /// # Returns
+    ///
+    /// The number of pages written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if serialization or writing fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::{index_store::IndexStore, page::PageBuilder};
+    ///
+    /// let mut store = IndexStore::default();
+    /// let page = PageBuilder::default()
+    ///     .url("https://example.com".parse().unwrap())
+    ///     .build()
+    ///     .unwrap();
+    /// store.store(&page, &[], &["crab"], &[]);
+    ///
+    /// let mut backup = Vec::new();
+    /// assert_eq!(store.export_jsonl(&mut backup).unwrap(), 1);
+    ///
+    /// let restored = IndexStore::import_jsonl(backup.as_slice()).unwrap();
+    /// assert_eq!(restored.search(&["crab"]), store.search(&["crab"]));
+    /// ```
+    pub fn export_jsonl(&self, mut writer: impl Write) -> Result<usize, io::Error> {
+        let mut urls: Vec<&Url> = self.url2pages.keys().collect();
+        urls.sort();
+
+        for url in &urls {
+            let record = PageRecord {
+                page: self.url2pages[*url].clone(),
+                words: self.invert_index.get(*url).cloned().unwrap_or_default(),
+                positions: self.positions.get(*url).cloned().unwrap_or_default(),
+                title_words: self.titles.get(*url).cloned().unwrap_or_default(),
+                case_sensitive_words: self
+                    .case_sensitive_words
+                    .get(*url)
+                    .cloned()
+                    .unwrap_or_default(),
+                outlinks: self.outlinks.get(*url).cloned().unwrap_or_default(),
+                backlinks: self.backlinks.get(*url).cloned().unwrap_or_default(),
+            };
+
+            serde_json::to_writer(&mut writer, &record)
+                .map_err(|e| io::Error::other(format!("Serialization error: {}", e)))?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+
+        Ok(urls.len())
+    }
+
+    /// Rebuilds an index from the JSON Lines written by [`IndexStore::export_jsonl`].
+    ///
+    /// Lines are read one at a time, so the export is never held in memory as a whole. Blank
+    /// lines are skipped, and a page listed twice keeps its last line. Backlinks are also
+    /// restored from the outlinks of the imported pages.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Where the lines are read from.
+    ///
+    /// # Returns
+    ///
+    /// The imported `IndexStore`, without a filepath.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if reading fails or a line is not a valid page.
+    pub fn import_jsonl(reader: impl BufRead) -> Result<Self, io::Error> {
+        let mut index_store = Self::default();
+
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let record: PageRecord = serde_json::from_str(&line).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Deserialization error on line {}: {}", number + 1, e),
+                )
+            })?;
+
+            index_store.insert_record(record);
+        }
+
+        Ok(index_store)
+    }
+
+    /// Adds a page read by [`IndexStore::import_jsonl`], replacing it if already indexed.
+    fn insert_record(&mut self, record: PageRecord) {
+        let url = record.page.url.clone();
+        self.remove(&url);
+
+        self.indexed_pages.insert(record.page.clone());
+        self.url2pages.insert(url.clone(), record.page);
+
+        for word in &record.words {
+            self.index_word(word, &url);
+        }
+        if !record.words.is_empty() {
+            self.invert_index.insert(url.clone(), record.words);
+        }
+
+        for word in &record.case_sensitive_words {
+            self.case_sensitive_index
+                .entry(word.clone())
+                .or_default()
+                .insert(url.clone());
+        }
+        if !record.case_sensitive_words.is_empty() {
+            self.case_sensitive_words
+                .insert(url.clone(), record.case_sensitive_words);
+        }
+
+        self.positions.insert(url.clone(), record.positions);
+        if !record.title_words.is_empty() {
+            self.titles.insert(url.clone(), record.title_words);
+        }
+
+        for outlink in &record.outlinks {
+            self.backlinks
+                .entry(outlink.clone())
+                .or_default()
+                .insert(url.clone());
+        }
+        self.outlinks.insert(url.clone(), record.outlinks);
+
+        if !record.backlinks.is_empty() {
+            self.backlinks
+                .entry(url)
+                .or_default()
+                .extend(record.backlinks);
+        }
+    }
+
+    /// Serializes the index in the given format, compressing it if the filepath ends in `.gz`.
+    fn encode(&self, format: StorageFormat) -> Result<Vec<u8>, io::Error> {
+        let bytes = format.serialize(self)?;
 
-            Error::new(ErrorKind::Other, format!("Serialization error: {}", e))
-        })?;
+        match is_gzipped(&self.filepath) {
+            true => gzip(&bytes),
+            false => Ok(bytes),
+        }
+    }
 
-        // WARN: filepath could be invalid
-        // dbg!(&self.filepath);
+    /// Path of the temporary file used by [`IndexStore::save`].
+    ///
+    /// It lives next to the index file so the final rename stays on the same filesystem.
+    fn temp_filepath(&self) -> PathBuf {
+        let mut filename = self
+            .filepath
+            .file_name()
+            .map(|name| name.to_os_string())
+            .unwrap_or_default();
+        filename.push(".tmp");
+
+        self.filepath.with_file_name(filename)
+    }
 
-        match File::create(&self.filepath)?.write(json.as_bytes()) {
-            Ok(size) => {
-                self.size_bytes = size;
-                Ok(size)
-            }
-            Err(e) => {
-                error!(
-                    "Failed to write to file {}: {}",
-                    &self.filepath.display(),
-                    e
-                );
-                Err(e)
+    /// Atomically moves `from` over `to`.
+    ///
+    /// On Windows, renaming over an existing file can fail, in which case the target is removed
+    /// first and the rename retried.
+    fn replace(from: &Path, to: &Path) -> Result<(), io::Error> {
+        match fs::rename(from, to) {
+            Err(_) if cfg!(windows) && to.exists() => {
+                fs::remove_file(to)?;
+                fs::rename(from, to)
             }
+            result => result,
         }
     }
 }
@@ -373,7 +2157,7 @@ mod tests {
             "https://link1.com".parse().unwrap(),
             "https://link2.com".parse().unwrap(),
         ];
-        index_store.store(&page1, &words1, &outlinks_for_page1);
+        index_store.store(&page1, &[], &words1, &outlinks_for_page1);
 
         let page2 = PageBuilder::default()
             .url("https://example.com/page2".parse().unwrap())
@@ -382,7 +2166,7 @@ mod tests {
             .unwrap();
         let words2 = ["rust", "web"];
         let outlinks_for_page2 = ["https://link3.com".parse().unwrap()];
-        index_store.store(&page2, &words2, &outlinks_for_page2);
+        index_store.store(&page2, &[], &words2, &outlinks_for_page2);
 
         let page3 = PageBuilder::default()
             .url("https://example.com/page3".parse().unwrap())
@@ -395,7 +2179,7 @@ mod tests {
             "https://link5.com".parse().unwrap(),
             "https://link6.com".parse().unwrap(),
         ];
-        index_store.store(&page3, &words3, &outlinks_for_page3);
+        index_store.store(&page3, &[], &words3, &outlinks_for_page3);
 
         // Add backlinks for testing search_by_relevance
         index_store.backlinks.insert(
@@ -421,63 +2205,739 @@ mod tests {
             .collect(),
         );
 
-        index_store
+        index_store
+    }
+
+    #[test]
+    fn test_search_single_word() {
+        let index_store = create_index_store();
+
+        let results = index_store.search(&["rust"]);
+        let urls: HashSet<Url> = results.iter().map(|p| p.url.clone()).collect();
+
+        assert_eq!(urls.len(), 2);
+        assert!(urls.contains(&Url::parse("https://example.com/page1").unwrap()));
+        assert!(urls.contains(&Url::parse("https://example.com/page2").unwrap()));
+    }
+
+    #[test]
+    fn test_search_multiple_words() {
+        let index_store = create_index_store();
+
+        // Search for pages containing both "rust" and "programming"
+        let results = index_store.search(&["rust", "programming"]);
+        let urls: HashSet<_> = results.iter().map(|p| p.url.clone()).collect();
+
+        assert_eq!(urls.len(), 1);
+        assert!(urls.contains(&Url::parse("https://example.com/page1").unwrap()));
+    }
+
+    #[test]
+    fn test_search_no_match() {
+        let index_store = create_index_store();
+
+        let results = index_store.search(&["nonexistent"]);
+        assert!(results.is_empty());
+
+        let results2 = index_store.search(&["rust", "nonexistent"]);
+        assert!(results2.is_empty());
+    }
+
+    #[test]
+    fn test_search_empty_input() {
+        let index_store = create_index_store();
+
+        let results = index_store.search::<&str>(&[]);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_search_case_insensitivity() {
+        let index_store = create_index_store();
+
+        let results_lower = index_store.search(&["rust"]);
+        let results_upper = index_store.search(&["RUST"]);
+
+        let urls_lower: HashSet<_> = results_lower.iter().map(|p| p.url.clone()).collect();
+        let urls_upper: HashSet<_> = results_upper.iter().map(|p| p.url.clone()).collect();
+
+        assert_eq!(urls_lower, urls_upper);
+    }
+
+    /// Tests that intersecting from the smallest set finds the pages a plain intersection of
+    /// every set does.
+    #[test]
+    fn test_search_intersection() {
+        use crate::testing::{VOCABULARY_SIZE, synthetic_word};
+
+        let index_store = IndexStore::synthetic(1_000, 50, 3);
+
+        let intersect = |words: &[String]| -> HashSet<Page> {
+            let sets: Vec<&HashSet<Url>> = words
+                .iter()
+                .filter_map(|word| index_store.index.get(word))
+                .collect();
+            if words.is_empty() || sets.len() < words.len() {
+                return HashSet::new();
+            }
+
+            sets.iter()
+                .skip(1)
+                .fold(sets[0].clone(), |acc, set| &acc & set)
+                .iter()
+                .map(|url| index_store.url2pages[url].clone())
+                .collect()
+        };
+
+        for ranks in [
+            &[][..],
+            &[0],
+            &[0, 1],
+            &[1, 0, 2],
+            &[0, 40],
+            &[VOCABULARY_SIZE / 2, 0],
+            &[0, 0],
+        ] {
+            let words: Vec<String> = ranks.iter().map(|&rank| synthetic_word(rank)).collect();
+
+            assert_eq!(index_store.search(&words), intersect(&words), "{:?}", words);
+        }
+        assert!(index_store.search(&["unknown"]).is_empty());
+    }
+
+    #[test]
+    fn test_search_any() {
+        let index_store = create_index_store();
+
+        let results = index_store.search_any(&["web", "tutorial"]);
+        let urls: HashSet<_> = results.iter().map(|p| p.url.clone()).collect();
+
+        assert_eq!(urls.len(), 2);
+        assert!(urls.contains(&Url::parse("https://example.com/page2").unwrap()));
+        assert!(urls.contains(&Url::parse("https://example.com/page3").unwrap()));
+
+        // Unknown words do not prevent other words from matching
+        assert_eq!(index_store.search_any(&["WEB", "nonexistent"]).len(), 1);
+        assert!(index_store.search_any::<&str>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut index_store = create_index_store();
+
+        let mut other = IndexStore::default();
+        let page = PageBuilder::default()
+            .url("https://example.com/page4".parse().unwrap())
+            .build()
+            .unwrap();
+        other.store(
+            &page,
+            &[],
+            &["rust", "merge"],
+            &[parse_url_panic(&"https://link1.com")],
+        );
+
+        index_store.merge(other);
+
+        assert_eq!(index_store.search(&["rust"]).len(), 3);
+        assert_eq!(index_store.search(&["merge"]).len(), 1);
+        assert_eq!(
+            index_store
+                .consult_backlinks(&parse_url_panic(&"https://link1.com"))
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_merge_replaces_recrawled_page() {
+        let mut index_store = create_index_store();
+        let url = parse_url_panic(&"https://example.com/page1");
+        let old_outlink = parse_url_panic(&"https://link1.com");
+        assert_eq!(index_store.consult_backlinks(&old_outlink).len(), 1);
+
+        let mut other = IndexStore::default();
+        let page = PageBuilder::default().url(url.clone()).build().unwrap();
+        other.store(
+            &page,
+            &[],
+            &["crab"],
+            &[parse_url_panic(&"https://link7.com")],
+        );
+
+        index_store.merge(other);
+
+        // The old words and outlinks of the page are gone
+        assert!(
+            index_store
+                .search(&["rust"])
+                .iter()
+                .all(|page| page.url != url)
+        );
+        assert_eq!(index_store.search(&["crab"]).len(), 1);
+        assert_eq!(
+            index_store.invert_index[&url],
+            HashSet::from(["crab".to_string()])
+        );
+        assert!(index_store.consult_backlinks(&old_outlink).is_empty());
+        assert_eq!(
+            index_store
+                .consult_backlinks(&parse_url_panic(&"https://link7.com"))
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut index_store = create_index_store();
+
+        let page1 = Url::parse("https://example.com/page1").unwrap();
+        let link1 = Url::parse("https://link1.com").unwrap();
+
+        assert!(index_store.remove(&page1));
+
+        assert!(!index_store.url2pages.contains_key(&page1));
+        assert!(index_store.indexed_pages.iter().all(|p| p.url != page1));
+        assert!(!index_store.invert_index.contains_key(&page1));
+        assert!(!index_store.outlinks.contains_key(&page1));
+
+        // "language" was only used by page1
+        assert!(!index_store.index.contains_key("language"));
+        assert_eq!(index_store.search(&["rust"]).len(), 1);
+
+        // page1 was the only page linking to link1
+        assert!(index_store.consult_backlinks(&link1).is_empty());
+        assert!(!index_store.backlinks.contains_key(&link1));
+
+        // Removing it again is a no-op
+        assert!(!index_store.remove(&page1));
+    }
+
+    #[test]
+    fn test_store_replaces_page() {
+        let mut index_store = IndexStore::default();
+        let url: Url = "https://example.com".parse().unwrap();
+        let link1 = parse_url_panic(&"https://link1.com");
+        let link2 = parse_url_panic(&"https://link2.com");
+
+        let page = PageBuilder::default()
+            .url(url.clone())
+            .timestamp(Utc::now() - chrono::Duration::days(30))
+            .build()
+            .unwrap();
+        index_store.store(&page, &[], &["old", "rust"], std::slice::from_ref(&link1));
+
+        let page = PageBuilder::default().url(url.clone()).build().unwrap();
+        index_store.store(&page, &[], &["new", "rust"], std::slice::from_ref(&link2));
+
+        assert!(index_store.search(&["old"]).is_empty());
+        assert_eq!(index_store.search(&["new", "rust"]).len(), 1);
+        assert_eq!(
+            index_store.consult_outlinks(&url),
+            HashSet::from([link2.clone()])
+        );
+        assert!(index_store.consult_backlinks(&link1).is_empty());
+        assert_eq!(index_store.stats().indexed_pages, 1);
+    }
+
+    #[test]
+    fn test_pages_indexed_before() {
+        let mut index_store = IndexStore::default();
+        let now = Utc::now();
+
+        for (url, days) in [
+            ("https://a.com/", 3),
+            ("https://b.com/", 10),
+            ("https://c.com/", 0),
+        ] {
+            let page = PageBuilder::default()
+                .url(url.parse().unwrap())
+                .timestamp(now - chrono::Duration::days(days))
+                .build()
+                .unwrap();
+            index_store.store(&page, &[], &["rust"], &[]);
+        }
+
+        let urls = |before, offset, limit| -> Vec<String> {
+            index_store
+                .pages_indexed_before(before, offset, limit)
+                .iter()
+                .map(|page| page.url.to_string())
+                .collect()
+        };
+
+        let day_ago = now - chrono::Duration::days(1);
+        assert_eq!(urls(day_ago, 0, 10), ["https://b.com/", "https://a.com/"]);
+        assert_eq!(urls(day_ago, 0, 1), ["https://b.com/"]);
+        assert_eq!(urls(day_ago, 1, 1), ["https://a.com/"]);
+        assert!(urls(now - chrono::Duration::days(30), 0, 10).is_empty());
+    }
+
+    #[test]
+    fn test_list_pages() {
+        let index_store = create_index_store();
+
+        let urls = |offset, limit| -> Vec<String> {
+            index_store
+                .list_pages(offset, limit)
+                .iter()
+                .map(|page| page.url.to_string())
+                .collect()
+        };
+
+        let all = [
+            "https://example.com/page1",
+            "https://example.com/page2",
+            "https://example.com/page3",
+        ];
+        assert_eq!(urls(0, 10), all);
+        assert_eq!(urls(0, 2), all[..2]);
+        assert_eq!(urls(1, 1), all[1..2]);
+        assert!(urls(3, 10).is_empty());
+        assert!(urls(0, 0).is_empty());
+    }
+
+    #[test]
+    fn test_stats() {
+        let index_store = create_index_store();
+
+        let stats = index_store.stats();
+
+        assert_eq!(stats.indexed_pages, 3);
+        assert_eq!(stats.words, 5);
+        assert_eq!(stats.outlinks, 6);
+        assert_eq!(stats.backlinks, 12);
+        assert_eq!(stats.size_bytes, index_store.size_bytes());
+    }
+
+    #[test]
+    fn test_read_limited() {
+        assert_eq!(read_limited(&b"crab"[..], 4).unwrap(), b"crab");
+
+        let error = read_limited(&b"crabs"[..], 4).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_document_frequency() {
+        let index_store = create_index_store();
+
+        assert_eq!(index_store.document_frequency("rust"), 2);
+        assert_eq!(index_store.document_frequency("Programming"), 2);
+        assert_eq!(index_store.document_frequency("web"), 1);
+        assert_eq!(index_store.document_frequency("python"), 0);
+
+        let vocabulary: HashMap<_, _> = index_store.vocabulary().collect();
+        assert_eq!(
+            vocabulary,
+            HashMap::from([
+                ("rust", 2),
+                ("programming", 2),
+                ("language", 1),
+                ("web", 1),
+                ("tutorial", 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_suggest() {
+        let index_store = create_index_store();
+
+        assert_eq!(
+            index_store.suggest("r", 10),
+            vec![(String::from("rust"), 2)]
+        );
+        assert_eq!(
+            index_store.suggest("", 3),
+            vec![
+                (String::from("programming"), 2),
+                (String::from("rust"), 2),
+                (String::from("language"), 1),
+            ]
+        );
+        assert_eq!(index_store.suggest("PROG", 10).len(), 1);
+        assert!(index_store.suggest("python", 10).is_empty());
+        assert!(index_store.suggest("rust", 0).is_empty());
+
+        // Words of any length after the prefix are suggested, but not the words around them
+        let mut index_store = IndexStore::default();
+        let page = PageBuilder::default()
+            .url("https://example.com".parse().unwrap())
+            .build()
+            .unwrap();
+        index_store.store(
+            &page,
+            &[],
+            &["ru", "rusa", "rust", "rustacean", "rv", "ruz"],
+            &[],
+        );
+        let words: Vec<_> = index_store
+            .suggest("rus", 10)
+            .into_iter()
+            .map(|(word, _)| word)
+            .collect();
+        assert_eq!(words, ["rusa", "rust", "rustacean"]);
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let mut index_store = create_index_store();
+        let page = PageBuilder::default()
+            .url("https://example.com".parse().unwrap())
+            .build()
+            .unwrap();
+        index_store.store(&page, &[], &["rusty"], &[]);
+
+        assert_eq!(index_store.did_you_mean("rsut"), Some(String::from("rust")));
+        assert_eq!(
+            index_store.did_you_mean("langauge"),
+            Some(String::from("language"))
+        );
+        // Ties are broken by document frequency
+        assert_eq!(
+            index_store.did_you_mean("RUSTT"),
+            Some(String::from("rust"))
+        );
+        // The closest word wins over the most common one
+        assert_eq!(
+            index_store.did_you_mean("rustyy"),
+            Some(String::from("rusty"))
+        );
+        // Only words in more pages are corrections
+        assert_eq!(
+            index_store.did_you_mean("rusty"),
+            Some(String::from("rust"))
+        );
+        assert_eq!(index_store.did_you_mean("rust"), None);
+        assert_eq!(index_store.did_you_mean("language"), None);
+        assert_eq!(index_store.did_you_mean("python"), None);
+        assert_eq!(index_store.did_you_mean(""), None);
+    }
+
+    #[test]
+    fn test_did_you_mean_after_load_and_remove() {
+        let mut store = create_index_store();
+        let url: Url = "https://example.com/rusty".parse().unwrap();
+        let page = PageBuilder::default().url(url.clone()).build().unwrap();
+        store.store(&page, &[], &["rusty", "crustacean"], &[]);
+
+        let temp_path = ".test_index_store_did_you_mean.json";
+        store.filepath = path::absolute(temp_path).unwrap();
+        store.save().expect("Failed to save index");
+
+        let mut loaded_store = IndexStore::load(temp_path).expect("Failed to load index");
+        fs::remove_file(temp_path).expect("Failed to delete temp file");
+
+        assert_eq!(loaded_store.words_by_length, store.words_by_length);
+        assert_eq!(
+            loaded_store.did_you_mean("rsut"),
+            Some(String::from("rust"))
+        );
+
+        assert!(loaded_store.remove(&url));
+        let words: BTreeSet<&String> = loaded_store.words_by_length.values().flatten().collect();
+        assert_eq!(words, loaded_store.index.keys().collect());
+    }
+
+    #[test]
+    fn test_store_skips_stop_words() {
+        let mut index_store =
+            IndexStore::default().with_stop_words(["The", "of"].map(String::from).into());
+        let page = PageBuilder::default()
+            .url("https://example.com".parse().unwrap())
+            .build()
+            .unwrap();
+
+        index_store.store(&page, &[], &["the", "book", "OF", "rust"], &[]);
+
+        assert!(index_store.search(&["the"]).is_empty());
+        assert!(index_store.search(&["of"]).is_empty());
+        assert_eq!(index_store.search(&["book", "rust"]).len(), 1);
+        assert_eq!(
+            index_store.invert_index[&page.url],
+            ["book", "rust"].map(String::from).into()
+        );
+
+        // Positions still account for the stop words
+        assert_eq!(index_store.positions[&page.url]["rust"], vec![3]);
+        assert!(index_store.search_phrase(&["book", "rust"]).is_empty());
+        assert_eq!(index_store.search_phrase(&["book", "of", "rust"]).len(), 1);
+        assert_eq!(index_store.search_phrase(&["the", "book"]).len(), 1);
+        assert!(index_store.search_phrase(&["the", "of"]).is_empty());
+    }
+
+    #[test]
+    fn test_search_skips_stop_words() {
+        let mut index_store = IndexStore::default()
+            .with_stop_words(["the"].map(String::from).into())
+            .with_case_sensitive(true);
+        let page = PageBuilder::default()
+            .url("https://example.com".parse().unwrap())
+            .build()
+            .unwrap();
+
+        index_store.store(&page, &[], &["the", "Rust", "book"], &[]);
+
+        assert_eq!(index_store.search(&["The", "rust", "book"]).len(), 1);
+        assert_eq!(index_store.search_any(&["the", "crab", "book"]).len(), 1);
+        assert_eq!(index_store.search_case_sensitive(&["THE", "Rust"]).len(), 1);
+        assert_eq!(
+            index_store
+                .search_any_case_sensitive(&["the", "Rust"])
+                .len(),
+            1
+        );
+        assert!(index_store.search(&["the"]).is_empty());
     }
 
     #[test]
-    fn test_search_single_word() {
-        let index_store = create_index_store();
+    fn test_stemming() {
+        let page = PageBuilder::default()
+            .url("https://example.com".parse().unwrap())
+            .build()
+            .unwrap();
 
-        let results = index_store.search(&["rust"]);
-        let urls: HashSet<Url> = results.iter().map(|p| p.url.clone()).collect();
+        // Off by default
+        let mut index_store = IndexStore::default();
+        index_store.store(&page, &[], &["Running", "crabs"], &[]);
+        assert!(index_store.search(&["run"]).is_empty());
 
-        assert_eq!(urls.len(), 2);
-        assert!(urls.contains(&Url::parse("https://example.com/page1").unwrap()));
-        assert!(urls.contains(&Url::parse("https://example.com/page2").unwrap()));
+        let mut index_store = IndexStore::default().with_stemming(true);
+        index_store.store(&page, &[], &["Running", "crabs"], &[]);
+
+        for query in [["run", "crab"], ["runs", "CRAB"], ["running", "crabs"]] {
+            assert_eq!(index_store.search(&query).len(), 1, "{:?}", query);
+            assert_eq!(index_store.search_any(&query).len(), 1, "{:?}", query);
+            assert_eq!(index_store.search_phrase(&query).len(), 1, "{:?}", query);
+            assert_eq!(index_store.search_by_tfidf(&query, 0.0).len(), 1);
+        }
+        assert!(index_store.search_phrase(&["crab", "run"]).is_empty());
     }
 
     #[test]
-    fn test_search_multiple_words() {
-        let index_store = create_index_store();
+    fn test_title_boost() {
+        let page = |url: &str| {
+            PageBuilder::default()
+                .url(url.parse().unwrap())
+                .build()
+                .unwrap()
+        };
+        let (body, title) = (page("https://body.com/"), page("https://title.com/"));
+
+        let ranked_urls = |title_boost| {
+            let mut index_store = IndexStore::default().with_title_boost(title_boost);
+            index_store.store(&body, &[], &["rust", "crab"], &[]);
+            index_store.store(&title, &["Rust"], &["crab"], &[]);
+            for linker in ["https://a.com/", "https://b.com/", "https://c.com/"] {
+                index_store.store::<&str>(&page(linker), &[], &[], std::slice::from_ref(&body.url));
+            }
 
-        // Search for pages containing both "rust" and "programming"
-        let results = index_store.search(&["rust", "programming"]);
-        let urls: HashSet<_> = results.iter().map(|p| p.url.clone()).collect();
+            let words = ["rust", "crab"];
+            let ranked = index_store.search_by_relevance(&words, 10);
+            // Fully ranking applies the boost too
+            let pages = index_store.search(&words);
+            assert_eq!(
+                index_store.rank_for(pages, &words, Ranking::Backlinks),
+                ranked
+            );
+
+            ranked
+                .into_iter()
+                .map(|page| (page.url.to_string(), page.rank.unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        // Title words are indexed, but ignored by the ranking by default
+        assert_eq!(
+            ranked_urls(0.0),
+            [
+                (String::from("https://body.com/"), 3.0),
+                (String::from("https://title.com/"), 0.0)
+            ]
+        );
+        // A title match is worth 2 backlinks
+        assert_eq!(ranked_urls(2.0)[0].0, "https://body.com/");
+        assert_eq!(
+            ranked_urls(10.0),
+            [
+                (String::from("https://title.com/"), 10.0),
+                (String::from("https://body.com/"), 3.0)
+            ]
+        );
+    }
 
-        assert_eq!(urls.len(), 1);
-        assert!(urls.contains(&Url::parse("https://example.com/page1").unwrap()));
+    #[test]
+    fn test_find_duplicates() {
+        let mut index_store = IndexStore::default();
+        for (url, hash) in [
+            ("https://mirror.com/", Some("a")),
+            ("https://other.com/", Some("b")),
+            ("https://example.com/", Some("a")),
+            ("https://unhashed.com/", None),
+            ("https://print.com/", Some("c")),
+            ("https://unhashed2.com/", None),
+            ("https://example.com/print", Some("c")),
+        ] {
+            let mut builder = PageBuilder::default();
+            builder.url(url.parse().unwrap());
+            if let Some(hash) = hash {
+                builder.content_hash(hash);
+            }
+            index_store.store(&builder.build().unwrap(), &[], &["rust"], &[]);
+        }
+
+        let duplicates: Vec<Vec<String>> = index_store
+            .find_duplicates()
+            .into_iter()
+            .map(|urls| urls.into_iter().map(String::from).collect())
+            .collect();
+        assert_eq!(
+            duplicates,
+            [
+                ["https://example.com/", "https://mirror.com/"],
+                ["https://example.com/print", "https://print.com/"]
+            ]
+        );
     }
 
     #[test]
-    fn test_search_no_match() {
-        let index_store = create_index_store();
+    fn test_collapse_duplicates() {
+        let page = |url: &str, hash: Option<&str>| {
+            let mut builder = PageBuilder::default();
+            builder.url(url.parse().unwrap());
+            if let Some(hash) = hash {
+                builder.content_hash(hash);
+            }
+            builder.build().unwrap()
+        };
+        let original = page("https://original.com/", Some("a"));
+        let mirror = page("https://mirror.com/", Some("a"));
+        let unhashed = page("https://unhashed.com/", None);
+        let unhashed_copy = page("https://unhashed2.com/", None);
+
+        let ranked_urls = |collapse_duplicates, limit| {
+            let mut index_store =
+                IndexStore::default().with_collapse_duplicates(collapse_duplicates);
+            for page in [&original, &mirror, &unhashed, &unhashed_copy] {
+                index_store.store(page, &[], &["rust"], &[]);
+            }
+            // The original is the most linked copy
+            index_store.store::<&str>(
+                &page("https://linker.com/", None),
+                &[],
+                &[],
+                std::slice::from_ref(&original.url),
+            );
+
+            index_store
+                .search_by_relevance(&["rust"], limit)
+                .into_iter()
+                .map(|page| page.url.to_string())
+                .collect::<Vec<_>>()
+        };
 
-        let results = index_store.search(&["nonexistent"]);
-        assert!(results.is_empty());
+        assert_eq!(
+            ranked_urls(false, 10),
+            [
+                "https://original.com/",
+                "https://mirror.com/",
+                "https://unhashed.com/",
+                "https://unhashed2.com/"
+            ]
+        );
+        assert_eq!(
+            ranked_urls(true, 10),
+            [
+                "https://original.com/",
+                "https://unhashed.com/",
+                "https://unhashed2.com/"
+            ]
+        );
 
-        let results2 = index_store.search(&["rust", "nonexistent"]);
-        assert!(results2.is_empty());
+        // Duplicates don't take the place of other pages in a shorter top
+        assert_eq!(
+            ranked_urls(false, 2),
+            ["https://original.com/", "https://mirror.com/"]
+        );
+        assert_eq!(
+            ranked_urls(true, 2),
+            ["https://original.com/", "https://unhashed.com/"]
+        );
+        assert!(ranked_urls(true, 0).is_empty());
     }
 
     #[test]
-    fn test_search_empty_input() {
-        let index_store = create_index_store();
+    fn test_store_and_remove_title() {
+        let mut index_store =
+            IndexStore::default().with_stop_words(["the"].map(String::from).into());
+        let page = PageBuilder::default()
+            .url("https://example.com".parse().unwrap())
+            .build()
+            .unwrap();
 
-        let results = index_store.search::<&str>(&[]);
-        assert!(results.is_empty());
+        index_store.store(&page, &["The", "Rust", "Book"], &["chapter"], &[]);
+        assert_eq!(
+            index_store.titles[&page.url],
+            ["rust", "book"].map(String::from).into()
+        );
+        assert_eq!(index_store.search(&["book", "chapter"]).len(), 1);
+        assert!(index_store.search(&["the"]).is_empty());
+
+        // Re-storing the page replaces its title
+        index_store.store(&page, &[], &["chapter"], &[]);
+        assert!(index_store.titles.is_empty());
+        assert!(index_store.search(&["book"]).is_empty());
     }
 
     #[test]
-    fn test_search_case_insensitivity() {
-        let index_store = create_index_store();
+    fn test_case_sensitive() {
+        let url: Url = "https://example.com/rust".parse().unwrap();
+        let page = PageBuilder::default().url(url.clone()).build().unwrap();
 
-        let results_lower = index_store.search(&["rust"]);
-        let results_upper = index_store.search(&["RUST"]);
+        // Off by default, searching case-insensitively instead
+        let mut index_store = IndexStore::default();
+        index_store.store(&page, &[], &["Rust", "crab"], &[]);
+        assert_eq!(index_store.search_case_sensitive(&["rust"]).len(), 1);
+        assert!(index_store.case_sensitive_index.is_empty());
 
-        let urls_lower: HashSet<_> = results_lower.iter().map(|p| p.url.clone()).collect();
-        let urls_upper: HashSet<_> = results_upper.iter().map(|p| p.url.clone()).collect();
+        let mut index_store = IndexStore::default()
+            .with_stop_words(["the"].map(String::from).into())
+            .with_case_sensitive(true);
+        index_store.store(&page, &[], &["The", "Rust", "crab"], &[]);
 
-        assert_eq!(urls_lower, urls_upper);
+        assert_eq!(index_store.search(&["rust", "CRAB"]).len(), 1);
+        assert_eq!(
+            index_store.search_case_sensitive(&["Rust", "crab"]).len(),
+            1
+        );
+        assert!(
+            index_store
+                .search_case_sensitive(&["rust", "crab"])
+                .is_empty()
+        );
+        assert!(index_store.search_case_sensitive(&["The"]).is_empty());
+        assert_eq!(
+            index_store
+                .search_any_case_sensitive(&["rust", "crab"])
+                .len(),
+            1
+        );
+        assert!(index_store.search_any_case_sensitive(&["RUST"]).is_empty());
+
+        let mut other = IndexStore::default().with_case_sensitive(true);
+        let other_page = PageBuilder::default()
+            .url("https://example.com/other".parse().unwrap())
+            .build()
+            .unwrap();
+        other.store(&other_page, &[], &["Rust"], &[]);
+        index_store.merge(other);
+        assert_eq!(index_store.search_case_sensitive(&["Rust"]).len(), 2);
+
+        assert!(index_store.remove(&url));
+        assert_eq!(index_store.search_case_sensitive(&["Rust"]).len(), 1);
+        assert!(!index_store.case_sensitive_index.contains_key("crab"));
     }
 
     #[test]
@@ -485,7 +2945,7 @@ mod tests {
         let index_store = create_index_store();
 
         // Search for pages containing "rust"
-        let sorted_pages = index_store.search_by_relevance(&["rust"]);
+        let sorted_pages = index_store.search_by_relevance(&["rust"], 10);
 
         // Expect pages sorted by backlinks: page3 (3), page1 (2), page2 (1)
         assert_eq!(sorted_pages.len(), 2);
@@ -501,6 +2961,185 @@ mod tests {
             .collect();
 
         assert!(backlink_counts.windows(2).all(|w| w[0] >= w[1]));
+
+        // Each page carries its score
+        let ranks: Vec<_> = sorted_pages.iter().map(|p| p.rank).collect();
+        let expected: Vec<_> = backlink_counts
+            .iter()
+            .map(|&count| Some(count as f64))
+            .collect();
+        assert_eq!(ranks, expected);
+
+        // Ranks are only set on search results
+        assert!(index_store.url2pages.values().all(|p| p.rank.is_none()));
+    }
+
+    /// Helper to build an index from `(url, outlinks)` pairs.
+    fn create_link_graph(links: &[(&str, &[&str])]) -> IndexStore {
+        let mut index_store = IndexStore::default();
+
+        for (url, outlinks) in links {
+            let page = PageBuilder::default()
+                .url(url.parse().unwrap())
+                .build()
+                .unwrap();
+            let outlinks: Vec<Url> = outlinks.iter().map(parse_url_panic).collect();
+
+            index_store.store(&page, &[], &["word"], &outlinks);
+        }
+
+        index_store
+    }
+
+    #[test]
+    fn test_pagerank_cycle() {
+        let index_store = create_link_graph(&[
+            ("https://a.com", &["https://b.com"]),
+            ("https://b.com", &["https://c.com"]),
+            ("https://c.com", &["https://a.com"]),
+        ]);
+
+        let ranks = index_store.compute_pagerank(0.85, 50);
+
+        assert_eq!(ranks.len(), 3);
+        for rank in ranks.values() {
+            assert!((rank - 1.0 / 3.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_pagerank_dangling_nodes() {
+        // b.com and c.com have no outlinks
+        let index_store = create_link_graph(&[
+            ("https://a.com", &["https://b.com", "https://c.com"]),
+            ("https://b.com", &[]),
+        ]);
+
+        let ranks = index_store.compute_pagerank(0.85, 50);
+
+        let total: f64 = ranks.values().sum();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        let a = ranks[&parse_url_panic(&"https://a.com")];
+        let b = ranks[&parse_url_panic(&"https://b.com")];
+        assert!(b > a);
+    }
+
+    #[test]
+    fn test_pagerank_duplicate_outlinks() {
+        let single = create_link_graph(&[
+            ("https://a.com", &["https://b.com", "https://c.com"]),
+            ("https://b.com", &["https://a.com"]),
+        ]);
+        let duplicated = create_link_graph(&[
+            (
+                "https://a.com",
+                &["https://b.com", "https://b.com", "https://c.com"],
+            ),
+            ("https://b.com", &["https://a.com"]),
+        ]);
+
+        assert_eq!(
+            single.compute_pagerank(0.85, 20),
+            duplicated.compute_pagerank(0.85, 20)
+        );
+    }
+
+    #[test]
+    fn test_search_by_relevance_orders_by_backlink_count() {
+        // Backlink counts are the reverse of the URL order, so ties can't explain the ordering
+        let index_store = create_link_graph(&[
+            ("https://a.com", &[]),
+            ("https://b.com", &[]),
+            ("https://c.com", &[]),
+            (
+                "https://d.com",
+                &["https://b.com", "https://c.com", "https://a.com"],
+            ),
+            ("https://e.com", &["https://b.com", "https://c.com"]),
+            ("https://f.com", &["https://b.com"]),
+        ]);
+
+        let results = index_store.search_by_relevance(&["word"], 10);
+        let urls: Vec<Url> = results.into_iter().map(|page| page.url).collect();
+
+        let expected: Vec<Url> = [
+            "https://b.com",
+            "https://c.com",
+            "https://a.com",
+            "https://d.com",
+            "https://e.com",
+            "https://f.com",
+        ]
+        .iter()
+        .map(parse_url_panic)
+        .collect();
+
+        assert_eq!(urls, expected);
+    }
+
+    #[test]
+    fn test_search_by_relevance_limit() {
+        let index_store = create_link_graph(&[
+            ("https://a.com", &[]),
+            ("https://b.com", &[]),
+            ("https://c.com", &[]),
+            (
+                "https://d.com",
+                &["https://b.com", "https://c.com", "https://a.com"],
+            ),
+            ("https://e.com", &["https://b.com", "https://c.com"]),
+            ("https://f.com", &["https://b.com"]),
+        ]);
+
+        // The top pages are the first ones of the whole ranking, ties included
+        let ranked = index_store.rank_by_relevance(index_store.search(&["word"]));
+        for limit in 0..=7 {
+            let top = index_store.search_by_relevance(&["word"], limit);
+
+            assert_eq!(top.len(), limit.min(6));
+            assert_eq!(
+                top.iter().map(|page| &page.url).collect::<Vec<_>>(),
+                ranked
+                    .iter()
+                    .take(limit)
+                    .map(|page| &page.url)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                top.iter().map(|page| page.rank).collect::<Vec<_>>(),
+                ranked
+                    .iter()
+                    .take(limit)
+                    .map(|page| page.rank)
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_by_relevance_with_pagerank() {
+        // Both b.com and c.com have one backlink, but b.com's comes from a popular page
+        let index_store = create_link_graph(&[
+            ("https://a.com", &["https://b.com"]),
+            ("https://d.com", &["https://a.com"]),
+            ("https://e.com", &["https://a.com"]),
+            ("https://f.com", &["https://c.com"]),
+            ("https://b.com", &[]),
+            ("https://c.com", &[]),
+        ]);
+
+        let ranking = Ranking::PageRank {
+            damping: 0.85,
+            iterations: 50,
+        };
+        let results = index_store.search_by_relevance_with(&["word"], ranking, 10);
+
+        // b.com inherits all of a.com's rank, which is fed by two pages
+        assert_eq!(results.len(), 6);
+        assert_eq!(results[0].url, parse_url_panic(&"https://b.com"));
+        assert_eq!(results[1].url, parse_url_panic(&"https://a.com"));
+        assert_eq!(results[2].url, parse_url_panic(&"https://c.com"));
     }
 
     #[test]
@@ -508,7 +3147,7 @@ mod tests {
         let index_store = create_index_store();
 
         // Search for non-existent words
-        let results = index_store.search_by_relevance(&["nonexistent"]);
+        let results = index_store.search_by_relevance(&["nonexistent"], 10);
         assert!(results.is_empty());
     }
 
@@ -523,10 +3162,10 @@ mod tests {
             .build()
             .unwrap();
         let words = ["tutorial"];
-        index_store.store(&page_no_backlinks, &words, &[]);
+        index_store.store(&page_no_backlinks, &[], &words, &[]);
 
         // Now search for "tutorial", which matches page3 and page4
-        let results = index_store.search_by_relevance(&["tutorial"]);
+        let results = index_store.search_by_relevance(&["tutorial"], 10);
         // Page3 has backlinks, page4 has none
         assert_eq!(results.len(), 2);
         assert_eq!(
@@ -535,6 +3174,130 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_search_phrase() {
+        let index_store = create_index_store();
+
+        let results = index_store.search_phrase(&["rust", "programming"]);
+        let urls: HashSet<_> = results.iter().map(|p| p.url.clone()).collect();
+        assert_eq!(urls.len(), 1);
+        assert!(urls.contains(&Url::parse("https://example.com/page1").unwrap()));
+
+        // Both words appear in page1, but not in this order
+        assert!(
+            index_store
+                .search_phrase(&["programming", "rust"])
+                .is_empty()
+        );
+        // Both words appear in page1, but not next to each other
+        assert!(index_store.search_phrase(&["rust", "language"]).is_empty());
+        assert!(index_store.search_phrase::<&str>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_load_without_positions() {
+        let json = r#"{
+            "indexed_pages": [],
+            "url2pages": {},
+            "index": {},
+            "invert_index": {},
+            "backlinks": {},
+            "outlinks": {}
+        }"#;
+
+        let index_store: IndexStore = serde_json::from_str(json).unwrap();
+        assert!(index_store.positions.is_empty());
+        assert!(index_store.case_sensitive_index.is_empty());
+    }
+
+    #[test]
+    fn test_search_paginated() {
+        let index_store = create_index_store();
+
+        let (first, total) = index_store.search_paginated(&["rust"], 0, 1);
+        assert_eq!(total, 2);
+        assert_eq!(first.len(), 1);
+        assert_eq!(
+            first[0].url,
+            Url::parse("https://example.com/page1").unwrap()
+        );
+
+        let (second, total) = index_store.search_paginated(&["rust"], 1, 1);
+        assert_eq!(total, 2);
+        assert_eq!(second.len(), 1);
+        assert_eq!(
+            second[0].url,
+            Url::parse("https://example.com/page2").unwrap()
+        );
+
+        // Same query, same pages
+        assert_eq!(index_store.search_paginated(&["rust"], 0, 1).0, first);
+    }
+
+    #[test]
+    fn test_search_paginated_offset_past_end() {
+        let index_store = create_index_store();
+
+        let (pages, total) = index_store.search_paginated(&["rust"], 10, 5);
+        assert!(pages.is_empty());
+        assert_eq!(total, 2);
+    }
+
+    #[test]
+    fn test_search_by_tfidf() {
+        let index_store = create_index_store();
+
+        // Both pages contain "rust", but page2 has fewer words so its term frequency is higher
+        let results = index_store.search_by_tfidf(&["rust"], 0.0);
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results[0].0.url,
+            Url::parse("https://example.com/page2").unwrap()
+        );
+        assert!(results.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert!(
+            results
+                .iter()
+                .all(|(page, score)| page.rank == Some(*score))
+        );
+
+        // With a heavy backlink weight, the more popular page1 wins
+        let results = index_store.search_by_tfidf(&["rust"], 10.0);
+        assert_eq!(
+            results[0].0.url,
+            Url::parse("https://example.com/page1").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_search_by_tfidf_word_in_every_page() {
+        let mut index_store = IndexStore::default();
+
+        for path in ["a", "b"] {
+            let page = PageBuilder::default()
+                .url(format!("https://example.com/{path}").parse().unwrap())
+                .build()
+                .unwrap();
+            index_store.store(&page, &[], &["common"], &[]);
+        }
+
+        let results = index_store.search_by_tfidf(&["common"], 0.0);
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(_, score)| *score == 0.0));
+    }
+
+    #[test]
+    fn test_search_by_tfidf_empty_input() {
+        let index_store = create_index_store();
+
+        assert!(index_store.search_by_tfidf::<&str>(&[], 1.0).is_empty());
+        assert!(
+            index_store
+                .search_by_tfidf(&["nonexistent"], 1.0)
+                .is_empty()
+        );
+    }
+
     #[test]
     fn test_save_and_load() {
         let mut store = create_index_store();
@@ -572,6 +3335,176 @@ mod tests {
         fs::remove_file(temp_path).expect("Failed to delete temp file");
     }
 
+    #[test]
+    fn test_remove_case_sensitive_after_load() {
+        let mut store = IndexStore::default().with_case_sensitive(true);
+        let url: Url = "https://example.com/rust".parse().unwrap();
+        let page = PageBuilder::default().url(url.clone()).build().unwrap();
+        store.store(&page, &["Rust"], &["Crab"], &[]);
+
+        let temp_path = ".test_index_store_case_sensitive.json";
+        store.filepath = path::absolute(temp_path).unwrap();
+        store.save().expect("Failed to save index");
+
+        let mut loaded_store = IndexStore::load(temp_path).expect("Failed to load index");
+        fs::remove_file(temp_path).expect("Failed to delete temp file");
+
+        assert!(loaded_store.remove(&url));
+        assert!(loaded_store.case_sensitive_index.is_empty());
+        assert!(loaded_store.case_sensitive_words.is_empty());
+        assert!(loaded_store.indexed_pages.is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load_binary() {
+        let mut store = create_index_store();
+        let temp_path = ".test_index_store.bin";
+        store.filepath = path::absolute(temp_path).unwrap();
+
+        let saved_size = store.save().expect("Failed to save index");
+        assert!(saved_size > 0);
+
+        // Binary files are not JSON
+        let bytes = fs::read(temp_path).expect("Failed to read temp file");
+        assert!(serde_json::from_slice::<IndexStore>(&bytes).is_err());
+
+        let loaded_store = IndexStore::load(temp_path).expect("Failed to load index");
+        assert_eq!(loaded_store.indexed_pages.len(), store.indexed_pages.len());
+        assert_eq!(loaded_store.index, store.index);
+        assert_eq!(loaded_store.backlinks, store.backlinks);
+
+        // Cleanup the temp file
+        fs::remove_file(temp_path).expect("Failed to delete temp file");
+    }
+
+    #[test]
+    fn test_load_old_binary() {
+        let store = create_index_store();
+        let temp_path = ".test_index_store_old.bin";
+
+        // Saved before binary files had a header
+        fs::write(temp_path, bincode::serialize(&store).unwrap()).unwrap();
+        let error = IndexStore::load(temp_path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // Saved by a later release
+        let mut bytes = BINARY_MAGIC.to_vec();
+        bytes.extend_from_slice(&(BINARY_VERSION + 1).to_le_bytes());
+        bytes.extend(bincode::serialize(&store).unwrap());
+        fs::write(temp_path, bytes).unwrap();
+        let error = IndexStore::load(temp_path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        fs::remove_file(temp_path).expect("Failed to delete temp file");
+    }
+
+    #[test]
+    fn test_save_and_load_gzip() {
+        let mut store = create_index_store();
+        let temp_path = ".test_index_store.json.gz";
+        store.filepath = path::absolute(temp_path).unwrap();
+
+        let saved_size = store.save().expect("Failed to save index");
+
+        // Starts with the gzip magic bytes
+        let bytes = fs::read(temp_path).expect("Failed to read temp file");
+        assert_eq!(bytes.len(), saved_size);
+        assert_eq!(bytes[..2], [0x1f, 0x8b]);
+        assert_eq!(store.stats().size_bytes, saved_size);
+
+        let loaded_store = IndexStore::load(&store.filepath).expect("Failed to load index");
+        assert_eq!(loaded_store, store);
+
+        // Cleanup the temp file
+        fs::remove_file(temp_path).expect("Failed to delete temp file");
+    }
+
+    #[test]
+    fn test_export_import_jsonl() {
+        let mut store = create_index_store().with_case_sensitive(true);
+        let page = PageBuilder::default()
+            .url("https://example.com/page4".parse().unwrap())
+            .build()
+            .unwrap();
+        store.store(
+            &page,
+            &["Crabs"],
+            &["Rust", "crabs"],
+            &["https://example.com/page1".parse().unwrap()],
+        );
+
+        let mut backup = Vec::new();
+        assert_eq!(store.export_jsonl(&mut backup).unwrap(), 4);
+        assert_eq!(backup.iter().filter(|&&byte| byte == b'\n').count(), 4);
+
+        // Like the other options, case sensitivity isn't part of the export
+        let imported = IndexStore::import_jsonl(backup.as_slice())
+            .unwrap()
+            .with_case_sensitive(true);
+        assert_eq!(imported, store);
+
+        for words in [&["rust"][..], &["programming", "tutorial"], &["crabs"]] {
+            assert_eq!(imported.search(words), store.search(words));
+            assert_eq!(
+                imported.search_by_relevance(words, 10),
+                store.search_by_relevance(words, 10)
+            );
+        }
+        assert_eq!(
+            imported.search_case_sensitive(&["Rust"]),
+            store.search_case_sensitive(&["Rust"])
+        );
+    }
+
+    #[test]
+    fn test_import_jsonl_invalid_line() {
+        let mut backup = Vec::new();
+        create_index_store().export_jsonl(&mut backup).unwrap();
+        backup.extend_from_slice(b"\n{\"page\": 1}\n");
+
+        let error = IndexStore::import_jsonl(backup.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("line 5"), "{}", error);
+    }
+
+    #[test]
+    fn test_load_detects_format() {
+        let mut store = create_index_store();
+        let temp_path = ".test_index_store_detect.json";
+        store.filepath = path::absolute(temp_path).unwrap();
+
+        // Binary content behind a `.json` extension
+        store
+            .save_as(StorageFormat::Binary)
+            .expect("Failed to save index");
+
+        let loaded_store = IndexStore::load(temp_path).expect("Failed to load index");
+        assert_eq!(loaded_store.index, store.index);
+
+        // Cleanup the temp file
+        fs::remove_file(temp_path).expect("Failed to delete temp file");
+    }
+
+    #[test]
+    fn test_interrupted_save_keeps_original() {
+        let mut store = create_index_store();
+        let temp_path = ".test_index_store_interrupted.json";
+        store.filepath = path::absolute(temp_path).unwrap();
+
+        store.save().expect("Failed to save index");
+
+        // Simulate a crash after a partial write to the temporary file
+        let partial_path = store.temp_filepath();
+        fs::write(&partial_path, "{\"indexed_pages\": [").unwrap();
+
+        let loaded_store = IndexStore::load(temp_path).expect("Original index is corrupted");
+        assert_eq!(loaded_store.indexed_pages.len(), store.indexed_pages.len());
+
+        // Cleanup the temp files
+        fs::remove_file(partial_path).expect("Failed to delete partial file");
+        fs::remove_file(temp_path).expect("Failed to delete temp file");
+    }
+
     #[test]
     fn test_load_nonexistent_file() {
         let nonexistent_path = "nonexistent_file.json";
@@ -8,12 +8,17 @@ pub mod address;
 pub mod barrel;
 pub mod fishfish;
 pub mod gateway;
+pub mod host_limiter;
 pub mod index_store;
 pub mod interactive;
 pub mod logger;
 pub mod page;
+pub mod rate_limiter;
+pub mod robots;
 pub mod serde;
 pub mod settings;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod top_searches;
 pub mod url;
 
@@ -27,6 +32,14 @@ enum GoogolStatus {
     AlreadyIndexedUrl = 3,
     /// No barrels where online on the time of request
     UnavailableBarrels = 4,
+    /// The url's host is blacklisted, or not whitelisted
+    BlacklistedUrl = 5,
+    /// The url is deeper than the maximum crawl depth
+    MaxDepthExceeded = 6,
+    /// The url's host is categorized as phishing or malware by FishFish
+    MaliciousUrl = 7,
+    /// The queue holds as many urls as it may
+    QueueFull = 8,
 }
 
 pub mod proto {
@@ -1,12 +1,15 @@
-//! A set of macros for convenient variable debugging and logging with different styles.
+//! A set of macros for convenient variable debugging and logging with different styles, and
+//! the logger setup shared by the components.
 //!
-//! These macros generate debug, error, and info logs for a given variable,
+//! These macros generate trace, debug, info, warn, and error logs for a given variable,
 //! optionally allowing the caller to specify the formatting style.
 //!
 //! # Macros
 //! - `debugv!` : Logs a variable at debug level.
 //! - `errorv!` : Logs a variable at error level.
 //! - `infov!`   : Logs a variable at info level.
+//! - `warnv!`   : Logs a variable at warn level.
+//! - `tracev!`  : Logs a variable at trace level.
 //!
 //! # Usage
 //! ```
@@ -32,6 +35,56 @@
 //! # Note
 //! These macros use `stringify!` to print the variable's name, followed by its value.
 
+use flexi_logger::{
+    Age, Cleanup, Criterion, Duplicate, FileSpec, FlexiLoggerError, Logger, LoggerHandle, Naming,
+    colored_default_format, detailed_format,
+};
+use std::path::Path;
+
+/// Number of rotated log files kept. Older ones are deleted.
+pub const LOG_FILES_KEPT: usize = 7;
+
+/// Sets up logging to stderr and, if `log_file` is set, to that file as well.
+///
+/// The file is rotated daily: logs are written to `<name>_rCURRENT.<extension>`, which is
+/// renamed with the rotation's timestamp every day. Only the last [`LOG_FILES_KEPT`] rotated
+/// files are kept.
+///
+/// The `RUST_LOG` environment variable, if set, takes precedence over `log_level`.
+///
+/// # Arguments
+/// * `log_file` - File logs are also written to.
+/// * `log_level` - Minimum level logged, or a specification like `info,googol::gateway=debug`.
+///
+/// # Returns
+/// The handle of the logger, which must be kept alive for logs to be written.
+///
+/// # Errors
+/// Returns a `FlexiLoggerError` if `log_level` is invalid, the file can't be created or a
+/// logger was already set up.
+pub fn init_logging(
+    log_file: Option<&Path>,
+    log_level: &str,
+) -> Result<LoggerHandle, FlexiLoggerError> {
+    let logger = Logger::try_with_env_or_str(log_level)?.format_for_stderr(colored_default_format);
+
+    let logger = match log_file {
+        Some(log_file) => logger
+            .log_to_file(FileSpec::try_from(log_file)?)
+            .format_for_files(detailed_format)
+            .rotate(
+                Criterion::Age(Age::Day),
+                Naming::Timestamps,
+                Cleanup::KeepLogFiles(LOG_FILES_KEPT),
+            )
+            .append()
+            .duplicate_to_stderr(Duplicate::All),
+        None => logger.log_to_stderr(),
+    };
+
+    logger.start()
+}
+
 /// Logs a variable at the debug level with optional styling.
 ///
 /// # Arguments
@@ -130,3 +183,92 @@ macro_rules! infov {
         info!("{} = {:?}", stringify!($var), $var);
     };
 }
+
+/// Logs a variable at the warn level with optional styling.
+///
+/// # Arguments
+/// - `var`: The variable to log.
+/// - `style` (optional): The style of formatting (`debug` or `display`).
+///
+/// # Examples
+/// ```
+/// use googol::warnv;
+/// use log::warn;
+///
+/// pretty_env_logger::init();
+///
+/// let retries = 3;
+/// warnv!(retries); // Uses Debug formatting
+/// warnv!(retries, display); // Uses Display formatting
+/// warnv!(retries, debug); // Uses pretty Debug formatting
+/// ```
+#[macro_export]
+macro_rules! warnv {
+    // Case when style is provided: e.g., warnv!(a, debug);
+    ($var:expr, debug) => {
+        warn!("{} = {:#?}", stringify!($var), $var);
+    };
+    ($var:expr, display) => {
+        warn!("{} = {}", stringify!($var), $var);
+    };
+    // Default case: if style is not specified, use Debug
+    ($var:expr) => {
+        warn!("{} = {:?}", stringify!($var), $var);
+    };
+}
+
+/// Logs a variable at the trace level with optional styling.
+///
+/// # Arguments
+/// - `var`: The variable to log.
+/// - `style` (optional): The style of formatting (`debug` or `display`).
+///
+/// # Examples
+/// ```
+/// use googol::tracev;
+/// use log::trace;
+///
+/// pretty_env_logger::init();
+///
+/// let bytes = [0x47, 0x6f];
+/// tracev!(bytes); // Uses Debug formatting
+/// tracev!(bytes.len(), display); // Uses Display formatting
+/// tracev!(bytes, debug); // Uses pretty Debug formatting
+/// ```
+#[macro_export]
+macro_rules! tracev {
+    // Case when style is provided: e.g., tracev!(a, debug);
+    ($var:expr, debug) => {
+        trace!("{} = {:#?}", stringify!($var), $var);
+    };
+    ($var:expr, display) => {
+        trace!("{} = {}", stringify!($var), $var);
+    };
+    // Default case: if style is not specified, use Debug
+    ($var:expr) => {
+        trace!("{} = {:?}", stringify!($var), $var);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_init_logging_to_file() {
+        let directory = std::env::temp_dir().join(format!("googol-logs-{}", std::process::id()));
+
+        // Only this module logs, so other tests don't write to stderr
+        let handle =
+            init_logging(Some(&directory.join("test.log")), "off,googol::logger=info").unwrap();
+        log::info!("Logged to a file");
+        handle.flush();
+
+        let logs = fs::read_to_string(directory.join("test_rCURRENT.log")).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(logs.contains("Logged to a file"), "{}", logs);
+        assert!(init_logging(None, "info").is_err(), "Logger already set up");
+    }
+}
@@ -18,7 +18,11 @@ use crate::{fishfish::domain::category::FishDomainCategory, proto};
 use chrono::{DateTime, Utc};
 use derive_builder::Builder;
 use serde::{Deserialize, Serialize};
-use std::cmp::Ordering;
+use sha2::{Digest, Sha256};
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
 use url::Url;
 
 pub mod web_server;
@@ -64,8 +68,7 @@ pub mod web_server;
 /// let deserialized: Page = serde_json::from_str(&json).unwrap();
 /// assert_eq!(page, deserialized);
 /// ```
-#[derive(Debug, Clone, Eq, Hash, Builder, Serialize, Deserialize)]
-#[allow(clippy::derived_hash_with_manual_eq)]
+#[derive(Debug, Clone, Builder, Serialize, Deserialize)]
 pub struct Page {
     /// The URL of the page.
     pub url: Url,
@@ -84,6 +87,109 @@ pub struct Page {
     /// Fish Domain category
     #[builder(setter(into, strip_option), default)]
     pub category: Option<FishDomainCategory>,
+    /// Relevance score of the page in a search, higher first. Not saved, as it only makes
+    /// sense for the search that computed it.
+    #[builder(setter(into, strip_option), default)]
+    #[serde(skip)]
+    pub rank: Option<f64>,
+    /// ISO 639-1 code of the page's language, e.g. `en`, if it was detected.
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Hash of the page's body text, see [`content_hash`]. Pages with the same content, such
+    /// as mirrors, share it.
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default)]
+    pub content_hash: Option<String>,
+}
+
+/// Hashes the text of a page, so that pages with the same content get the same hash.
+///
+/// The text is normalized first, collapsing whitespace and lowercasing it, so differences in
+/// formatting alone don't change the hash.
+///
+/// # Arguments
+///
+/// * `text` - The body text of the page.
+///
+/// # Returns
+///
+/// The hex-encoded SHA-256 of the normalized text, or `None` if there is no text, as pages
+/// without any aren't duplicates of each other.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::page::content_hash;
+///
+/// assert_eq!(content_hash("Hello,\n  World"), content_hash("hello, world"));
+/// assert_ne!(content_hash("Hello, World"), content_hash("Hello World"));
+/// assert_eq!(content_hash(" \n "), None);
+/// ```
+pub fn content_hash(text: &str) -> Option<String> {
+    let normalized = text
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if normalized.is_empty() {
+        return None;
+    }
+
+    Some(format!("{:x}", Sha256::digest(normalized.as_bytes())))
+}
+
+impl PageBuilder {
+    /// Sets the summary to the beginning of `text`, cut at a word boundary.
+    ///
+    /// Whitespace is collapsed, and a summary longer than `max_len` characters is truncated
+    /// and ends with an ellipsis. Empty text leaves the summary unset.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to summarize, e.g. the body of the page.
+    /// * `max_len` - Maximum number of characters kept from `text`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::page::PageBuilder;
+    ///
+    /// let page = PageBuilder::default()
+    ///     .url("https://example.com".parse().unwrap())
+    ///     .summarize_from("The quick brown fox jumps over the lazy dog", 20)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(page.summary.as_deref(), Some("The quick brown fox…"));
+    /// ```
+    pub fn summarize_from(&mut self, text: &str, max_len: usize) -> &mut Self {
+        if let Some(summary) = summarize(text, max_len) {
+            self.summary(summary);
+        }
+        self
+    }
+}
+
+/// Truncates `text` to at most `max_len` characters at a word boundary, adding an ellipsis if
+/// anything was cut. Returns `None` if nothing is left.
+fn summarize(text: &str, max_len: usize) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if text.chars().count() <= max_len {
+        return (!text.is_empty()).then_some(text);
+    }
+
+    // Keep the next character too, so a cut right before a space keeps the last word
+    let prefix: String = text.chars().take(max_len + 1).collect();
+    let summary = match prefix.rfind(' ') {
+        Some(end) => prefix[..end].to_string(),
+        // A single word longer than `max_len`
+        None => prefix.chars().take(max_len).collect(),
+    };
+
+    (!summary.is_empty()).then(|| format!("{}…", summary))
 }
 
 impl From<proto::Page> for Page {
@@ -109,6 +215,15 @@ impl From<proto::Page> for Page {
             },
             timestamp: Utc::now(),
             category: FishDomainCategory::from_string(value.category),
+            rank: value.rank,
+            language: match value.language.len() {
+                0 => None,
+                _ => Some(value.language),
+            },
+            content_hash: match value.content_hash.len() {
+                0 => None,
+                _ => Some(value.content_hash),
+            },
         }
     }
 }
@@ -125,6 +240,9 @@ impl From<Page> for proto::Page {
                 Some(fish_category) => fish_category.to_string(),
                 None => "".to_string(),
             },
+            rank: val.rank,
+            language: val.language.unwrap_or_default(),
+            content_hash: val.content_hash.unwrap_or_default(),
         }
     }
 }
@@ -136,6 +254,16 @@ impl PartialEq for Page {
     }
 }
 
+impl Eq for Page {}
+
+impl Hash for Page {
+    /// Hashes the URL and date, the fields compared by `PartialEq`.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.url.hash(state);
+        self.timestamp.date_naive().hash(state);
+    }
+}
+
 impl PartialOrd for Page {
     /// Orders pages based on their timestamps.
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
@@ -148,6 +276,7 @@ impl PartialOrd for Page {
 mod tests {
     use super::*;
     use chrono::TimeZone;
+    use std::collections::HashSet;
 
     #[test]
     fn test_into_proto() {
@@ -155,6 +284,7 @@ mod tests {
             .url("https://google.com".parse().unwrap())
             .title("example")
             .summary("summary")
+            .content_hash("hash")
             .build()
             .unwrap();
 
@@ -164,6 +294,9 @@ mod tests {
             summary: "summary".to_string(),
             icon: "".to_string(),
             category: "".to_string(),
+            rank: None,
+            language: "".to_string(),
+            content_hash: "hash".to_string(),
         };
 
         let expected_proto_page: proto::Page = page.into();
@@ -187,11 +320,50 @@ mod tests {
             summary: "summary".to_string(),
             icon: "".to_string(),
             category: "".to_string(),
+            rank: None,
+            language: "".to_string(),
+            content_hash: "".to_string(),
         };
 
         assert_eq!(Page::from(proto_page), page);
     }
 
+    #[test]
+    fn test_summarize_from() {
+        let summarize_from = |text: &str, max_len: usize| {
+            PageBuilder::default()
+                .url("https://example.com".parse().unwrap())
+                .summarize_from(text, max_len)
+                .build()
+                .unwrap()
+                .summary
+        };
+
+        // Cut at the last word that fits
+        assert_eq!(
+            summarize_from("The quick brown fox", 12).as_deref(),
+            Some("The quick…")
+        );
+        // Cut right before a space
+        assert_eq!(
+            summarize_from("The quick brown fox", 9).as_deref(),
+            Some("The quick…")
+        );
+        // A single long word is cut mid-word
+        assert_eq!(
+            summarize_from("Supercalifragilistic", 5).as_deref(),
+            Some("Super…")
+        );
+        // Short text is kept whole, whitespace collapsed
+        assert_eq!(
+            summarize_from("  The quick\n\tbrown fox ", 19).as_deref(),
+            Some("The quick brown fox")
+        );
+        assert_eq!(summarize_from("", 10), None);
+        assert_eq!(summarize_from(" \n ", 10), None);
+        assert_eq!(summarize_from("The quick brown fox", 0), None);
+    }
+
     #[test]
     fn test_serialization() {
         let page = PageBuilder::default()
@@ -250,6 +422,31 @@ mod tests {
         assert_ne!(page1, page3);
     }
 
+    #[test]
+    fn test_hash_matches_equality() {
+        let date = Utc.with_ymd_and_hms(2025, 6, 1, 8, 0, 0).unwrap();
+
+        let page1 = PageBuilder::default()
+            .url("https://example.com".parse().unwrap())
+            .title("Title")
+            .timestamp(date)
+            .build()
+            .unwrap();
+        let page2 = PageBuilder::default()
+            .url("https://example.com".parse().unwrap())
+            .title("Another Title")
+            .timestamp(date + chrono::Duration::hours(8))
+            .build()
+            .unwrap();
+        assert_eq!(page1, page2);
+
+        let mut pages = HashSet::new();
+        assert!(pages.insert(page1.clone()));
+        assert!(!pages.insert(page2.clone()));
+        assert!(pages.contains(&page2));
+        assert_eq!(pages.len(), 1);
+    }
+
     #[test]
     fn test_ordering() {
         let ts1 = Utc::now() - chrono::Duration::seconds(10);
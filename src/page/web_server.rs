@@ -18,12 +18,16 @@ use serde::Serialize;
 ///     summary: "An example page".to_string(),
 ///     icon: "icon.png".to_string(),
 ///     category: "".to_string(),
+///     rank: None,
+///     language: "en".to_string(),
+///     content_hash: "".to_string(),
 /// };
 ///
 /// // Convert from proto::Page to Page
 /// let page: Page = proto_page.into();
 /// assert_eq!(page.href, "https://example.com");
 /// assert_eq!(page.title.as_deref(), Some("Example"));
+/// assert_eq!(page.language.as_deref(), Some("en"));
 /// ```
 ///
 /// Converting a `Page` into its protocol buffer representation:
@@ -37,6 +41,8 @@ use serde::Serialize;
 ///     summary: Some("The Rust Programming Language".to_string()),
 ///     icon: None,
 ///     category: None,
+///     rank: Some(3.0),
+///     language: None,
 /// };
 ///
 /// // Convert to proto::Page
@@ -55,6 +61,10 @@ pub struct Page {
     pub icon: Option<String>,
     /// Fish domain category
     pub category: Option<FishDomainCategory>,
+    /// Relevance score of the page in the search, higher first. `null` if unranked.
+    pub rank: Option<f64>,
+    /// ISO 639-1 code of the page's language, e.g. `en`. `null` if it wasn't detected.
+    pub language: Option<String>,
 }
 
 impl From<proto::Page> for Page {
@@ -83,6 +93,11 @@ impl From<proto::Page> for Page {
                 _ => Some(value.icon),
             },
             category: FishDomainCategory::from_string(value.category),
+            rank: value.rank,
+            language: match value.language.len() {
+                0 => None,
+                _ => Some(value.language),
+            },
         }
     }
 }
@@ -103,6 +118,46 @@ impl From<Page> for proto::Page {
                 Some(category) => category.to_string(),
                 None => "".to_string(),
             },
+            rank: val.rank,
+            language: val.language.unwrap_or_default(),
+            content_hash: "".to_string(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_serialization() {
+        let mut page = Page {
+            href: "https://example.com".to_string(),
+            title: None,
+            summary: None,
+            icon: None,
+            category: None,
+            rank: None,
+            language: None,
+        };
+
+        // Unranked pages are told apart from pages ranked 0
+        let json = serde_json::to_value(&page).unwrap();
+        assert!(json["rank"].is_null());
+
+        page.rank = Some(0.0);
+        let json = serde_json::to_value(&page).unwrap();
+        assert_eq!(json["rank"], 0.0);
+    }
+
+    #[test]
+    fn test_rank_from_proto() {
+        let proto_page = proto::Page {
+            url: "https://example.com".to_string(),
+            rank: Some(2.5),
+            ..Default::default()
+        };
+
+        assert_eq!(Page::from(proto_page).rank, Some(2.5));
+    }
+}
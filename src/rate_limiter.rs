@@ -0,0 +1,211 @@
+//! Per-client limits on the rate of requests, as token buckets.
+//!
+//! Each IP address gets a bucket holding up to `burst` tokens, refilled at
+//! `requests_per_second`. A request takes a token, or is rejected if the bucket is empty, so
+//! clients may send `burst` requests at once but no more than `requests_per_second` on average.
+//!
+//! IPv6 clients share the bucket of their /64 network, since a single host is usually given a
+//! whole /64 to pick addresses from.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use googol::rate_limiter::RateLimiter;
+//! use std::net::{IpAddr, Ipv4Addr};
+//!
+//! let limiter = RateLimiter::new(1., 2);
+//! let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+//!
+//! assert!(limiter.check(ip).is_ok());
+//! assert!(limiter.check(ip).is_ok());
+//! // The burst is exhausted, until a token is refilled a second later
+//! assert!(limiter.check(ip).is_err());
+//! ```
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv6Addr},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Number of buckets kept before the full ones are forgotten.
+const MIN_CLEANUP_THRESHOLD: usize = 1024;
+
+/// Length of the IPv6 prefix of the addresses sharing a bucket.
+const IPV6_PREFIX_LENGTH: u32 = 64;
+
+/// The address whose bucket `ip` takes tokens from: itself for IPv4, its /64 network for IPv6.
+/// IPv4-mapped IPv6 addresses are treated as IPv4.
+fn bucket_key(ip: IpAddr) -> IpAddr {
+    match ip.to_canonical() {
+        IpAddr::V6(ip) => {
+            let mask = u128::MAX << (128 - IPV6_PREFIX_LENGTH);
+            IpAddr::V6(Ipv6Addr::from_bits(ip.to_bits() & mask))
+        }
+        ip => ip,
+    }
+}
+
+/// Tokens of a single IP address, or IPv6 network.
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    /// When the tokens were last refilled.
+    refilled: Instant,
+}
+
+#[derive(Debug)]
+struct Buckets {
+    by_ip: HashMap<IpAddr, Bucket>,
+    /// Number of buckets after which the full ones are forgotten, so memory only grows with
+    /// the clients that were recently limited.
+    cleanup_threshold: usize,
+}
+
+/// Limits the rate of requests of each IP address, or IPv6 /64 network. Shared between threads.
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests_per_second: f64,
+    burst: f64,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    /// Creates a new `RateLimiter`.
+    ///
+    /// # Arguments
+    ///
+    /// * `requests_per_second` - Rate tokens are refilled at. Must be positive.
+    /// * `burst` - Maximum number of tokens of a bucket. At least 1.
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        Self {
+            requests_per_second,
+            burst: f64::from(burst.max(1)),
+            buckets: Mutex::new(Buckets {
+                by_ip: HashMap::new(),
+                cleanup_threshold: MIN_CLEANUP_THRESHOLD,
+            }),
+        }
+    }
+
+    /// Takes a token from the bucket of `ip`, shared with its /64 network if IPv6.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the request is allowed, or `Err` with the time to wait until it would be.
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        if buckets.by_ip.len() >= buckets.cleanup_threshold {
+            buckets
+                .by_ip
+                .retain(|_, bucket| self.tokens(bucket, now) < self.burst);
+            buckets.cleanup_threshold = (buckets.by_ip.len() * 2).max(MIN_CLEANUP_THRESHOLD);
+        }
+
+        let bucket = buckets.by_ip.entry(bucket_key(ip)).or_insert(Bucket {
+            tokens: self.burst,
+            refilled: now,
+        });
+
+        bucket.tokens = self.tokens(bucket, now);
+        bucket.refilled = now;
+
+        if bucket.tokens >= 1. {
+            bucket.tokens -= 1.;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1. - bucket.tokens) / self.requests_per_second,
+            ))
+        }
+    }
+
+    /// Tokens of `bucket` once refilled up to `now`.
+    fn tokens(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.refilled).as_secs_f64();
+
+        (bucket.tokens + elapsed * self.requests_per_second).min(self.burst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, last))
+    }
+
+    #[test]
+    fn test_burst_then_refill() {
+        let limiter = RateLimiter::new(2., 3);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at(ip(1), start).is_ok());
+        }
+        assert_eq!(
+            limiter.check_at(ip(1), start),
+            Err(Duration::from_millis(500))
+        );
+
+        // Other addresses have their own bucket
+        assert!(limiter.check_at(ip(2), start).is_ok());
+
+        // A token is refilled every half second
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.check_at(ip(1), later).is_ok());
+        assert!(limiter.check_at(ip(1), later).is_err());
+
+        // Never more than the burst, however long the wait
+        let much_later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.check_at(ip(1), much_later).is_ok());
+        }
+        assert!(limiter.check_at(ip(1), much_later).is_err());
+    }
+
+    #[test]
+    fn test_ipv6_network_shares_bucket() {
+        let limiter = RateLimiter::new(1., 1);
+        let start = Instant::now();
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+
+        assert!(limiter.check_at(ip("2001:db8:0:1::1"), start).is_ok());
+        // Any other address of the /64 network is limited too
+        assert!(limiter.check_at(ip("2001:db8:0:1:ffff::2"), start).is_err());
+        assert!(limiter.check_at(ip("2001:db8:0:2::1"), start).is_ok());
+
+        // IPv4-mapped addresses are limited as IPv4
+        assert!(limiter.check_at(ip("10.0.0.1"), start).is_ok());
+        assert!(limiter.check_at(ip("::ffff:10.0.0.1"), start).is_err());
+        assert!(limiter.check_at(ip("::ffff:10.0.0.2"), start).is_ok());
+    }
+
+    #[test]
+    fn test_forgets_full_buckets() {
+        let limiter = RateLimiter::new(1., 1);
+        let start = Instant::now();
+
+        for i in 0..MIN_CLEANUP_THRESHOLD {
+            let ip = IpAddr::V4(Ipv4Addr::from(i as u32));
+            assert!(limiter.check_at(ip, start).is_ok());
+        }
+        assert_eq!(
+            limiter.buckets.lock().unwrap().by_ip.len(),
+            MIN_CLEANUP_THRESHOLD
+        );
+
+        // Every bucket is full again a second later
+        let later = start + Duration::from_secs(1);
+        assert!(limiter.check_at(ip(1), later).is_ok());
+        assert_eq!(limiter.buckets.lock().unwrap().by_ip.len(), 1);
+    }
+}
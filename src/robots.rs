@@ -0,0 +1,648 @@
+//! Parsing and caching of `robots.txt` files.
+//!
+//! Only the `User-agent`, `Allow`, `Disallow` and `Crawl-delay` directives are supported, including
+//! the `*` and `$` wildcards in paths. When several rules match a path, the longest one wins, with
+//! `Allow` winning ties.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use googol::robots::Robots;
+//! use url::Url;
+//!
+//! let robots = Robots::parse("User-agent: *\nDisallow: /private/", "googol");
+//!
+//! assert!(robots.is_allowed(&Url::parse("https://example.com/public").unwrap()));
+//! assert!(!robots.is_allowed(&Url::parse("https://example.com/private/page").unwrap()));
+//! ```
+
+use log::{debug, warn};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::OnceCell;
+use url::Url;
+
+/// An `Allow` or `Disallow` rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+impl Rule {
+    /// Checks if `path` matches the rule's pattern.
+    ///
+    /// `*` matches any sequence of characters, and a trailing `$` anchors the pattern to the
+    /// end of the path. Otherwise, the pattern matches any path it is a prefix of.
+    fn matches(&self, path: &str) -> bool {
+        let (pattern, anchored) = match self.pattern.strip_suffix('$') {
+            Some(pattern) => (pattern, true),
+            None => (self.pattern.as_str(), false),
+        };
+
+        let mut parts = pattern.split('*');
+        let first = parts.next().unwrap_or_default();
+
+        if !path.starts_with(first) {
+            return false;
+        }
+
+        let mut position = first.len();
+        let rest: Vec<&str> = parts.collect();
+
+        for (i, part) in rest.iter().enumerate() {
+            // An anchored pattern must end with its last part
+            if anchored && i == rest.len() - 1 {
+                return path.len() >= position + part.len() && path.ends_with(part);
+            }
+
+            match path[position..].find(part) {
+                Some(index) => position += index + part.len(),
+                None => return false,
+            }
+        }
+
+        !anchored || position == path.len()
+    }
+}
+
+/// The directives of a group of user agents.
+#[derive(Debug, Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Group {
+    /// Checks if the group has no directives yet, so another `User-agent` line joins it.
+    fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.crawl_delay.is_none()
+    }
+}
+
+/// The rules of a `robots.txt` file that apply to a given user agent.
+///
+/// The default `Robots` allows everything, as when a host has no `robots.txt` file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Robots {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// Parses the content of a `robots.txt` file, keeping only the rules for `user_agent`.
+    ///
+    /// The group naming the user agent is used if there is one, otherwise the `*` group.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The content of the `robots.txt` file.
+    /// * `user_agent` - The user agent the downloader identifies as.
+    ///
+    /// # Returns
+    ///
+    /// The rules applying to the user agent.
+    pub fn parse(content: &str, user_agent: &str) -> Self {
+        let user_agent = user_agent.to_lowercase();
+
+        // Groups of user agents sharing the same rules
+        let mut groups: Vec<Group> = vec![];
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+
+            match key.trim().to_lowercase().as_str() {
+                "user-agent" => match groups.last_mut() {
+                    // Consecutive user agents share a group
+                    Some(group) if group.is_empty() => group.agents.push(value.to_lowercase()),
+                    _ => groups.push(Group {
+                        agents: vec![value.to_lowercase()],
+                        ..Default::default()
+                    }),
+                },
+                directive @ ("allow" | "disallow") => {
+                    // An empty `Disallow` allows everything
+                    if value.is_empty() {
+                        continue;
+                    }
+
+                    if let Some(group) = groups.last_mut() {
+                        group.rules.push(Rule {
+                            allow: directive == "allow",
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    let delay = value
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+
+                    match (groups.last_mut(), delay) {
+                        (Some(group), Some(delay)) => group.crawl_delay = Some(delay),
+                        (_, None) => debug!("Ignoring invalid Crawl-delay: {}", value),
+                        (None, _) => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let specific = groups
+            .iter()
+            .filter_map(|group| {
+                group
+                    .agents
+                    .iter()
+                    .filter(|agent| *agent != "*" && user_agent.contains(agent.as_str()))
+                    .map(|agent| agent.len())
+                    .max()
+                    .map(|len| (len, group))
+            })
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, group)| group);
+
+        let group = specific.or_else(|| {
+            groups
+                .iter()
+                .find(|group| group.agents.iter().any(|agent| agent == "*"))
+        });
+
+        match group {
+            Some(group) => Self {
+                rules: group.rules.clone(),
+                crawl_delay: group.crawl_delay,
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// Returns the minimum time to wait between two requests to the host, if the file sets a
+    /// `Crawl-delay`.
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+
+    /// Checks if the rules allow crawling `url`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to check. Only its path and query are considered.
+    ///
+    /// # Returns
+    ///
+    /// `true` if no rule disallows the URL, `false` otherwise.
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+
+        if path == "/robots.txt" {
+            return true;
+        }
+
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(&path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+/// Maximum number of bytes of a `robots.txt` file parsed. The rest of the file is ignored.
+pub const MAX_ROBOTS_BYTES: usize = 512 * 1024;
+/// Maximum number of origins whose rules are cached. Expired ones are evicted first, then the
+/// oldest.
+const MAX_ENTRIES: usize = 10_000;
+
+/// Rules of an origin, fetched by whichever task needs them first.
+#[derive(Debug)]
+struct Entry {
+    /// When the entry was created, its rules being fetched right after.
+    created: Instant,
+    robots: OnceCell<Arc<Robots>>,
+}
+
+/// A per host cache of `robots.txt` rules, refetched once older than a TTL.
+///
+/// Files are fetched without holding the cache, so a slow host only delays the tasks crawling
+/// it. Tasks needing the rules of an origin being fetched wait for that fetch instead of
+/// starting another.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::robots::RobotsCache;
+/// use std::time::Duration;
+///
+/// let cache = RobotsCache::new("googol", Duration::from_secs(3600), Duration::from_secs(30));
+/// ```
+#[derive(Debug)]
+pub struct RobotsCache {
+    client: reqwest::Client,
+    user_agent: String,
+    ttl: Duration,
+    /// Rules of each origin. Only locked to look entries up, never while fetching.
+    entries: Mutex<HashMap<String, Arc<Entry>>>,
+}
+
+impl RobotsCache {
+    /// Creates an empty cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent the downloader identifies as.
+    /// * `ttl` - How long the rules of a host are kept before being fetched again.
+    /// * `timeout` - Time a `robots.txt` fetch may take, after which the host allows everything.
+    pub fn new(user_agent: &str, ttl: Duration, timeout: Duration) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .user_agent(user_agent)
+                .timeout(timeout)
+                .build()
+                .unwrap_or_default(),
+            user_agent: user_agent.to_string(),
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Checks if the `robots.txt` of the URL's host allows crawling it, fetching the file if it
+    /// is not cached or has expired.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to check.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the URL may be crawled, `false` otherwise.
+    pub async fn is_allowed(&self, url: &Url) -> bool {
+        self.robots(url).await.is_allowed(url)
+    }
+
+    /// Returns the `Crawl-delay` of the URL's host, fetching its `robots.txt` if it is not cached
+    /// or has expired.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Any URL of the host.
+    ///
+    /// # Returns
+    ///
+    /// The minimum time between two requests to the host, or `None` if the host sets none.
+    pub async fn crawl_delay(&self, url: &Url) -> Option<Duration> {
+        self.robots(url).await.crawl_delay()
+    }
+
+    /// Returns the rules of the URL's host, fetching them if they are not cached or have
+    /// expired.
+    async fn robots(&self, url: &Url) -> Arc<Robots> {
+        let origin = url.origin().ascii_serialization();
+        let entry = self.entry(&origin);
+
+        let robots = entry
+            .robots
+            .get_or_init(|| async { Arc::new(self.fetch(&origin).await) })
+            .await;
+
+        Arc::clone(robots)
+    }
+
+    /// Returns the entry of `origin`, creating an empty one if there is none or it has expired.
+    fn entry(&self, origin: &str) -> Arc<Entry> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(entry) = entries
+            .get(origin)
+            .filter(|entry| entry.created.elapsed() < self.ttl)
+        {
+            return Arc::clone(entry);
+        }
+
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, entry| entry.created.elapsed() < self.ttl);
+        }
+        if entries.len() >= MAX_ENTRIES
+            && let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.created)
+                .map(|(origin, _)| origin.clone())
+        {
+            entries.remove(&oldest);
+        }
+
+        let entry = Arc::new(Entry {
+            created: Instant::now(),
+            robots: OnceCell::new(),
+        });
+        entries.insert(origin.to_string(), Arc::clone(&entry));
+
+        entry
+    }
+
+    /// Fetches and parses the `robots.txt` of `origin`, up to [`MAX_ROBOTS_BYTES`].
+    ///
+    /// Hosts without a `robots.txt` file, or whose file cannot be fetched in time, allow
+    /// everything.
+    async fn fetch(&self, origin: &str) -> Robots {
+        let robots_url = format!("{}/robots.txt", origin);
+        debug!("Fetching {}", robots_url);
+
+        let mut response = match self.client.get(&robots_url).send().await {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => {
+                debug!("No robots.txt at {}: {}", robots_url, response.status());
+                return Robots::default();
+            }
+            Err(e) => {
+                warn!("Failed to fetch {}: {}", robots_url, e);
+                return Robots::default();
+            }
+        };
+
+        let mut content = Vec::new();
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    content.extend_from_slice(&chunk);
+
+                    if content.len() >= MAX_ROBOTS_BYTES {
+                        debug!("Ignoring {} past {} bytes", robots_url, MAX_ROBOTS_BYTES);
+                        content.truncate(MAX_ROBOTS_BYTES);
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("Failed to read {}: {}", robots_url, e);
+                    return Robots::default();
+                }
+            }
+        }
+
+        Robots::parse(&String::from_utf8_lossy(&content), &self.user_agent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = r#"
+        # Comments are ignored
+        User-agent: *
+        Disallow: /private/
+        Allow: /private/public
+        Disallow: /*.pdf$
+
+        User-agent: BadBot
+        User-agent: googol
+        Disallow: /googol-only/
+
+        User-agent: evil
+        Disallow: /
+
+        User-agent: slow
+        Crawl-delay: 2.5
+        User-agent: slower
+        Disallow: /slow/
+    "#;
+
+    fn url(path: &str) -> Url {
+        Url::parse("https://example.com")
+            .unwrap()
+            .join(path)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_wildcard_group() {
+        let robots = Robots::parse(ROBOTS, "other");
+
+        assert!(robots.is_allowed(&url("/")));
+        assert!(!robots.is_allowed(&url("/private/secret")));
+        // The longest matching rule wins
+        assert!(robots.is_allowed(&url("/private/public/page")));
+        assert!(!robots.is_allowed(&url("/docs/file.pdf")));
+        assert!(robots.is_allowed(&url("/docs/file.pdf.html")));
+    }
+
+    #[test]
+    fn test_specific_group() {
+        let robots = Robots::parse(ROBOTS, "Mozilla/5.0 (compatible; Googol/1.0)");
+
+        assert!(!robots.is_allowed(&url("/googol-only/page")));
+        // Rules of the `*` group don't apply anymore
+        assert!(robots.is_allowed(&url("/private/secret")));
+    }
+
+    #[test]
+    fn test_disallow_all() {
+        let robots = Robots::parse(ROBOTS, "evil");
+
+        assert!(!robots.is_allowed(&url("/")));
+        assert!(!robots.is_allowed(&url("/page?query=1")));
+        assert!(robots.is_allowed(&url("/robots.txt")));
+    }
+
+    #[test]
+    fn test_empty_allows_all() {
+        let robots = Robots::parse("User-agent: *\nDisallow:", "googol");
+
+        assert_eq!(robots, Robots::default());
+        assert!(robots.is_allowed(&url("/anything")));
+    }
+
+    #[test]
+    fn test_crawl_delay() {
+        assert_eq!(
+            Robots::parse(ROBOTS, "slow").crawl_delay(),
+            Some(Duration::from_millis(2500))
+        );
+        // A user agent after a `Crawl-delay` starts a new group
+        let robots = Robots::parse(ROBOTS, "slower");
+        assert_eq!(robots.crawl_delay(), None);
+        assert!(!robots.is_allowed(&url("/slow/page")));
+
+        assert_eq!(Robots::parse(ROBOTS, "googol").crawl_delay(), None);
+
+        for invalid in ["-1", "soon", ""] {
+            let content = format!("User-agent: *\nCrawl-delay: {}", invalid);
+
+            assert_eq!(Robots::parse(&content, "googol").crawl_delay(), None);
+        }
+    }
+
+    #[test]
+    fn test_rule_matches() {
+        let rule = |pattern: &str| Rule {
+            allow: false,
+            pattern: pattern.to_string(),
+        };
+
+        assert!(rule("/a").matches("/a/b"));
+        assert!(rule("/a*c").matches("/a/b/c/d"));
+        assert!(rule("/a*c$").matches("/a/b/c"));
+        assert!(!rule("/a*c$").matches("/a/b/c/d"));
+        assert!(rule("/a$").matches("/a"));
+        assert!(!rule("/a$").matches("/a/"));
+        assert!(rule("*.php").matches("/index.php?q=1"));
+    }
+
+    /// Caches `robots` for `origin`, as if fetched at `created`.
+    fn insert(cache: &RobotsCache, origin: &str, created: Instant, robots: Robots) {
+        cache.entries.lock().unwrap().insert(
+            origin.to_string(),
+            Arc::new(Entry {
+                created,
+                robots: OnceCell::new_with(Some(Arc::new(robots))),
+            }),
+        );
+    }
+
+    fn cache() -> RobotsCache {
+        RobotsCache::new("googol", Duration::from_secs(60), Duration::from_secs(5))
+    }
+
+    #[test]
+    fn test_cache_ttl() {
+        let cache = cache();
+        let origin = "https://example.com";
+
+        insert(&cache, origin, Instant::now(), Robots::default());
+        assert!(cache.entry(origin).robots.initialized());
+
+        if let Some(expired) = Instant::now().checked_sub(Duration::from_secs(120)) {
+            insert(&cache, origin, expired, Robots::default());
+            assert!(!cache.entry(origin).robots.initialized());
+        }
+    }
+
+    #[test]
+    fn test_cache_is_bounded() {
+        let cache = cache();
+
+        for i in 0..MAX_ENTRIES {
+            insert(
+                &cache,
+                &format!("https://{}.example.com", i),
+                Instant::now(),
+                Robots::default(),
+            );
+        }
+
+        // The oldest origin is evicted to make room
+        cache.entry("https://example.com");
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert!(entries.contains_key("https://example.com"));
+        assert!(!entries.contains_key("https://0.example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_cache_uses_cached_rules() {
+        let cache = cache();
+
+        // Cached rules are used instead of fetching the file
+        insert(
+            &cache,
+            "https://example.com",
+            Instant::now(),
+            Robots::parse(ROBOTS, "googol"),
+        );
+
+        assert!(!cache.is_allowed(&url("/googol-only/page")).await);
+        assert!(cache.is_allowed(&url("/page")).await);
+        assert_eq!(cache.crawl_delay(&url("/")).await, None);
+    }
+
+    /// Serves `robots.txt` with `content` on a random local port, or never answers if `None`.
+    async fn serve(content: Option<String>) -> Url {
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let content = content.clone();
+                tokio::spawn(async move {
+                    let mut request = [0; 1024];
+                    let _ = stream.read(&mut request).await;
+
+                    match content {
+                        Some(content) => {
+                            let response = format!(
+                                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                                content.len(),
+                                content
+                            );
+                            let _ = stream.write_all(response.as_bytes()).await;
+                        }
+                        None => std::future::pending().await,
+                    }
+                });
+            }
+        });
+
+        Url::parse(&format!("http://{}/page", address)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fetch() {
+        let url = serve(Some("User-agent: *\nDisallow: /".to_string())).await;
+
+        assert!(!cache().is_allowed(&url).await);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_ignores_content_past_limit() {
+        let content = format!(
+            "#{}\nUser-agent: *\nDisallow: /",
+            " ".repeat(MAX_ROBOTS_BYTES)
+        );
+        let url = serve(Some(content)).await;
+
+        assert!(cache().is_allowed(&url).await);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_timeout() {
+        let url = serve(None).await;
+        let cache = RobotsCache::new(
+            "googol",
+            Duration::from_secs(60),
+            Duration::from_millis(200),
+        );
+
+        // A host that never answers allows everything, without holding other hosts up
+        let other = url::Url::parse("https://example.com/page").unwrap();
+        insert(
+            &cache,
+            "https://example.com",
+            Instant::now(),
+            Robots::parse("User-agent: *\nDisallow: /", "googol"),
+        );
+        let (allowed, other_allowed) = tokio::join!(cache.is_allowed(&url), async {
+            tokio::time::timeout(Duration::from_millis(100), cache.is_allowed(&other)).await
+        });
+
+        assert!(allowed);
+        assert_eq!(other_allowed, Ok(false));
+    }
+}
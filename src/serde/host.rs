@@ -1,5 +1,5 @@
 use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use url::Host;
 
 /// Serialize `HashSet<Host>` as a list of strings
@@ -33,3 +33,32 @@ where
     }
     Ok(hosts)
 }
+
+/// Serialize `HashMap<Host, V>` as a map with string keys
+pub fn serialize_host_map<S, V>(map: &HashMap<Host, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    V: Serialize,
+{
+    let map: HashMap<String, &V> = map
+        .iter()
+        .map(|(host, value)| (host.to_string(), value))
+        .collect();
+    map.serialize(serializer)
+}
+
+/// Deserialize `HashMap<Host, V>` from a map with string keys, normalizing the hosts
+pub fn deserialize_host_map<'de, D, V>(deserializer: D) -> Result<HashMap<Host, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    let map: HashMap<String, V> = HashMap::deserialize(deserializer)?;
+
+    map.into_iter()
+        .map(|(s, value)| match Host::parse(&s) {
+            Ok(host) => Ok((host, value)),
+            Err(_) => Err(de::Error::custom(format!("Invalid host string: {}", s))),
+        })
+        .collect()
+}
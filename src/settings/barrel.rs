@@ -1,5 +1,11 @@
+use super::{Validate, tls::TlsServerConfig};
+use config::ConfigError;
 use serde::Deserialize;
-use std::net::SocketAddr;
+use std::{
+    collections::HashSet,
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+};
 
 /// Configuration settings for the Barrel component.
 ///
@@ -11,6 +17,115 @@ pub struct BarrelConfig {
     pub address: SocketAddr,
     /// The file path to store or load data related to the Barrel service.
     pub filepath: String,
+    /// Words never indexed, whichever way pages reach the barrel.
+    #[serde(default)]
+    pub stop_words: HashSet<String>,
+    /// Whether words are reduced to their stem, so that e.g. "running" matches "run". Changing
+    /// it requires rebuilding the index.
+    #[serde(default)]
+    pub stemming: bool,
+    /// Whether words are also indexed as written, so that searches can match case. Takes
+    /// roughly twice the memory and disk space for the word index. Only pages indexed while
+    /// it is enabled can be found case-sensitively.
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Score added to a search result for each searched word in its title, on top of its
+    /// number of backlinks. `0.0` ignores titles.
+    #[serde(default = "default_title_boost")]
+    pub title_boost: f64,
+    /// Whether search results with the same content, such as mirrors, are collapsed into the
+    /// most relevant one. The total number of results still counts every copy.
+    #[serde(default)]
+    pub collapse_duplicates: bool,
+    /// Serves over TLS if set, instead of plaintext.
+    #[serde(default)]
+    pub tls: Option<TlsServerConfig>,
+    /// Whether the gRPC messages sent are gzip-compressed. Compressed messages are
+    /// accepted either way.
+    #[serde(default = "super::default_compression")]
+    pub compression: bool,
+    /// Largest gRPC message sent or received, in bytes.
+    #[serde(default = "super::default_max_message_size")]
+    pub max_message_size: usize,
+    /// Seconds between saves of the index, if pages were indexed since the last one.
+    #[serde(default = "default_save_interval_secs")]
+    pub save_interval_secs: u64,
+    /// Number of indexed pages after which the index is saved right away, without waiting
+    /// for the next periodic save.
+    #[serde(default = "default_save_every_pages")]
+    pub save_every_pages: usize,
+    /// File logs are also written to, rotated daily. Logs only go to stderr if not set.
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+    /// Minimum level logged, e.g. `info`. The `RUST_LOG` environment variable takes precedence.
+    #[serde(default = "super::default_log_level")]
+    pub log_level: String,
+}
+
+fn default_title_boost() -> f64 {
+    10.0
+}
+
+fn default_save_interval_secs() -> u64 {
+    5
+}
+
+fn default_save_every_pages() -> usize {
+    100
+}
+
+impl Default for BarrelConfig {
+    /// Listens on `127.0.0.1:50052`, storing data in `.barrel-data.json`.
+    fn default() -> Self {
+        Self {
+            address: (Ipv4Addr::LOCALHOST, 50052).into(),
+            filepath: ".barrel-data.json".to_string(),
+            stop_words: HashSet::new(),
+            stemming: false,
+            case_sensitive: false,
+            title_boost: default_title_boost(),
+            collapse_duplicates: false,
+            tls: None,
+            compression: super::default_compression(),
+            max_message_size: super::default_max_message_size(),
+            save_interval_secs: default_save_interval_secs(),
+            save_every_pages: default_save_every_pages(),
+            log_file: None,
+            log_level: super::default_log_level(),
+        }
+    }
+}
+
+impl Validate for BarrelConfig {
+    /// Checks that the data has somewhere to be stored, is saved at some point, that
+    /// messages can be received, and that titles are boosted by a sensible amount.
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invariants = [
+            (!self.filepath.is_empty(), "filepath must not be empty"),
+            (
+                self.save_interval_secs >= 1,
+                "save_interval_secs must be at least 1",
+            ),
+            (
+                self.save_every_pages >= 1,
+                "save_every_pages must be at least 1",
+            ),
+            (
+                self.max_message_size >= 1,
+                "max_message_size must be at least 1",
+            ),
+            (
+                self.title_boost.is_finite() && self.title_boost >= 0.0,
+                "title_boost must be a finite, non-negative number",
+            ),
+        ];
+
+        if let Some((_, message)) = invariants.iter().find(|(holds, _)| !holds) {
+            return Err(ConfigError::Message(message.to_string()));
+        }
+
+        Ok(())
+    }
 }
 
 impl super::Load for BarrelConfig {
@@ -29,7 +144,7 @@ impl super::Load for BarrelConfig {
     /// ```rust
     /// use googol::settings::{barrel::BarrelConfig, Load};
     ///
-    /// let config = BarrelConfig::default();
+    /// let config = <BarrelConfig as Load>::default();
     /// ```
     fn default() -> Result<Self::Item, config::ConfigError> {
         Self::load(".barrel")
@@ -39,6 +154,7 @@ impl super::Load for BarrelConfig {
 #[cfg(test)]
 mod tests {
     use crate::settings::Load;
+    use std::path::PathBuf;
     use std::str::FromStr; // Assuming Load trait is in crate::settings
 
     use super::*;
@@ -73,6 +189,136 @@ mod tests {
         assert_eq!(config.filepath, "./.barrel-data.json".to_string());
     }
 
+    /// Tests that the stop words are optional.
+    #[test]
+    fn test_stop_words_config() {
+        let config = BarrelConfig::from_str(VALID).unwrap();
+        assert!(config.stop_words.is_empty());
+
+        let config =
+            BarrelConfig::from_str(&format!("stop_words = [\"the\", \"a\"]\n{}", VALID)).unwrap();
+        assert_eq!(config.stop_words, ["the", "a"].map(String::from).into());
+    }
+
+    /// Tests that stemming is optional, and off by default.
+    #[test]
+    fn test_stemming_config() {
+        let config = BarrelConfig::from_str(VALID).unwrap();
+        assert!(!config.stemming);
+
+        let config = BarrelConfig::from_str(&format!("stemming = true\n{}", VALID)).unwrap();
+        assert!(config.stemming);
+    }
+
+    /// Tests that logging is optional, only to stderr and of errors by default.
+    #[test]
+    fn test_log_config() {
+        let config = BarrelConfig::from_str(VALID).unwrap();
+        assert_eq!(config.log_file, None);
+        assert_eq!(config.log_level, "error");
+
+        let config = BarrelConfig::from_str(&format!(
+            "log_file = \"logs/barrel.log\"\nlog_level = \"info\"\n{}",
+            VALID
+        ))
+        .unwrap();
+        assert_eq!(config.log_file, Some(PathBuf::from("logs/barrel.log")));
+        assert_eq!(config.log_level, "info");
+    }
+
+    /// Tests that title matches are boosted by default, and can be ignored.
+    #[test]
+    fn test_title_boost_config() {
+        let config = BarrelConfig::from_str(VALID).unwrap();
+        assert_eq!(config.title_boost, 10.0);
+
+        let config = BarrelConfig::from_str(&format!("title_boost = 0.0\n{}", VALID)).unwrap();
+        assert_eq!(config.title_boost, 0.0);
+    }
+
+    /// Tests that collapsing duplicate search results is optional, and off by default.
+    #[test]
+    fn test_collapse_duplicates_config() {
+        let config = BarrelConfig::from_str(VALID).unwrap();
+        assert!(!config.collapse_duplicates);
+
+        let config =
+            BarrelConfig::from_str(&format!("collapse_duplicates = true\n{}", VALID)).unwrap();
+        assert!(config.collapse_duplicates);
+    }
+
+    /// Tests that the case-sensitive index is optional, and off by default.
+    #[test]
+    fn test_case_sensitive_config() {
+        let config = BarrelConfig::from_str(VALID).unwrap();
+        assert!(!config.case_sensitive);
+
+        let config = BarrelConfig::from_str(&format!("case_sensitive = true\n{}", VALID)).unwrap();
+        assert!(config.case_sensitive);
+    }
+
+    /// Tests that compression is on by default.
+    #[test]
+    fn test_compression_config() {
+        let config = BarrelConfig::from_str(VALID).unwrap();
+        assert!(config.compression);
+
+        let config = BarrelConfig::from_str(&format!("compression = false\n{}", VALID)).unwrap();
+        assert!(!config.compression);
+    }
+
+    /// Tests that TLS is optional, and that mutual TLS is optional on top of it.
+    #[test]
+    fn test_tls_config() {
+        let config = BarrelConfig::from_str(VALID).unwrap();
+        assert!(config.tls.is_none());
+
+        let config = BarrelConfig::from_str(&format!(
+            "{}\n[tls]\ncert = \"server.pem\"\nkey = \"server.key\"",
+            VALID
+        ))
+        .unwrap();
+        let tls = config.tls.unwrap();
+        assert_eq!(tls.cert, PathBuf::from("server.pem"));
+        assert_eq!(tls.key, PathBuf::from("server.key"));
+        assert!(tls.client_ca.is_none());
+    }
+
+    /// Tests that the index is saved every 5 seconds or 100 pages by default.
+    #[test]
+    fn test_save_config() {
+        let config = BarrelConfig::from_str(VALID).unwrap();
+        assert_eq!(config.save_interval_secs, 5);
+        assert_eq!(config.save_every_pages, 100);
+
+        let config = BarrelConfig::from_str(&format!(
+            "save_interval_secs = 60\nsave_every_pages = 1\n{}",
+            VALID
+        ))
+        .unwrap();
+        assert_eq!(config.save_interval_secs, 60);
+        assert_eq!(config.save_every_pages, 1);
+    }
+
+    /// Tests that an empty filepath, never saving, or a nonsensical title boost is rejected.
+    #[test]
+    fn test_validate() {
+        let config = BarrelConfig::from_str(&VALID.replace("./.barrel-data.json", ""));
+        assert!(config.is_err());
+
+        for invalid in [
+            "save_interval_secs = 0",
+            "save_every_pages = 0",
+            "max_message_size = 0",
+            "title_boost = -1.0",
+            "title_boost = nan",
+            "title_boost = inf",
+        ] {
+            let config = BarrelConfig::from_str(&format!("{}\n{}", invalid, VALID));
+            assert!(config.is_err(), "{}", invalid);
+        }
+    }
+
     /// Tests that invalid configuration strings produce errors.
     #[test]
     fn test_invalid_config() {
@@ -1,5 +1,7 @@
+use super::{Validate, tls::TlsClientConfig};
+use config::ConfigError;
 use serde::Deserialize;
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr};
 
 /// Configuration settings for the Client component.
 ///
@@ -12,6 +14,64 @@ pub struct ClientConfig {
     pub gateway: SocketAddr,
     /// The maximum number of retry attempts for client requests.
     pub max_retries: usize,
+    /// Longest wait between two attempts, in seconds. The wait doubles after each attempt up
+    /// to this.
+    #[serde(default = "default_max_backoff_secs")]
+    pub max_backoff_secs: u64,
+    /// Connects to the gateway over TLS if set, instead of plaintext.
+    #[serde(default)]
+    pub gateway_tls: Option<TlsClientConfig>,
+    /// Whether the gRPC messages sent to the gateway are gzip-compressed. Compressed messages are
+    /// accepted either way.
+    #[serde(default = "super::default_compression")]
+    pub compression: bool,
+    /// Largest gRPC message sent or received, in bytes.
+    #[serde(default = "super::default_max_message_size")]
+    pub max_message_size: usize,
+}
+
+fn default_max_backoff_secs() -> u64 {
+    30
+}
+
+impl Default for ClientConfig {
+    /// Connects to a gateway at `127.0.0.1:50051`.
+    fn default() -> Self {
+        Self {
+            gateway: (Ipv4Addr::LOCALHOST, 50051).into(),
+            max_retries: 7,
+            max_backoff_secs: default_max_backoff_secs(),
+            gateway_tls: None,
+            compression: super::default_compression(),
+            max_message_size: super::default_max_message_size(),
+        }
+    }
+}
+
+impl Validate for ClientConfig {
+    /// Checks that attempts are spaced, that messages can be sent, and that the TLS settings,
+    /// if any, are complete.
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_backoff_secs < 1 {
+            return Err(ConfigError::Message(
+                "max_backoff_secs must be at least 1".to_string(),
+            ));
+        }
+
+        if self.max_message_size < 1 {
+            return Err(ConfigError::Message(
+                "max_message_size must be at least 1".to_string(),
+            ));
+        }
+
+        if let Some(gateway_tls) = &self.gateway_tls {
+            gateway_tls
+                .validate()
+                .map_err(|e| ConfigError::Message(format!("gateway_tls: {}", e)))?;
+        }
+
+        Ok(())
+    }
 }
 
 impl super::Load for ClientConfig {
@@ -30,7 +90,7 @@ impl super::Load for ClientConfig {
     /// ```rust
     /// use googol::settings::{client::ClientConfig, Load};
     ///
-    /// let config = ClientConfig::default();
+    /// let config = <ClientConfig as Load>::default();
     /// ```
     fn default() -> Result<Self::Item, config::ConfigError> {
         Self::load(".client")
@@ -49,4 +109,25 @@ mod tests {
 
         assert!(config.is_ok());
     }
+
+    #[test]
+    fn test_max_backoff_secs() {
+        let config = ClientConfig::load("examples/config/client.toml").unwrap();
+        assert_eq!(config.max_backoff_secs, default_max_backoff_secs());
+
+        let config = ClientConfig {
+            max_backoff_secs: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_max_message_size() {
+        let config = ClientConfig {
+            max_message_size: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
 }
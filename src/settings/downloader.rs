@@ -1,12 +1,18 @@
+use super::{Validate, tls::TlsClientConfig};
+use config::ConfigError;
 use serde::Deserialize;
-use std::{collections::HashSet, net::SocketAddr};
+use std::{
+    collections::{HashMap, HashSet},
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+};
 
 /// Configuration settings for the Downloader component.
 ///
 /// This struct includes the number of threads to spawn, the gateway address
 /// for connecting, and a set of stop words to filter out during processing.
 /// It is deserialized from configuration files or strings using Serde.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct DownloaderConfig {
     /// The number of worker threads to spawn for downloading.
     pub threads: usize,
@@ -14,6 +20,168 @@ pub struct DownloaderConfig {
     pub gateway: SocketAddr,
     /// A set of stop words to be ignored or filtered during processing.
     pub stop_words: HashSet<String>,
+    /// Stop words of specific languages, keyed by lowercase ISO 639-1 code (e.g. `pt`). Pages
+    /// in any other language, or whose language couldn't be detected, use `stop_words`.
+    #[serde(default)]
+    pub stop_words_by_language: HashMap<String, HashSet<String>>,
+    /// The user agent sent with every request, and used to pick the `robots.txt` rules to
+    /// follow.
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String,
+    /// Seconds a host's `robots.txt` is cached before being fetched again.
+    #[serde(default = "default_robots_ttl_secs")]
+    pub robots_ttl_secs: u64,
+    /// Maximum number of concurrent requests to the same host.
+    #[serde(default = "default_max_per_host")]
+    pub max_per_host: usize,
+    /// Minimum milliseconds between two requests to the same host. A longer `Crawl-delay` in the
+    /// host's `robots.txt` takes precedence.
+    #[serde(default = "default_min_delay_ms")]
+    pub min_delay_ms: u64,
+    /// Longest `Crawl-delay` honored, in seconds. Urls of hosts asking for longer are skipped
+    /// rather than holding up a worker.
+    #[serde(default = "default_max_crawl_delay_secs")]
+    pub max_crawl_delay_secs: u64,
+    /// Seconds a page fetch may take, redirects included. Also bounds the fetches of
+    /// `robots.txt` files and the FishFish lookups.
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+    /// Maximum number of redirects followed when fetching a page.
+    #[serde(default = "default_max_redirects")]
+    pub max_redirects: usize,
+    /// Number of urls each thread dequeues at once, crawling them before asking for more.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: u32,
+    /// Connects to the gateway over TLS if set, instead of plaintext.
+    #[serde(default)]
+    pub gateway_tls: Option<TlsClientConfig>,
+    /// Whether the gRPC messages sent to the gateway are gzip-compressed. Compressed messages are
+    /// accepted either way.
+    #[serde(default = "super::default_compression")]
+    pub compression: bool,
+    /// Largest gRPC message sent or received, in bytes.
+    #[serde(default = "super::default_max_message_size")]
+    pub max_message_size: usize,
+    /// File logs are also written to, rotated daily. Logs only go to stderr if not set.
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+    /// Minimum level logged, e.g. `info`. The `RUST_LOG` environment variable takes precedence.
+    #[serde(default = "super::default_log_level")]
+    pub log_level: String,
+}
+
+fn default_user_agent() -> String {
+    "googol".to_string()
+}
+
+fn default_robots_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_max_per_host() -> usize {
+    2
+}
+
+fn default_min_delay_ms() -> u64 {
+    1000
+}
+
+fn default_max_crawl_delay_secs() -> u64 {
+    60
+}
+
+fn default_request_timeout_secs() -> u64 {
+    30
+}
+
+fn default_max_redirects() -> usize {
+    5
+}
+
+fn default_batch_size() -> u32 {
+    4
+}
+
+impl Default for DownloaderConfig {
+    /// Runs 4 threads against a gateway at `127.0.0.1:50051`, without stop words.
+    fn default() -> Self {
+        Self {
+            threads: 4,
+            gateway: (Ipv4Addr::LOCALHOST, 50051).into(),
+            stop_words: HashSet::new(),
+            stop_words_by_language: HashMap::new(),
+            user_agent: default_user_agent(),
+            robots_ttl_secs: default_robots_ttl_secs(),
+            max_per_host: default_max_per_host(),
+            min_delay_ms: default_min_delay_ms(),
+            max_crawl_delay_secs: default_max_crawl_delay_secs(),
+            request_timeout_secs: default_request_timeout_secs(),
+            max_redirects: default_max_redirects(),
+            batch_size: default_batch_size(),
+            gateway_tls: None,
+            compression: super::default_compression(),
+            max_message_size: super::default_max_message_size(),
+            log_file: None,
+            log_level: super::default_log_level(),
+        }
+    }
+}
+
+impl DownloaderConfig {
+    /// Returns the stop words of `language`, or the default `stop_words` if it is unknown or
+    /// has none configured.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::settings::downloader::DownloaderConfig;
+    ///
+    /// let mut config = DownloaderConfig::default();
+    /// config.stop_words.insert("the".to_string());
+    /// config
+    ///     .stop_words_by_language
+    ///     .insert("pt".to_string(), ["o".to_string()].into());
+    ///
+    /// assert!(config.stop_words_for(Some("pt")).contains("o"));
+    /// assert!(config.stop_words_for(Some("fr")).contains("the"));
+    /// assert!(config.stop_words_for(None).contains("the"));
+    /// ```
+    pub fn stop_words_for(&self, language: Option<&str>) -> &HashSet<String> {
+        language
+            .and_then(|language| self.stop_words_by_language.get(language))
+            .unwrap_or(&self.stop_words)
+    }
+}
+
+impl Validate for DownloaderConfig {
+    /// Checks that there is at least one worker, and that pages can actually be fetched.
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invariants = [
+            (self.threads >= 1, "threads must be at least 1"),
+            (self.max_per_host >= 1, "max_per_host must be at least 1"),
+            (self.batch_size >= 1, "batch_size must be at least 1"),
+            (
+                self.request_timeout_secs >= 1,
+                "request_timeout_secs must be at least 1",
+            ),
+            (
+                self.max_message_size >= 1,
+                "max_message_size must be at least 1",
+            ),
+        ];
+
+        if let Some((_, message)) = invariants.iter().find(|(holds, _)| !holds) {
+            return Err(ConfigError::Message(message.to_string()));
+        }
+
+        if let Some(gateway_tls) = &self.gateway_tls {
+            gateway_tls
+                .validate()
+                .map_err(|e| ConfigError::Message(format!("gateway_tls: {}", e)))?;
+        }
+
+        Ok(())
+    }
 }
 
 impl super::Load for DownloaderConfig {
@@ -32,7 +200,7 @@ impl super::Load for DownloaderConfig {
     /// ```rust
     /// use googol::settings::{downloader::DownloaderConfig, Load};
     ///
-    /// let config = DownloaderConfig::default();
+    /// let config = <DownloaderConfig as Load>::default();
     /// ```
     fn default() -> Result<Self::Item, config::ConfigError> {
         Self::load(".downloader")
@@ -82,6 +250,110 @@ mod tests {
         );
     }
 
+    /// Tests that the robots settings are optional.
+    #[test]
+    fn test_robots_config() {
+        let config = DownloaderConfig::from_str(VALID).unwrap();
+        assert_eq!(config.user_agent, "googol");
+        assert_eq!(config.robots_ttl_secs, 3600);
+
+        let config = DownloaderConfig::from_str(&format!(
+            "user_agent = \"bot\"\nrobots_ttl_secs = 60\n{}",
+            VALID
+        ))
+        .unwrap();
+        assert_eq!(config.user_agent, "bot");
+        assert_eq!(config.robots_ttl_secs, 60);
+    }
+
+    /// Tests that the per host limits are optional.
+    #[test]
+    fn test_host_limits_config() {
+        let config = DownloaderConfig::from_str(VALID).unwrap();
+        assert_eq!(config.max_per_host, 2);
+        assert_eq!(config.min_delay_ms, 1000);
+        assert_eq!(config.max_crawl_delay_secs, 60);
+
+        let config = DownloaderConfig::from_str(&format!(
+            "max_per_host = 1\nmin_delay_ms = 0\nmax_crawl_delay_secs = 5\n{}",
+            VALID
+        ))
+        .unwrap();
+        assert_eq!(config.max_per_host, 1);
+        assert_eq!(config.min_delay_ms, 0);
+        assert_eq!(config.max_crawl_delay_secs, 5);
+    }
+
+    /// Tests that the request limits are optional.
+    #[test]
+    fn test_request_limits_config() {
+        let config = DownloaderConfig::from_str(VALID).unwrap();
+        assert_eq!(config.request_timeout_secs, 30);
+        assert_eq!(config.max_redirects, 5);
+        assert_eq!(config.batch_size, 4);
+
+        let config = DownloaderConfig::from_str(&format!(
+            "request_timeout_secs = 5\nmax_redirects = 0\nbatch_size = 1\n{}",
+            VALID
+        ))
+        .unwrap();
+        assert_eq!(config.request_timeout_secs, 5);
+        assert_eq!(config.max_redirects, 0);
+        assert_eq!(config.batch_size, 1);
+    }
+
+    /// Tests that the per language stop words are optional, and fall back to `stop_words`.
+    #[test]
+    fn test_stop_words_by_language_config() {
+        let config = DownloaderConfig::from_str(VALID).unwrap();
+        assert!(config.stop_words_by_language.is_empty());
+
+        let config = DownloaderConfig::from_str(&format!(
+            "{}
+[stop_words_by_language]
+pt = [\"o\", \"de\"]",
+            VALID
+        ))
+        .unwrap();
+        assert_eq!(
+            config.stop_words_for(Some("pt")),
+            &["o", "de"].iter().map(|word| word.to_string()).collect()
+        );
+        assert_eq!(config.stop_words_for(Some("en")), &config.stop_words);
+        assert_eq!(config.stop_words_for(None), &config.stop_words);
+    }
+
+    /// Tests that configurations that can't download anything are rejected.
+    #[test]
+    fn test_validate() {
+        let invalids = [
+            ("threads", VALID.replace("threads = 4", "threads = 0")),
+            ("max_per_host", format!("max_per_host = 0\n{}", VALID)),
+            ("batch_size", format!("batch_size = 0\n{}", VALID)),
+            (
+                "request_timeout_secs",
+                format!("request_timeout_secs = 0\n{}", VALID),
+            ),
+            (
+                "max_message_size",
+                format!("max_message_size = 0\n{}", VALID),
+            ),
+            (
+                "gateway_tls",
+                format!(
+                    "{}\n[gateway_tls]\nca = \"ca.pem\"\ncert = \"client.pem\"",
+                    VALID
+                ),
+            ),
+        ];
+
+        for (field, invalid) in invalids {
+            let error = DownloaderConfig::from_str(&invalid).unwrap_err();
+
+            assert!(error.to_string().starts_with(field), "{}", error);
+        }
+    }
+
     /// Tests that invalid configuration strings produce errors.
     #[test]
     fn test_invalid_config() {
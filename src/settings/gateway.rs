@@ -1,8 +1,16 @@
-use crate::serde::host::{deserialize_hosts, serialize_hosts};
+use super::{
+    Validate, collides,
+    tls::{TlsClientConfig, TlsServerConfig},
+};
+use crate::serde::host::{
+    deserialize_host_map, deserialize_hosts, serialize_host_map, serialize_hosts,
+};
+use config::ConfigError;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashSet, VecDeque},
-    net::SocketAddr,
+    collections::{HashMap, HashSet, VecDeque},
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
 };
 use url::{Host, Url};
 
@@ -11,6 +19,10 @@ use url::{Host, Url};
 /// It provides methods to check if a URL's host
 /// is whitelisted or blacklisted.
 ///
+/// Hosts are matched exactly, unless `whitelist_subdomains` or `blacklist_subdomains` is set,
+/// in which case `example.com` also matches `www.example.com` and `docs.api.example.com`.
+/// Crawling a host can further be restricted to some paths with `path_prefixes`.
+///
 /// # Examples
 ///
 /// ```rust
@@ -26,16 +38,25 @@ use url::{Host, Url};
 /// let filter = DomainsFilter {
 ///     whitelist,
 ///     blacklist,
+///     whitelist_subdomains: true,
+///     blacklist_subdomains: false,
+///     path_prefixes: [(Host::parse("example.com").unwrap(), vec!["/docs/".to_string()])].into(),
 /// };
 ///
 /// // Check if a URL host is whitelisted or blacklisted
 /// let url = Url::parse("https://example.com/page").unwrap();
 /// assert!(filter.is_whitelisted(&url));
+/// assert!(filter.is_whitelisted(&Url::parse("https://www.example.com").unwrap()));
 ///
 /// let url2 = Url::parse("https://bad.com/malicious").unwrap();
 /// assert!(filter.is_blacklisted(&url2));
+/// assert!(!filter.is_blacklisted(&Url::parse("https://www.bad.com").unwrap()));
+///
+/// // Only the docs of example.com are crawled
+/// assert!(!filter.is_allowed(&url));
+/// assert!(filter.is_allowed(&Url::parse("https://example.com/docs/intro").unwrap()));
 /// ```
-#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct DomainsFilter {
     /// Set of hosts that are explicitly allowed.
     #[serde(
@@ -50,6 +71,48 @@ pub struct DomainsFilter {
         deserialize_with = "deserialize_hosts"
     )]
     pub blacklist: HashSet<Host>,
+
+    /// Whether whitelisted hosts also match their subdomains.
+    #[serde(default)]
+    pub whitelist_subdomains: bool,
+
+    /// Whether blacklisted hosts also match their subdomains.
+    #[serde(default)]
+    pub blacklist_subdomains: bool,
+
+    /// Paths a host is restricted to, as prefixes of the allowed paths, e.g.
+    /// `{ "example.com" = ["/docs/"] }`. Hosts without prefixes allow every path. Subdomains
+    /// follow the prefixes of their domain if `whitelist_subdomains` is set. Hosts are
+    /// normalized when parsed, e.g. `Example.COM` is `example.com`.
+    #[serde(
+        default,
+        serialize_with = "serialize_host_map",
+        deserialize_with = "deserialize_host_map"
+    )]
+    pub path_prefixes: HashMap<Host, Vec<String>>,
+}
+
+/// Whether `host` is in `hosts`, or, if `subdomains` is set, a subdomain of one of them.
+fn matches_host(hosts: &HashSet<Host>, host: Host<&str>, subdomains: bool) -> bool {
+    let host = host.to_owned();
+    if hosts.contains(&host) {
+        return true;
+    }
+
+    match host {
+        Host::Domain(domain) if subdomains => {
+            parent_domains(&domain).any(|parent| hosts.contains(&Host::Domain(parent.to_string())))
+        }
+        _ => false,
+    }
+}
+
+/// Domains `domain` is a subdomain of, from the closest one, e.g. `api.example.com` and
+/// `example.com` for `docs.api.example.com`.
+fn parent_domains(domain: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(domain.split_once('.').map(|(_, parent)| parent), |domain| {
+        domain.split_once('.').map(|(_, parent)| parent)
+    })
 }
 
 impl DomainsFilter {
@@ -73,6 +136,7 @@ impl DomainsFilter {
     /// let filter = DomainsFilter {
     ///     whitelist: HashSet::default(),
     ///     blacklist: ["bad.com"].iter().map(|d| Host::parse(d).unwrap()).collect(),
+    ///     ..Default::default()
     /// };
     ///
     /// let url = Url::parse("https://bad.com/malicious").unwrap();
@@ -80,7 +144,7 @@ impl DomainsFilter {
     /// ```
     pub fn is_blacklisted(&self, url: &Url) -> bool {
         if let Some(host) = url.host() {
-            self.blacklist.contains(&host.to_owned())
+            matches_host(&self.blacklist, host, self.blacklist_subdomains)
         } else {
             false
         }
@@ -106,6 +170,7 @@ impl DomainsFilter {
     /// let filter = DomainsFilter {
     ///     whitelist: ["example.com"].iter().map(|d| Host::parse(d).unwrap()).collect(),
     ///     blacklist: HashSet::default(),
+    ///     ..Default::default()
     /// };
     ///
     /// let url = Url::parse("https://example.com/page").unwrap();
@@ -113,11 +178,295 @@ impl DomainsFilter {
     /// ```
     pub fn is_whitelisted(&self, url: &Url) -> bool {
         if let Some(host) = url.host() {
-            self.whitelist.contains(&host.to_owned())
+            matches_host(&self.whitelist, host, self.whitelist_subdomains)
         } else {
             false
         }
     }
+
+    /// Checks if the path of the given URL is allowed by the `path_prefixes` of its host.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to check.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the path starts with one of the prefixes of its host, or if its host has
+    /// none, `false` otherwise.
+    pub fn is_path_allowed(&self, url: &Url) -> bool {
+        let Some(host) = url.host() else {
+            return true;
+        };
+
+        // Addresses have no subdomains
+        let parents = match (&host, self.whitelist_subdomains) {
+            (Host::Domain(domain), true) => parent_domains(domain)
+                .map(|parent| Host::Domain(parent.to_string()))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let hosts = std::iter::once(host.to_owned()).chain(parents);
+        let mut prefixes = hosts
+            .filter_map(|host| self.path_prefixes.get(&host))
+            .flatten()
+            .peekable();
+
+        prefixes.peek().is_none() || prefixes.any(|prefix| url.path().starts_with(prefix))
+    }
+
+    /// Checks if the given URL may be crawled.
+    ///
+    /// A URL is allowed if its host is not blacklisted and, when the whitelist is not empty,
+    /// its host is whitelisted. An empty whitelist allows every host. The path must also be
+    /// allowed by [`DomainsFilter::is_path_allowed`].
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to check.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the URL passes the filter, `false` otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url::{Url, Host};
+    /// use googol::settings::gateway::DomainsFilter;
+    /// use std::collections::HashSet;
+    ///
+    /// let filter = DomainsFilter {
+    ///     whitelist: HashSet::default(),
+    ///     blacklist: ["bad.com"].iter().map(|d| Host::parse(d).unwrap()).collect(),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert!(filter.is_allowed(&Url::parse("https://example.com").unwrap()));
+    /// assert!(!filter.is_allowed(&Url::parse("https://bad.com").unwrap()));
+    /// ```
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        !self.is_blacklisted(url)
+            && (self.whitelist.is_empty() || self.is_whitelisted(url))
+            && self.is_path_allowed(url)
+    }
+}
+
+/// Rules rewriting urls before they are enqueued or indexed, so that urls pointing to the same
+/// page are crawled and indexed once.
+///
+/// Hosts are always lowercased and default ports removed, as `Url` does when parsing http(s)
+/// urls.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::settings::gateway::UrlNormalization;
+/// use url::Url;
+///
+/// let normalization = UrlNormalization::default();
+/// let url = Url::parse("https://Example.com:443/page?utm_source=feed&id=1#top").unwrap();
+///
+/// assert_eq!(normalization.normalize(url).as_str(), "https://example.com/page?id=1");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UrlNormalization {
+    /// Whether fragments (`#section`) are removed.
+    pub strip_fragment: bool,
+    /// Query parameters removed, e.g. `utm_source`.
+    pub tracking_params: HashSet<String>,
+    /// Whether a trailing slash is removed from paths other than `/`.
+    pub strip_trailing_slash: bool,
+}
+
+impl Default for UrlNormalization {
+    /// Removes fragments and common tracking parameters, keeping trailing slashes.
+    fn default() -> Self {
+        Self {
+            strip_fragment: true,
+            tracking_params: [
+                "utm_source",
+                "utm_medium",
+                "utm_campaign",
+                "utm_term",
+                "utm_content",
+                "gclid",
+                "fbclid",
+            ]
+            .map(String::from)
+            .into(),
+            strip_trailing_slash: false,
+        }
+    }
+}
+
+impl UrlNormalization {
+    /// Rewrites `url` following these rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to normalize.
+    ///
+    /// # Returns
+    ///
+    /// The normalized URL. Normalizing it again leaves it unchanged.
+    pub fn normalize(&self, mut url: Url) -> Url {
+        if let Some(host) = url.host_str()
+            && host.chars().any(|c| c.is_ascii_uppercase())
+        {
+            let host = host.to_ascii_lowercase();
+            let _ = url.set_host(Some(&host));
+        }
+
+        if self.strip_fragment {
+            url.set_fragment(None);
+        }
+
+        if url.query().is_some() && !self.tracking_params.is_empty() {
+            let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+            let kept: Vec<_> = pairs
+                .iter()
+                .filter(|(name, _)| !self.tracking_params.contains(name))
+                .collect();
+
+            // The query is only rewritten if needed, as that may change its encoding
+            if kept.is_empty() {
+                url.set_query(None);
+            } else if kept.len() < pairs.len() {
+                url.query_pairs_mut().clear().extend_pairs(kept);
+            }
+        }
+
+        if self.strip_trailing_slash && url.path().len() > 1 && url.path().ends_with('/') {
+            let path = url.path().trim_end_matches('/').to_string();
+            url.set_path(&path);
+        }
+
+        url
+    }
+}
+
+/// Configuration of the gateway's pending index cache.
+///
+/// Index requests that could not be delivered to any barrel are stored in `filepath` and
+/// retried every `retry_interval_secs` seconds. While some requests keep failing, the interval
+/// doubles after each attempt, up to `max_retry_interval_secs`.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::settings::gateway::IndexCacheConfig;
+///
+/// let config = IndexCacheConfig::default();
+/// assert_eq!(config.filepath, ".gateway-index-cache.bin");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IndexCacheConfig {
+    /// File the pending index requests are persisted to.
+    pub filepath: String,
+    /// Maximum number of cached index requests.
+    pub max_entries: usize,
+    /// Seconds between attempts to deliver cached index requests.
+    pub retry_interval_secs: u64,
+    /// Maximum seconds between attempts, reached by backing off while requests keep failing.
+    pub max_retry_interval_secs: u64,
+}
+
+impl Default for IndexCacheConfig {
+    fn default() -> Self {
+        Self {
+            filepath: ".gateway-index-cache.bin".to_string(),
+            max_entries: crate::gateway::cache::DEFAULT_MAX_ENTRIES,
+            retry_interval_secs: 30,
+            max_retry_interval_secs: 600,
+        }
+    }
+}
+
+/// Configuration of the recrawling of stale pages.
+///
+/// Every `check_interval_secs` seconds, up to `batch_size` pages the barrels last indexed more
+/// than `interval_secs` seconds ago are enqueued again, least recently indexed first.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::settings::gateway::RecrawlConfig;
+///
+/// let config = RecrawlConfig::default();
+/// assert_eq!(config.interval_secs, 7 * 24 * 60 * 60);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RecrawlConfig {
+    /// Seconds after which an indexed page is recrawled.
+    pub interval_secs: u64,
+    /// Seconds between checks for stale pages.
+    pub check_interval_secs: u64,
+    /// Maximum number of pages enqueued by each check.
+    pub batch_size: u32,
+}
+
+impl Default for RecrawlConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 7 * 24 * 60 * 60,
+            check_interval_secs: 60 * 60,
+            batch_size: 100,
+        }
+    }
+}
+
+/// Barrels a search is sent to.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::settings::gateway::SearchScope;
+///
+/// assert_eq!(SearchScope::default(), SearchScope::Single);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchScope {
+    /// Only the first barrel that answers. Fastest, but complete only if every barrel holds
+    /// the whole index.
+    #[default]
+    Single,
+    /// Every online barrel, merging their results. Waits for the slowest barrel, but finds
+    /// pages indexed in any of them.
+    All,
+}
+
+/// Barrels an indexed page is sent to.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::settings::gateway::Sharding;
+///
+/// assert_eq!(Sharding::default(), Sharding::Replicate);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sharding {
+    /// Every barrel, so each one holds the whole index.
+    #[default]
+    Replicate,
+    /// Only the barrel responsible for the page's host (see [`crate::gateway::sharding`]),
+    /// so each barrel holds a part of the index. Requires searching every barrel. Backlinks
+    /// and outlinks are still consulted on a single barrel, so they may be incomplete.
+    ///
+    /// Each barrel ranks its pages from its own backlinks and word statistics, so a link
+    /// between hosts of different barrels doesn't count towards the linked page's rank. As
+    /// the ranks of different barrels aren't comparable, search results take the pages of
+    /// each barrel in turns rather than sorting them by rank.
+    ///
+    /// Pages already indexed stay where they are when barrels are added or removed, only the
+    /// following ones are sent to the new owner of their host.
+    Host,
 }
 
 /// Configuration for the Gateway component, including network settings,
@@ -128,7 +477,10 @@ impl DomainsFilter {
 /// ```rust
 /// use std::collections::{VecDeque, HashSet};
 /// use url::Url;
-/// use googol::settings::gateway::{GatewayConfig, DomainsFilter};
+/// use googol::settings::gateway::{
+///     GatewayConfig, DomainsFilter, IndexCacheConfig, RecrawlConfig, SearchScope, Sharding,
+///     UrlNormalization,
+/// };
 ///
 /// // Example of creating a GatewayConfig instance manually
 /// let config = GatewayConfig {
@@ -139,11 +491,28 @@ impl DomainsFilter {
 ///             .collect::<VecDeque<_>>(),
 ///     barrels: HashSet::new(),
 ///     domains_filter: DomainsFilter::default(),
+///     index_cache: IndexCacheConfig::default(),
+///     max_depth: Some(3),
+///     queue_filepath: ".gateway-queue.json".to_string(),
+///     queue_max_size: None,
+///     fishfish: true,
+///     fishfish_timeout_secs: 5,
+///     tls: None,
+///     barrel_tls: None,
+///     compression: true,
+///     max_message_size: 64 * 1024 * 1024,
+///     search_scope: SearchScope::Single,
+///     sharding: Sharding::Replicate,
+///     url_normalization: UrlNormalization::default(),
+///     metrics_address: None,
+///     recrawl: Some(RecrawlConfig::default()),
+///     log_file: None,
+///     log_level: "info".to_string(),
 /// };
 /// ```
 ///
 /// You can also load from a configuration file as shown in the tests.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize)]
 pub struct GatewayConfig {
     /// The socket address the gateway listens on.
     pub address: SocketAddr,
@@ -153,6 +522,196 @@ pub struct GatewayConfig {
     pub barrels: HashSet<SocketAddr>,
     /// Domain filtering rules.
     pub domains_filter: DomainsFilter,
+    /// Cache of index requests pending delivery to the barrels.
+    #[serde(default)]
+    pub index_cache: IndexCacheConfig,
+    /// Maximum crawl depth, seeds being at depth 0. Unlimited if not set.
+    #[serde(default)]
+    pub max_depth: Option<u32>,
+    /// File the queue is saved to on shutdown, and restored from on boot.
+    #[serde(default = "default_queue_filepath")]
+    pub queue_filepath: String,
+    /// Maximum number of pending urls. Unlimited if not set.
+    #[serde(default)]
+    pub queue_max_size: Option<usize>,
+    /// Whether hosts are checked against FishFish, rejecting phishing and malware urls.
+    /// Disable to run offline.
+    #[serde(default = "default_fishfish")]
+    pub fishfish: bool,
+    /// Seconds to wait for the FishFish API before treating a host as unknown.
+    #[serde(default = "default_fishfish_timeout_secs")]
+    pub fishfish_timeout_secs: u64,
+    /// Serves over TLS if set, instead of plaintext.
+    #[serde(default)]
+    pub tls: Option<TlsServerConfig>,
+    /// Connects to the barrels over TLS if set, instead of plaintext.
+    #[serde(default)]
+    pub barrel_tls: Option<TlsClientConfig>,
+    /// Whether the gRPC messages sent, both to clients and to the barrels, are gzip-compressed.
+    /// Compressed messages are accepted either way.
+    #[serde(default = "super::default_compression")]
+    pub compression: bool,
+    /// Largest gRPC message sent or received, in bytes.
+    #[serde(default = "super::default_max_message_size")]
+    pub max_message_size: usize,
+    /// Barrels searches are sent to.
+    #[serde(default)]
+    pub search_scope: SearchScope,
+    /// Barrels indexed pages are sent to.
+    #[serde(default)]
+    pub sharding: Sharding,
+    /// Rules rewriting urls before they are enqueued or indexed.
+    #[serde(default)]
+    pub url_normalization: UrlNormalization,
+    /// Serves Prometheus metrics over HTTP at `/metrics` on this address if set.
+    #[serde(default)]
+    pub metrics_address: Option<SocketAddr>,
+    /// Recrawls pages indexed too long ago if set. Pages are never recrawled otherwise.
+    #[serde(default)]
+    pub recrawl: Option<RecrawlConfig>,
+    /// File logs are also written to, rotated daily. Logs only go to stderr if not set.
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+    /// Minimum level logged, e.g. `info`. The `RUST_LOG` environment variable takes precedence.
+    #[serde(default = "super::default_log_level")]
+    pub log_level: String,
+}
+
+impl Default for GatewayConfig {
+    /// Listens on `127.0.0.1:50051` with a barrel at `127.0.0.1:50052`, an empty queue and
+    /// no domain filters.
+    fn default() -> Self {
+        Self {
+            address: (Ipv4Addr::LOCALHOST, 50051).into(),
+            queue: VecDeque::new(),
+            barrels: HashSet::from([(Ipv4Addr::LOCALHOST, 50052).into()]),
+            domains_filter: DomainsFilter::default(),
+            index_cache: IndexCacheConfig::default(),
+            max_depth: None,
+            queue_filepath: default_queue_filepath(),
+            queue_max_size: None,
+            fishfish: default_fishfish(),
+            fishfish_timeout_secs: default_fishfish_timeout_secs(),
+            tls: None,
+            barrel_tls: None,
+            compression: super::default_compression(),
+            max_message_size: super::default_max_message_size(),
+            search_scope: SearchScope::default(),
+            sharding: Sharding::default(),
+            url_normalization: UrlNormalization::default(),
+            metrics_address: None,
+            recrawl: None,
+            log_file: None,
+            log_level: super::default_log_level(),
+        }
+    }
+}
+
+fn default_queue_filepath() -> String {
+    ".gateway-queue.json".to_string()
+}
+
+fn default_fishfish() -> bool {
+    true
+}
+
+fn default_fishfish_timeout_secs() -> u64 {
+    crate::fishfish::DEFAULT_TIMEOUT.as_secs()
+}
+
+impl Validate for GatewayConfig {
+    /// Checks that no barrel is on the gateway's own port, that messages can be sent, and that
+    /// no host is both whitelisted and blacklisted.
+    ///
+    /// There may be no barrels, as they can be added while the gateway runs.
+    fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(barrel) = self
+            .barrels
+            .iter()
+            .find(|barrel| collides(&self.address, barrel))
+        {
+            return Err(ConfigError::Message(format!(
+                "address {} collides with barrel {}",
+                self.address, barrel
+            )));
+        }
+
+        if let Some(metrics_address) = self
+            .metrics_address
+            .filter(|metrics_address| collides(&self.address, metrics_address))
+        {
+            return Err(ConfigError::Message(format!(
+                "metrics_address {} collides with address {}",
+                metrics_address, self.address
+            )));
+        }
+
+        if self.max_message_size < 1 {
+            return Err(ConfigError::Message(
+                "max_message_size must be at least 1".to_string(),
+            ));
+        }
+
+        if self.sharding == Sharding::Host && self.search_scope != SearchScope::All {
+            return Err(ConfigError::Message(
+                "sharding = \"host\" requires search_scope = \"all\"".to_string(),
+            ));
+        }
+
+        if let Some(host) = self
+            .domains_filter
+            .whitelist
+            .intersection(&self.domains_filter.blacklist)
+            .next()
+        {
+            return Err(ConfigError::Message(format!(
+                "{} is both whitelisted and blacklisted",
+                host
+            )));
+        }
+
+        if let Some((host, prefix)) = self
+            .domains_filter
+            .path_prefixes
+            .iter()
+            .flat_map(|(host, prefixes)| prefixes.iter().map(move |prefix| (host, prefix)))
+            .find(|(_, prefix)| !prefix.starts_with('/'))
+        {
+            return Err(ConfigError::Message(format!(
+                "path prefix {} of {} must start with /",
+                prefix, host
+            )));
+        }
+
+        if let Some(recrawl) = &self.recrawl {
+            let invariants = [
+                (
+                    recrawl.interval_secs > 0,
+                    "recrawl.interval_secs must be positive",
+                ),
+                (
+                    recrawl.check_interval_secs > 0,
+                    "recrawl.check_interval_secs must be positive",
+                ),
+                (
+                    recrawl.batch_size > 0,
+                    "recrawl.batch_size must be positive",
+                ),
+            ];
+
+            if let Some((_, message)) = invariants.iter().find(|(holds, _)| !holds) {
+                return Err(ConfigError::Message(message.to_string()));
+            }
+        }
+
+        if let Some(barrel_tls) = &self.barrel_tls {
+            barrel_tls
+                .validate()
+                .map_err(|e| ConfigError::Message(format!("barrel_tls: {}", e)))?;
+        }
+
+        Ok(())
+    }
 }
 
 impl super::Load for GatewayConfig {
@@ -170,7 +729,7 @@ impl super::Load for GatewayConfig {
     /// ```rust
     /// use googol::settings::{gateway::GatewayConfig, Load};
     ///
-    /// let config = GatewayConfig::default();
+    /// let config = <GatewayConfig as Load>::default();
     /// ```
     fn default() -> Result<Self::Item, config::ConfigError> {
         Self::load(".gateway")
@@ -180,7 +739,7 @@ impl super::Load for GatewayConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::settings::Load;
+    use crate::settings::{FileFormat, Load};
     use url::Host;
 
     /// A valid configuration string in TOML format for testing.
@@ -206,6 +765,42 @@ mod tests {
         "#,
     ];
 
+    /// `VALID` in YAML format.
+    const VALID_YAML: &str = r#"
+address: "0.0.0.0:50051"
+queue:
+  - "https://en.wikipedia.org/wiki/Rust_(programming_language)"
+barrels:
+  - "127.0.0.1:50052"
+  - "192.168.41.13:50052"
+domains_filter:
+  whitelist: ["example.com", "test.org"]
+  blacklist: ["bad.com"]
+"#;
+
+    /// `VALID` in JSON format.
+    const VALID_JSON: &str = r#"{
+        "address": "0.0.0.0:50051",
+        "queue": ["https://en.wikipedia.org/wiki/Rust_(programming_language)"],
+        "barrels": ["127.0.0.1:50052", "192.168.41.13:50052"],
+        "domains_filter": {
+            "whitelist": ["example.com", "test.org"],
+            "blacklist": ["bad.com"]
+        }
+    }"#;
+
+    /// Tests that equivalent TOML, YAML and JSON strings produce the same configuration.
+    #[test]
+    fn test_config_formats() {
+        let toml = GatewayConfig::from_str_with_format(VALID, FileFormat::Toml).unwrap();
+        let yaml = GatewayConfig::from_str_with_format(VALID_YAML, FileFormat::Yaml).unwrap();
+        let json = GatewayConfig::from_str_with_format(VALID_JSON, FileFormat::Json).unwrap();
+
+        assert_eq!(toml, GatewayConfig::from_str(VALID).unwrap());
+        assert_eq!(toml, yaml);
+        assert_eq!(toml, json);
+    }
+
     /// Tests parsing of a valid configuration string.
     #[test]
     fn test_valid_config() {
@@ -247,6 +842,225 @@ mod tests {
         );
     }
 
+    /// Tests that the index cache configuration is optional.
+    #[test]
+    fn test_index_cache_config() {
+        let config = GatewayConfig::from_str(VALID).unwrap();
+        assert_eq!(config.index_cache.max_entries, 10_000);
+
+        let config =
+            GatewayConfig::from_str(&format!("{}\n[index_cache]\nmax_entries = 5", VALID)).unwrap();
+        assert_eq!(config.index_cache.max_entries, 5);
+        assert_eq!(config.index_cache.retry_interval_secs, 30);
+        assert_eq!(config.index_cache.max_retry_interval_secs, 600);
+    }
+
+    /// Tests that the maximum crawl depth is optional.
+    #[test]
+    fn test_max_depth_config() {
+        let config = GatewayConfig::from_str(VALID).unwrap();
+        assert_eq!(config.max_depth, None);
+
+        let config = GatewayConfig::from_str(&format!("max_depth = 2\n{}", VALID)).unwrap();
+        assert_eq!(config.max_depth, Some(2));
+    }
+
+    /// Tests that the queue filepath is optional.
+    #[test]
+    fn test_queue_filepath_config() {
+        let config = GatewayConfig::from_str(VALID).unwrap();
+        assert_eq!(config.queue_filepath, ".gateway-queue.json");
+
+        let config =
+            GatewayConfig::from_str(&format!("queue_filepath = \"queue.json\"\n{}", VALID))
+                .unwrap();
+        assert_eq!(config.queue_filepath, "queue.json");
+    }
+
+    /// Tests that the queue size is optional.
+    #[test]
+    fn test_queue_max_size_config() {
+        let config = GatewayConfig::from_str(VALID).unwrap();
+        assert_eq!(config.queue_max_size, None);
+
+        let config = GatewayConfig::from_str(&format!("queue_max_size = 100\n{}", VALID)).unwrap();
+        assert_eq!(config.queue_max_size, Some(100));
+    }
+
+    /// Tests that FishFish can be disabled.
+    #[test]
+    fn test_fishfish_config() {
+        let config = GatewayConfig::from_str(VALID).unwrap();
+        assert!(config.fishfish);
+        assert_eq!(config.fishfish_timeout_secs, 5);
+
+        let config = GatewayConfig::from_str(&format!("fishfish = false\n{}", VALID)).unwrap();
+        assert!(!config.fishfish);
+    }
+
+    /// Tests that searching every barrel is opt-in.
+    #[test]
+    fn test_search_scope_config() {
+        let config = GatewayConfig::from_str(VALID).unwrap();
+        assert_eq!(config.search_scope, SearchScope::Single);
+
+        let config =
+            GatewayConfig::from_str(&format!("search_scope = \"all\"\n{}", VALID)).unwrap();
+        assert_eq!(config.search_scope, SearchScope::All);
+
+        assert!(GatewayConfig::from_str(&format!("search_scope = \"some\"\n{}", VALID)).is_err());
+    }
+
+    /// Tests that pages are replicated to every barrel by default.
+    #[test]
+    fn test_sharding_config() {
+        let config = GatewayConfig::from_str(VALID).unwrap();
+        assert_eq!(config.sharding, Sharding::Replicate);
+
+        let config = GatewayConfig::from_str(&format!(
+            "sharding = \"host\"\nsearch_scope = \"all\"\n{}",
+            VALID
+        ))
+        .unwrap();
+        assert_eq!(config.sharding, Sharding::Host);
+    }
+
+    /// Tests that the metrics endpoint is off by default.
+    #[test]
+    fn test_metrics_address_config() {
+        let config = GatewayConfig::from_str(VALID).unwrap();
+        assert_eq!(config.metrics_address, None);
+
+        let config =
+            GatewayConfig::from_str(&format!("metrics_address = \"0.0.0.0:9100\"\n{}", VALID))
+                .unwrap();
+        assert_eq!(
+            config.metrics_address,
+            Some("0.0.0.0:9100".parse().unwrap())
+        );
+    }
+
+    /// Tests that recrawling is off unless its section is set.
+    #[test]
+    fn test_recrawl_config() {
+        let config = GatewayConfig::from_str(VALID).unwrap();
+        assert_eq!(config.recrawl, None);
+
+        let config =
+            GatewayConfig::from_str(&format!("{}\n[recrawl]\nbatch_size = 10", VALID)).unwrap();
+        assert_eq!(
+            config.recrawl,
+            Some(RecrawlConfig {
+                batch_size: 10,
+                ..Default::default()
+            })
+        );
+    }
+
+    /// Tests that the url normalization rules are optional, and can be replaced.
+    #[test]
+    fn test_url_normalization_config() {
+        let config = GatewayConfig::from_str(VALID).unwrap();
+        assert_eq!(config.url_normalization, UrlNormalization::default());
+
+        let config = GatewayConfig::from_str(&format!(
+            "{}\n[url_normalization]\ntracking_params = [\"ref\"]\nstrip_trailing_slash = true",
+            VALID
+        ))
+        .unwrap();
+        assert!(config.url_normalization.strip_fragment);
+        assert_eq!(
+            config.url_normalization.tracking_params,
+            HashSet::from(["ref".to_string()])
+        );
+        assert!(config.url_normalization.strip_trailing_slash);
+    }
+
+    fn normalize(normalization: &UrlNormalization, url: &str) -> String {
+        normalization
+            .normalize(Url::parse(url).unwrap())
+            .to_string()
+    }
+
+    #[test]
+    fn test_normalize_fragment() {
+        let mut normalization = UrlNormalization::default();
+        assert_eq!(
+            normalize(&normalization, "https://example.com/page#section"),
+            "https://example.com/page"
+        );
+
+        normalization.strip_fragment = false;
+        assert_eq!(
+            normalize(&normalization, "https://example.com/page#section"),
+            "https://example.com/page#section"
+        );
+    }
+
+    #[test]
+    fn test_normalize_tracking_params() {
+        let mut normalization = UrlNormalization::default();
+        assert_eq!(
+            normalize(
+                &normalization,
+                "https://example.com/?utm_source=a&q=rust&fbclid=b"
+            ),
+            "https://example.com/?q=rust"
+        );
+        assert_eq!(
+            normalize(&normalization, "https://example.com/?utm_source=a"),
+            "https://example.com/"
+        );
+        // Untouched queries keep their encoding
+        assert_eq!(
+            normalize(&normalization, "https://example.com/?q=a%20b"),
+            "https://example.com/?q=a%20b"
+        );
+
+        normalization.tracking_params.clear();
+        assert_eq!(
+            normalize(&normalization, "https://example.com/?utm_source=a"),
+            "https://example.com/?utm_source=a"
+        );
+    }
+
+    #[test]
+    fn test_normalize_trailing_slash() {
+        let mut normalization = UrlNormalization::default();
+        assert_eq!(
+            normalize(&normalization, "https://example.com/page/"),
+            "https://example.com/page/"
+        );
+
+        normalization.strip_trailing_slash = true;
+        assert_eq!(
+            normalize(&normalization, "https://example.com/page/"),
+            "https://example.com/page"
+        );
+        assert_eq!(
+            normalize(&normalization, "https://example.com/"),
+            "https://example.com/"
+        );
+    }
+
+    #[test]
+    fn test_normalize_host_and_port() {
+        let normalization = UrlNormalization::default();
+
+        assert_eq!(
+            normalize(&normalization, "HTTPS://Example.COM:443/Page"),
+            "https://example.com/Page"
+        );
+        assert_eq!(
+            normalize(&normalization, "http://example.com:80/"),
+            "http://example.com/"
+        );
+        assert_eq!(
+            normalize(&normalization, "http://example.com:8080/"),
+            "http://example.com:8080/"
+        );
+    }
+
     /// Tests domain filtering methods.
     #[test]
     fn test_url_domain() {
@@ -261,6 +1075,125 @@ mod tests {
         assert!(config.domains_filter.is_blacklisted(&url2));
     }
 
+    /// Tests that subdomains are only matched if enabled, for each list.
+    #[test]
+    fn test_subdomains() {
+        let config = GatewayConfig::from_str(VALID).unwrap();
+        let url = |url: &str| Url::parse(url).unwrap();
+
+        // Exact matches by default
+        let filter = config.domains_filter;
+        assert!(!filter.is_whitelisted(&url("https://www.example.com/")));
+        assert!(!filter.is_blacklisted(&url("https://www.bad.com/")));
+
+        let filter = DomainsFilter {
+            whitelist_subdomains: true,
+            ..filter
+        };
+        for whitelisted in [
+            "https://example.com/",
+            "https://www.example.com/",
+            "https://docs.api.example.com/",
+            "https://www.test.org/",
+        ] {
+            assert!(filter.is_whitelisted(&url(whitelisted)), "{}", whitelisted);
+        }
+        for other in [
+            "https://notexample.com/",
+            "https://example.com.evil.net/",
+            "https://com/",
+        ] {
+            assert!(!filter.is_whitelisted(&url(other)), "{}", other);
+        }
+        assert!(!filter.is_blacklisted(&url("https://www.bad.com/")));
+
+        let filter = DomainsFilter {
+            blacklist_subdomains: true,
+            ..filter
+        };
+        assert!(filter.is_blacklisted(&url("https://cdn.www.bad.com/")));
+        assert!(!filter.is_allowed(&url("https://cdn.www.bad.com/")));
+    }
+
+    /// Tests that hosts with path prefixes are only crawled under them.
+    #[test]
+    fn test_path_prefixes() {
+        let config = GatewayConfig::from_str(&format!(
+            "{}\npath_prefixes = {{ \"Example.COM\" = [\"/docs/\", \"/blog/\"] }}",
+            VALID
+        ))
+        .unwrap();
+        let url = |url: &str| Url::parse(url).unwrap();
+
+        let filter = config.domains_filter;
+        assert!(filter.is_allowed(&url("https://example.com/docs/intro")));
+        assert!(filter.is_allowed(&url("https://example.com/blog/")));
+        assert!(!filter.is_allowed(&url("https://example.com/")));
+        assert!(!filter.is_allowed(&url("https://example.com/about")));
+        // Other hosts aren't restricted
+        assert!(filter.is_allowed(&url("https://test.org/about")));
+        // Hosts are normalized, as in URLs
+        assert_eq!(
+            filter.path_prefixes.keys().collect::<Vec<_>>(),
+            [&Host::parse("example.com").unwrap()]
+        );
+
+        // Subdomains only follow the prefixes of their domain if whitelisted with it
+        assert!(filter.is_path_allowed(&url("https://www.example.com/about")));
+        let filter = DomainsFilter {
+            whitelist_subdomains: true,
+            ..filter
+        };
+        assert!(!filter.is_allowed(&url("https://www.example.com/about")));
+        assert!(filter.is_allowed(&url("https://www.example.com/docs/")));
+    }
+
+    /// Tests that configurations violating the gateway's invariants are rejected.
+    #[test]
+    fn test_validate() {
+        let barrels = r#"barrels = [ "127.0.0.1:50052", "192.168.41.13:50052" ]"#;
+        let blacklist = r#"blacklist = ["bad.com"]"#;
+
+        let colliding_barrel = VALID.replace(barrels, r#"barrels = [ "127.0.0.1:50051" ]"#);
+        let overlapping_filters =
+            VALID.replace(blacklist, r#"blacklist = ["bad.com", "example.com"]"#);
+        let colliding_metrics = format!("metrics_address = \"127.0.0.1:50051\"\n{}", VALID);
+        let empty_recrawl_batch = format!("{}\n[recrawl]\nbatch_size = 0", VALID);
+        let sharding_single_search = format!("sharding = \"host\"\n{}", VALID);
+        let no_message_size = format!("max_message_size = 0\n{}", VALID);
+        let invalid_path_prefix_host = format!(
+            "{}\npath_prefixes = {{ \"exa mple.com\" = [\"/docs/\"] }}",
+            VALID
+        );
+        let relative_path_prefix = format!(
+            "{}\npath_prefixes = {{ \"example.com\" = [\"docs/\"] }}",
+            VALID
+        );
+
+        for invalid in [
+            colliding_barrel,
+            overlapping_filters,
+            colliding_metrics,
+            empty_recrawl_batch,
+            sharding_single_search,
+            no_message_size,
+            relative_path_prefix,
+            invalid_path_prefix_host,
+        ] {
+            assert_ne!(invalid, VALID);
+            assert!(GatewayConfig::from_str(&invalid).is_err(), "{}", invalid);
+        }
+
+        // Barrels can be added later
+        let no_barrels = VALID.replace(barrels, "barrels = []");
+        assert!(
+            GatewayConfig::from_str(&no_barrels)
+                .unwrap()
+                .barrels
+                .is_empty()
+        );
+    }
+
     /// Tests loading configuration from invalid strings.
     #[test]
     fn test_invalid_config() {
@@ -8,6 +8,7 @@
 //! # Main Components
 //!
 //! - `Load` trait: Defines methods to load configuration data from files or strings.
+//! - `Validate` trait: Checks the invariants of a configuration once it is loaded.
 //! - `GoogolConfig` struct: Encapsulates configuration for all components.
 //!
 //! # Usage
@@ -32,41 +33,131 @@
 //! - `client`
 //! - `downloader`
 //! - `gateway`
+//! - `tls`
+//! - `watch`
 //! - `web_server`
 //!
 //! Each module contains specific configuration options relevant to its component.
+//!
+//! # Environment Variables
+//!
+//! Values loaded from a file can be overridden by environment variables prefixed with
+//! `GOOGOL`, using `__` to separate the prefix and each level of nesting. For example,
+//! `GOOGOL__GATEWAY__ADDRESS=0.0.0.0:50051` overrides `address` in the `[gateway]` table.
 
 use barrel::BarrelConfig;
 use client::ClientConfig;
-use config::{Config, ConfigError, File, FileFormat};
+use config::{Config, ConfigError, Environment, File};
 use downloader::DownloaderConfig;
 use gateway::GatewayConfig;
 use serde::{Deserialize, de::DeserializeOwned};
+use std::net::SocketAddr;
 use web_server::WebServerConfig;
 
+pub use config::FileFormat;
+
 pub mod barrel;
 pub mod client;
 pub mod downloader;
 pub mod gateway;
+pub mod tls;
+pub mod watch;
 pub mod web_server;
 
+/// Prefix of the environment variables overriding configuration values.
+pub const ENV_PREFIX: &str = "GOOGOL";
+
+/// Separator between the prefix and the nested keys of environment variables.
+pub const ENV_SEPARATOR: &str = "__";
+
+/// Trait for checking the invariants of a configuration that deserialization can't express.
+pub trait Validate {
+    /// Checks the configuration makes sense, e.g. that a component has something to work with.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the configuration is valid, or a `ConfigError::Message` describing the
+    /// first violated invariant.
+    fn validate(&self) -> Result<(), ConfigError>;
+}
+
+/// Checks whether two listening addresses would compete for the same port on this host.
+///
+/// Besides equal addresses, an unspecified address takes the port on every interface of this
+/// host, so it collides with loopback and unspecified ones. Other addresses may belong to
+/// other hosts, so they never collide with it.
+fn collides(a: &SocketAddr, b: &SocketAddr) -> bool {
+    let is_local =
+        |address: &SocketAddr| address.ip().is_loopback() || address.ip().is_unspecified();
+
+    a.port() == b.port()
+        && (a.ip() == b.ip()
+            || (a.ip().is_unspecified() && is_local(b))
+            || (b.ip().is_unspecified() && is_local(a)))
+}
+
+/// gRPC messages are gzip-compressed unless disabled, e.g. to inspect the traffic.
+fn default_compression() -> bool {
+    true
+}
+
+/// Largest gRPC message sent or received by default, in bytes.
+///
+/// Well above tonic's own limit of 4 MiB, which the indexes of large pages and long search
+/// results exceed.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+fn default_max_message_size() -> usize {
+    DEFAULT_MAX_MESSAGE_SIZE
+}
+
+/// The environment variables overriding the values of configuration files, see [`Load::load`].
+fn environment() -> Environment {
+    Environment::with_prefix(ENV_PREFIX).separator(ENV_SEPARATOR)
+}
+
+/// Loads and validates the configuration of `file`, with values overridden by `environment`.
+fn load_with<T>(file: &str, environment: Environment) -> Result<T, ConfigError>
+where
+    T: DeserializeOwned + Validate,
+{
+    let item: T = Config::builder()
+        .add_source(File::with_name(file))
+        .add_source(environment)
+        .build()?
+        .try_deserialize()?;
+
+    item.validate()?;
+
+    Ok(item)
+}
+
+/// Only errors are logged unless set otherwise, as with `RUST_LOG` unset.
+fn default_log_level() -> String {
+    "error".to_string()
+}
+
 /// Trait for loading configuration data from files or strings.
 ///
 /// Implemented for types that can be deserialized from configuration files or strings.
-/// Provides default loading behavior.
+/// Provides default loading behavior. Loaded items are validated before being returned.
 pub trait Load {
     /// The type of the configuration item.
-    type Item: DeserializeOwned;
+    type Item: DeserializeOwned + Validate;
 
     /// Loads configuration from a file specified by `file`.
     ///
+    /// Environment variables of the form `GOOGOL__<SECTION>__<KEY>` take precedence over
+    /// the values in the file.
+    ///
     /// # Arguments
     ///
     /// * `file` - Path to the configuration file.
     ///
     /// # Returns
     ///
-    /// `Ok(Self::Item)` if successful, or a `ConfigError` if loading or deserialization fails.
+    /// `Ok(Self::Item)` if successful, or a `ConfigError` if loading, deserialization or
+    /// validation fails.
     ///
     /// # Example
     ///
@@ -76,10 +167,7 @@ pub trait Load {
     /// let config = GoogolConfig::load("googol"); // Load googol.toml
     /// ```
     fn load(file: &str) -> Result<Self::Item, ConfigError> {
-        Config::builder()
-            .add_source(File::with_name(file))
-            .build()?
-            .try_deserialize()
+        load_with(file, environment())
     }
 
     /// Loads configuration from a string input, expected to be in TOML format.
@@ -106,14 +194,49 @@ pub trait Load {
     /// assert!(config.is_err());
     /// ```
     fn from_str(input: &str) -> Result<Self::Item, ConfigError> {
-        Config::builder()
-            .add_source(File::from_str(input, FileFormat::Toml))
+        Self::from_str_with_format(input, FileFormat::Toml)
+    }
+
+    /// Loads configuration from a string input in the given format.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - String containing the configuration data.
+    /// * `format` - Format of the configuration data, e.g. `FileFormat::Yaml`.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Self::Item)` if successful, or a `ConfigError` if parsing, deserialization or
+    /// validation fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use googol::settings::{FileFormat, Load, client::ClientConfig};
+    ///
+    /// let config = ClientConfig::from_str_with_format(
+    ///     r#"{ "gateway": "127.0.0.1:50051", "max_retries": 3 }"#,
+    ///     FileFormat::Json,
+    /// );
+    /// assert!(config.is_ok());
+    /// ```
+    fn from_str_with_format(input: &str, format: FileFormat) -> Result<Self::Item, ConfigError> {
+        let item: Self::Item = Config::builder()
+            .add_source(File::from_str(input, format))
             .build()?
-            .try_deserialize()
+            .try_deserialize()?;
+
+        item.validate()?;
+
+        Ok(item)
     }
 
     /// Loads configuration from a default file.
     ///
+    /// Unlike `std::default::Default`, which the configuration types also implement with
+    /// hardcoded values, this fails if the file is missing. Call it as `<T as Load>::default()`
+    /// when both traits are in scope.
+    ///
     /// # Returns
     ///
     /// `Ok(Self::Item)` if successful, or a `ConfigError` if loading/deserialization fails.
@@ -123,7 +246,7 @@ pub trait Load {
     /// ```rust
     /// use googol::settings::{GoogolConfig, Load};
     ///
-    /// let default_config = GoogolConfig::default();
+    /// let default_config = <GoogolConfig as Load>::default();
     /// ```
     fn default() -> Result<Self::Item, ConfigError>;
 }
@@ -131,8 +254,9 @@ pub trait Load {
 /// Main configuration struct aggregating configurations for all components.
 ///
 /// This struct is deserialized from configuration sources
-/// and contains nested configurations for each subsystem.
-#[derive(Debug, Deserialize)]
+/// and contains nested configurations for each subsystem. Its `Default` holds the defaults of
+/// each subsystem, all of them on localhost.
+#[derive(Debug, Default, Deserialize)]
 pub struct GoogolConfig {
     /// Configuration for the `barrel` component.
     pub barrel: BarrelConfig,
@@ -146,6 +270,42 @@ pub struct GoogolConfig {
     pub web_server: WebServerConfig,
 }
 
+impl Validate for GoogolConfig {
+    /// Validates every component, and checks that the servers don't share a port.
+    fn validate(&self) -> Result<(), ConfigError> {
+        let components: [(&str, &dyn Validate); 5] = [
+            ("barrel", &self.barrel),
+            ("client", &self.client),
+            ("downloader", &self.downloader),
+            ("gateway", &self.gateway),
+            ("web_server", &self.web_server),
+        ];
+        for (name, component) in components {
+            component
+                .validate()
+                .map_err(|e| ConfigError::Message(format!("{}: {}", name, e)))?;
+        }
+
+        let servers = [
+            ("gateway", &self.gateway.address),
+            ("barrel", &self.barrel.address),
+            ("web_server", &self.web_server.address),
+        ];
+        for (i, (a, a_address)) in servers.iter().enumerate() {
+            for (b, b_address) in &servers[i + 1..] {
+                if collides(a_address, b_address) {
+                    return Err(ConfigError::Message(format!(
+                        "{} address {} collides with {} address {}",
+                        a, a_address, b, b_address
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl Load for GoogolConfig {
     type Item = Self;
 
@@ -165,4 +325,69 @@ mod tests {
 
         assert!(config.is_ok(), "Failed to load example configuration");
     }
+
+    /// Tests that components sharing a port are rejected.
+    #[test]
+    fn test_colliding_servers() {
+        let mut config: GoogolConfig = Default::default();
+        assert!(config.validate().is_ok());
+
+        config.web_server.address = "0.0.0.0:50052".parse().unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    /// Tests that invalid components invalidate the whole configuration.
+    #[test]
+    fn test_invalid_component() {
+        let mut config: GoogolConfig = Default::default();
+        config.downloader.threads = 0;
+
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.starts_with("downloader:"), "{}", error);
+    }
+
+    /// Tests the addresses considered to collide.
+    #[test]
+    fn test_collides() {
+        let addr = |s: &str| s.parse::<SocketAddr>().unwrap();
+
+        assert!(collides(&addr("127.0.0.1:80"), &addr("127.0.0.1:80")));
+        assert!(collides(&addr("0.0.0.0:80"), &addr("127.0.0.1:80")));
+        assert!(collides(&addr("[::1]:80"), &addr("[::]:80")));
+        assert!(collides(&addr("0.0.0.0:80"), &addr("[::]:80")));
+        assert!(!collides(&addr("0.0.0.0:80"), &addr("10.0.0.1:80")));
+        assert!(!collides(&addr("127.0.0.1:80"), &addr("127.0.0.2:80")));
+        assert!(!collides(&addr("127.0.0.1:80"), &addr("127.0.0.1:81")));
+        assert!(!collides(&addr("10.0.0.1:80"), &addr("10.0.0.2:80")));
+    }
+
+    /// Tests that the in-memory defaults of the components point at each other.
+    #[test]
+    fn test_default_config() {
+        let config: GoogolConfig = Default::default();
+
+        assert_eq!(config.client.gateway, config.gateway.address);
+        assert_eq!(config.downloader.gateway, config.gateway.address);
+        assert_eq!(config.web_server.gateway_address, config.gateway.address);
+        assert!(config.gateway.barrels.contains(&config.barrel.address));
+        assert!(config.gateway.queue.is_empty());
+    }
+
+    /// Tests environment variables overriding the configuration file.
+    #[test]
+    fn test_env_override() {
+        // Given as the environment, rather than set in the process shared with other tests
+        let variables = [("GOOGOL__GATEWAY__ADDRESS", "127.0.0.1:60051")]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        let environment = environment().source(Some(variables));
+
+        let config: Result<GoogolConfig, _> = load_with("example.googol.toml", environment);
+
+        assert_eq!(
+            config.unwrap().gateway.address,
+            "127.0.0.1:60051".parse().unwrap()
+        );
+    }
 }
@@ -0,0 +1,193 @@
+//! TLS settings of the gRPC servers and of the clients connecting to them.
+//!
+//! Certificates and keys are PEM encoded files, read when a server starts or a client first
+//! connects. Without these settings, gRPC traffic is sent in plaintext.
+
+use super::Validate;
+use config::ConfigError;
+use serde::Deserialize;
+use std::{fs, io, net::SocketAddr, path::PathBuf};
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint, Error, Identity, ServerTlsConfig};
+
+/// TLS settings of a gRPC server.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::settings::tls::TlsServerConfig;
+///
+/// let config = TlsServerConfig {
+///     cert: "examples/tls/server.pem".into(),
+///     key: "examples/tls/server.key".into(),
+///     client_ca: Some("examples/tls/ca.pem".into()),
+/// };
+///
+/// assert!(config.read().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TlsServerConfig {
+    /// Certificate chain presented to clients.
+    pub cert: PathBuf,
+    /// Private key of the certificate.
+    pub key: PathBuf,
+    /// CA certificate that signs client certificates. If set, clients must present one
+    /// (mutual TLS).
+    #[serde(default)]
+    pub client_ca: Option<PathBuf>,
+}
+
+impl TlsServerConfig {
+    /// Reads the certificates and key into a configuration for `Server::tls_config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if any of the files can't be read.
+    pub fn read(&self) -> Result<ServerTlsConfig, io::Error> {
+        let identity = Identity::from_pem(fs::read(&self.cert)?, fs::read(&self.key)?);
+        let config = ServerTlsConfig::new().identity(identity);
+
+        Ok(match &self.client_ca {
+            Some(client_ca) => config.client_ca_root(Certificate::from_pem(fs::read(client_ca)?)),
+            None => config,
+        })
+    }
+}
+
+/// TLS settings of a gRPC client.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::settings::tls::TlsClientConfig;
+///
+/// let config = TlsClientConfig {
+///     ca: "examples/tls/ca.pem".into(),
+///     domain: None,
+///     cert: Some("examples/tls/client.pem".into()),
+///     key: Some("examples/tls/client.key".into()),
+/// };
+///
+/// assert!(config.read().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TlsClientConfig {
+    /// CA certificate that signs the server's certificate.
+    pub ca: PathBuf,
+    /// Name the server's certificate is issued for. Defaults to the IP address connected to.
+    #[serde(default)]
+    pub domain: Option<String>,
+    /// Certificate presented to the server, for mutual TLS. Requires `key`.
+    #[serde(default)]
+    pub cert: Option<PathBuf>,
+    /// Private key of `cert`.
+    #[serde(default)]
+    pub key: Option<PathBuf>,
+}
+
+impl TlsClientConfig {
+    /// Reads the certificates and key into a configuration for [`endpoint`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if any of the files can't be read.
+    pub fn read(&self) -> Result<ClientTlsConfig, io::Error> {
+        let mut config =
+            ClientTlsConfig::new().ca_certificate(Certificate::from_pem(fs::read(&self.ca)?));
+
+        if let Some(domain) = &self.domain {
+            config = config.domain_name(domain);
+        }
+
+        if let (Some(cert), Some(key)) = (&self.cert, &self.key) {
+            config = config.identity(Identity::from_pem(fs::read(cert)?, fs::read(key)?));
+        }
+
+        Ok(config)
+    }
+}
+
+impl Validate for TlsClientConfig {
+    /// Checks that a client certificate comes with its key.
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.cert.is_some() != self.key.is_some() {
+            return Err(ConfigError::Message(
+                "cert and key must be set together".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds the endpoint of the gRPC server at `address`, connecting over TLS if `tls` is set.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::settings::tls::endpoint;
+///
+/// let endpoint = endpoint("127.0.0.1:50051".parse().unwrap(), None).unwrap();
+///
+/// assert_eq!(endpoint.uri(), "http://127.0.0.1:50051/");
+/// ```
+pub fn endpoint(address: SocketAddr, tls: Option<&ClientTlsConfig>) -> Result<Endpoint, Error> {
+    match tls {
+        Some(tls) => Endpoint::from_shared(format!("https://{}", address))?.tls_config(tls.clone()),
+        None => Endpoint::from_shared(format!("http://{}", address)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client_config() -> TlsClientConfig {
+        TlsClientConfig {
+            ca: "examples/tls/ca.pem".into(),
+            domain: None,
+            cert: None,
+            key: None,
+        }
+    }
+
+    /// Tests that a client certificate without its key, or the reverse, is rejected.
+    #[test]
+    fn test_validate() {
+        let mut config = client_config();
+        assert!(config.validate().is_ok());
+
+        config.cert = Some("examples/tls/client.pem".into());
+        assert!(config.validate().is_err());
+
+        config.key = Some("examples/tls/client.key".into());
+        assert!(config.validate().is_ok());
+
+        config.cert = None;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_read_missing_file() {
+        let config = TlsClientConfig {
+            ca: "examples/tls/missing.pem".into(),
+            ..client_config()
+        };
+        assert!(config.read().is_err());
+
+        let config = TlsServerConfig {
+            cert: "examples/tls/server.pem".into(),
+            key: "examples/tls/missing.key".into(),
+            client_ca: None,
+        };
+        assert!(config.read().is_err());
+    }
+
+    #[test]
+    fn test_endpoint_scheme() {
+        let address = "127.0.0.1:50051".parse().unwrap();
+
+        let endpoint = endpoint(address, Some(&client_config().read().unwrap())).unwrap();
+
+        assert_eq!(endpoint.uri().scheme_str(), Some("https"));
+    }
+}
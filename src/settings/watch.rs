@@ -0,0 +1,77 @@
+//! Watching a configuration file, so components can reload it without restarting.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use googol::settings::watch::FileWatcher;
+//!
+//! # async fn run() -> notify::Result<()> {
+//! let mut watcher = FileWatcher::new("googol.toml")?;
+//!
+//! while watcher.changed().await {
+//!     println!("googol.toml changed");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use log::error;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+/// Notifies the changes of a file. The file stops being watched once dropped.
+pub struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    changes: mpsc::UnboundedReceiver<()>,
+}
+
+impl FileWatcher {
+    /// Starts watching `path`, which doesn't have to exist yet.
+    ///
+    /// # Errors
+    /// Returns an error if the directory of the file can't be watched.
+    pub fn new(path: impl AsRef<Path>) -> notify::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (tx, changes) = mpsc::unbounded_channel();
+
+        // Editors often replace the file instead of writing to it, so watch its directory
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let event: notify::Event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    error!("Failed to watch {}: {}", path.display(), e);
+                    return;
+                }
+            };
+
+            let modified = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event
+                    .paths
+                    .iter()
+                    .any(|changed| changed.file_name() == path.file_name());
+            if modified {
+                let _ = tx.send(());
+            }
+        })?;
+        watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// Waits for the file to be created or modified.
+    ///
+    /// # Returns
+    /// `false` if the file can no longer be watched.
+    pub async fn changed(&mut self) -> bool {
+        self.changes.recv().await.is_some()
+    }
+}
@@ -1,5 +1,10 @@
-use serde::Deserialize;
-use std::net::SocketAddr;
+use super::{Validate, collides, tls::TlsClientConfig};
+use config::ConfigError;
+use serde::{Deserialize, Serialize};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+};
 
 /// Configuration for the web server, including the server's address and the gateway's address.
 ///
@@ -15,6 +20,14 @@ use std::net::SocketAddr;
 /// let config = WebServerConfig {
 ///     address: "0.0.0.0:8080".parse().unwrap(),
 ///     gateway_address: "127.0.0.1:50051".parse().unwrap(),
+///     allowed_origins: vec!["http://localhost:3000".to_string()],
+///     gateway_tls: None,
+///     compression: true,
+///     max_message_size: 64 * 1024 * 1024,
+///     rate_limit: None,
+///     serve_ui: true,
+///     log_file: None,
+///     log_level: "info".to_string(),
 /// };
 ///
 /// // Accessing the addresses
@@ -29,6 +42,127 @@ pub struct WebServerConfig {
     pub address: SocketAddr,
     /// The address of the gateway.
     pub gateway_address: SocketAddr,
+    /// Origins allowed to make cross-origin requests, e.g. `http://localhost:3000`, or `*` for
+    /// any origin. Only same-origin requests are allowed by default.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// Connects to the gateway over TLS if set, instead of plaintext.
+    #[serde(default)]
+    pub gateway_tls: Option<TlsClientConfig>,
+    /// Whether the gRPC messages sent to the gateway are gzip-compressed. Compressed messages are
+    /// accepted either way.
+    #[serde(default = "super::default_compression")]
+    pub compression: bool,
+    /// Largest gRPC message sent or received, in bytes.
+    #[serde(default = "super::default_max_message_size")]
+    pub max_message_size: usize,
+    /// Limits the rate of search and enqueue requests of each client if set. Requests are never
+    /// limited otherwise.
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Whether a search page, with a live status panel, is served at `/`. Only the JSON API is
+    /// served otherwise, e.g. for headless deployments.
+    #[serde(default = "default_serve_ui")]
+    pub serve_ui: bool,
+    /// File logs are also written to, rotated daily. Logs only go to stderr if not set.
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+    /// Minimum level logged, e.g. `info`. The `RUST_LOG` environment variable takes precedence.
+    #[serde(default = "super::default_log_level")]
+    pub log_level: String,
+}
+
+impl Default for WebServerConfig {
+    /// Listens on `127.0.0.1:8080`, with a gateway at `127.0.0.1:50051`.
+    fn default() -> Self {
+        Self {
+            address: (Ipv4Addr::LOCALHOST, 8080).into(),
+            gateway_address: (Ipv4Addr::LOCALHOST, 50051).into(),
+            allowed_origins: Vec::new(),
+            gateway_tls: None,
+            compression: super::default_compression(),
+            max_message_size: super::default_max_message_size(),
+            rate_limit: None,
+            serve_ui: default_serve_ui(),
+            log_file: None,
+            log_level: super::default_log_level(),
+        }
+    }
+}
+
+fn default_serve_ui() -> bool {
+    true
+}
+
+/// Per-IP limit on the rate of requests, as a token bucket: a client may send `burst` requests
+/// at once, but no more than `requests_per_second` on average.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::settings::web_server::RateLimitConfig;
+///
+/// let config = RateLimitConfig::default();
+/// assert_eq!(config.requests_per_second, 5.0);
+/// assert_eq!(config.burst, 10);
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Requests allowed per second from the same IP address, on average.
+    pub requests_per_second: f64,
+    /// Requests allowed at once from the same IP address.
+    pub burst: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 5.,
+            burst: 10,
+        }
+    }
+}
+
+impl Validate for WebServerConfig {
+    /// Checks that the web server doesn't listen on the gateway's port, and that messages can
+    /// be sent.
+    fn validate(&self) -> Result<(), ConfigError> {
+        if collides(&self.address, &self.gateway_address) {
+            return Err(ConfigError::Message(format!(
+                "address {} collides with gateway_address {}",
+                self.address, self.gateway_address
+            )));
+        }
+
+        if self.max_message_size < 1 {
+            return Err(ConfigError::Message(
+                "max_message_size must be at least 1".to_string(),
+            ));
+        }
+
+        if let Some(rate_limit) = &self.rate_limit {
+            let invariants = [
+                (
+                    rate_limit.requests_per_second > 0.,
+                    "rate_limit.requests_per_second must be positive",
+                ),
+                (rate_limit.burst > 0, "rate_limit.burst must be positive"),
+            ];
+
+            if let Some((_, message)) = invariants.iter().find(|(holds, _)| !holds) {
+                return Err(ConfigError::Message(message.to_string()));
+            }
+        }
+
+        if let Some(gateway_tls) = &self.gateway_tls {
+            gateway_tls
+                .validate()
+                .map_err(|e| ConfigError::Message(format!("gateway_tls: {}", e)))?;
+        }
+
+        Ok(())
+    }
 }
 
 impl super::Load for WebServerConfig {
@@ -46,7 +180,7 @@ impl super::Load for WebServerConfig {
     /// ```rust
     /// use googol::settings::{web_server::WebServerConfig, Load};
     ///
-    /// let config = WebServerConfig::default();
+    /// let config = <WebServerConfig as Load>::default();
     /// ```
     fn default() -> Result<Self::Item, config::ConfigError> {
         Self::load(".web-server")
@@ -94,6 +228,69 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_allowed_origins() {
+        let config = WebServerConfig::from_str(VALID).unwrap();
+        assert!(config.allowed_origins.is_empty());
+
+        let config = WebServerConfig::from_str(&format!(
+            "allowed_origins = [\"http://localhost:3000\"]\n{}",
+            VALID
+        ))
+        .unwrap();
+        assert_eq!(config.allowed_origins, vec!["http://localhost:3000"]);
+    }
+
+    /// Tests that the search page is served by default, and can be disabled.
+    #[test]
+    fn test_serve_ui_config() {
+        let config = WebServerConfig::from_str(VALID).unwrap();
+        assert!(config.serve_ui);
+
+        let config = WebServerConfig::from_str(&format!("serve_ui = false\n{}", VALID)).unwrap();
+        assert!(!config.serve_ui);
+    }
+
+    /// Tests that rate limiting is optional, with defaults for what isn't set.
+    #[test]
+    fn test_rate_limit_config() {
+        let config = WebServerConfig::from_str(VALID).unwrap();
+        assert_eq!(config.rate_limit, None);
+
+        let config =
+            WebServerConfig::from_str(&format!("{}\n[rate_limit]\nburst = 20", VALID)).unwrap();
+        assert_eq!(
+            config.rate_limit,
+            Some(RateLimitConfig {
+                burst: 20,
+                ..Default::default()
+            })
+        );
+    }
+
+    /// Tests that listening on the gateway's port is rejected.
+    #[test]
+    fn test_validate() {
+        let config = WebServerConfig::from_str(
+            r#"
+            address = "0.0.0.0:50051"
+            gateway_address = "127.0.0.1:50051"
+            "#,
+        );
+
+        assert!(config.is_err());
+
+        for rate_limit in ["requests_per_second = 0.0", "burst = 0"] {
+            let config =
+                WebServerConfig::from_str(&format!("{}\n[rate_limit]\n{}", VALID, rate_limit));
+
+            assert!(config.is_err(), "{}", rate_limit);
+        }
+
+        let config = WebServerConfig::from_str(&format!("max_message_size = 0\n{}", VALID));
+        assert!(config.is_err());
+    }
+
     /// Tests handling of invalid configuration strings.
     #[test]
     fn test_invalid_config() {
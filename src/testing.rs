@@ -0,0 +1,182 @@
+//! Helpers building realistic data for tests and benchmarks.
+//!
+//! Only compiled with the `testing` feature, e.g. `cargo bench --features testing`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use googol::{index_store::IndexStore, testing::synthetic_word};
+//!
+//! let index_store = IndexStore::synthetic(1_000, 20, 42);
+//!
+//! // The most frequent words are found on many pages
+//! assert!(index_store.search(&[synthetic_word(0)]).len() > 100);
+//!
+//! // And the same seed always builds the same index
+//! assert_eq!(index_store, IndexStore::synthetic(1_000, 20, 42));
+//! ```
+
+use crate::{index_store::IndexStore, page::PageBuilder};
+use rand::{
+    Rng, SeedableRng,
+    distr::{Distribution, weighted::WeightedIndex},
+    rngs::StdRng,
+};
+use url::Url;
+
+/// Number of distinct words pages are written with.
+pub const VOCABULARY_SIZE: usize = 10_000;
+/// Number of words in the title of each page.
+const TITLE_WORDS: usize = 3;
+/// Number of links out of each page.
+const OUTLINKS: usize = 5;
+/// Share of links pointing to pages that are already linked to, rather than to any page.
+const PREFERENTIAL_LINKS: f64 = 0.8;
+/// Number of consecutive pages of each host.
+const PAGES_PER_HOST: usize = 100;
+
+/// Syllables the words of the vocabulary are made of.
+const SYLLABLES: [&str; 20] = [
+    "ba", "ce", "di", "fo", "gu", "ha", "je", "ki", "lo", "mu", "na", "pe", "qui", "ro", "su",
+    "ta", "ve", "xi", "yo", "zu",
+];
+
+/// Word of the synthetic vocabulary with the given frequency rank, the most frequent being 0.
+///
+/// Words are pronounceable and distinct, e.g. `ba` for 0 and `ceba` for 21, so they stem and
+/// get corrected like real ones.
+///
+/// # Examples
+///
+/// ```rust
+/// use googol::testing::synthetic_word;
+///
+/// assert_eq!(synthetic_word(0), "ba");
+/// assert_eq!(synthetic_word(21), "ceba");
+/// ```
+pub fn synthetic_word(rank: usize) -> String {
+    let mut word = String::new();
+    let mut rank = rank;
+
+    loop {
+        word.push_str(SYLLABLES[rank % SYLLABLES.len()]);
+        rank /= SYLLABLES.len();
+
+        if rank == 0 {
+            return word;
+        }
+        rank -= 1;
+    }
+}
+
+/// Url of the synthetic page with the given number.
+pub fn synthetic_url(page: usize) -> Url {
+    format!(
+        "https://site{}.example.com/page/{}",
+        page / PAGES_PER_HOST,
+        page
+    )
+    .parse()
+    .expect("Synthetic urls are valid")
+}
+
+impl IndexStore {
+    /// Builds an index of `pages` pages, the same for a given `seed`.
+    ///
+    /// Words are drawn from [`VOCABULARY_SIZE`] words with a Zipf distribution, as in real
+    /// text, so [`synthetic_word`] `(0)` is on most pages while rare words are on a few.
+    /// Links mostly point to pages already linked to, so a few pages gather most backlinks,
+    /// as on the web.
+    ///
+    /// # Arguments
+    ///
+    /// * `pages` - Number of pages indexed.
+    /// * `words_per_page` - Number of words of each page, besides its title.
+    /// * `seed` - Seed of the random choices.
+    ///
+    /// # Returns
+    ///
+    /// The index, without a filepath.
+    pub fn synthetic(pages: usize, words_per_page: usize, seed: u64) -> IndexStore {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let zipf = WeightedIndex::new((1..=VOCABULARY_SIZE).map(|rank| 1. / rank as f64))
+            .expect("Weights are positive");
+
+        let mut index_store = IndexStore::default();
+        // Every link target so far, so picking one at random favors the most linked pages
+        let mut targets: Vec<usize> = Vec::with_capacity(pages * OUTLINKS);
+
+        for page in 0..pages {
+            let mut draw_words = |count| -> Vec<String> {
+                (0..count)
+                    .map(|_| synthetic_word(zipf.sample(&mut rng)))
+                    .collect()
+            };
+            let title_words = draw_words(TITLE_WORDS);
+            let words = draw_words(words_per_page);
+
+            let outlinks: Vec<Url> = (0..OUTLINKS)
+                .map(|_| {
+                    let target = match targets.is_empty() || !rng.random_bool(PREFERENTIAL_LINKS) {
+                        true => rng.random_range(0..pages),
+                        false => targets[rng.random_range(0..targets.len())],
+                    };
+                    targets.push(target);
+
+                    synthetic_url(target)
+                })
+                .collect();
+
+            let page = PageBuilder::default()
+                .url(synthetic_url(page))
+                .title(title_words.join(" "))
+                .summary(words.iter().take(20).cloned().collect::<Vec<_>>().join(" "))
+                .build()
+                .expect("Synthetic pages are valid");
+
+            index_store.store(&page, &title_words, &words, &outlinks);
+        }
+
+        index_store
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_synthetic_words_are_distinct() {
+        let words: HashSet<String> = (0..VOCABULARY_SIZE).map(synthetic_word).collect();
+
+        assert_eq!(words.len(), VOCABULARY_SIZE);
+    }
+
+    #[test]
+    fn test_synthetic_is_seeded() {
+        let index_store = IndexStore::synthetic(500, 30, 1);
+
+        assert_eq!(index_store, IndexStore::synthetic(500, 30, 1));
+        assert_ne!(index_store, IndexStore::synthetic(500, 30, 2));
+        assert_eq!(index_store.stats().indexed_pages, 500);
+    }
+
+    #[test]
+    fn test_synthetic_is_realistic() {
+        let index_store = IndexStore::synthetic(2_000, 50, 7);
+
+        // Frequent words are on most pages, rare ones on few
+        let frequent = index_store.document_frequency(&synthetic_word(0));
+        let rare = index_store.document_frequency(&synthetic_word(VOCABULARY_SIZE / 2));
+        assert!(frequent > 1_000, "{}", frequent);
+        assert!(rare < 50, "{}", rare);
+
+        // A few pages gather many backlinks
+        let most_linked = (0..2_000)
+            .map(|page| index_store.consult_backlinks(&synthetic_url(page)).len())
+            .max()
+            .unwrap();
+        assert!(most_linked > 50, "{}", most_linked);
+    }
+}
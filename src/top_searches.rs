@@ -12,9 +12,18 @@
 
 use std::{
     cmp::Reverse,
-    collections::{BinaryHeap, HashMap},
+    collections::{BinaryHeap, HashMap, VecDeque},
+    time::{Duration, Instant},
 };
 
+/// How long search timestamps are kept by default, bounding the window of
+/// [`TopSearches::top_n_recent`].
+pub const DEFAULT_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Number of keywords with timestamps kept before those searched only before the retention
+/// period are forgotten.
+const MIN_PRUNE_THRESHOLD: usize = 1024;
+
 /// Maintains a collection of search keywords and provides functionality
 /// to retrieve the most frequently searched terms.
 ///
@@ -39,10 +48,28 @@ use std::{
 /// # Thread Safety
 ///
 /// Not thread-safe. For concurrent use, consider wrapping in synchronization primitives.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct TopSearches {
     /// Maps search keywords to their respective counts.
     counts: HashMap<String, usize>,
+    /// Maps search keywords to when they were searched, oldest first.
+    timestamps: HashMap<String, VecDeque<Instant>>,
+    /// How long timestamps are kept before being pruned.
+    retention: Duration,
+    /// Number of keywords in `timestamps` after which the expired ones are forgotten, so memory
+    /// only grows with the keywords searched within the retention period.
+    prune_threshold: usize,
+}
+
+impl Default for TopSearches {
+    fn default() -> Self {
+        Self {
+            counts: HashMap::new(),
+            timestamps: HashMap::new(),
+            retention: DEFAULT_RETENTION,
+            prune_threshold: MIN_PRUNE_THRESHOLD,
+        }
+    }
 }
 
 impl TopSearches {
@@ -60,9 +87,29 @@ impl TopSearches {
     /// let searches = TopSearches::new();
     /// ```
     pub fn new() -> Self {
-        Self {
-            counts: HashMap::new(),
-        }
+        Self::default()
+    }
+
+    /// Sets how long search timestamps are kept.
+    ///
+    /// Windows passed to [`TopSearches::top_n_recent`] longer than the retention only count
+    /// the searches of the retention period.
+    ///
+    /// # Arguments
+    ///
+    /// * `retention` - How long a search is remembered for recency queries.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::top_searches::TopSearches;
+    /// use std::time::Duration;
+    ///
+    /// let searches = TopSearches::new().with_retention(Duration::from_secs(3600));
+    /// ```
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
     }
 
     /// Records a new search for the given `word`.
@@ -86,7 +133,57 @@ impl TopSearches {
     /// assert_eq!(searches.count("rust"), 2);
     /// ```
     pub fn add_search(&mut self, word: &str) {
+        self.add_search_at(word, Instant::now());
+    }
+
+    /// Records a search for the given `word` made at `when`.
+    ///
+    /// Timestamps of `word` older than the retention period are pruned. Once many keywords
+    /// were searched, those not searched within the retention period are forgotten too.
+    ///
+    /// # Arguments
+    ///
+    /// * `word` - The search term to record.
+    /// * `when` - When the search was made.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::top_searches::TopSearches;
+    /// use std::time::Instant;
+    ///
+    /// let mut searches = TopSearches::new();
+    /// searches.add_search_at("rust", Instant::now());
+    /// assert_eq!(searches.count("rust"), 1);
+    /// ```
+    pub fn add_search_at(&mut self, word: &str, when: Instant) {
         *self.counts.entry(word.to_string()).or_insert(0) += 1;
+
+        let cutoff = when.checked_sub(self.retention);
+
+        if self.timestamps.len() >= self.prune_threshold {
+            self.timestamps.retain(|_, timestamps| {
+                Self::prune(timestamps, cutoff);
+                !timestamps.is_empty()
+            });
+            self.prune_threshold = (self.timestamps.len() * 2).max(MIN_PRUNE_THRESHOLD);
+        }
+
+        let timestamps = self.timestamps.entry(word.to_string()).or_default();
+        timestamps.push_back(when);
+        Self::prune(timestamps, cutoff);
+    }
+
+    /// Drops the timestamps older than `cutoff`, if any.
+    fn prune(timestamps: &mut VecDeque<Instant>, cutoff: Option<Instant>) {
+        if let Some(cutoff) = cutoff {
+            while timestamps
+                .front()
+                .is_some_and(|&timestamp| timestamp < cutoff)
+            {
+                timestamps.pop_front();
+            }
+        }
     }
 
     /// Returns the number of times the given `word` has been searched.
@@ -114,6 +211,23 @@ impl TopSearches {
         self.counts.get(word).cloned().unwrap_or(0)
     }
 
+    /// Returns the total number of recorded searches, across all keywords.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use googol::top_searches::TopSearches;
+    ///
+    /// let mut searches = TopSearches::new();
+    /// searches.add_search("rust");
+    /// searches.add_search("rust");
+    /// searches.add_search("programming");
+    /// assert_eq!(searches.total(), 3);
+    /// ```
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
     /// Retrieves the top `n` most searched keywords along with their counts.
     ///
     /// The results are sorted in descending order of count.
@@ -139,19 +253,79 @@ impl TopSearches {
     /// assert_eq!(top, vec![("rust".to_string(), 2), ("programming".to_string(), 1)]);
     /// ```
     pub fn top_n(&self, n: usize) -> Vec<(String, usize)> {
+        Self::top_n_of(
+            self.counts.iter().map(|(keyword, &count)| (keyword, count)),
+            n,
+        )
+    }
+
+    /// Retrieves the top `n` keywords searched within the last `window`, along with their
+    /// number of searches in that window.
+    ///
+    /// The results are sorted in descending order of count.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of top entries to retrieve.
+    /// * `window` - How far back searches are counted. Capped by the retention period.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `(keyword, count)` tuples for the top `n` recent searches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use googol::top_searches::TopSearches;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut searches = TopSearches::new();
+    /// let now = Instant::now();
+    /// if let Some(two_hours_ago) = now.checked_sub(Duration::from_secs(2 * 60 * 60)) {
+    ///     searches.add_search_at("old", two_hours_ago);
+    ///     searches.add_search_at("old", two_hours_ago);
+    /// }
+    /// searches.add_search_at("new", now);
+    ///
+    /// let top = searches.top_n_recent(2, Duration::from_secs(60 * 60));
+    /// assert_eq!(top, vec![("new".to_string(), 1)]);
+    /// ```
+    pub fn top_n_recent(&self, n: usize, window: Duration) -> Vec<(String, usize)> {
+        let cutoff = Instant::now().checked_sub(window.min(self.retention));
+
+        let counts = self.timestamps.iter().filter_map(|(keyword, timestamps)| {
+            let count = match cutoff {
+                Some(cutoff) => timestamps
+                    .iter()
+                    .filter(|&&timestamp| timestamp >= cutoff)
+                    .count(),
+                None => timestamps.len(),
+            };
+
+            (count > 0).then_some((keyword, count))
+        });
+
+        Self::top_n_of(counts, n)
+    }
+
+    /// Retrieves the top `n` `(keyword, count)` pairs, sorted in descending order of count.
+    fn top_n_of<'a>(
+        counts: impl Iterator<Item = (&'a String, usize)>,
+        n: usize,
+    ) -> Vec<(String, usize)> {
         // Use a min-heap to keep track of top n counts
         let mut heap: BinaryHeap<Reverse<(usize, String)>> = BinaryHeap::new();
 
-        for (keyword, &count) in &self.counts {
+        for (keyword, count) in counts {
             if heap.len() < n {
                 // Fill the heap initially
                 heap.push(Reverse((count, keyword.clone())));
-            } else if let Some(&Reverse((min_count, _))) = heap.peek() {
-                if count > min_count {
-                    // Replace the smallest in the heap if current count is higher
-                    heap.pop();
-                    heap.push(Reverse((count, keyword.clone())));
-                }
+            } else if let Some(&Reverse((min_count, _))) = heap.peek()
+                && count > min_count
+            {
+                // Replace the smallest in the heap if current count is higher
+                heap.pop();
+                heap.push(Reverse((count, keyword.clone())));
             }
         }
 
@@ -162,7 +336,7 @@ impl TopSearches {
             .collect();
 
         // Sort in descending order of counts
-        result.sort_by(|a, b| b.0.cmp(&a.0));
+        result.sort_by_key(|(count, _)| Reverse(*count));
 
         // Convert to (keyword, count) tuples
         result
@@ -218,4 +392,78 @@ mod tests {
         assert_eq!(searches.count("programming"), 1);
         assert_eq!(searches.count("language"), 0);
     }
+
+    #[test]
+    fn test_top_n_recent() {
+        let mut searches = TopSearches::new();
+        let now = Instant::now();
+        let hour = Duration::from_secs(60 * 60);
+
+        let Some(two_hours_ago) = now.checked_sub(2 * hour) else {
+            return;
+        };
+
+        for _ in 0..3 {
+            searches.add_search_at("yesterday", two_hours_ago);
+        }
+        searches.add_search_at("rust", now);
+        searches.add_search_at("rust", now);
+        searches.add_search_at("code", now);
+
+        // All-time counts are unaffected
+        assert_eq!(searches.top_n(1), vec![("yesterday".to_string(), 3)]);
+
+        assert_eq!(
+            searches.top_n_recent(3, hour),
+            vec![("rust".to_string(), 2), ("code".to_string(), 1)]
+        );
+        assert_eq!(
+            searches.top_n_recent(1, 3 * hour)[0],
+            ("yesterday".to_string(), 3)
+        );
+    }
+
+    #[test]
+    fn test_retention_prunes_timestamps() {
+        let hour = Duration::from_secs(60 * 60);
+        let mut searches = TopSearches::new().with_retention(hour);
+        let now = Instant::now();
+
+        let Some(two_hours_ago) = now.checked_sub(2 * hour) else {
+            return;
+        };
+
+        searches.add_search_at("rust", two_hours_ago);
+        searches.add_search_at("rust", now);
+
+        assert_eq!(searches.count("rust"), 2);
+        assert_eq!(searches.timestamps["rust"].len(), 1);
+        // Windows are capped by the retention
+        assert_eq!(
+            searches.top_n_recent(1, 3 * hour),
+            vec![("rust".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_retention_forgets_expired_keywords() {
+        let hour = Duration::from_secs(60 * 60);
+        let mut searches = TopSearches::new().with_retention(hour);
+        let now = Instant::now();
+
+        let Some(two_hours_ago) = now.checked_sub(2 * hour) else {
+            return;
+        };
+
+        for i in 0..MIN_PRUNE_THRESHOLD {
+            searches.add_search_at(&format!("old-{}", i), two_hours_ago);
+        }
+        assert_eq!(searches.timestamps.len(), MIN_PRUNE_THRESHOLD);
+
+        searches.add_search_at("rust", now);
+        assert_eq!(searches.timestamps.len(), 1);
+        // All-time counts are kept
+        assert_eq!(searches.count("old-0"), 1);
+        assert_eq!(searches.total(), MIN_PRUNE_THRESHOLD + 1);
+    }
 }
@@ -0,0 +1,178 @@
+"use strict";
+
+/** Number of results asked for at once. */
+const PAGE_SIZE = 10;
+/** Milliseconds before reconnecting to the status updates. */
+const RECONNECT_DELAY_MS = 3000;
+
+const elements = {
+  moreButton: document.getElementById('more-button'),
+  results: document.getElementById('results'),
+  searchForm: document.getElementById('search-form'),
+  searchInput: document.getElementById('search-input'),
+  searchMessage: document.getElementById('search-message'),
+  statusBarrels: document.getElementById('status-barrels'),
+  statusConnection: document.getElementById('status-connection'),
+  statusQueue: document.getElementById('status-queue'),
+  statusResponseTime: document.getElementById('status-response-time'),
+  statusTop10: document.getElementById('status-top10'),
+};
+
+/** Words of the current search, and how many results were already shown. */
+const search = { words: [], offset: 0 };
+
+/**
+ * @typedef {Object} Page
+ * @property {string} href
+ * @property {?string} title
+ * @property {?string} summary
+ */
+
+/**
+ * Only links to web pages are clickable, so indexed urls can't run scripts.
+ * @param {string} href
+ */
+const isWebUrl = href => {
+  try {
+    return ['http:', 'https:'].includes(new URL(href).protocol);
+  } catch {
+    return false;
+  }
+};
+
+/**
+ * Appends a result to the list.
+ * @param {Page} page
+ */
+const renderPage = page => {
+  const item = document.createElement('li');
+  item.className = 'result';
+
+  const title = document.createElement(isWebUrl(page.href) ? 'a' : 'span');
+  title.className = 'result-title';
+  title.textContent = page.title || page.href;
+  if (title.tagName === 'A') {
+    title.href = page.href;
+  }
+  item.appendChild(title);
+
+  const link = document.createElement('div');
+  link.className = 'result-url';
+  link.textContent = page.href;
+  item.appendChild(link);
+
+  if (page.summary) {
+    const summary = document.createElement('p');
+    summary.textContent = page.summary;
+    item.appendChild(summary);
+  }
+
+  elements.results.appendChild(item);
+};
+
+/** Fetches the next results of the current search. */
+const fetchResults = async () => {
+  const params = new URLSearchParams({
+    words: search.words.join(','),
+    offset: search.offset,
+    limit: PAGE_SIZE,
+  });
+
+  elements.moreButton.hidden = true;
+
+  try {
+    const response = await fetch(`/search?${params}`);
+    const body = await response.json();
+
+    if (response.status === 429) {
+      const retryAfter = response.headers.get('Retry-After');
+      elements.searchMessage.textContent = `Too many searches, try again in ${retryAfter} seconds.`;
+      return;
+    }
+    if (!response.ok) {
+      elements.searchMessage.textContent = body.error || response.statusText;
+      return;
+    }
+
+    body.forEach(renderPage);
+    search.offset += body.length;
+
+    elements.searchMessage.textContent = search.offset === 0
+      ? 'No results found.'
+      : `Showing ${search.offset} results.`;
+    elements.moreButton.hidden = body.length < PAGE_SIZE;
+  } catch (error) {
+    elements.searchMessage.textContent = `Search failed: ${error}`;
+  }
+};
+
+elements.searchForm.addEventListener('submit', event => {
+  event.preventDefault();
+
+  const words = elements.searchInput.value.trim().toLowerCase().split(/\s+/).filter(word => word);
+  if (words.length === 0) {
+    return;
+  }
+
+  search.words = words;
+  search.offset = 0;
+  elements.results.replaceChildren();
+  elements.searchMessage.textContent = 'Searching...';
+
+  fetchResults();
+});
+
+elements.moreButton.addEventListener('click', fetchResults);
+
+/**
+ * Shows a status update of the gateway.
+ * @param {Object} status
+ */
+const renderStatus = status => {
+  const online = status.barrels.filter(barrel => barrel.online).length;
+  elements.statusBarrels.textContent = `${online} / ${status.barrels.length}`;
+  elements.statusQueue.textContent = status.queue.length;
+  elements.statusResponseTime.textContent = [
+    status.avg_response_time_ms,
+    status.p50_response_time_ms,
+    status.p95_response_time_ms,
+    status.p99_response_time_ms,
+  ].map(ms => (ms ?? 0).toFixed(1)).join(' / ') + ' ms';
+
+  elements.statusTop10.replaceChildren(...status.top10_searches.map(word => {
+    const item = document.createElement('li');
+    item.textContent = word;
+    return item;
+  }));
+};
+
+/** Subscribes to the status updates, reconnecting whenever the connection is lost. */
+const connectStatus = () => {
+  const protocol = location.protocol === 'https:' ? 'wss:' : 'ws:';
+  const socket = new WebSocket(`${protocol}//${location.host}/ws`);
+
+  const setConnected = connected => {
+    elements.statusConnection.textContent = connected ? 'live' : 'offline';
+    elements.statusConnection.className = connected ? 'online' : 'offline';
+  };
+
+  socket.onopen = () => {
+    setConnected(true);
+    socket.send(JSON.stringify({ action: 'subscribe', topic: 'status' }));
+  };
+
+  socket.onmessage = event => {
+    try {
+      renderStatus(JSON.parse(event.data));
+    } catch (error) {
+      console.error('Invalid status update', error);
+    }
+  };
+
+  socket.onclose = () => {
+    setConnected(false);
+    setTimeout(connectStatus, RECONNECT_DELAY_MS);
+  };
+};
+
+connectStatus();
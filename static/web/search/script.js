@@ -21,17 +21,69 @@ console.debug('elements', elements);
  */
 
 /**
- * Get results
+ * Render a page at the end of the results
+ * @param {Page} page - The page to render.
+ */
+const renderPage = page => {
+  console.debug('page', page);
+
+  let { title, href, summary, icon } = page;
+
+  const resultDiv = document.createElement('div');
+  resultDiv.className = 'result';
+
+  const linkA = document.createElement('a');
+  linkA.href = href;
+  linkA.textContent = title.length > 0 ? title : href;
+  linkA.target = '_blank'; // optional: open in new tab
+  linkA.style.display = 'block'; // optional: display each link on its own line
+
+  if (icon) {
+    const img = document.createElement('img');
+    img.src = icon;
+    img.alt = 'Icon';
+    img.style.width = '20px';
+    img.style.height = '20px';
+    img.style.marginRight = '8px';
+    linkA.appendChild(img);
+    // resultDiv.appendChild(img);
+  }
+
+  resultDiv.appendChild(linkA);
+
+  if (summary && summary.length > 0) {
+    const summaryPara = document.createElement('p');
+    summaryPara.textContent = summary;
+    resultDiv.appendChild(summaryPara);
+  } else {
+    const summarizeButton = document.createElement('button');
+    summarizeButton.id = 'summarize-button';
+    summarizeButton.innerHTML = 'AI Summarize';
+    summarizeButton.style.margin = '0 0 10px 0';
+
+    summarizeButton.addEventListener('click', _ => {
+      console.log(`Summarizing ${page.href} ...`);
+      alert('Summarize feature coming soon!');
+    });
+
+    resultDiv.appendChild(summarizeButton);
+  }
+
+  elements.resultsDiv.appendChild(resultDiv);
+};
+
+/**
+ * Stream results, calling `onPages` with each batch of pages as it arrives
  * @param {string[]} words - Array of string search terms.
- * @returns {Page[]}
+ * @param {(pages: Page[]) => void} onPages - Called with each batch of pages.
  */
-const getResults = async words => {
+const streamResults = async (words, onPages) => {
   console.debug('words', words);
 
   const dummy = elements.dummyCheckbox.checked;
 
   if (dummy) {
-    return [
+    onPages([
       {
         href: 'https://google.com',
         title: 'Google',
@@ -51,39 +103,65 @@ const getResults = async words => {
         title: 'Lion - Wikipedia',
         icon: 'data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAABwAAAAcCAAAAABXZoBIAAAAnklEQVR4AeTNIQiDQABG4b+u17X1aF6PK3YEO9iMJqPVau82y4FgMezS0oVLhqsHtrcqeqzDXv3CEz/6L4yTtZM3dnHmPTtjzXZAXKYVo4agkU2GI2Lloc6JDez1+flswMu1EQZ3xlE7lK8eKDkjtwE+crBMV+wesKmCiisGGepZIfQJpMj9SNb2MYWrChjVkULuCyCfRvsdmBieyQQAsoDk/9ryhFMAAAAASUVORK5CYII=',
       },
-    ];
+    ]);
+    return;
   }
 
   const webServerAddress = elements.webServerAddressInput.value.trim();
   if (!webServerAddress) {
     alert("Insert a valid WebServer address!");
-    return [];
+    return;
   }
 
-  /** @type {Page[]} */
-  const results = await fetch(`http://${webServerAddress}/search?words=${words.concat(',')}`)
-    .then(response => {
-      if (!response.ok) {
-        // Handle HTTP errors
-        throw new Error(`Network response was not ok: ${response.statusText}`);
+  try {
+    const response = await fetch(`http://${webServerAddress}/search/stream?words=${words.concat(',')}`);
+    if (!response.ok) {
+      // Handle HTTP errors
+      throw new Error(`Network response was not ok: ${response.statusText}`);
+    }
+
+    const reader = response.body.pipeThrough(new TextDecoderStream()).getReader();
+    let buffer = '';
+
+    // Each line holds a batch of pages
+    const handleLine = line => {
+      if (line.trim().length === 0) {
+        return;
+      }
+
+      const data = JSON.parse(line);
+      console.log('data', data);
+
+      if (!Array.isArray(data)) {
+        throw new Error(data.error);
       }
 
-      return response;
-    })
-    .then(response => response.json())
-    // .then(data => { console.log('data', data); return data; })
-    .then(data => data.map(page => {
-      const { title, href, summary, icon } = page;
+      onPages(data.map(page => {
+        const { title, href, summary, icon } = page;
+
+        /** @type{Page} */
+        const pageO = { title, href, summary, icon };
+
+        return pageO;
+      }));
+    };
 
-      /** @type{Page} */
-      const pageO = { title, href, summary, icon };
+    while (true) {
+      const { value, done } = await reader.read();
+      if (done) {
+        break;
+      }
 
-      return pageO;
-    }))
-    .then(data => { console.log('data', data); return data; })
-    .catch(error => console.error('There was a problem with the fetch operation: ', error));
+      buffer += value;
+      const lines = buffer.split('\n');
+      buffer = lines.pop();
+      lines.forEach(handleLine);
+    }
 
-  return results;
+    handleLine(buffer);
+  } catch (error) {
+    console.error('There was a problem with the fetch operation: ', error);
+  }
 }
 
 elements.searchButton.addEventListener('click', async (event) => {
@@ -96,60 +174,18 @@ elements.searchButton.addEventListener('click', async (event) => {
   const searchTerms = query.split(' ').filter(word => word.length > 0);
   console.log('searchTerms', searchTerms);
 
-  const results = await getResults(searchTerms);
-  console.debug('results', results);
-
-  elements.resultsCountDiv.innerHTML = `${results.length} Results found.`;
-
+  let count = 0;
+  elements.resultsCountDiv.innerHTML = '';
   elements.resultsDiv.innerHTML = '';
 
-  results.map(page => {
-    console.debug('page', page);
-
-    let { title, href, summary, icon } = page;
-
-    const resultDiv = document.createElement('div');
-    resultDiv.className = 'result';
-
-    const linkA = document.createElement('a');
-    linkA.href = href;
-    linkA.textContent = title.length > 0 ? title : href;
-    linkA.target = '_blank'; // optional: open in new tab
-    linkA.style.display = 'block'; // optional: display each link on its own line
-
-    if (icon) {
-      const img = document.createElement('img');
-      img.src = icon;
-      img.alt = 'Icon';
-      img.style.width = '20px';
-      img.style.height = '20px';
-      img.style.marginRight = '8px';
-      linkA.appendChild(img);
-      // resultDiv.appendChild(img);
-    }
-
-    resultDiv.appendChild(linkA);
-
-    if (summary && summary.length > 0) {
-      const summaryPara = document.createElement('p');
-      summaryPara.textContent = summary;
-      resultDiv.appendChild(summaryPara);
-    } else {
-      const summarizeButton = document.createElement('button');
-      summarizeButton.id = 'summarize-button';
-      summarizeButton.innerHTML = 'AI Summarize';
-      summarizeButton.style.margin = '0 0 10px 0';
+  await streamResults(searchTerms, pages => {
+    console.debug('pages', pages);
 
-      summarizeButton.addEventListener('click', _ => {
-        console.log(`Summarizing ${page.href} ...`);
-        alert('Summarize feature coming soon!');
-      });
+    count += pages.length;
+    elements.resultsCountDiv.innerHTML = `${count} Results found.`;
 
-      resultDiv.appendChild(summarizeButton);
-    }
-
-    return resultDiv;
-  }).forEach(resultDiv => {
-    elements.resultsDiv.appendChild(resultDiv);
+    pages.forEach(renderPage);
   });
+
+  elements.resultsCountDiv.innerHTML = `${count} Results found.`;
 });
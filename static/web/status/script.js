@@ -5,6 +5,9 @@ const elements = {
   barrelsDiv: document.getElementById('barrels'),
   connectButton: document.getElementById('connect-button'),
   connectP: document.getElementById('connect-p'),
+  p50ResponseTimeSpan: document.getElementById('p50-response-time'),
+  p95ResponseTimeSpan: document.getElementById('p95-response-time'),
+  p99ResponseTimeSpan: document.getElementById('p99-response-time'),
   queueUl: document.getElementById('queue'),
   top10Ol: document.getElementById('top10'),
   webServerAddressInput: document.getElementById('web-server-address-input'),
@@ -43,6 +46,17 @@ const createSocket = (url) => {
         elements.avgResponseTimeSpan.textContent = data.avg_response_time_ms.toFixed(3);
       }
 
+      // Update response time percentiles
+      [
+        [elements.p50ResponseTimeSpan, data.p50_response_time_ms],
+        [elements.p95ResponseTimeSpan, data.p95_response_time_ms],
+        [elements.p99ResponseTimeSpan, data.p99_response_time_ms],
+      ].forEach(([span, value]) => {
+        if (value !== undefined) {
+          span.textContent = value.toFixed(3);
+        }
+      });
+
       // Update barrels
       if (Array.isArray(data.barrels)) {
         // Clear existing barrels